@@ -1,6 +1,6 @@
 #![allow(dead_code)]
 use anyhow::{Context, Result, anyhow, bail};
-use serde::{Deserialize, Deserializer};
+use serde::{Deserialize, Deserializer, Serialize};
 /// Handles loading hardware configuration files and creating objects
 /// for use by sddr-gen/sdrr-info.
 // Copyright (C) 2025 Piers Finlayson <piers@piers.rocks>
@@ -56,6 +56,20 @@ impl std::fmt::Display for Port {
     }
 }
 
+impl Port {
+    // Port name for machine-readable output - None if the port isn't used
+    fn name(&self) -> Option<String> {
+        match self {
+            Port::None => None,
+            Port::Zero => Some("0".to_string()),
+            Port::A => Some("A".to_string()),
+            Port::B => Some("B".to_string()),
+            Port::C => Some("C".to_string()),
+            Port::D => Some("D".to_string()),
+        }
+    }
+}
+
 impl<'de> Deserialize<'de> for Port {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -150,6 +164,28 @@ where
     Ok(rom_map)
 }
 
+/// A curated, serializable view of a hardware configuration's capabilities,
+/// for tools which need to present valid options for it.  Unused ports are
+/// `None`.
+#[derive(Debug, Clone, Serialize)]
+pub struct HwCapabilities {
+    pub name: String,
+    pub description: String,
+    pub family: String,
+    pub rom_pins: u8,
+    pub rom_types: Vec<String>,
+    pub data_port: Option<String>,
+    pub addr_port: Option<String>,
+    pub cs_port: Option<String>,
+    pub sel_port: Option<String>,
+    pub status_port: Option<String>,
+    pub sel_pins: usize,
+    pub supports_multi_rom_sets: bool,
+    pub max_roms_per_set: usize,
+    pub supports_banked_roms: bool,
+    pub supports_status_led: bool,
+}
+
 /// Represents the hardware configuration for a particular SDRR hardware
 /// config - see /sdrr-hw-config/README.md for details.
 #[derive(Debug, Clone, Deserialize)]
@@ -229,6 +265,21 @@ impl HwConfig {
         self.phys_pin_to_data_map
     }
 
+    /// Returns the physical address (the state of the MCU's address pins)
+    /// which selects the given logical address (the state of the ROM's
+    /// address lines) on this hardware, for the given ROM type.  The inverse
+    /// of [`Self::physical_to_logical`].
+    pub fn logical_to_physical(&self, logical: usize, rom_type: &RomType) -> usize {
+        logical_to_physical(logical, &self.get_phys_pin_to_addr_map(rom_type.num_addr_lines()))
+    }
+
+    /// Returns the logical address selected by the given physical address on
+    /// this hardware, for the given ROM type.  Physical pins which aren't
+    /// address lines for the ROM type are ignored.
+    pub fn physical_to_logical(&self, physical: usize, rom_type: &RomType) -> usize {
+        physical_to_logical(physical, &self.get_phys_pin_to_addr_map(rom_type.num_addr_lines()))
+    }
+
     pub fn port_data(&self) -> Port {
         self.mcu.ports.data_port
     }
@@ -285,6 +336,11 @@ impl HwConfig {
         self.mcu.pins.sel.get(sel).copied().unwrap_or(255)
     }
 
+    /// Returns the number of image select pins wired on this hardware
+    pub fn sel_pin_count(&self) -> usize {
+        self.mcu.pins.sel.iter().filter(|&&pin| pin < 255).count()
+    }
+
     pub fn sel_jumper_pull(&self) -> u8 {
         self.mcu.pins.sel_jumper_pull
     }
@@ -302,6 +358,45 @@ impl HwConfig {
         }
     }
 
+    /// Returns the ROM types which can be served by this hardware
+    pub fn supported_rom_types(&self) -> Vec<RomType> {
+        match self.rom.pins.quantity {
+            24 => vec![RomType::Rom2316, RomType::Rom2332, RomType::Rom2364],
+            28 => vec![RomType::Rom23128],
+            _ => Vec::new(),
+        }
+    }
+
+    /// Returns whether this hardware has a status LED
+    pub fn supports_status_led(&self) -> bool {
+        self.port_status() != Port::None && self.pin_status() != 255
+    }
+
+    /// Returns a serializable summary of this hardware's capabilities
+    pub fn capabilities(&self) -> HwCapabilities {
+        HwCapabilities {
+            name: self.name.clone(),
+            description: self.description.clone(),
+            family: self.mcu.family.to_string(),
+            rom_pins: self.rom.pins.quantity,
+            rom_types: self
+                .supported_rom_types()
+                .iter()
+                .map(|rom_type| rom_type.name().to_string())
+                .collect(),
+            data_port: self.port_data().name(),
+            addr_port: self.port_addr().name(),
+            cs_port: self.port_cs().name(),
+            sel_port: self.port_sel().name(),
+            status_port: self.port_status().name(),
+            sel_pins: self.sel_pin_count(),
+            supports_multi_rom_sets: self.supports_multi_rom_sets(),
+            max_roms_per_set: self.max_roms_per_set(),
+            supports_banked_roms: self.supports_banked_roms(),
+            supports_status_led: self.supports_status_led(),
+        }
+    }
+
     pub fn supports_banked_roms(&self) -> bool {
         self.supports_multi_rom_sets()
     }
@@ -318,6 +413,23 @@ impl HwConfig {
         }
         false
     }
+
+    /// Returns the maximum number of ROMs a multi-ROM set can contain on this
+    /// hardware.  Each ROM in the set is selected by its own chip select line,
+    /// CS1, X1 or X2, so this is the number of those lines exposed.
+    /// Hardware which doesn't support multi-ROM sets is limited to 1.
+    pub fn max_roms_per_set(&self) -> usize {
+        if !self.supports_multi_rom_sets() {
+            return 1;
+        }
+
+        let cs1 = self.mcu.pins.cs1.values().any(|&pin| pin < 255);
+        let x_pins = [self.mcu.pins.x1, self.mcu.pins.x2]
+            .into_iter()
+            .filter(|pin| matches!(pin, Some(pin) if *pin < 255))
+            .count();
+        cs1 as usize + x_pins
+    }
 }
 
 fn normalize_name(name: &str) -> String {
@@ -623,6 +735,29 @@ fn validate_config(name: &str, config: &HwConfig) -> Result<()> {
     Ok(())
 }
 
+/// Transforms a logical ROM address to a physical address, using a map from
+/// physical pin to address line, as returned by
+/// [`HwConfig::get_phys_pin_to_addr_map`].
+pub fn logical_to_physical(logical: usize, phys_pin_to_addr_map: &[Option<usize>]) -> usize {
+    phys_pin_to_addr_map
+        .iter()
+        .enumerate()
+        .filter_map(|(pin, addr_line)| addr_line.map(|line| (pin, line)))
+        .filter(|(_, line)| logical & (1 << line) != 0)
+        .fold(0, |physical, (pin, _)| physical | (1 << pin))
+}
+
+/// Transforms a physical address to a logical ROM address, using a map from
+/// physical pin to address line.  The inverse of [`logical_to_physical`].
+pub fn physical_to_logical(physical: usize, phys_pin_to_addr_map: &[Option<usize>]) -> usize {
+    phys_pin_to_addr_map
+        .iter()
+        .enumerate()
+        .filter_map(|(pin, addr_line)| addr_line.map(|line| (pin, line)))
+        .filter(|(pin, _)| physical & (1 << pin) != 0)
+        .fold(0, |logical, (_, line)| logical | (1 << line))
+}
+
 fn get_config_dirs() -> Result<Vec<PathBuf>> {
     // Find first existing root directory
     let root = HW_CONFIG_DIRS
@@ -748,4 +883,57 @@ mod tests {
         assert_eq!(normalize_name("28_A"), "28-a");
         assert_eq!(normalize_name("28-A"), "28-a");
     }
+
+    #[test]
+    fn test_capabilities() {
+        let json = include_str!("../../../sdrr-hw-config/24-d.json");
+        let capabilities = HwConfig::new(json, "24-d").unwrap().capabilities();
+        assert_eq!(capabilities.rom_types, vec!["2316", "2332", "2364"]);
+        assert_eq!(capabilities.status_port, None);
+        assert_eq!(capabilities.max_roms_per_set, 1);
+        assert!(!capabilities.supports_status_led);
+
+        let json = include_str!("../../../sdrr-hw-config/24-f.json");
+        let capabilities = HwConfig::new(json, "24-f").unwrap().capabilities();
+        assert_eq!(capabilities.family, "F4");
+        assert_eq!(capabilities.data_port.as_deref(), Some("A"));
+        assert!(capabilities.supports_status_led);
+        assert!(capabilities.supports_multi_rom_sets);
+        assert_eq!(capabilities.max_roms_per_set, 3);
+
+        let json = include_str!("../../../sdrr-hw-config/28-a.json");
+        let capabilities = HwConfig::new(json, "28-a").unwrap().capabilities();
+        assert_eq!(capabilities.rom_pins, 28);
+        assert_eq!(capabilities.rom_types, vec!["23128"]);
+    }
+
+    #[test]
+    fn test_address_round_trip() {
+        let hw_revs = [
+            ("24-d", include_str!("../../../sdrr-hw-config/24-d.json")),
+            ("24-e", include_str!("../../../sdrr-hw-config/24-e.json")),
+            ("24-f", include_str!("../../../sdrr-hw-config/24-f.json")),
+            ("24-g", include_str!("../../../sdrr-hw-config/24-g.json")),
+            ("28-a", include_str!("../../../sdrr-hw-config/28-a.json")),
+            ("p24-a", include_str!("../../../sdrr-hw-config/p24-a.json")),
+        ];
+
+        for (name, json) in hw_revs {
+            let hw = HwConfig::new(json, name).unwrap();
+            for rom_type in hw.supported_rom_types() {
+                for logical in 0..rom_type.size_bytes() {
+                    let physical = hw.logical_to_physical(logical, &rom_type);
+                    assert_eq!(
+                        hw.physical_to_logical(physical, &rom_type),
+                        logical,
+                        "{} {}: logical 0x{:04X} -> physical 0x{:04X}",
+                        name,
+                        rom_type.name(),
+                        logical,
+                        physical
+                    );
+                }
+            }
+        }
+    }
 }
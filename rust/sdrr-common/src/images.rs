@@ -0,0 +1,153 @@
+// Copyright (C) 2025 Piers Finlayson <piers@piers.rocks>
+//
+// MIT License
+
+//! The images file format, written by `sdrr-gen --emit-images` and read by
+//! `sdrr-info --images`, containing every ROM set's transformed image,
+//! exactly as stored in the firmware, preceded by an index.  All values are
+//! little-endian:
+//!
+//! ```text
+//! Offset  Size  Contents
+//! 0x00    4     Magic bytes "SDRI"
+//! 0x04    1     Format version, currently 1
+//! 0x05    1     Number of ROM sets, N
+//! 0x06    2     Reserved, 0
+//! 0x08    12*N  Index, one entry per ROM set, in firmware order:
+//!               +0x00 u32 Offset of the set's image from the start of the file
+//!               +0x04 u32 Size of the set's image in bytes
+//!               +0x08 u8  ROM set ID
+//!               +0x09 u8  Number of ROMs in the set
+//!               +0x0A u8  1 if the set is banked, otherwise 0
+//!               +0x0B u8  Reserved, 0
+//! ```
+//!
+//! The images follow the index, one after the other, in the same order and
+//! with the same sizes as the firmware places them in flash.
+
+use anyhow::{Result, anyhow};
+
+/// Magic bytes at the start of an images file
+pub const IMAGES_MAGIC: &[u8; 4] = b"SDRI";
+
+/// Images file format version
+pub const IMAGES_VERSION: u8 = 1;
+
+/// Size of the images file header, before the index
+pub const IMAGES_HEADER_SIZE: usize = 8;
+
+/// Size of each ROM set's entry in the images file index
+pub const IMAGES_INDEX_ENTRY_SIZE: usize = 12;
+
+/// A ROM set's entry in the images file index
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImagesIndexEntry {
+    /// Offset of the set's image from the start of the file
+    pub offset: usize,
+    /// Size of the set's image in bytes
+    pub size: usize,
+    /// ROM set ID
+    pub set_id: u8,
+    /// Number of ROMs in the set
+    pub rom_count: u8,
+    /// Whether the set is banked
+    pub is_banked: bool,
+}
+
+impl ImagesIndexEntry {
+    /// Returns the entry as stored in the images file index
+    pub fn to_bytes(&self) -> [u8; IMAGES_INDEX_ENTRY_SIZE] {
+        let mut bytes = [0u8; IMAGES_INDEX_ENTRY_SIZE];
+        bytes[0..4].copy_from_slice(&(self.offset as u32).to_le_bytes());
+        bytes[4..8].copy_from_slice(&(self.size as u32).to_le_bytes());
+        bytes[8] = self.set_id;
+        bytes[9] = self.rom_count;
+        bytes[10] = self.is_banked as u8;
+        bytes
+    }
+}
+
+/// Returns the images file header, for a file containing `count` ROM sets
+pub fn images_header(count: u8) -> [u8; IMAGES_HEADER_SIZE] {
+    let mut header = [0u8; IMAGES_HEADER_SIZE];
+    header[0..4].copy_from_slice(IMAGES_MAGIC);
+    header[4] = IMAGES_VERSION;
+    header[5] = count;
+    header
+}
+
+/// Parses an images file's index, returning an entry for each ROM set, in
+/// firmware order.  Errors if the file isn't an images file, or any image
+/// is beyond the end of the file.
+pub fn parse_images_index(images: &[u8]) -> Result<Vec<ImagesIndexEntry>> {
+    if images.len() < IMAGES_HEADER_SIZE || &images[0..4] != IMAGES_MAGIC {
+        return Err(anyhow!("Not an SDRR images file"));
+    }
+    if images[4] != IMAGES_VERSION {
+        return Err(anyhow!(
+            "Unsupported images file version {}, expected {}",
+            images[4],
+            IMAGES_VERSION
+        ));
+    }
+
+    let count = images[5] as usize;
+    let index_end = IMAGES_HEADER_SIZE + count * IMAGES_INDEX_ENTRY_SIZE;
+    if images.len() < index_end {
+        return Err(anyhow!("Images file index is truncated"));
+    }
+
+    let u32_at = |offset: usize| {
+        u32::from_le_bytes(images[offset..offset + 4].try_into().unwrap()) as usize
+    };
+    (0..count)
+        .map(|set| {
+            let entry = IMAGES_HEADER_SIZE + set * IMAGES_INDEX_ENTRY_SIZE;
+            let (offset, size) = (u32_at(entry), u32_at(entry + 4));
+            if offset.checked_add(size).is_none_or(|end| end > images.len()) {
+                return Err(anyhow!(
+                    "Images file is truncated - set {} image at offset 0x{:X} ({} bytes) is beyond its end",
+                    set,
+                    offset,
+                    size
+                ));
+            }
+            Ok(ImagesIndexEntry {
+                offset,
+                size,
+                set_id: images[entry + 8],
+                rom_count: images[entry + 9],
+                is_banked: images[entry + 10] != 0,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_images_index() {
+        let entry = ImagesIndexEntry {
+            offset: IMAGES_HEADER_SIZE + IMAGES_INDEX_ENTRY_SIZE,
+            size: 4,
+            set_id: 3,
+            rom_count: 2,
+            is_banked: true,
+        };
+        let mut images = images_header(1).to_vec();
+        assert_eq!(images, b"SDRI\x01\x01\x00\x00");
+        images.extend_from_slice(&entry.to_bytes());
+        images.extend_from_slice(&[1, 2, 3, 4]);
+        assert_eq!(parse_images_index(&images).unwrap(), vec![entry]);
+
+        let err = |images: &[u8]| parse_images_index(images).unwrap_err().to_string();
+        assert!(err(&images[..images.len() - 1]).contains("set 0 image"));
+        assert_eq!(err(&images[..12]), "Images file index is truncated");
+        images[4] = 2;
+        assert_eq!(err(&images), "Unsupported images file version 2, expected 1");
+        images[0] = b'X';
+        assert_eq!(err(&images), "Not an SDRR images file");
+    }
+}
@@ -4,7 +4,10 @@
 
 pub mod args;
 pub mod hardware;
+pub mod images;
+pub mod rom_db;
 mod sdrr_types;
 
 pub use hardware::HwConfig;
 pub use sdrr_types::{CsLogic, RomType, ServeAlg, McuFamily, McuProcessor, McuVariant};
+pub use sdrr_types::{USB_CLOCK_KHZ, USB_CLOCK_TOLERANCE_KHZ};
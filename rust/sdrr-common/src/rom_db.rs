@@ -0,0 +1,164 @@
+// Copyright (C) 2025 Piers Finlayson <piers@piers.rocks>
+//
+// MIT License
+
+//! A small database of well known ROM images, identified by CRC32, used to
+//! identify ROM images and infer their type.
+//!
+//! The built-in table is deliberately kept short.  Users can supply further
+//! entries in a CSV file, see [`load_csv`].
+
+use std::path::Path;
+
+use crate::RomType;
+
+// Built-in known ROMs - CRC32, name and ROM type
+const BUILTIN_ROMS: &[(u32, &str, RomType)] = &[
+    // Commodore 64
+    (0xDCE782FA, "C64 kernal 901227-01", RomType::Rom2364),
+    (0xA5C687B3, "C64 kernal 901227-02", RomType::Rom2364),
+    (0xDBE3E7C7, "C64 kernal 901227-03", RomType::Rom2364),
+    (0xF833D117, "C64 basic 901226-01", RomType::Rom2364),
+    (0xEC4272EE, "C64 character 901225-01", RomType::Rom2332),
+    // Commodore VIC-20
+    (0x4BE07CB4, "VIC-20 kernal (NTSC) 901486-07", RomType::Rom2364),
+    (0xDB4C43C1, "VIC-20 basic 901486-01", RomType::Rom2364),
+    (0x83E032A6, "VIC-20 character 901460-03", RomType::Rom2332),
+];
+
+/// A known ROM image
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KnownRom {
+    pub crc32: u32,
+    pub name: String,
+    pub rom_type: RomType,
+}
+
+/// Returns the built-in known ROMs
+pub fn builtin_roms() -> Vec<KnownRom> {
+    BUILTIN_ROMS
+        .iter()
+        .map(|(crc32, name, rom_type)| KnownRom {
+            crc32: *crc32,
+            name: name.to_string(),
+            rom_type: *rom_type,
+        })
+        .collect()
+}
+
+/// Loads known ROMs from a CSV file, one per line, in the form:
+///
+/// ```text
+/// crc32,name,type
+/// DBE3E7C7,C64 kernal 901227-03,2364
+/// ```
+///
+/// The CRC32 is in hex, with an optional 0x prefix.  A header line, blank
+/// lines and lines starting with '#' are ignored.
+pub fn load_csv(path: &Path) -> Result<Vec<KnownRom>, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read ROM database {}: {}", path.display(), e))?;
+    parse_csv(&contents).map_err(|e| format!("ROM database {}: {}", path.display(), e))
+}
+
+fn parse_csv(contents: &str) -> Result<Vec<KnownRom>, String> {
+    let mut roms = Vec::new();
+
+    for (ii, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.to_lowercase().starts_with("crc32,") {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        if fields.len() != 3 {
+            return Err(format!(
+                "line {}: expected crc32,name,type, got: {}",
+                ii + 1,
+                line
+            ));
+        }
+
+        let crc = fields[0]
+            .strip_prefix("0x")
+            .or_else(|| fields[0].strip_prefix("0X"))
+            .unwrap_or(fields[0]);
+        let crc32 = u32::from_str_radix(crc, 16)
+            .map_err(|_| format!("line {}: invalid CRC32: {}", ii + 1, fields[0]))?;
+        let rom_type = RomType::try_from_str(fields[2])
+            .ok_or_else(|| format!("line {}: invalid ROM type: {}", ii + 1, fields[2]))?;
+
+        roms.push(KnownRom {
+            crc32,
+            name: fields[1].to_string(),
+            rom_type,
+        });
+    }
+
+    Ok(roms)
+}
+
+/// Returns all known ROMs in `db` matching the CRC32.
+pub fn identify(db: &[KnownRom], crc32: u32) -> Vec<&KnownRom> {
+    db.iter().filter(|rom| rom.crc32 == crc32).collect()
+}
+
+/// Builds a database from the built-in known ROMs, plus any user supplied
+/// ones.  User supplied entries replace built-in entries with the same CRC32.
+pub fn with_user_roms(user: Vec<KnownRom>) -> Vec<KnownRom> {
+    let mut db: Vec<KnownRom> = builtin_roms()
+        .into_iter()
+        .filter(|rom| !user.iter().any(|user_rom| user_rom.crc32 == rom.crc32))
+        .collect();
+    db.extend(user);
+    db
+}
+
+impl RomType {
+    /// Looks up a ROM image's CRC32 in the built-in known ROMs, returning its
+    /// name and type if there is a unique match.
+    pub fn from_crc(crc32: u32) -> Option<(&'static str, RomType)> {
+        let mut matches = BUILTIN_ROMS.iter().filter(|(crc, _, _)| *crc == crc32);
+        match (matches.next(), matches.next()) {
+            (Some((_, name, rom_type)), None) => Some((name, *rom_type)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_crc() {
+        assert_eq!(
+            RomType::from_crc(0xDBE3E7C7),
+            Some(("C64 kernal 901227-03", RomType::Rom2364))
+        );
+        assert_eq!(RomType::from_crc(0x12345678), None);
+    }
+
+    #[test]
+    fn test_user_roms() {
+        let user = parse_csv(
+            "crc32,name,type\n\
+             # Comment\n\
+             \n\
+             0x12345678, My ROM, 2316\n\
+             DBE3E7C7,Patched kernal,2364\n",
+        )
+        .unwrap();
+        assert_eq!(user.len(), 2);
+
+        let db = with_user_roms(user);
+        assert_eq!(identify(&db, 0x12345678)[0].rom_type, RomType::Rom2316);
+        let kernal = identify(&db, 0xDBE3E7C7);
+        assert_eq!(kernal.len(), 1);
+        assert_eq!(kernal[0].name, "Patched kernal");
+
+        assert!(parse_csv("1234,bad").is_err());
+        assert!(parse_csv("XYZ,bad,2364").is_err());
+        assert!(parse_csv("1234,bad,2716").is_err());
+    }
+}
@@ -6,6 +6,12 @@ use std::fmt;
 
 use crate::hardware::Port;
 
+/// Required USB clock frequency, in kHz
+pub const USB_CLOCK_KHZ: u32 = 48_000;
+
+/// Maximum deviation of the USB clock from [`USB_CLOCK_KHZ`], in kHz - 0.25%
+pub const USB_CLOCK_TOLERANCE_KHZ: u32 = 120;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum RomType {
     Rom2316,
@@ -74,6 +80,9 @@ impl RomType {
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum McuFamily {
     Stm32F4,
+    /// Not yet supported - there are no F7 variants.  Included so a port has
+    /// somewhere to start.
+    Stm32F7,
     Rp2350,
 }
 
@@ -87,6 +96,7 @@ impl McuFamily {
     pub fn try_from_str(s: &str) -> Option<Self> {
         match s.to_lowercase().as_str() {
             "f4" => Some(McuFamily::Stm32F4),
+            "f7" => Some(McuFamily::Stm32F7),
             "rp2350" => Some(McuFamily::Rp2350),
             _ => None,
         }
@@ -94,70 +104,87 @@ impl McuFamily {
 
     pub fn valid_pin_num(&self, pin: u8) -> bool {
         match self {
-            McuFamily::Stm32F4 => pin <= Self::MAX_STM_PIN_NUM,
+            McuFamily::Stm32F4 | McuFamily::Stm32F7 => pin <= Self::MAX_STM_PIN_NUM,
             McuFamily::Rp2350 => pin <= Self::MAX_RP2350_PIN_NUM,
         }
     }
 
     pub fn max_valid_addr_pin(&self) -> u8 {
         match self {
-            McuFamily::Stm32F4 => Self::MAX_STM_PIN_NUM - 2, // Top two reserved for X1/X2
+            McuFamily::Stm32F4 | McuFamily::Stm32F7 => Self::MAX_STM_PIN_NUM - 2, // Top two reserved for X1/X2
             McuFamily::Rp2350 => Self::MAX_RP2350_ADDR_CS_PIN_NUM,  // Any
         }
     }
 
     pub fn max_valid_addr_cs_pin(&self) -> u8 {
         match self {
-            McuFamily::Stm32F4 => Self::MAX_STM_PIN_NUM,
+            McuFamily::Stm32F4 | McuFamily::Stm32F7 => Self::MAX_STM_PIN_NUM,
             McuFamily::Rp2350 => Self::MAX_RP2350_ADDR_CS_PIN_NUM,
         }
     }
 
     pub fn max_valid_data_pin(&self) -> u8 {
         match self {
-            McuFamily::Stm32F4 => Self::MAX_STM_DATA_PIN_NUM,
+            McuFamily::Stm32F4 | McuFamily::Stm32F7 => Self::MAX_STM_DATA_PIN_NUM,
             McuFamily::Rp2350 => Self::MAX_RP2350_DATA_PIN_NUM,
         }
     }
 
     pub fn allowed_data_port(&self) -> Port {
         match self {
-            McuFamily::Stm32F4 => Port::A,
+            McuFamily::Stm32F4 | McuFamily::Stm32F7 => Port::A,
             McuFamily::Rp2350 => Port::Zero,
         }
     }
 
     pub fn allowed_addr_port(&self) -> Port {
         match self {
-            McuFamily::Stm32F4 => Port::C,
+            McuFamily::Stm32F4 | McuFamily::Stm32F7 => Port::C,
             McuFamily::Rp2350 => Port::Zero,
         }
     }
 
     pub fn allowed_cs_port(&self) -> Port {
         match self {
-            McuFamily::Stm32F4 => Port::C,
+            McuFamily::Stm32F4 | McuFamily::Stm32F7 => Port::C,
             McuFamily::Rp2350 => Port::Zero,
         }
     }
 
     pub fn allowed_sel_port(&self) -> Port {
         match self {
-            McuFamily::Stm32F4 => Port::B,
+            McuFamily::Stm32F4 | McuFamily::Stm32F7 => Port::B,
             McuFamily::Rp2350 => Port::Zero,
         }
     }
 
+    /// Whether sdrr-gen and the firmware support this family yet
+    pub fn is_supported(&self) -> bool {
+        match self {
+            McuFamily::Stm32F4 | McuFamily::Rp2350 => true,
+            McuFamily::Stm32F7 => false,
+        }
+    }
+
+    /// Frequency in MHz of the clock fed to the PLL - the HSI on the STM32s
+    /// and the XOSC on the RP2350
+    pub fn pll_input_mhz(&self) -> u32 {
+        match self {
+            McuFamily::Stm32F4 | McuFamily::Stm32F7 => 16,
+            McuFamily::Rp2350 => 12,
+        }
+    }
+
     pub fn valid_x1_pins(&self) -> Vec<u8> {
         match self {
-            McuFamily::Stm32F4 => vec![14],
+            McuFamily::Stm32F4 | McuFamily::Stm32F7 => vec![14],
             McuFamily::Rp2350 => vec![0,1,2,3,4,5,6,7,8,9,10,11,12,13,14,15],
         }
     }
 
     pub fn valid_x2_pins(&self) -> Vec<u8> {
         match self {
-            McuFamily::Stm32F4 => vec![15],
+            McuFamily::Stm32F4 | McuFamily::Stm32F7 => vec![15],
             McuFamily::Rp2350 => vec![0,1,2,3,4,5,6,7,8,9,10,11,12,13,14,15],
         }
     }
@@ -167,6 +194,7 @@ impl fmt::Display for McuFamily {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             McuFamily::Stm32F4 => write!(f, "F4"),
+            McuFamily::Stm32F7 => write!(f, "F7"),
             McuFamily::Rp2350 => write!(f, "RP2350"),
         }
     }
@@ -183,6 +211,17 @@ pub enum McuProcessor {
 }
 
 impl McuProcessor {
+    pub fn family(&self) -> McuFamily {
+        match self {
+            McuProcessor::F401BC
+            | McuProcessor::F401DE
+            | McuProcessor::F405
+            | McuProcessor::F411
+            | McuProcessor::F446 => McuFamily::Stm32F4,
+            McuProcessor::Rp2350 => McuFamily::Rp2350,
+        }
+    }
+
     pub fn vco_min_mhz(&self) -> u32 {
         match self {
             McuProcessor::F401BC => 192,
@@ -227,6 +266,33 @@ impl McuProcessor {
         }
     }
 
+    /// The highest overclocked frequency which has been tested as running
+    /// stably for this processor.  Beyond this, firmware may not boot.
+    pub fn overclock_tested_mhz(&self) -> u32 {
+        match self {
+            McuProcessor::F401BC => 120,
+            McuProcessor::F401DE => 120,
+            McuProcessor::F405 => 250,
+            McuProcessor::F411 => 150,
+            McuProcessor::F446 => 300,
+            McuProcessor::Rp2350 => 300,
+        }
+    }
+
+    /// The highest overclocked frequency any part of this processor is
+    /// believed to be able to run at.  The RP2350 needs its core voltage
+    /// raised well beyond the stock 1.1V to approach this.
+    pub fn overclock_max_mhz(&self) -> u32 {
+        match self {
+            McuProcessor::F401BC => 168,
+            McuProcessor::F401DE => 168,
+            McuProcessor::F405 => 400,
+            McuProcessor::F411 => 200,
+            McuProcessor::F446 => 400,
+            McuProcessor::Rp2350 => 540,
+        }
+    }
+
     /// Calculate PLL values for target frequency using HSI (16 MHz)
     /// Returns (PLLM, PLLN, PLLP, PLLQ) or None if frequency not achievable
     fn calculate_stm32_pll_hsi(
@@ -240,9 +306,8 @@ impl McuProcessor {
         }
 
         // HSI = 16 MHz, target VCO input = 2 MHz for best jitter
-        const HSI_MHZ: u32 = 16;
         const PLLM: u8 = 8; // 16/8 = 2 MHz VCO input
-        const VCO_IN_MHZ: u32 = HSI_MHZ / PLLM as u32;
+        let vco_in_mhz = self.family().pll_input_mhz() / PLLM as u32;
 
         // Try PLLP values: 2, 4, 6, 8
         for pllp in [2u8, 4, 6, 8] {
@@ -250,7 +315,7 @@ impl McuProcessor {
 
             // Check VCO frequency is in valid range
             if vco_mhz >= self.vco_min_mhz() && vco_mhz <= self.vco_max_mhz(overclock) {
-                let plln = vco_mhz / VCO_IN_MHZ;
+                let plln = vco_mhz / vco_in_mhz;
 
                 // Check PLLN is in valid range (50-432)
                 if (50..=432).contains(&plln) {
@@ -278,7 +343,7 @@ impl McuProcessor {
             return None;
         }
         
-        const XOSC_MHZ: u32 = 12;
+        let xosc_mhz = self.family().pll_input_mhz();
         const REFDIV: u8 = 1;  // Fixed for 12MHz
         
         // Try POSTDIV combinations (prefer higher PD1:PD2 ratios)
@@ -287,8 +352,8 @@ impl McuProcessor {
                 let vco_mhz = target_freq_mhz * pd1 as u32 * pd2 as u32;
                 
                 if vco_mhz >= self.vco_min_mhz() && vco_mhz <= self.vco_max_mhz(overclock) {
-                    let fbdiv = vco_mhz / XOSC_MHZ;
-                    if (16..=320).contains(&fbdiv) && (vco_mhz % XOSC_MHZ == 0) {
+                    let fbdiv = vco_mhz / xosc_mhz;
+                    if (16..=320).contains(&fbdiv) && (vco_mhz % xosc_mhz == 0) {
                         return Some((REFDIV, fbdiv as u16, pd1, pd2));
                     }
                 }
@@ -301,7 +366,7 @@ impl McuProcessor {
     fn generate_stm32_pll_defines(&self, target_freq_mhz: u32, overclock: bool) -> Option<String> {
         if let Some((m, n, p, q)) = self.calculate_stm32_pll_hsi(target_freq_mhz, overclock) {
             // Calculate intermediate values for comments
-            let hsi_mhz = 16;
+            let hsi_mhz = self.family().pll_input_mhz();
             let vco_input_mhz = hsi_mhz / m as u32;
             let fvco_mhz = vco_input_mhz * n as u32;
             let sysclk_mhz = fvco_mhz / p as u32;
@@ -329,20 +394,63 @@ impl McuProcessor {
     fn generate_rp2350_pll_defines(&self, target_freq_mhz: u32, overclock: bool) -> Option<String> {
         if let Some((refdiv, fbdiv, postdiv1, postdiv2)) = self.calculate_rp2350_pll_12mhz(target_freq_mhz, overclock) {
             // Calculate intermediate values for comments
-            const CLK_REF_MHZ: u32 = 12;
-            let vco_input_mhz = CLK_REF_MHZ / refdiv as u32;
+            let clk_ref_mhz = self.family().pll_input_mhz();
+            let vco_input_mhz = clk_ref_mhz / refdiv as u32;
             let fvco_mhz = vco_input_mhz * fbdiv as u32;
             let sysclk_mhz = fvco_mhz / (postdiv1 as u32 * postdiv2 as u32);
 
             Some(format!(
                 "//   CLK_REF={}MHz\n//   VCO_input={}MHz\n//   fVCO={}MHz\n//   SYSCLK={}MHz\n#define PLL_SYS_REFDIV    {}\n#define PLL_SYS_FBDIV     {}\n#define PLL_SYS_POSTDIV1  {}\n#define PLL_SYS_POSTDIV2  {}",
-                CLK_REF_MHZ, vco_input_mhz, fvco_mhz, sysclk_mhz, refdiv, fbdiv, postdiv1, postdiv2
+                clk_ref_mhz, vco_input_mhz, fvco_mhz, sysclk_mhz, refdiv, fbdiv, postdiv1, postdiv2
             ))
         } else {
             None
         }
     }
 
+    /// Returns the SYSCLK frequency, in MHz, actually produced by the PLL
+    /// configuration chosen for the target frequency, or None if the target
+    /// isn't achievable.  This may differ from the target if it can't be
+    /// represented exactly.
+    pub fn actual_sysclk_mhz(&self, target_freq_mhz: u32, overclock: bool) -> Option<u32> {
+        let (div, mul, postdiv1, postdiv2) = self.calculate_pll_hsi(target_freq_mhz, overclock)?;
+        let fvco_mhz = self.family().pll_input_mhz() / div as u32 * mul as u32;
+        let postdiv = match self {
+            McuProcessor::Rp2350 => postdiv1 as u32 * postdiv2 as u32,
+            _ => postdiv1 as u32,
+        };
+        Some(fvco_mhz / postdiv)
+    }
+
+    /// Returns the USB clock frequency, in kHz, produced by the PLL
+    /// configuration chosen for the target frequency (PLL48CLK, fVCO/PLLQ,
+    /// on the STM32F4), or None if the target isn't achievable.  The RP2350
+    /// clocks USB from its own PLL, so always returns None.
+    pub fn usb_clock_khz(&self, target_freq_mhz: u32, overclock: bool) -> Option<u32> {
+        match self {
+            McuProcessor::Rp2350 => None,
+            _ => {
+                let (m, n, _, q) = self.calculate_stm32_pll_hsi(target_freq_mhz, overclock)?;
+                let fvco_khz = self.family().pll_input_mhz() * 1000 / m as u32 * n as u32;
+                Some(fvco_khz / q as u32)
+            }
+        }
+    }
+
+    /// Returns whether the USB clock produced for the target frequency is
+    /// within USB's tolerance of 48MHz +/-0.25%.  PLLQ is chosen to get as
+    /// close to 48MHz as possible, but many SYSCLK frequencies can't also
+    /// produce a valid USB clock.  Always true for the RP2350, whose USB
+    /// clock is independent of SYSCLK.
+    pub fn usb_clock_valid(&self, target_freq_mhz: u32, overclock: bool) -> bool {
+        match self {
+            McuProcessor::Rp2350 => true,
+            _ => self
+                .usb_clock_khz(target_freq_mhz, overclock)
+                .is_some_and(|khz| khz.abs_diff(USB_CLOCK_KHZ) <= USB_CLOCK_TOLERANCE_KHZ),
+        }
+    }
+
     pub fn generate_pll_defines(&self, target_freq_mhz: u32, overclock: bool) -> Option<String> {
         match self {
             McuProcessor::Rp2350 => self.generate_rp2350_pll_defines(target_freq_mhz, overclock),
@@ -361,6 +469,64 @@ impl McuProcessor {
         }
     }
 
+    /// Returns every SYSCLK frequency, in whole MHz, which can be produced
+    /// by a valid combination of PLL dividers within the VCO limits, sorted
+    /// and deduplicated.
+    ///
+    /// The PLL input divider is fixed (PLLM on the STM32F4, REFDIV on the
+    /// RP2350), as it is when the PLL configuration is generated, so every
+    /// value returned is accepted by [`Self::is_frequency_valid`].
+    pub fn achievable_frequencies(&self, overclock: bool) -> Vec<u32> {
+        let mut freqs = Vec::new();
+        let pll_input_mhz = self.family().pll_input_mhz();
+
+        match self {
+            McuProcessor::Rp2350 => {
+                // 12 MHz XOSC, REFDIV 1
+                for fbdiv in 16..=320u32 {
+                    let vco_mhz = pll_input_mhz * fbdiv;
+                    for pd in 1..=7u32 {
+                        for pd2 in 1..=7u32 {
+                            if vco_mhz.is_multiple_of(pd * pd2) {
+                                freqs.push(vco_mhz / (pd * pd2));
+                            }
+                        }
+                    }
+                }
+            }
+            _ => {
+                // 16 MHz HSI, PLLM 8, so 2 MHz VCO input
+                for plln in 50..=432u32 {
+                    let vco_mhz = pll_input_mhz / 8 * plln;
+                    for pllp in [2u32, 4, 6, 8] {
+                        if vco_mhz.is_multiple_of(pllp) {
+                            freqs.push(vco_mhz / pllp);
+                        }
+                    }
+                }
+            }
+        }
+
+        freqs.sort();
+        freqs.dedup();
+        freqs.retain(|&freq| self.is_frequency_valid(freq, overclock));
+        freqs
+    }
+
+    /// Returns the achievable frequency closest to, but not above, the
+    /// target frequency, or the lowest achievable frequency if the target is
+    /// below all of them.
+    pub fn nearest_achievable_frequency(&self, target_freq_mhz: u32, overclock: bool) -> u32 {
+        let freqs = self.achievable_frequencies(overclock);
+        freqs
+            .iter()
+            .rev()
+            .find(|&&freq| freq <= target_freq_mhz)
+            .or(freqs.first())
+            .copied()
+            .expect("Internal error: no achievable frequencies")
+    }
+
     pub fn is_frequency_valid(&self, target_freq_mhz: u32, overclock: bool) -> bool {
         #[allow(clippy::match_single_binding)]
         match self {
@@ -464,7 +630,13 @@ impl McuVariant {
         // 96KB flash as requires:
         // - 64KB for total of 1 set of 4x16KB banked images
         // - 32KB for firmware
-        self.ram_kb() > 72 && self.flash_storage_kb() >= 96
+        self.ram_kb() > 64 + self.ram_overhead_kb() && self.flash_storage_kb() >= 96
+    }
+
+    /// RAM in KB the firmware needs in addition to any ROM images preloaded
+    /// to RAM - 4KB for the logging buffer and 4KB for everything else.
+    pub fn ram_overhead_kb(&self) -> usize {
+        8
     }
 
     pub fn supports_multi_rom_sets(&self) -> bool {
@@ -535,6 +707,7 @@ impl McuVariant {
     pub fn define_var_fam(&self) -> &str {
         match self.family() {
             McuFamily::Stm32F4 => "#define STM32F4        1",
+            McuFamily::Stm32F7 => "#define STM32F7        1",
             McuFamily::Rp2350 => "#define RP235X         1",
         }
     }
@@ -559,6 +732,24 @@ impl McuVariant {
             .generate_pll_defines(target_freq_mhz, overclock)
     }
 
+    /// Returns the SYSCLK frequency actually produced for the target frequency
+    pub fn actual_sysclk_mhz(&self, target_freq_mhz: u32, overclock: bool) -> Option<u32> {
+        self.processor()
+            .actual_sysclk_mhz(target_freq_mhz, overclock)
+    }
+
+    /// Returns the USB clock frequency, in kHz, produced for the target
+    /// frequency, if derived from the system PLL
+    pub fn usb_clock_khz(&self, target_freq_mhz: u32, overclock: bool) -> Option<u32> {
+        self.processor().usb_clock_khz(target_freq_mhz, overclock)
+    }
+
+    /// Returns whether the USB clock produced for the target frequency is
+    /// within tolerance
+    pub fn usb_clock_valid(&self, target_freq_mhz: u32, overclock: bool) -> bool {
+        self.processor().usb_clock_valid(target_freq_mhz, overclock)
+    }
+
     /// Used to pass into sdrr Makefile as VARIANT
     pub fn makefile_var(&self) -> &str {
         match self {
@@ -596,9 +787,15 @@ impl McuVariant {
     }
 }
 
+/// User selectable serving algorithms for single ROM and bank switched sets.
+///
+/// Values match `sdrr_serve_t` in `sdrr/include/config_base.h`, and
+/// `SdrrServe` in `sdrr-fw-parser`.  Multi-ROM sets always use
+/// `SERVE_ADDR_ON_ANY_CS`.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ServeAlg {
-    /// default
+    /// default - the firmware's default for single ROM sets,
+    /// `SERVE_DEFAULT_1_ROM`, which is currently the same as `AddrOnCs`
     Default,
 
     /// a
@@ -613,19 +810,30 @@ impl ServeAlg {
         match s.to_lowercase().as_str() {
             "default" => Some(ServeAlg::Default),
             "a" | "two_cs_one_addr" => Some(ServeAlg::TwoCsOneAddr),
-            "b" => Some(ServeAlg::AddrOnCs),
+            "b" | "addr_on_cs" => Some(ServeAlg::AddrOnCs),
             _ => None,
         }
     }
 
+    /// Returns the `sdrr_serve_t` value to use in generated C code.
+    /// `Default` uses the firmware's default, so follows any change to it.
     pub fn c_value(&self) -> &str {
         match self {
-            ServeAlg::Default => "SERVE_ADDR_ON_CS",
+            ServeAlg::Default => "SERVE_DEFAULT_1_ROM",
             ServeAlg::TwoCsOneAddr => "SERVE_TWO_CS_ONE_ADDR",
             ServeAlg::AddrOnCs => "SERVE_ADDR_ON_CS",
         }
     }
 
+    /// Returns the raw `sdrr_serve_t` value the firmware will report, as
+    /// read by `SdrrServe::from_u8` in `sdrr-fw-parser`.
+    pub fn to_u8(&self) -> u8 {
+        match self {
+            ServeAlg::TwoCsOneAddr => 0,
+            ServeAlg::Default | ServeAlg::AddrOnCs => 1,
+        }
+    }
+
     pub fn c_value_multi_rom_set(&self) -> &str {
         "SERVE_ADDR_ON_ANY_CS"
     }
@@ -651,3 +859,101 @@ impl CsLogic {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_achievable_frequencies() {
+        for processor in [
+            McuProcessor::F401BC,
+            McuProcessor::F401DE,
+            McuProcessor::F405,
+            McuProcessor::F411,
+            McuProcessor::F446,
+            McuProcessor::Rp2350,
+        ] {
+            for overclock in [false, true] {
+                let freqs = processor.achievable_frequencies(overclock);
+                assert!(!freqs.is_empty());
+                assert!(freqs.windows(2).all(|pair| pair[0] < pair[1]));
+                assert!(
+                    freqs
+                        .iter()
+                        .all(|&freq| processor.is_frequency_valid(freq, overclock))
+                );
+                if !overclock {
+                    assert_eq!(*freqs.last().unwrap(), processor.max_sysclk_mhz());
+                }
+            }
+        }
+
+        let freqs = McuProcessor::F446.achievable_frequencies(false);
+        assert!(freqs.contains(&170));
+        assert!(!freqs.contains(&181));
+        assert!(McuProcessor::F446.achievable_frequencies(true).contains(&250));
+    }
+
+    #[test]
+    fn test_nearest_achievable_frequency() {
+        let f446 = McuProcessor::F446;
+        assert_eq!(f446.nearest_achievable_frequency(170, false), 170);
+        assert_eq!(f446.nearest_achievable_frequency(181, false), 180);
+        assert_eq!(f446.nearest_achievable_frequency(400, false), 180);
+        let lowest = f446.achievable_frequencies(false)[0];
+        assert_eq!(f446.nearest_achievable_frequency(1, false), lowest);
+        for freq in [0, 100, 181, 250, 1000] {
+            let nearest = f446.nearest_achievable_frequency(freq, true);
+            assert!(f446.is_frequency_valid(nearest, true));
+        }
+    }
+
+    #[test]
+    fn test_actual_sysclk() {
+        assert_eq!(McuProcessor::F446.actual_sysclk_mhz(170, false), Some(170));
+        assert_eq!(McuProcessor::F446.actual_sysclk_mhz(181, false), None);
+        assert_eq!(McuProcessor::F411.actual_sysclk_mhz(100, false), Some(100));
+        assert_eq!(McuProcessor::Rp2350.actual_sysclk_mhz(150, false), Some(150));
+        assert_eq!(McuProcessor::Rp2350.actual_sysclk_mhz(250, true), Some(250));
+    }
+
+    #[test]
+    fn test_usb_clock() {
+        // 84MHz: fVCO 336MHz, PLLQ 7
+        assert_eq!(McuProcessor::F401DE.usb_clock_khz(84, false), Some(48_000));
+        assert!(McuProcessor::F401DE.usb_clock_valid(84, false));
+        // 100MHz: fVCO 200MHz, so the nearest PLLQ of 4 gives 50MHz
+        assert_eq!(McuProcessor::F411.usb_clock_khz(100, false), Some(50_000));
+        assert!(!McuProcessor::F411.usb_clock_valid(100, false));
+        // Overclocked beyond what the maximum PLLQ can divide down
+        assert!(!McuProcessor::F446.usb_clock_valid(400, true));
+        assert!(!McuProcessor::F446.usb_clock_valid(181, false));
+        assert_eq!(McuProcessor::Rp2350.usb_clock_khz(150, false), None);
+        assert!(McuProcessor::Rp2350.usb_clock_valid(150, false));
+    }
+
+    #[test]
+    fn test_unsupported_family() {
+        let family = McuFamily::try_from_str("F7").unwrap();
+        assert_eq!(family, McuFamily::Stm32F7);
+        assert!(!family.is_supported());
+        assert_eq!(family.pll_input_mhz(), McuFamily::Stm32F4.pll_input_mhz());
+        assert_eq!(family.allowed_data_port(), Port::A);
+        assert_eq!(family.to_string(), "F7");
+    }
+
+    #[test]
+    fn test_serve_alg() {
+        // Default is intentionally the same algorithm as AddrOnCs, but
+        // generated as the firmware's own default
+        assert_eq!(ServeAlg::Default.to_u8(), ServeAlg::AddrOnCs.to_u8());
+        assert_eq!(ServeAlg::Default.c_value(), "SERVE_DEFAULT_1_ROM");
+        assert_ne!(ServeAlg::Default.c_value(), ServeAlg::AddrOnCs.c_value());
+        assert_eq!(ServeAlg::TwoCsOneAddr.to_u8(), 0);
+
+        assert_eq!(ServeAlg::try_from_str("addr_on_cs"), Some(ServeAlg::AddrOnCs));
+        assert_eq!(ServeAlg::try_from_str("A"), Some(ServeAlg::TwoCsOneAddr));
+        assert_eq!(ServeAlg::try_from_str("c"), None);
+    }
+}
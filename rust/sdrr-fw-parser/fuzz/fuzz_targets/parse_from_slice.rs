@@ -0,0 +1,14 @@
+// Fuzz target for the SDRR firmware parser
+//
+// Run from sdrr-fw-parser/ with:
+//   cargo +nightly fuzz run parse_from_slice
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use sdrr_fw_parser::{STM32F4_FLASH_BASE, parse_from_slice};
+
+fuzz_target!(|data: &[u8]| {
+    // Errors are fine - panics are not
+    let _ = parse_from_slice(data, STM32F4_FLASH_BASE);
+});
@@ -6,19 +6,23 @@
 //!
 //! Structures used to represent the parsed SDRR firmware information
 
+use core::fmt;
 use deku::prelude::*;
 
-use crate::{ParseError, Parser, Reader};
+use crate::writer::Writer;
+use crate::parsing::SdrrRuntimeInfoHeader;
+use crate::{ErrorKind, ParseError, Parser, Reader};
+use crate::{SDRR_RUNTIME_INFO_FW_OFFSET, STM32F4_RAM_BASE};
 use crate::{
-    SdrrAddress, SdrrCsState, SdrrLogicalAddress, SdrrRomType, SdrrServe, SdrrMcuPort, McuLine,
-    McuStorage,
+    BootConfig, SdrrAddress, SdrrCsSet, SdrrCsState, SdrrLogicalAddress, SdrrRomType, SdrrServe,
+    SdrrMcuPort, McuLine, McuStorage,
 };
 
 #[cfg(not(feature = "std"))]
 use alloc::{format, string::String, vec, vec::Vec};
 
 /// Container for both the parsed firmware information and runtime information
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub struct Sdrr {
     pub flash: Option<SdrrInfo>,
     pub ram: Option<SdrrRuntimeInfo>,
@@ -28,7 +32,7 @@ pub struct Sdrr {
 /// from RAM.
 /// 
 /// Reflects `sdrr_runtime_info_t` from `sdrr/include/config_base.h`
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub struct SdrrRuntimeInfo {
     pub image_sel: u8,
     pub rom_set_index: u8,
@@ -43,7 +47,12 @@ pub struct SdrrRuntimeInfo {
 /// from the firmware file.
 ///
 /// Reflects `sdrr_info_t` from `sdrr/include/config_base.h`
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+///
+/// Two parses of the same firmware compare equal, and hash the same, so can
+/// be used to deduplicate firmware configurations.  ROM image data isn't
+/// held, so isn't compared, other than through
+/// [`rom_crc32`](Self::rom_crc32) for firmware which includes it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub struct SdrrInfo {
     // Core fields that are always present
     pub major_version: u16,
@@ -61,9 +70,12 @@ pub struct SdrrInfo {
     pub status_led_enabled: bool,
     pub boot_logging_enabled: bool,
     pub mco_enabled: bool,
+    /// Whether MCO2 is also enabled (STM32F4 only).  Always false before
+    /// v0.5.0, which didn't record it.
+    pub mco2_enabled: bool,
     pub rom_set_count: u8,
     pub count_rom_access: bool,
-    pub boot_config: [u8; 4],
+    pub boot_config: BootConfig,
 
     // Fields that might fail to parse
     pub build_date: Option<String>,
@@ -76,9 +88,181 @@ pub struct SdrrInfo {
 
     /// Extra information
     pub extra_info: Option<SdrrExtraInfo>,
+
+    /// CRC32 of the ROM set images, in ROM set order.  `None` for firmware
+    /// before v0.5.0.
+    pub rom_crc32: Option<u32>,
 }
 
 impl SdrrInfo {
+    /// Returns an iterator over every ROM in every set, yielding the set
+    /// index, the ROM's index within its set, and the ROM information.
+    ///
+    /// Does not allocate, so can be used in `no_std` environments.
+    ///
+    /// ```
+    /// use sdrr_fw_parser::{SdrrInfo, SdrrRomType};
+    ///
+    /// fn print_2364s(info: &SdrrInfo) {
+    ///     for (set, rom, rom_info) in info
+    ///         .iter_roms()
+    ///         .filter(|(_, _, rom_info)| rom_info.rom_type == SdrrRomType::Rom2364)
+    ///     {
+    ///         println!("Set {set} ROM {rom}: {:?}", rom_info.filename);
+    ///     }
+    /// }
+    /// ```
+    pub fn iter_roms(&self) -> SdrrRomIter<'_> {
+        SdrrRomIter {
+            rom_sets: &self.rom_sets,
+            set: 0,
+            rom: 0,
+        }
+    }
+
+    /// Returns the total number of ROMs across all sets.
+    pub fn rom_count(&self) -> usize {
+        self.rom_sets.iter().map(|set| set.roms.len()).sum()
+    }
+
+    /// Returns the RAM address of the live ROM access counter, in the SDRR
+    /// runtime info, if the firmware was built to count ROM accesses.
+    ///
+    /// Read the current count from a running device using
+    /// [`Parser::parse_ram`], or directly from this address.
+    pub fn access_count_address(&self) -> Option<u32> {
+        self.count_rom_access.then(|| {
+            STM32F4_RAM_BASE
+                + SDRR_RUNTIME_INFO_FW_OFFSET
+                + SdrrRuntimeInfoHeader::access_count_offset() as u32
+        })
+    }
+
+    /// Returns a single label for the MCU variant the firmware was built for,
+    /// such as `"F446RE"` or `"RP2350"`, combining [`Self::stm_line`] and
+    /// [`Self::stm_storage`].
+    ///
+    /// Returns `None` if the line and storage don't correspond to a variant
+    /// supported by SDRR.
+    pub fn stm_variant_name(&self) -> Option<&'static str> {
+        match (self.stm_line, self.stm_storage) {
+            (McuLine::F446, McuStorage::StorageC) => Some("F446RC"),
+            (McuLine::F446, McuStorage::StorageE) => Some("F446RE"),
+            (McuLine::F411, McuStorage::StorageC) => Some("F411RC"),
+            (McuLine::F411, McuStorage::StorageE) => Some("F411RE"),
+            (McuLine::F405, McuStorage::StorageG) => Some("F405RG"),
+            (McuLine::F401DE, McuStorage::StorageE) => Some("F401RE"),
+            (McuLine::F401BC, McuStorage::StorageB) => Some("F401RB"),
+            (McuLine::F401BC, McuStorage::StorageC) => Some("F401RC"),
+            (McuLine::Rp2350, _) => Some("RP2350"),
+            _ => None,
+        }
+    }
+
+    /// Performs structural self-consistency checks on the parsed firmware
+    /// information, returning a list of any inconsistencies found.
+    ///
+    /// Parsing succeeding doesn't guarantee the firmware is coherent - for
+    /// example, some firmware has shipped with the status LED enabled on
+    /// hardware which has no status LED pin.  Nothing here is treated as
+    /// fatal, so tools can choose to warn about the results, or use each
+    /// error's [`ParseError::severity`] to decide whether to proceed.
+    ///
+    /// An empty list means no problems were found.
+    pub fn validate(&self) -> Vec<ParseError> {
+        let mut errors = Vec::new();
+
+        if self.rom_set_count as usize != self.rom_sets.len() {
+            errors.push(ParseError::error(
+                "rom_set_count",
+                ErrorKind::Inconsistent,
+                format!(
+                    "Header says {} ROM sets, but {} were parsed",
+                    self.rom_set_count,
+                    self.rom_sets.len()
+                ),
+            ));
+        }
+
+        for (ii, rom_set) in self.rom_sets.iter().enumerate() {
+            let field = format!("rom_set[{}]", ii);
+
+            if rom_set.rom_count as usize != rom_set.roms.len() {
+                errors.push(ParseError::error(
+                    field.clone(),
+                    ErrorKind::Inconsistent,
+                    format!(
+                        "Set says {} ROMs, but {} were parsed",
+                        rom_set.rom_count,
+                        rom_set.roms.len()
+                    ),
+                ));
+            }
+
+            // Single ROM sets are stored at the MCU's image size, multi-ROM
+            // sets always at the full 64KB set size
+            let expected_size = if rom_set.roms.len() > 1 || self.stm_line == McuLine::Rp2350 {
+                65536
+            } else {
+                16384
+            };
+            if rom_set.size != expected_size {
+                errors.push(ParseError::warning(
+                    field.clone(),
+                    ErrorKind::Inconsistent,
+                    format!(
+                        "Size is {} bytes, expected {} bytes",
+                        rom_set.size, expected_size
+                    ),
+                ));
+            }
+
+            for (jj, rom) in rom_set.roms.iter().enumerate() {
+                if rom.rom_type.rom_size() > rom_set.size as usize {
+                    errors.push(ParseError::error(
+                        format!("{}.roms[{}]", field, jj),
+                        ErrorKind::OutOfBounds,
+                        format!(
+                            "{} ROM ({} bytes) is larger than its set ({} bytes)",
+                            rom.rom_type,
+                            rom.rom_type.rom_size(),
+                            rom_set.size
+                        ),
+                    ));
+                }
+            }
+        }
+
+        if self.status_led_enabled
+            && let Some(pins) = &self.pins
+            && (pins.status == 255 || pins.status_port == SdrrMcuPort::None)
+        {
+            errors.push(ParseError::warning(
+                "status_led_enabled",
+                ErrorKind::Inconsistent,
+                "Status LED is enabled, but the pin configuration has no status LED pin",
+            ));
+        }
+
+        if !self.boot_config.is_unset() {
+            errors.push(ParseError::warning(
+                "boot_config",
+                ErrorKind::UnsupportedValue,
+                format!("Boot config is {}", self.boot_config),
+            ));
+        }
+
+        errors
+    }
+
+    /// Serializes the `sdrr_info_t` header for this firmware.  Pointers are
+    /// set to where [`Writer`] lays out the rest of the metadata from the
+    /// default base flash address.  Use [`Writer`] directly to produce a
+    /// complete firmware image.
+    pub fn to_header_bytes(&self) -> Result<Vec<u8>, String> {
+        Writer::new(self).header_bytes()
+    }
+
     /// Demangles a byte from the physical pin representation to the logical
     /// representation which is served on D0-D7.  Use when looking up a byte
     /// from the ROM image data to get the "real" byte.
@@ -88,15 +272,7 @@ impl SdrrInfo {
             .as_ref()
             .ok_or("Pin configuration not available")?;
 
-        assert!(pins.data.len() == 8, "Expected 8 data pins");
-        let mut result = 0u8;
-        for (logic_bit, &phys_pin) in pins.data.iter().enumerate() {
-            assert!(phys_pin < 8, "Physical pin {} out of range", phys_pin);
-            if (byte & (1 << phys_pin)) != 0 {
-                result |= 1 << logic_bit;
-            }
-        }
-        Ok(result)
+        Ok(pins.demangle_byte(byte))
     }
 
     /// Takes a logical address and all chip select line states, and produces
@@ -104,15 +280,21 @@ impl SdrrInfo {
     /// image stored in firmware.  Use to get the address to index into the
     /// ROM data stored in the firmware, and then use `demangle_byte()` to
     /// turn into a logical byte.
-    #[allow(unused_variables)]
+    ///
+    /// Uses the ROM type and ROM count of the first ROM in the first set.  Use
+    /// [`Self::mangle_address_for_rom()`] for other sets.
     pub fn mangle_address(&self, addr: &SdrrLogicalAddress) -> Result<u32, String> {
-        let cs1 = addr.cs_set().cs1();
-        let cs2 = addr.cs_set().cs2();
-        let cs3 = addr.cs_set().cs3();
-        let x1 = addr.cs_set().x1();
-        let x2 = addr.cs_set().x2();
-        let addr = addr.addr();
+        self.mangle_address_for_rom(0, 0, addr)
+    }
 
+    /// As [`Self::mangle_address()`], but uses the ROM type of the given ROM
+    /// within the given set, and that set's ROM count.
+    pub fn mangle_address_for_rom(
+        &self,
+        set: u8,
+        rom: u8,
+        addr: &SdrrLogicalAddress,
+    ) -> Result<u32, String> {
         let pins = self
             .pins
             .as_ref()
@@ -121,120 +303,53 @@ impl SdrrInfo {
         if self.rom_sets.is_empty() {
             return Err("No ROM sets available".into());
         }
-
-        let mut pin_to_addr_map = [None; 16];
-        assert!(pins.addr.len() <= 16, "Expected up to 16 address pins");
-        for (addr_bit, &phys_pin) in pins.addr.iter().enumerate() {
-            if phys_pin < 16 {
-                pin_to_addr_map[phys_pin as usize] = Some(addr_bit);
-            }
-        }
-
-        let num_roms = self.rom_sets[0].rom_count as usize;
-        if num_roms > 1 {
-            assert!(
-                pins.x1 < 16 && pins.x2 < 16,
-                "X1 and X2 pins must be less than 16"
-            );
-            assert!(
-                pin_to_addr_map[pins.x1 as usize].is_none()
-                    && pin_to_addr_map[pins.x2 as usize].is_none(),
-                "X1 and X2 pins must not overlap with other address pins"
-            );
-            pin_to_addr_map[pins.x1 as usize] = Some(14);
-            pin_to_addr_map[pins.x2 as usize] = Some(15);
+        let rom_set = self
+            .rom_sets
+            .get(set as usize)
+            .ok_or_else(|| format!("ROM set {} not found", set))?;
+        if rom as usize >= rom_set.roms.len() {
+            return Err(format!("ROM {} not found in set {}", rom, set));
         }
 
-        let rom_type = self.rom_sets[0].roms[0].rom_type;
-        let addr_mask = match rom_type {
-            SdrrRomType::Rom2364 => {
-                assert!(pins.cs1_2364 < 16, "CS1 pin for 2364 must be less than 16");
-                pin_to_addr_map[pins.cs1_2364 as usize] = Some(13);
-                0x1FFF // 13-bit address
-            }
-            SdrrRomType::Rom2332 => {
-                assert!(pins.cs1_2332 < 16, "CS1 pin for 2332 must be less than 16");
-                assert!(pins.cs2_2332 < 16, "CS2 pin for 2332 must be less than 16");
-                pin_to_addr_map[pins.cs1_2332 as usize] = Some(13);
-                pin_to_addr_map[pins.cs2_2332 as usize] = Some(12);
-                0x0FFF // 12-bit address
-            }
-            SdrrRomType::Rom2316 => {
-                assert!(pins.cs1_2316 < 16, "CS1 pin for 2316 must be less than 16");
-                assert!(pins.cs2_2316 < 16, "CS2 pin for 2316 must be less than 16");
-                assert!(pins.cs3_2316 < 16, "CS3 pin for 2316 must be less than 16");
-                pin_to_addr_map[pins.cs1_2316 as usize] = Some(13);
-                pin_to_addr_map[pins.cs2_2316 as usize] = Some(11);
-                pin_to_addr_map[pins.cs3_2316 as usize] = Some(12);
-                0x07FF // 11-bit address
-            }
-        };
-
-        let overflow = addr & !addr_mask;
-        if overflow != 0 {
-            return Err(format!(
-                "Requested Address 0x{:08X} overflows the address space for ROM type {}",
-                addr, rom_type
-            ));
-        }
+        rom_set.mangle_address(pins, rom, addr)
+    }
 
-        let mut input_addr = addr & addr_mask;
-        match rom_type {
-            SdrrRomType::Rom2364 => {
-                if cs1 {
-                    input_addr |= 1 << 13;
-                }
-            }
-            SdrrRomType::Rom2332 => {
-                if cs1 {
-                    input_addr |= 1 << 13;
-                }
-                if let Some(cs2) = cs2 {
-                    if cs2 {
-                        input_addr |= 1 << 12;
-                    }
-                }
-            }
-            SdrrRomType::Rom2316 => {
-                if cs1 {
-                    input_addr |= 1 << 13;
-                }
-                if let Some(cs2) = cs2 {
-                    if cs2 {
-                        input_addr |= 1 << 12;
-                    }
-                }
-                if let Some(cs3) = cs3 {
-                    if cs3 {
-                        input_addr |= 1 << 11;
-                    }
-                }
-            }
+    /// Whether this firmware's hardware revision supports mangling and
+    /// demangling addresses, as the address lookup functions do.  This
+    /// requires the pin configuration, with every ROM's chip select lines,
+    /// and the X1/X2 lines for multi-ROM and bank switched sets, on the
+    /// address port's pins 0-15, alongside the address lines, and the data
+    /// lines on the data port's pins 0-7.
+    ///
+    /// Check this before calling [`Self::mangle_address()`] and friends, which
+    /// may panic if it returns false.
+    pub fn hw_rev_supports_mangling(&self) -> bool {
+        let Some(pins) = self.pins.as_ref() else {
+            return false;
         };
-
-        if num_roms > 1 {
-            if let Some(x1) = x1 {
-                if x1 {
-                    input_addr |= 1 << 14;
-                }
-            }
-            if let Some(x2) = x2 {
-                if x2 {
-                    input_addr |= 1 << 15;
-                }
-            }
-        }
-
-        let mut result = 0;
-        for (pin, item) in pin_to_addr_map.iter().enumerate() {
-            if let Some(addr_bit) = item {
-                if (input_addr & (1 << addr_bit)) != 0 {
-                    result |= 1 << pin;
-                }
-            }
+        let valid = |pin: u8| pin < 16;
+        if pins.data.len() != 8 || pins.data.iter().any(|&pin| pin >= 8) {
+            return false;
         }
 
-        Ok(result)
+        self.rom_sets.iter().all(|rom_set| {
+            let x_valid = rom_set.rom_count <= 1
+                || (valid(pins.x1)
+                    && valid(pins.x2)
+                    && !pins.addr.contains(&pins.x1)
+                    && !pins.addr.contains(&pins.x2));
+            x_valid
+                && rom_set.roms.iter().all(|rom| {
+                    let cs_lines: &[u8] = match rom.rom_type {
+                        SdrrRomType::Rom2364 => &[1],
+                        SdrrRomType::Rom2332 | SdrrRomType::Rom23128 => &[1, 2],
+                        SdrrRomType::Rom2316 => &[1, 2, 3],
+                    };
+                    cs_lines
+                        .iter()
+                        .all(|&which| pins.cs_pin(rom.rom_type, which).is_some_and(valid))
+                })
+        })
     }
 
     /// Read a range of bytes from a ROM set.
@@ -254,7 +369,9 @@ impl SdrrInfo {
             return Err("Read extends past ROM set data".into());
         }
 
-        let addr = rom_set.data_ptr + offset;
+        let addr = parser
+            .resolve_ptr(rom_set.data_ptr + offset, buf.len())
+            .map_err(|e| format!("{}", e))?;
         parser
             .reader
             .read(addr, buf)
@@ -273,28 +390,59 @@ impl SdrrInfo {
     }
 
     /// Read a single byte from a ROM image at the specified logical address.
+    ///
+    /// Logical addresses are mangled using the first ROM in the set.  Use
+    /// [`Self::read_rom_byte_demangled_for_rom()`] for other ROMs.
     pub async fn read_rom_byte_demangled(
         &mut self,
         parser: &mut Parser<impl Reader>,
         set: u8,
         addr: SdrrAddress,
     ) -> Result<u8, String> {
-        let byte = self.read_rom_byte_raw(parser, set, addr).await?;
+        self.read_rom_byte_demangled_for_rom(parser, set, 0, addr).await
+    }
+
+    /// As [`Self::read_rom_byte_demangled()`], but mangles logical addresses
+    /// using the ROM type of the given ROM within the set.
+    pub async fn read_rom_byte_demangled_for_rom(
+        &mut self,
+        parser: &mut Parser<impl Reader>,
+        set: u8,
+        rom: u8,
+        addr: SdrrAddress,
+    ) -> Result<u8, String> {
+        let byte = self.read_rom_byte_raw_for_rom(parser, set, rom, addr).await?;
 
         self.demangle_byte(byte)
     }
 
+    /// Read a single byte, as stored in the firmware, from a ROM set.
+    ///
+    /// Logical addresses are mangled using the first ROM in the set.  Use
+    /// [`Self::read_rom_byte_raw_for_rom()`] for other ROMs.
     pub async fn read_rom_byte_raw(
         &mut self,
         parser: &mut Parser<impl Reader>,
         set: u8,
         addr: SdrrAddress,
+    ) -> Result<u8, String> {
+        self.read_rom_byte_raw_for_rom(parser, set, 0, addr).await
+    }
+
+    /// As [`Self::read_rom_byte_raw()`], but mangles logical addresses using
+    /// the ROM type of the given ROM within the set.
+    pub async fn read_rom_byte_raw_for_rom(
+        &mut self,
+        parser: &mut Parser<impl Reader>,
+        set: u8,
+        rom: u8,
+        addr: SdrrAddress,
     ) -> Result<u8, String> {
         let physical_addr = match addr {
             SdrrAddress::Raw(raw_addr) => raw_addr,
             SdrrAddress::Logical(logical_addr) => {
                 // Mangle the logical address to get the physical address
-                logical_addr.mangle(self)?
+                self.mangle_address_for_rom(set, rom, &logical_addr)?
             }
         };
 
@@ -307,10 +455,40 @@ impl SdrrInfo {
     }
 }
 
+/// Iterator over every ROM in every set of an [`SdrrInfo`].
+///
+/// Created by [`SdrrInfo::iter_roms()`].  Yields `(set_index, rom_index,
+/// rom_info)` tuples.
+#[derive(Debug, Clone)]
+pub struct SdrrRomIter<'a> {
+    rom_sets: &'a [SdrrRomSet],
+    set: usize,
+    rom: usize,
+}
+
+impl<'a> Iterator for SdrrRomIter<'a> {
+    type Item = (u8, u8, &'a SdrrRomInfo);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let rom_set = self.rom_sets.get(self.set)?;
+            if let Some(rom_info) = rom_set.roms.get(self.rom) {
+                let item = (self.set as u8, self.rom as u8, rom_info);
+                self.rom += 1;
+                return Some(item);
+            }
+
+            // Move onto the next set
+            self.set += 1;
+            self.rom = 0;
+        }
+    }
+}
+
 /// Extra information about this One ROM
 ///
 /// Reflects `sdrr_extra_info` from `sdrr/include/config_base.h`
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub struct SdrrExtraInfo {
     /// Pointer to the RTT control block in RAM
     pub rtt_ptr: u32,
@@ -325,7 +503,7 @@ pub struct SdrrExtraInfo {
 /// Current maximum number of ROMs in a set is 3.
 ///
 /// Reflects `sdrr_rom_set_t` from `sdrr/include/config_base.h`
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub struct SdrrRomSet {
     /// Pointer to the ROM image data in the firmware.
     pub data_ptr: u32,
@@ -350,10 +528,260 @@ pub struct SdrrRomSet {
     pub multi_rom_cs1_state: SdrrCsState,
 }
 
+impl SdrrRomSet {
+    /// Returns the CS and X line states which select the given ROM within
+    /// this set, based on the CS configuration stored in the firmware.
+    ///
+    /// In multi-ROM sets CS1, X1 and X2 select ROMs 0, 1 and 2 respectively,
+    /// all sharing the set's CS1 active state.  In bank switched sets X1 and
+    /// X2 select the bank, with the jumper pull direction determining the
+    /// level of a closed jumper.
+    pub fn cs_set_selecting_rom(&self, pins: &SdrrPins, rom: u8) -> Result<SdrrCsSet, String> {
+        let rom_info = self
+            .roms
+            .get(rom as usize)
+            .ok_or_else(|| format!("ROM {} not found in set", rom))?;
+        let selected = rom_info.cs_set(true);
+        let (cs2, cs3) = (selected.cs2(), selected.cs3());
+
+        if self.rom_count == 1 {
+            return Ok(selected);
+        }
+
+        if self.serve == SdrrServe::AddrOnAnyCs {
+            let state = self.multi_rom_cs1_state;
+            let level = |selects: bool| {
+                if selects {
+                    state.active_level()
+                } else {
+                    state.inactive_level()
+                }
+            };
+            let cs1 = level(rom == 0).unwrap_or(false);
+            Ok(SdrrCsSet::new(cs1, cs2, cs3, level(rom == 1), level(rom == 2)))
+        } else {
+            let pulled_high = pins.x_jumper_pull == 1;
+            let x1 = Some(((rom & 1) != 0) == pulled_high);
+            let x2 = Some(((rom & 2) != 0) == pulled_high);
+            Ok(SdrrCsSet::new(selected.cs1(), cs2, cs3, x1, x2))
+        }
+    }
+
+    /// Reads this set's data from the firmware using the parser, and returns
+    /// the demangled logical image of the given ROM within it - see
+    /// [`Self::logical_image_from_data()`].
+    pub async fn logical_image(
+        &self,
+        parser: &mut Parser<impl Reader>,
+        pins: &SdrrPins,
+        rom: u8,
+    ) -> Result<Vec<u8>, String> {
+        // Check the set is within the firmware before allocating for it, in
+        // case its size is corrupt
+        let addr = parser
+            .resolve_ptr(self.data_ptr, self.size as usize)
+            .map_err(|e| format!("{}", e))?;
+        let mut data = vec![0u8; self.size as usize];
+        parser
+            .reader
+            .read(addr, &mut data)
+            .await
+            .map_err(|_| "Failed to read ROM data")?;
+
+        self.logical_image_from_data(pins, rom, &data)
+    }
+
+    /// Returns the demangled logical image of the given ROM within this set,
+    /// as it would be read from the original ROM chip, given the set's raw
+    /// (mangled) data.  The image is exactly the ROM type's size.
+    ///
+    /// The ROM is read with the CS and X line states which select it - see
+    /// [`Self::cs_set_selecting_rom()`].
+    pub fn logical_image_from_data(
+        &self,
+        pins: &SdrrPins,
+        rom: u8,
+        data: &[u8],
+    ) -> Result<Vec<u8>, String> {
+        if data.len() != self.size as usize {
+            return Err(format!(
+                "ROM set data is {} bytes, expected {} bytes",
+                data.len(),
+                self.size
+            ));
+        }
+
+        let cs_set = self.cs_set_selecting_rom(pins, rom)?;
+        let rom_type = self.roms[rom as usize].rom_type;
+
+        (0..rom_type.rom_size() as u32)
+            .map(|addr| {
+                let addr = SdrrLogicalAddress::new(addr, cs_set);
+                let physical = self.mangle_address(pins, rom, &addr)?;
+                data.get(physical as usize)
+                    .map(|&byte| pins.demangle_byte(byte))
+                    .ok_or_else(|| format!("Address 0x{:04X} is beyond the ROM set data", physical))
+            })
+            .collect()
+    }
+
+    // Turns a logical address into the address of the byte in this set's data,
+    // using the ROM type of the given ROM, and this set's ROM count.
+    #[allow(unused_variables)]
+    fn mangle_address(
+        &self,
+        pins: &SdrrPins,
+        rom: u8,
+        addr: &SdrrLogicalAddress,
+    ) -> Result<u32, String> {
+        let cs1 = addr.cs_set().cs1();
+        let cs2 = addr.cs_set().cs2();
+        let cs3 = addr.cs_set().cs3();
+        let x1 = addr.cs_set().x1();
+        let x2 = addr.cs_set().x2();
+        let addr = addr.addr();
+
+        let rom_info = self
+            .roms
+            .get(rom as usize)
+            .ok_or_else(|| format!("ROM {} not found in set", rom))?;
+
+        let mut pin_to_addr_map = [None; 16];
+        assert!(pins.addr.len() <= 16, "Expected up to 16 address pins");
+        for (addr_bit, &phys_pin) in pins.addr.iter().enumerate() {
+            if phys_pin < 16 {
+                pin_to_addr_map[phys_pin as usize] = Some(addr_bit);
+            }
+        }
+
+        let num_roms = self.rom_count as usize;
+        if num_roms > 1 {
+            assert!(
+                pins.x1 < 16 && pins.x2 < 16,
+                "X1 and X2 pins must be less than 16"
+            );
+            assert!(
+                pin_to_addr_map[pins.x1 as usize].is_none()
+                    && pin_to_addr_map[pins.x2 as usize].is_none(),
+                "X1 and X2 pins must not overlap with other address pins"
+            );
+            pin_to_addr_map[pins.x1 as usize] = Some(14);
+            pin_to_addr_map[pins.x2 as usize] = Some(15);
+        }
+
+        let rom_type = rom_info.rom_type;
+        let addr_mask = rom_info.effective_address_mask();
+        match rom_type {
+            SdrrRomType::Rom2364 => {
+                assert!(pins.cs1_2364 < 16, "CS1 pin for 2364 must be less than 16");
+                pin_to_addr_map[pins.cs1_2364 as usize] = Some(13);
+            }
+            SdrrRomType::Rom2332 => {
+                assert!(pins.cs1_2332 < 16, "CS1 pin for 2332 must be less than 16");
+                assert!(pins.cs2_2332 < 16, "CS2 pin for 2332 must be less than 16");
+                pin_to_addr_map[pins.cs1_2332 as usize] = Some(13);
+                pin_to_addr_map[pins.cs2_2332 as usize] = Some(12);
+            }
+            SdrrRomType::Rom2316 => {
+                assert!(pins.cs1_2316 < 16, "CS1 pin for 2316 must be less than 16");
+                assert!(pins.cs2_2316 < 16, "CS2 pin for 2316 must be less than 16");
+                assert!(pins.cs3_2316 < 16, "CS3 pin for 2316 must be less than 16");
+                pin_to_addr_map[pins.cs1_2316 as usize] = Some(13);
+                pin_to_addr_map[pins.cs2_2316 as usize] = Some(11);
+                pin_to_addr_map[pins.cs3_2316 as usize] = Some(12);
+            }
+            SdrrRomType::Rom23128 => {
+                // The 14 address lines come from the pin map above.  CE and
+                // OE are both driven by the CS1 state, and occupy the bits
+                // used by X1/X2 on 24-pin hardware, so there is no room for
+                // multi-ROM sets.
+                if num_roms > 1 {
+                    return Err("Multi-ROM sets not supported for 23128 ROM type".into());
+                }
+                assert!(pins.ce_23128 < 16, "CE pin for 23128 must be less than 16");
+                assert!(pins.oe_23128 < 16, "OE pin for 23128 must be less than 16");
+                pin_to_addr_map[pins.ce_23128 as usize] = Some(14);
+                pin_to_addr_map[pins.oe_23128 as usize] = Some(15);
+            }
+        }
+
+        let overflow = addr & !addr_mask;
+        if overflow != 0 {
+            return Err(format!(
+                "Requested Address 0x{:08X} overflows the address space for ROM type {}",
+                addr, rom_type
+            ));
+        }
+
+        let mut input_addr = addr & addr_mask;
+        match rom_type {
+            SdrrRomType::Rom2364 => {
+                if cs1 {
+                    input_addr |= 1 << 13;
+                }
+            }
+            SdrrRomType::Rom2332 => {
+                if cs1 {
+                    input_addr |= 1 << 13;
+                }
+                if let Some(cs2) = cs2 {
+                    if cs2 {
+                        input_addr |= 1 << 12;
+                    }
+                }
+            }
+            SdrrRomType::Rom2316 => {
+                if cs1 {
+                    input_addr |= 1 << 13;
+                }
+                if let Some(cs2) = cs2 {
+                    if cs2 {
+                        input_addr |= 1 << 12;
+                    }
+                }
+                if let Some(cs3) = cs3 {
+                    if cs3 {
+                        input_addr |= 1 << 11;
+                    }
+                }
+            }
+            SdrrRomType::Rom23128 => {
+                if cs1 {
+                    input_addr |= (1 << 14) | (1 << 15);
+                }
+            }
+        };
+
+        if num_roms > 1 {
+            if let Some(x1) = x1 {
+                if x1 {
+                    input_addr |= 1 << 14;
+                }
+            }
+            if let Some(x2) = x2 {
+                if x2 {
+                    input_addr |= 1 << 15;
+                }
+            }
+        }
+
+        let mut result = 0;
+        for (pin, item) in pin_to_addr_map.iter().enumerate() {
+            if let Some(addr_bit) = item {
+                if (input_addr & (1 << addr_bit)) != 0 {
+                    result |= 1 << pin;
+                }
+            }
+        }
+
+        Ok(result)
+    }
+}
+
 /// Information about a single ROM in an SDRR firmware
 ///
 /// Reflects `sdrr_rom_info_t` from `sdrr/include/config_base.h`
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub struct SdrrRomInfo {
     /// The type of the ROM
     pub rom_type: SdrrRomType,
@@ -371,6 +799,47 @@ pub struct SdrrRomInfo {
     pub filename: Option<String>,
 }
 
+impl SdrrRomInfo {
+    /// Returns the CS line levels which select (or, if `selected` is false,
+    /// deselect) this ROM, taking each line's polarity into account.  Use to
+    /// construct an [`SdrrLogicalAddress`] for this ROM.
+    ///
+    /// Lines which aren't used, or which this ROM type doesn't have, are
+    /// `None`, except for CS1, which is low if unused.  Deselecting only
+    /// drives CS1 inactive, as that is sufficient, leaving the other lines
+    /// active.  X1/X2 are always `None` - they are a property of the set.
+    pub fn cs_set(&self, selected: bool) -> SdrrCsSet {
+        let cs1 = if selected {
+            self.cs1_state.active_level()
+        } else {
+            self.cs1_state.inactive_level()
+        };
+        let cs2 = self
+            .rom_type
+            .supports_cs2()
+            .then(|| self.cs2_state.active_level())
+            .flatten();
+        let cs3 = self
+            .rom_type
+            .supports_cs3()
+            .then(|| self.cs3_state.active_level())
+            .flatten();
+        SdrrCsSet::new(cs1.unwrap_or(false), cs2, cs3, None, None)
+    }
+
+    /// Returns the mask of the logical address bits this ROM decodes - for
+    /// example 0x1FFF for a 2364.  Any address with bits set outside the
+    /// mask is beyond the end of the ROM.
+    pub fn effective_address_mask(&self) -> u32 {
+        self.rom_type.address_mask()
+    }
+
+    /// Returns the highest valid logical address in this ROM
+    pub fn max_address(&self) -> u32 {
+        self.rom_type.max_addr()
+    }
+}
+
 /// SDRR pin configuration
 ///
 /// All pin fields refer to the physical STM32 port pin number.
@@ -379,7 +848,31 @@ pub struct SdrrRomInfo {
 /// A pin value of 255 is used to indicate that the pin is not used.
 ///
 /// Reflects `sdrr_pins_t` from `sdrr/include/config_base.h`
-#[derive(Debug, DekuRead, DekuWrite, serde::Serialize, serde::Deserialize)]
+///
+/// To construct a pin configuration, for example to test code which
+/// consumes it, start from [`SdrrPins::default()`], which has every pin
+/// unused, and set the pins required using the `with_` methods:
+///
+/// ```
+/// use sdrr_fw_parser::{SdrrMcuPort, SdrrPins, SdrrRomType};
+///
+/// let pins = SdrrPins::default()
+///     .with_data_port(SdrrMcuPort::PortA)
+///     .with_data_pin(0, 7)
+///     .with_cs_pin(SdrrRomType::Rom2364, 1, 10);
+/// assert_eq!(pins.cs_pin(SdrrRomType::Rom2364, 1), Some(10));
+/// ```
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    Hash,
+    DekuRead,
+    DekuWrite,
+    serde::Serialize,
+    serde::Deserialize,
+)]
 pub struct SdrrPins {
     pub data_port: SdrrMcuPort,
     pub addr_port: SdrrMcuPort,
@@ -418,6 +911,16 @@ pub struct SdrrPins {
     pub status: u8,
 }
 
+/// The ports used by each set of pins, as returned by [`SdrrPins::ports()`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SdrrPorts {
+    pub data: SdrrMcuPort,
+    pub addr: SdrrMcuPort,
+    pub cs: SdrrMcuPort,
+    pub sel: SdrrMcuPort,
+    pub status: SdrrMcuPort,
+}
+
 impl SdrrPins {
     const SDRR_PINS_SIZE: usize = 64;
     pub(crate) const fn size() -> usize {
@@ -425,4 +928,284 @@ impl SdrrPins {
         // increase its size.
         Self::SDRR_PINS_SIZE
     }
+
+    /// Returns the ports used for the data, address, CS, image select and
+    /// status LED pins
+    pub fn ports(&self) -> SdrrPorts {
+        SdrrPorts {
+            data: self.data_port,
+            addr: self.addr_port,
+            cs: self.cs_port,
+            sel: self.sel_port,
+            status: self.status_port,
+        }
+    }
+
+    // Demangles a byte from the physical data pin representation to the
+    // logical one served on D0-D7.
+    pub(crate) fn demangle_byte(&self, byte: u8) -> u8 {
+        assert!(self.data.len() == 8, "Expected 8 data pins");
+        let mut result = 0u8;
+        for (logic_bit, &phys_pin) in self.data.iter().enumerate() {
+            assert!(phys_pin < 8, "Physical pin {} out of range", phys_pin);
+            if (byte & (1 << phys_pin)) != 0 {
+                result |= 1 << logic_bit;
+            }
+        }
+        result
+    }
+
+    /// Returns an iterator over the used data pins, yielding the data line
+    /// (Dx) and the port pin it is connected to.
+    pub fn data_pins(&self) -> impl Iterator<Item = (usize, u8)> + '_ {
+        used_pins(&self.data)
+    }
+
+    /// Returns an iterator over the used address pins, yielding the address
+    /// line (Ax) and the port pin it is connected to.
+    pub fn addr_pins(&self) -> impl Iterator<Item = (usize, u8)> + '_ {
+        used_pins(&self.addr)
+    }
+
+    /// Returns an iterator over the used image select pins, yielding the
+    /// select line (SELx) and the port pin it is connected to.
+    pub fn sel_pins(&self) -> impl Iterator<Item = (usize, u8)> {
+        let sel = [
+            self.sel0, self.sel1, self.sel2, self.sel3, self.sel4, self.sel5, self.sel6,
+        ];
+        sel.into_iter()
+            .enumerate()
+            .filter(|(_, pin)| *pin != 0xFF)
+    }
+
+    /// Returns the port pin for a ROM type's chip select line, or `None` if
+    /// the ROM type doesn't have that line or the pin isn't used.
+    ///
+    /// `which` is the chip select line number, 1-3.  For the 23128, which
+    /// has CE and OE rather than numbered chip selects, 1 is CE and 2 is OE.
+    pub fn cs_pin(&self, rom_type: SdrrRomType, which: u8) -> Option<u8> {
+        let pin = match (rom_type, which) {
+            (SdrrRomType::Rom2364, 1) => self.cs1_2364,
+            (SdrrRomType::Rom2332, 1) => self.cs1_2332,
+            (SdrrRomType::Rom2332, 2) => self.cs2_2332,
+            (SdrrRomType::Rom2316, 1) => self.cs1_2316,
+            (SdrrRomType::Rom2316, 2) => self.cs2_2316,
+            (SdrrRomType::Rom2316, 3) => self.cs3_2316,
+            (SdrrRomType::Rom23128, 1) => self.ce_23128,
+            (SdrrRomType::Rom23128, 2) => self.oe_23128,
+            _ => return None,
+        };
+        (pin != 0xFF).then_some(pin)
+    }
+}
+
+/// Every pin unused (0xFF), and every port [`SdrrMcuPort::None`].  The ROM
+/// pin count and jumper pull directions are also 0xFF, so must be set if
+/// used.
+impl Default for SdrrPins {
+    fn default() -> Self {
+        Self {
+            data_port: SdrrMcuPort::None,
+            addr_port: SdrrMcuPort::None,
+            cs_port: SdrrMcuPort::None,
+            sel_port: SdrrMcuPort::None,
+            status_port: SdrrMcuPort::None,
+            rom_pins: 0xFF,
+            data: vec![0xFF; 8],
+            addr: vec![0xFF; 16],
+            cs1_2364: 0xFF,
+            cs1_2332: 0xFF,
+            cs1_2316: 0xFF,
+            cs2_2332: 0xFF,
+            cs2_2316: 0xFF,
+            cs3_2316: 0xFF,
+            x1: 0xFF,
+            x2: 0xFF,
+            ce_23128: 0xFF,
+            oe_23128: 0xFF,
+            x_jumper_pull: 0xFF,
+            sel0: 0xFF,
+            sel1: 0xFF,
+            sel2: 0xFF,
+            sel3: 0xFF,
+            sel4: 0xFF,
+            sel5: 0xFF,
+            sel6: 0xFF,
+            sel_jumper_pull: 0xFF,
+            status: 0xFF,
+        }
+    }
+}
+
+/// Builder style setters, for constructing a pin configuration in code.
+impl SdrrPins {
+    /// Sets the port the data lines are on
+    pub fn with_data_port(mut self, port: SdrrMcuPort) -> Self {
+        self.data_port = port;
+        self
+    }
+
+    /// Sets the port the address lines are on
+    pub fn with_addr_port(mut self, port: SdrrMcuPort) -> Self {
+        self.addr_port = port;
+        self
+    }
+
+    /// Sets the port the chip select and X1/X2 lines are on
+    pub fn with_cs_port(mut self, port: SdrrMcuPort) -> Self {
+        self.cs_port = port;
+        self
+    }
+
+    /// Sets the port the image select lines are on
+    pub fn with_sel_port(mut self, port: SdrrMcuPort) -> Self {
+        self.sel_port = port;
+        self
+    }
+
+    /// Sets the number of pins on the ROM being emulated
+    pub fn with_rom_pins(mut self, rom_pins: u8) -> Self {
+        self.rom_pins = rom_pins;
+        self
+    }
+
+    /// Sets the port pin data line D`line` is connected to.
+    ///
+    /// Panics if `line` is not 0-7.
+    pub fn with_data_pin(mut self, line: usize, pin: u8) -> Self {
+        assert!(line < 8, "Data line D{} out of range", line);
+        self.data[line] = pin;
+        self
+    }
+
+    /// Sets the port pin address line A`line` is connected to.
+    ///
+    /// Panics if `line` is not 0-15.
+    pub fn with_addr_pin(mut self, line: usize, pin: u8) -> Self {
+        assert!(line < 16, "Address line A{} out of range", line);
+        self.addr[line] = pin;
+        self
+    }
+
+    /// Sets the port pin for a ROM type's chip select line.  `which` is
+    /// as for [`SdrrPins::cs_pin()`].
+    ///
+    /// Panics if the ROM type doesn't have that chip select line.
+    pub fn with_cs_pin(mut self, rom_type: SdrrRomType, which: u8, pin: u8) -> Self {
+        let field = match (rom_type, which) {
+            (SdrrRomType::Rom2364, 1) => &mut self.cs1_2364,
+            (SdrrRomType::Rom2332, 1) => &mut self.cs1_2332,
+            (SdrrRomType::Rom2332, 2) => &mut self.cs2_2332,
+            (SdrrRomType::Rom2316, 1) => &mut self.cs1_2316,
+            (SdrrRomType::Rom2316, 2) => &mut self.cs2_2316,
+            (SdrrRomType::Rom2316, 3) => &mut self.cs3_2316,
+            (SdrrRomType::Rom23128, 1) => &mut self.ce_23128,
+            (SdrrRomType::Rom23128, 2) => &mut self.oe_23128,
+            _ => panic!("ROM type {} has no chip select line {}", rom_type, which),
+        };
+        *field = pin;
+        self
+    }
+
+    /// Sets the port pins for the X1 and X2 lines, used by multi-ROM and bank
+    /// switched sets, and the direction closing their jumper pulls them (1
+    /// for high)
+    pub fn with_x_pins(mut self, x1: u8, x2: u8, jumper_pull: u8) -> Self {
+        self.x1 = x1;
+        self.x2 = x2;
+        self.x_jumper_pull = jumper_pull;
+        self
+    }
+
+    /// Sets the port pin image select line SEL`line` is connected to.
+    ///
+    /// Panics if `line` is not 0-6.
+    pub fn with_sel_pin(mut self, line: usize, pin: u8) -> Self {
+        let field = match line {
+            0 => &mut self.sel0,
+            1 => &mut self.sel1,
+            2 => &mut self.sel2,
+            3 => &mut self.sel3,
+            4 => &mut self.sel4,
+            5 => &mut self.sel5,
+            6 => &mut self.sel6,
+            _ => panic!("Image select line SEL{} out of range", line),
+        };
+        *field = pin;
+        self
+    }
+
+    /// Sets the direction closing an image select jumper pulls its line (1
+    /// for high)
+    pub fn with_sel_jumper_pull(mut self, jumper_pull: u8) -> Self {
+        self.sel_jumper_pull = jumper_pull;
+        self
+    }
+
+    /// Sets the port and pin of the status LED
+    pub fn with_status_pin(mut self, port: SdrrMcuPort, pin: u8) -> Self {
+        self.status_port = port;
+        self.status = pin;
+        self
+    }
+}
+
+// Filters out unused (0xFF) pins, keeping the index of the line each is for
+fn used_pins(pins: &[u8]) -> impl Iterator<Item = (usize, u8)> + '_ {
+    pins.iter()
+        .copied()
+        .enumerate()
+        .filter(|(_, pin)| *pin != 0xFF)
+}
+
+/// Renders the full pin mapping, one pin per line, skipping unused pins.
+impl fmt::Display for SdrrPins {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Data pin mapping:")?;
+        for (ii, pin) in self.data_pins() {
+            let pad = if ii < 10 { " " } else { "" };
+            writeln!(f, "  D{}: {}P{}:{}", ii, pad, self.data_port, pin)?;
+        }
+        writeln!(f)?;
+
+        writeln!(f, "Address pin mapping:")?;
+        for (ii, pin) in self.addr_pins() {
+            let pad = if ii < 10 { " " } else { "" };
+            writeln!(f, "  A{}: {}P{}:{}", ii, pad, self.addr_port, pin)?;
+        }
+        writeln!(f)?;
+
+        writeln!(f, "Chip select pins:")?;
+        let cs_pins = [
+            ("2364 CS1", self.cs1_2364),
+            ("2332 CS1", self.cs1_2332),
+            ("2332 CS2", self.cs2_2332),
+            ("2316 CS1", self.cs1_2316),
+            ("2316 CS2", self.cs2_2316),
+            ("2316 CS3", self.cs3_2316),
+            ("23128 CE", self.ce_23128),
+            ("23128 OE", self.oe_23128),
+            ("Multi X1", self.x1),
+            ("Multi X2", self.x2),
+            ("X1/2 Jumper Pull", self.x_jumper_pull),
+        ];
+        for (name, pin) in cs_pins.into_iter().filter(|(_, pin)| *pin != 0xFF) {
+            writeln!(f, "  {}: P{}:{}", name, self.cs_port, pin)?;
+        }
+        writeln!(f)?;
+
+        writeln!(f, "Image select pins:")?;
+        for (ii, pin) in self.sel_pins() {
+            writeln!(f, "  SEL{}: P{}:{}", ii, self.sel_port, pin)?;
+        }
+        writeln!(f, "  Pin jumper pull: {}", self.sel_jumper_pull)?;
+        writeln!(f)?;
+
+        writeln!(f, "Status LED pin:")?;
+        if self.status_port == SdrrMcuPort::None {
+            writeln!(f, "  Pin: None")
+        } else {
+            writeln!(f, "  Pin: P{}:{}", self.status_port, self.status)
+        }
+    }
 }
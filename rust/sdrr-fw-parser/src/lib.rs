@@ -36,34 +36,46 @@ use esp_println as _;
 
 /// Maximum SDRR firmware versions supported by this version of`sdrr-fw-parser`
 pub const MAX_VERSION_MAJOR: u16 = 0;
-pub const MAX_VERSION_MINOR: u16 = 4;
-pub const MAX_VERSION_PATCH: u16 = 1;
+pub const MAX_VERSION_MINOR: u16 = 5;
+pub const MAX_VERSION_PATCH: u16 = 0;
 
 // lib.rs - Public API and core traits
 pub mod info;
 mod parsing;
 pub mod readers;
+pub mod storage;
 pub mod types;
+pub mod writer;
 
 // Use alloc if no-std.
 #[cfg(not(feature = "std"))]
 extern crate alloc;
 
 use core::fmt;
+use deku::DekuContainerRead;
 #[allow(unused_imports)]
 use log::{debug, error, info, trace, warn};
 
-pub use info::{Sdrr, SdrrInfo, SdrrPins, SdrrRomInfo, SdrrRomSet, SdrrRuntimeInfo, SdrrExtraInfo};
+pub use info::{
+    Sdrr, SdrrInfo, SdrrPins, SdrrPorts, SdrrRomInfo, SdrrRomIter, SdrrRomSet, SdrrRuntimeInfo,
+    SdrrExtraInfo,
+};
+pub use storage::{SdrrInfoStorage, StorageError};
 pub use types::{
-    SdrrAddress, SdrrCsSet, SdrrCsState, SdrrLogicalAddress, SdrrRomType, SdrrServe, SdrrMcuPort,
-    McuLine, McuStorage,
+    BootConfig, SdrrAddress, SdrrCsSet, SdrrCsState, SdrrLogicalAddress, SdrrRomType, SdrrServe,
+    SdrrMcuPort, McuLine, McuStorage, format_hex_dump, format_hex_dump_row,
+    format_hex_dump_row_wrapped,
 };
 
 use crate::parsing::{parse_and_validate_header, parse_and_validate_runtime_info, SdrrInfoHeader, SdrrRuntimeInfoHeader};
+use crate::parsing::SdrrRomSetHeader;
 
 /// Offset from start of the firmware where the SDRR info header is located.
 ///
 /// The first 4 "magic" bytes are b"SDRR" (upper case).
+///
+/// This is the default.  Use [`Parser::with_info_offset`] or
+/// [`Parser::find_header`] for firmware with the header elsewhere.
 pub const SDRR_INFO_FW_OFFSET: u32 = 0x200;
 
 /// Offset from the start of RAM where the SDRR runtime info header is located.
@@ -71,15 +83,28 @@ pub const SDRR_INFO_FW_OFFSET: u32 = 0x200;
 /// The first 4 "magic" bytes are b"sdrr" (lower case).
 pub const SDRR_RUNTIME_INFO_FW_OFFSET: u32 = 0x0;
 
+/// Default number of bytes read at a time when reading strings from the
+/// firmware.  See [`Parser::with_string_read_size`].
+pub const DEFAULT_STRING_READ_SIZE: usize = 256;
+
+// Number of bytes read at a time when reading ROM data to verify its CRC32
+const CHECKSUM_READ_SIZE: usize = 1024;
+
 // Use std/no-std String and Vec types
 #[cfg(not(feature = "std"))]
 use alloc::{format, string::String, vec::Vec};
 
-// STM32F4 flash base address.  Required to find offset from pointers
-pub(crate) const STM32F4_FLASH_BASE: u32 = 0x08000000;
+/// STM32F4 flash base address.  Required to find offset from pointers, and
+/// the base address to use when reading STM32F4 firmware.
+pub const STM32F4_FLASH_BASE: u32 = 0x08000000;
 
-// STM32F4 RAM base address.  Required to find offset from pointers
-pub(crate) const STM32F4_RAM_BASE: u32 = 0x20000000;
+/// RP2350 flash base address.  Used instead of the STM32F4 flash base once the
+/// firmware is detected as being for the RP2350.
+pub const RP2350_FLASH_BASE: u32 = 0x10000000;
+
+/// STM32F4 RAM base address.  Required to find offset from pointers, and
+/// the base address to use when reading a dump of STM32F4 RAM.
+pub const STM32F4_RAM_BASE: u32 = 0x20000000;
 
 /// Trait for reading firmware data from a source.
 ///
@@ -153,6 +178,13 @@ pub trait Reader {
     /// to change.
     fn update_base_address(&mut self, new_base: u32);
 
+    /// Returns the length of the firmware in bytes, from the base address, if
+    /// known.  Used by the parser to check pointers before reading through
+    /// them.  The default implementation returns `None`.
+    fn firmware_len(&self) -> Option<u32> {
+        None
+    }
+
 }
 
 /// Parser for Software Defined Retro ROM (SDRR) firmware images.
@@ -212,6 +244,11 @@ pub struct Parser<R: Reader> {
     reader: R,
     base_flash_address: u32,
     base_ram_address: u32,
+    version_policy: VersionPolicy,
+    strings: parsing::StringOptions,
+    info_offset: u32,
+    firmware_len: Option<u32>,
+    verify_checksum: bool,
 }
 
 impl<R: Reader> Parser<R> {
@@ -238,6 +275,11 @@ impl<R: Reader> Parser<R> {
             reader,
             base_flash_address: STM32F4_FLASH_BASE,
             base_ram_address: STM32F4_RAM_BASE,
+            version_policy: VersionPolicy::default(),
+            strings: parsing::StringOptions::default(),
+            info_offset: SDRR_INFO_FW_OFFSET,
+            firmware_len: None,
+            verify_checksum: true,
         }
     }
 
@@ -256,26 +298,257 @@ impl<R: Reader> Parser<R> {
             reader,
             base_flash_address,
             base_ram_address,
+            version_policy: VersionPolicy::default(),
+            strings: parsing::StringOptions::default(),
+            info_offset: SDRR_INFO_FW_OFFSET,
+            firmware_len: None,
+            verify_checksum: true,
         }
     }
 
-    // Retrieve the SDRR info header from the firmware.
-    async fn retrieve_header(&mut self) -> Result<SdrrInfoHeader, String> {
-        // Try to find SDRR info at standard location
-        let sdrr_info_addr = self.base_flash_address + SDRR_INFO_FW_OFFSET;
+    /// Set how firmware newer than this parser supports is handled.  The
+    /// default is [`VersionPolicy::Strict`].
+    pub fn set_version_policy(&mut self, version_policy: VersionPolicy) {
+        self.version_policy = version_policy;
+    }
+
+    /// Set whether strings - the build date, hardware revision and ROM
+    /// filenames - are read from the firmware.  The default is `true`.
+    ///
+    /// Skipping strings reduces the number of reads made, and, for
+    /// [`Parser::parse_into`], the storage used.
+    pub fn with_strings(mut self, with_strings: bool) -> Self {
+        self.strings.enabled = with_strings;
+        self
+    }
+
+    /// Set how many bytes are read at a time when reading strings.  The
+    /// default is [`DEFAULT_STRING_READ_SIZE`].
+    ///
+    /// Each string is read in blocks of this size until its NUL terminator
+    /// is found, so a larger size means fewer reads - worthwhile where each
+    /// read is a slow round-trip, such as over SWD - at the cost of reading
+    /// some bytes beyond the end of most strings.  Strings are still limited
+    /// to 1KB.
+    pub fn with_string_read_size(mut self, read_size: usize) -> Self {
+        self.strings.read_size = read_size;
+        self
+    }
+
+    /// Set whether strings must be valid UTF-8.  The default is `false`.
+    ///
+    /// By default, a string which isn't valid UTF-8 - such as a ROM filename
+    /// containing Latin-1 characters - is decoded as Latin-1, and a warning
+    /// [`ParseError`] is recorded.  As Latin-1 maps each byte to the
+    /// character with the same value, the original bytes can be recovered
+    /// from the string.  With strict UTF-8, the string is `None` instead.
+    pub fn with_strict_utf8(mut self, strict_utf8: bool) -> Self {
+        self.strings.strict_utf8 = strict_utf8;
+        self
+    }
+
+    /// Set whether the ROM data is checked against the CRC32 in the SDRR info
+    /// header.  The default is `true`.
+    ///
+    /// Checking reads all of the ROM set images, which may be slow where each
+    /// read is a slow round-trip, such as over SWD.  A mismatch is recorded
+    /// as an [`ErrorKind::ChecksumMismatch`] [`ParseError`].  Firmware before
+    /// v0.5.0 has no CRC32, so is never checked.
+    pub fn verify_checksum(mut self, verify: bool) -> Self {
+        self.verify_checksum = verify;
+        self
+    }
+
+    /// Set the offset of the SDRR info header from the base flash address.
+    /// The default is [`SDRR_INFO_FW_OFFSET`].
+    ///
+    /// Use this for firmware built with a custom linker script, which places
+    /// the header elsewhere.  If the offset isn't known, use
+    /// [`Parser::find_header`].
+    pub fn with_info_offset(mut self, offset: u32) -> Self {
+        self.info_offset = offset;
+        self
+    }
+
+    /// Set the length of the firmware in bytes, from the base flash address.
+    /// By default, the reader's [`Reader::firmware_len`] is used.
+    ///
+    /// When the length is known, pointers read from the firmware are checked
+    /// to be within it before being read through, so a corrupt pointer is
+    /// reported as an [`ErrorKind::OutOfBounds`] [`ParseError`], rather than
+    /// as a failed, and possibly slow, read.
+    pub fn with_firmware_len(mut self, len: u32) -> Self {
+        self.firmware_len = Some(len);
+        self
+    }
+
+    /// Returns the length of the firmware in bytes, if known - see
+    /// [`Parser::with_firmware_len`].
+    pub fn firmware_len(&self) -> Option<u32> {
+        self.firmware_len.or_else(|| self.reader.firmware_len())
+    }
+
+    /// Returns the underlying reader, for making custom reads between or
+    /// after parses - for example, polling runtime info over the same probe
+    /// connection the firmware was parsed through.
+    pub fn reader_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// Consumes the parser, returning the underlying reader.
+    pub fn into_reader(self) -> R {
+        self.reader
+    }
+
+    /// Check that `needed` bytes from `ptr` are within the firmware, before
+    /// reading them.  Returns the address to read from, or an
+    /// [`ErrorKind::InvalidPointer`] or [`ErrorKind::OutOfBounds`] error.
+    ///
+    /// The end of the firmware is only checked if its length is known - see
+    /// [`Parser::with_firmware_len`].
+    pub fn resolve_ptr(&self, ptr: u32, needed: usize) -> Result<u32, ParseError> {
+        self.flash()
+            .resolve_ptr(ptr, needed)
+            .map_err(|e| ParseError::from_read("Pointer", e))
+    }
+
+    // Where the firmware is, for checking pointers
+    fn flash(&self) -> parsing::Flash {
+        parsing::Flash {
+            base: self.base_flash_address,
+            len: self.firmware_len(),
+        }
+    }
+
+    /// Returns the offset of the SDRR info header from the base flash
+    /// address, as set by [`Parser::with_info_offset`] or found by
+    /// [`Parser::find_header`].
+    pub fn info_offset(&self) -> u32 {
+        self.info_offset
+    }
+
+    /// Searches for the SDRR info header, for firmware where it isn't at the
+    /// expected offset.
+    ///
+    /// The current offset (by default [`SDRR_INFO_FW_OFFSET`]) is tried
+    /// first.  Then each 4 byte aligned offset within `search_range` (offsets
+    /// from the base flash address) is checked for the `SDRR` magic bytes,
+    /// and the first which is followed by a valid header is used.
+    ///
+    /// Returns the offset found, which is used by subsequent parsing, or
+    /// [`ParseFirmwareError::HeaderNotFound`] if no valid header was found.
+    pub async fn find_header(
+        &mut self,
+        search_range: core::ops::Range<u32>,
+    ) -> Result<u32, ParseFirmwareError> {
+        if self.retrieve_header().await.is_ok() {
+            return Ok(self.info_offset);
+        }
+
+        let start = search_range.start.next_multiple_of(4);
+        for offset in (start..search_range.end).step_by(4) {
+            if self.header_valid_at(offset).await {
+                debug!("Found SDRR info header at offset 0x{:X}", offset);
+                self.info_offset = offset;
+                return Ok(offset);
+            }
+        }
+
+        Err(ParseFirmwareError::HeaderNotFound {
+            start: search_range.start,
+            end: search_range.end,
+        })
+    }
+
+    /// Searches for every SDRR info header in the firmware, for images
+    /// containing more than one, such as A/B update schemes.
+    ///
+    /// Offsets from the base flash address, from 0 up to `end`, are checked
+    /// every `step` bytes (rounded up to a multiple of 4) for the `SDRR` magic
+    /// bytes.  As these could appear by chance, such as within ROM data, an
+    /// offset is only returned if a valid header, of a supported version,
+    /// follows.
+    ///
+    /// Returns the offsets found, in order.  Use [`Parser::parse_at`] to parse
+    /// each of them.  The offset used by [`Parser::parse_flash`] is unchanged.
+    pub async fn find_all_headers(&mut self, step: u32, end: u32) -> Vec<u32> {
+        let step = step.max(1).next_multiple_of(4);
+        let mut offsets = Vec::new();
+        for offset in (0..end).step_by(step as usize) {
+            if self.header_valid_at(offset).await {
+                debug!("Found SDRR info header at offset 0x{:X}", offset);
+                offsets.push(offset);
+            }
+        }
+        offsets
+    }
+
+    /// Parse the SDRR info header at `offset` from the base flash address,
+    /// and the metadata it points to, as [`Parser::parse_flash`] does.
+    ///
+    /// The offset used by subsequent calls to [`Parser::parse_flash`] is
+    /// unchanged - use [`Parser::with_info_offset`] to change it.
+    pub async fn parse_at(&mut self, offset: u32) -> Result<SdrrInfo, ParseFirmwareError> {
+        let default_offset = self.info_offset;
+        self.info_offset = offset;
+        let result = self.parse_flash().await;
+        self.info_offset = default_offset;
+        result
+    }
+
+    // Whether there is a valid SDRR info header at `offset`.  Checks the
+    // magic bytes first, to avoid reading the whole header at most offsets.
+    //
+    // As well as parsing, and being a supported version, the header must
+    // have ROM sets, and its ROM sets and pins pointers must be within flash,
+    // as the magic bytes followed by zeros would otherwise be accepted.
+    async fn header_valid_at(&mut self, offset: u32) -> bool {
+        let mut magic = [0u8; 4];
+        if self.reader.read(self.base_flash_address + offset, &mut magic).await.is_err()
+            || &magic != b"SDRR"
+        {
+            return false;
+        }
+
+        let default_offset = self.info_offset;
+        self.info_offset = offset;
+        let header = self.retrieve_header().await;
+        self.info_offset = default_offset;
+
+        let Ok((header, _)) = header else {
+            return false;
+        };
+        let flash = parsing::Flash {
+            base: match header.stm_line {
+                McuLine::Rp2350 => RP2350_FLASH_BASE,
+                _ => self.base_flash_address,
+            },
+            len: self.firmware_len(),
+        };
+        header.rom_set_count > 0
+            && flash.resolve_ptr(header.rom_sets_ptr, SdrrRomSetHeader::size()).is_ok()
+            && flash.resolve_ptr(header.pins_ptr, SdrrPins::size()).is_ok()
+    }
+
+    // Retrieve the SDRR info header from the firmware, plus any version
+    // warning.
+    async fn retrieve_header(
+        &mut self,
+    ) -> Result<(SdrrInfoHeader, Option<String>), ParseFirmwareError> {
+        let sdrr_info_addr = self.base_flash_address + self.info_offset;
 
         // Read the header
         let mut header_buf = [0u8; SdrrInfoHeader::size()];
         self.reader
             .read(sdrr_info_addr, &mut header_buf)
             .await
-            .map_err(|_| "Failed to read SDRR header")?;
+            .map_err(|_| ParseFirmwareError::HeaderReadFailed)?;
 
         // Parse and validate header using the helper
-        parse_and_validate_header(&header_buf)
+        parse_and_validate_header(&header_buf, self.version_policy)
     }
 
-    async fn retrieve_runtime_header(&mut self) -> Result<SdrrRuntimeInfoHeader, String> {
+    async fn retrieve_runtime_header(&mut self) -> Result<SdrrRuntimeInfoHeader, ParseFirmwareError> {
         // Try to find SDRR runtime info at standard location
         let sdrr_runtime_info_addr = self.base_ram_address + SDRR_RUNTIME_INFO_FW_OFFSET;
 
@@ -284,7 +557,7 @@ impl<R: Reader> Parser<R> {
         self.reader
             .read(sdrr_runtime_info_addr, &mut runtime_buf)
             .await
-            .map_err(|_| "Failed to read SDRR runtime info")?;
+            .map_err(|_| ParseFirmwareError::RuntimeInfoReadFailed)?;
         // Parse and validate runtime info using the helper
         parse_and_validate_runtime_info(&runtime_buf)
     }
@@ -292,13 +565,11 @@ impl<R: Reader> Parser<R> {
     /// Function to do a brief check whether this is an SDRR device.
     ///
     /// Returns:
-    /// - `true` if the SDRR header was found and is valid
-    /// - `false` if the header was not found (or an error occured)
-    pub async fn detect(&mut self) -> bool {
-        match self.retrieve_header().await {
-            Ok(_header) => true,
-            Err(_) => false,
-        }
+    /// - `Ok(())` if the SDRR header was found and is valid
+    /// - `Err` with the reason if the header was not found, couldn't be read,
+    ///   or is for an unsupported version
+    pub async fn detect(&mut self) -> Result<(), ParseFirmwareError> {
+        self.retrieve_header().await.map(|_| ())
     }
 
     /// Parses both flash and RAM
@@ -347,9 +618,10 @@ impl<R: Reader> Parser<R> {
     /// # Returns
     ///
     /// Returns `Ok(SdrrInfo)` if the header was found and core fields parsed successfully.
-    /// Returns `Err` if:
+    /// Returns a [`ParseFirmwareError`] if:
     /// - SDRR magic bytes not found at expected location
-    /// - Version is newer than this parser supports
+    /// - Version is newer than this parser supports (unless a lenient
+    ///   [`VersionPolicy`] is set, and only the minor/patch version is newer)
     /// - Critical header fields are corrupted
     ///
     /// # Example
@@ -377,48 +649,51 @@ impl<R: Reader> Parser<R> {
     /// }
     /// # Ok::<(), Box<dyn std::error::Error>>(())
     /// ```
-    pub async fn parse_flash(&mut self) -> Result<SdrrInfo, String> {
+    pub async fn parse_flash(&mut self) -> Result<SdrrInfo, ParseFirmwareError> {
         // Parse and validate header using the helper
-        let header = self.retrieve_header().await?;
+        let (header, version_warning) = self.retrieve_header().await?;
 
         // Update our base address based on the header - before this we don't
         // need to have the correct base_flash_address set.  Base RAM is the
         // same.
         if header.stm_line == McuLine::Rp2350 {
-            self.base_flash_address = 0x10000000; // RP2350 flash base address
+            self.base_flash_address = RP2350_FLASH_BASE;
             self.reader.update_base_address(self.base_flash_address);
         }
 
         let mut parse_errors = Vec::new();
+        if let Some(warning) = version_warning {
+            parse_errors.push(ParseError::warning(
+                "Version",
+                ErrorKind::UnsupportedVersion,
+                warning,
+            ));
+        }
 
         // Parse strings with error collection
-        let build_date = match self.read_string_at_ptr(header.build_date_ptr).await {
-            Ok(s) => Some(s),
-            Err(e) => {
-                parse_errors.push(ParseError::new("Build Date", e));
-                None
-            }
-        };
+        let mut build_date = None;
+        let mut hw_rev = None;
+        if self.strings.enabled {
+            let flash = self.flash();
+            let strings = self.strings;
+            let reader = &mut self.reader;
+            let errors = &mut parse_errors;
 
-        let hw_rev = match self.read_string_at_ptr(header.hw_rev_ptr).await {
-            Ok(s) => Some(s),
-            Err(e) => {
-                parse_errors.push(ParseError::new("Hardware Revision", e));
-                None
-            }
-        };
+            let result = parsing::read_string_at_ptr(reader, header.build_date_ptr, flash, strings);
+            build_date = parsing::record_string_read("Build Date", result.await, errors);
+
+            let result = parsing::read_string_at_ptr(reader, header.hw_rev_ptr, flash, strings);
+            hw_rev = parsing::record_string_read("Hardware Revision", result.await, errors);
+        }
 
         // Parse extra info
-        let extra_info = match parsing::read_extra_info(
-            &mut self.reader,
-            header.extra_ptr,
-            self.base_flash_address,
-        )
-        .await
+        let flash = self.flash();
+        let extra_info = match parsing::read_extra_info(&mut self.reader, header.extra_ptr, flash)
+            .await
         {
             Ok(info) => Some(info),
             Err(e) => {
-                parse_errors.push(ParseError::new("Extra Info", e));
+                parse_errors.push(ParseError::from_read("Extra Info", e));
                 None
             }
         };
@@ -428,27 +703,49 @@ impl<R: Reader> Parser<R> {
             &mut self.reader,
             header.rom_sets_ptr,
             header.rom_set_count,
-            self.base_flash_address,
+            flash,
             header.boot_logging_enabled != 0,
+            self.strings,
+            &mut parse_errors,
         )
         .await
         {
             Ok(sets) => sets,
             Err(e) => {
-                parse_errors.push(ParseError::new("ROM Sets", e));
+                parse_errors.push(ParseError::from_read("ROM Sets", e));
                 Vec::new()
             }
         };
 
+        // Check the ROM data against the CRC32, if the firmware has one, and
+        // all of the ROM sets were parsed
+        let rom_crc32 = parsing::version_has_rom_crc32(header.major_version, header.minor_version)
+            .then_some(header.rom_crc32);
+        if let Some(expected) = rom_crc32
+            && self.verify_checksum
+            && rom_sets.len() == header.rom_set_count as usize
+        {
+            let result =
+                parsing::rom_sets_crc32(&mut self.reader, &rom_sets, CHECKSUM_READ_SIZE).await;
+            match result {
+                Ok(actual) if actual != expected => parse_errors.push(ParseError::error(
+                    "ROM Data",
+                    ErrorKind::ChecksumMismatch,
+                    format!("CRC32 0x{:08X} doesn't match header CRC32 0x{:08X}", actual, expected),
+                )),
+                Ok(_) => (),
+                Err(e) => parse_errors.push(ParseError::from_read("ROM Data", e)),
+            }
+        }
+
         // Parse pins
-        let pins =
-            match parsing::read_pins(&mut self.reader, header.pins_ptr, self.base_flash_address).await {
-                Ok(p) => Some(p),
-                Err(e) => {
-                    parse_errors.push(ParseError::new("Pins", e));
-                    None
-                }
-            };
+        let pins = match parsing::read_pins(&mut self.reader, header.pins_ptr, flash).await {
+            Ok(p) => Some(p),
+            Err(e) => {
+                parse_errors.push(ParseError::from_read("Pins", e));
+                None
+            }
+        };
  
         Ok(SdrrInfo {
             major_version: header.major_version,
@@ -468,17 +765,113 @@ impl<R: Reader> Parser<R> {
             status_led_enabled: header.status_led_enabled != 0,
             boot_logging_enabled: header.boot_logging_enabled != 0,
             mco_enabled: header.mco_enabled != 0,
+            mco2_enabled: header.mco2_enabled != 0,
             rom_set_count: header.rom_set_count,
             count_rom_access: header.count_rom_access != 0,
             rom_sets,
             pins,
-            boot_config: header.boot_config,
+            boot_config: header.boot_config.into(),
             parse_errors,
             extra_info,
+            rom_crc32,
         })
     }
 
-    pub async fn parse_ram(&mut self) -> Result<SdrrRuntimeInfo, String> {
+    /// Parse SDRR metadata from the firmware into caller provided, fixed
+    /// capacity, storage.
+    ///
+    /// This is an alternative to [`Parser::parse_flash`] for bootloaders and
+    /// other environments which shouldn't allocate.  It does not build any
+    /// `Vec`s or `String`s - see [`storage`] for what is stored, and the
+    /// maximum number of ROM sets and ROMs supported.  The crate still
+    /// requires an allocator to link.
+    ///
+    /// Unlike [`Parser::parse_flash`], any error is fatal, and `storage` is
+    /// left partially filled.  Use [`Parser::with_strings`] to skip reading
+    /// the build date and hardware revision.
+    pub async fn parse_into(&mut self, storage: &mut SdrrInfoStorage) -> Result<(), StorageError> {
+        let sdrr_info_addr = self.base_flash_address + self.info_offset;
+        let mut header_buf = [0u8; SdrrInfoHeader::size()];
+        self.reader
+            .read(sdrr_info_addr, &mut header_buf)
+            .await
+            .map_err(|_| StorageError::Read(sdrr_info_addr))?;
+        let (_, header) = SdrrInfoHeader::from_bytes((&header_buf, 0))
+            .map_err(|_| StorageError::InvalidHeader)?;
+
+        // A newer major version may have an incompatible layout, so is never
+        // accepted
+        storage.version_newer =
+            parsing::version_is_newer(header.major_version, header.minor_version, header.patch_version);
+        if storage.version_newer
+            && (self.version_policy == VersionPolicy::Strict || header.major_version > MAX_VERSION_MAJOR)
+        {
+            return Err(StorageError::UnsupportedVersion {
+                major: header.major_version,
+                minor: header.minor_version,
+                patch: header.patch_version,
+            });
+        }
+
+        if header.stm_line == McuLine::Rp2350 {
+            self.base_flash_address = RP2350_FLASH_BASE;
+            self.reader.update_base_address(self.base_flash_address);
+        }
+
+        storage.major_version = header.major_version;
+        storage.minor_version = header.minor_version;
+        storage.patch_version = header.patch_version;
+        storage.build_number = header.build_number;
+        storage.commit = header.commit;
+        storage.stm_line = header.stm_line;
+        storage.stm_storage = header.stm_storage;
+        storage.freq = header.freq;
+        storage.overclock = header.overclock != 0;
+        storage.swd_enabled = header.swd_enabled != 0;
+        storage.preload_image_to_ram = header.preload_image_to_ram != 0;
+        storage.bootloader_capable = header.bootloader_capable != 0;
+        storage.status_led_enabled = header.status_led_enabled != 0;
+        storage.boot_logging_enabled = header.boot_logging_enabled != 0;
+        storage.mco_enabled = header.mco_enabled != 0;
+        storage.mco2_enabled = header.mco2_enabled != 0;
+        storage.count_rom_access = header.count_rom_access != 0;
+        storage.boot_config = header.boot_config.into();
+        storage.pins_ptr = header.pins_ptr;
+
+        if self.strings.enabled {
+            storage::read_string_into(
+                &mut self.reader,
+                header.build_date_ptr,
+                self.base_flash_address,
+                &mut storage.build_date,
+            )
+            .await?;
+            storage::read_string_into(
+                &mut self.reader,
+                header.hw_rev_ptr,
+                self.base_flash_address,
+                &mut storage.hw_rev,
+            )
+            .await?;
+        } else {
+            storage.build_date = storage::SdrrStringStorage::new();
+            storage.hw_rev = storage::SdrrStringStorage::new();
+        }
+
+        storage::read_rom_sets_into(
+            &mut self.reader,
+            header.rom_sets_ptr,
+            header.rom_set_count,
+            self.base_flash_address,
+            header.boot_logging_enabled != 0,
+            storage,
+        )
+        .await
+    }
+
+    /// Parse SDRR runtime information from a RAM dump, or a running device's
+    /// RAM.
+    pub async fn parse_ram(&mut self) -> Result<SdrrRuntimeInfo, ParseFirmwareError> {
         // Parse and validate runtime info using the helper
         let runtime_info = self.retrieve_runtime_header().await?;
 
@@ -492,41 +885,46 @@ impl<R: Reader> Parser<R> {
             rom_table_size: runtime_info.rom_table_size,
         })
     }
+}
 
-    async fn read_string_at_ptr(&mut self, ptr: u32) -> Result<String, String> {
-        if ptr < self.base_flash_address {
-            return Err(format!("Invalid pointer: 0x{:08X}", ptr));
-        }
-
-        // Read in chunks to find null terminator
-        let mut result = Vec::new();
-        let mut addr = ptr;
-        let mut buf = [0u8; 64];
-
-        loop {
-            let chunk_size = buf.len().min(1024 - result.len()); // Limit total size
-            self.reader
-                .read(addr, &mut buf[..chunk_size])
-                .await
-                .map_err(|_| format!("Failed to read string at 0x{ptr:08X}"))?;
-
-            if let Some(null_pos) = buf[..chunk_size].iter().position(|&b| b == 0) {
-                result.extend_from_slice(&buf[..null_pos]);
-                break;
-            }
-
-            result.extend_from_slice(&buf[..chunk_size]);
-            addr += chunk_size as u32;
+/// Parses firmware from a slice of bytes, loaded at `base` - typically
+/// [`STM32F4_FLASH_BASE`].
+///
+/// This is a synchronous equivalent of [`Parser::parse_flash()`] using a
+/// [`readers::MemoryReader`], with default options.  It never panics,
+/// whatever the contents of `data`, so is suitable as a fuzz target - see
+/// `fuzz/` in this crate.
+pub fn parse_from_slice(data: &[u8], base: u32) -> Result<SdrrInfo, ParseFirmwareError> {
+    let mut parser = Parser::new(readers::MemoryReader::new(data.to_vec(), base));
+    block_on_ready(parser.parse_flash())
+}
 
-            if result.len() >= 1024 {
-                return Err("String too long (>1KB)".into());
-            }
+// Runs a future which never waits, such as one reading from a MemoryReader, to
+// completion without needing an executor.
+fn block_on_ready<F: core::future::Future>(future: F) -> F::Output {
+    let mut future = core::pin::pin!(future);
+    let mut cx = core::task::Context::from_waker(core::task::Waker::noop());
+    loop {
+        if let core::task::Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
         }
-
-        String::from_utf8(result).map_err(|_| "Invalid UTF-8 string".into())
     }
 }
 
+/// How the parser handles firmware with a newer version than it supports.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VersionPolicy {
+    /// Refuse to parse firmware newer than this parser supports.
+    #[default]
+    Strict,
+
+    /// Parse firmware with a newer minor or patch version, as the core header
+    /// layout is stable across these.  Only known fields are parsed, and a
+    /// warning is recorded in [`SdrrInfo::parse_errors`].  Firmware with a
+    /// newer major version is still refused.
+    Lenient,
+}
+
 /// Error information for non-fatal parsing failures.
 ///
 /// When parsing SDRR firmware, some sections may fail to parse due to corruption,
@@ -537,13 +935,15 @@ impl<R: Reader> Parser<R> {
 /// # Examples
 ///
 /// ```rust
-/// # use sdrr_fw_parser::ParseError;
+/// # use sdrr_fw_parser::{ErrorKind, ParseError, Severity};
 /// let error = ParseError {
 ///     field: "build_date".to_string(),
 ///     reason: "Invalid pointer: 0xFFFFFFFF".to_string(),
+///     severity: Severity::Error,
+///     kind: ErrorKind::InvalidPointer,
 /// };
 /// ```
-#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub struct ParseError {
     /// The field or structure that failed to parse.
     ///
@@ -563,16 +963,110 @@ pub struct ParseError {
     /// - `"ROM data extends past end of firmware"`
     /// - `"Unsupported ROM type value: 255"`
     pub reason: String,
+
+    /// How serious the problem is.
+    pub severity: Severity,
+
+    /// Machine readable category of the problem.  Unlike `reason`, this is
+    /// stable, so can be matched on by tools.
+    pub kind: ErrorKind,
 }
 
 impl ParseError {
-    /// Create a new parse error.
+    /// Create a new parse error, with [`Severity::Error`] and
+    /// [`ErrorKind::Other`].
     pub fn new(field: impl Into<String>, reason: impl Into<String>) -> Self {
+        Self::error(field, ErrorKind::Other, reason)
+    }
+
+    /// Create a new parse error with [`Severity::Error`].
+    pub fn error(field: impl Into<String>, kind: ErrorKind, reason: impl Into<String>) -> Self {
         Self {
             field: field.into(),
             reason: reason.into(),
+            severity: Severity::Error,
+            kind,
         }
     }
+
+    /// Create a new parse error with [`Severity::Warning`].
+    pub fn warning(field: impl Into<String>, kind: ErrorKind, reason: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            ..Self::error(field, kind, reason)
+        }
+    }
+
+    // Create a parse error from a failure reading part of the firmware.
+    // Strings which are too long or not UTF-8 don't stop the rest of the
+    // firmware being parsed, so are only warnings.
+    fn from_read(field: &str, (kind, reason): parsing::ReadError) -> Self {
+        match kind {
+            ErrorKind::StringTooLong | ErrorKind::BadUtf8 => Self::warning(field, kind, reason),
+            _ => Self::error(field, kind, reason),
+        }
+    }
+
+    /// Whether this is a warning, rather than an error.
+    pub fn is_warning(&self) -> bool {
+        self.severity == Severity::Warning
+    }
+}
+
+/// The severity of a [`ParseError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum Severity {
+    /// Something unexpected, but the affected information is still usable.
+    Warning,
+
+    /// The affected information is missing or unusable.
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "Warning"),
+            Severity::Error => write!(f, "Error"),
+        }
+    }
+}
+
+/// The category of a [`ParseError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum ErrorKind {
+    /// A pointer does not point into the firmware.
+    InvalidPointer,
+
+    /// The [`Reader`] failed to read from the firmware.
+    ReadFailed,
+
+    /// Something extends beyond where it should, such as a ROM larger than
+    /// its set.
+    OutOfBounds,
+
+    /// A string is not valid UTF-8.
+    BadUtf8,
+
+    /// A string is not null terminated within the maximum length.
+    StringTooLong,
+
+    /// A field contains a value this parser does not support, such as an
+    /// unknown ROM type.
+    UnsupportedValue,
+
+    /// The firmware version is newer than this parser supports.
+    UnsupportedVersion,
+
+    /// Fields disagree with each other, such as a count not matching the
+    /// number of items.
+    Inconsistent,
+
+    /// Data doesn't match its checksum, so has been corrupted.
+    ChecksumMismatch,
+
+    /// Any other problem.
+    Other,
 }
 
 impl fmt::Display for ParseError {
@@ -580,3 +1074,1091 @@ impl fmt::Display for ParseError {
         write!(f, "{}: {}", self.field, self.reason)
     }
 }
+
+/// Fatal error parsing SDRR firmware, returned by [`Parser::parse_flash`],
+/// [`Parser::parse_ram`], [`Parser::detect`] and [`Parser::find_header`].
+///
+/// Unlike [`ParseError`], which records a problem with part of the firmware
+/// while parsing continues, this means nothing useful could be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseFirmwareError {
+    /// The [`Reader`] failed to read the SDRR info header.
+    HeaderReadFailed,
+
+    /// The SDRR info header buffer is smaller than the header.
+    HeaderTooSmall,
+
+    /// The SDRR info header couldn't be decoded.
+    InvalidHeader(String),
+
+    /// The firmware version is newer than this parser supports.
+    UnsupportedVersion { major: u16, minor: u16, patch: u16 },
+
+    /// No valid SDRR info header was found within the offsets searched by
+    /// [`Parser::find_header`].
+    HeaderNotFound { start: u32, end: u32 },
+
+    /// The [`Reader`] failed to read the SDRR runtime info.
+    RuntimeInfoReadFailed,
+
+    /// The SDRR runtime info buffer is smaller than the runtime info header.
+    RuntimeInfoTooSmall,
+
+    /// The SDRR runtime info header couldn't be decoded.
+    InvalidRuntimeInfo(String),
+
+    /// The SDRR runtime info reports a size smaller than its header.
+    InvalidRuntimeInfoSize { size: u8, min: usize },
+}
+
+impl fmt::Display for ParseFirmwareError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::HeaderReadFailed => write!(f, "Failed to read SDRR header"),
+            Self::HeaderTooSmall => write!(f, "Header data too small"),
+            Self::InvalidHeader(e) => write!(f, "Failed to parse header: {}", e),
+            Self::UnsupportedVersion { major, minor, patch } => write!(
+                f,
+                "SDRR firmware version v{}.{}.{} unsupported - max version v{}.{}.{}",
+                major, minor, patch, MAX_VERSION_MAJOR, MAX_VERSION_MINOR, MAX_VERSION_PATCH
+            ),
+            Self::HeaderNotFound { start, end } => {
+                write!(f, "SDRR header not found in offsets 0x{:X}-0x{:X}", start, end)
+            }
+            Self::RuntimeInfoReadFailed => write!(f, "Failed to read SDRR runtime info"),
+            Self::RuntimeInfoTooSmall => write!(f, "Runtime info data too small"),
+            Self::InvalidRuntimeInfo(e) => write!(f, "Failed to parse runtime info header: {}", e),
+            Self::InvalidRuntimeInfoSize { size, min } => {
+                write!(f, "Invalid runtime info size: {} < {}", size, min)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseFirmwareError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::readers::MemoryReader;
+    use crate::readers::tests::counting_reader;
+    use crate::writer::Writer;
+    use crate::writer::tests::{place_rom_sets, test_info, test_rom};
+
+    fn parse_version(
+        major: u16,
+        minor: u16,
+        patch: u16,
+        version_policy: VersionPolicy,
+    ) -> Result<SdrrInfo, ParseFirmwareError> {
+        let mut info = test_info();
+        info.major_version = major;
+        info.minor_version = minor;
+        info.patch_version = patch;
+        let image = Writer::new(&info).write().unwrap();
+
+        // No ROM data CRC32 is written, so don't check it
+        let reader = MemoryReader::new(image, STM32F4_FLASH_BASE);
+        let mut parser = Parser::new(reader).verify_checksum(false);
+        parser.set_version_policy(version_policy);
+        smol::block_on(parser.parse_flash())
+    }
+
+    #[test]
+    fn test_version_policy_strict() {
+        assert!(parse_version(0, MAX_VERSION_MINOR + 1, 0, VersionPolicy::Strict).is_err());
+        let patch = MAX_VERSION_PATCH + 1;
+        assert!(parse_version(0, MAX_VERSION_MINOR, patch, VersionPolicy::Strict).is_err());
+
+        let info = parse_version(0, MAX_VERSION_MINOR, 0, VersionPolicy::Strict).unwrap();
+        assert!(info.parse_errors.is_empty());
+    }
+
+    #[test]
+    fn test_version_policy_lenient() {
+        let info = parse_version(0, MAX_VERSION_MINOR + 1, 0, VersionPolicy::Lenient).unwrap();
+        assert_eq!(info.parse_errors.len(), 1);
+        assert_eq!(info.parse_errors[0].field, "Version");
+        assert_eq!(info.parse_errors[0].kind, ErrorKind::UnsupportedVersion);
+        assert!(info.parse_errors[0].is_warning());
+        assert_eq!(info.rom_sets.len(), 2);
+        assert!(info.pins.is_some());
+
+        // Newer major versions are always refused
+        assert!(parse_version(MAX_VERSION_MAJOR + 1, 0, 0, VersionPolicy::Lenient).is_err());
+    }
+
+    #[test]
+    fn test_parse_23128() {
+        let mut info = test_info();
+        info.rom_sets.truncate(1);
+        info.rom_set_count = 1;
+        info.rom_sets[0].roms[0] = test_rom(SdrrRomType::Rom23128, "kernal_16k.bin");
+
+        // 28-pin hardware revision A pin layout
+        let pins = info.pins.as_mut().unwrap();
+        pins.rom_pins = 28;
+        pins.addr = vec![5, 4, 6, 7, 3, 2, 1, 0, 9, 10, 12, 11, 8, 13, 255, 255];
+        pins.ce_23128 = 15;
+        pins.oe_23128 = 14;
+        place_rom_sets(&mut info);
+
+        let image = Writer::new(&info).write().unwrap();
+        let mut parser = Parser::new(MemoryReader::new(image, STM32F4_FLASH_BASE));
+        let parsed = smol::block_on(parser.parse_flash()).unwrap();
+        assert!(parsed.parse_errors.is_empty());
+        assert_eq!(parsed.rom_sets[0].roms[0].rom_type, SdrrRomType::Rom23128);
+        assert_eq!(parsed.rom_sets[0].roms[0].rom_type.rom_size(), 16384);
+
+        // A13 is on pin 13, CE/OE on pins 15/14
+        let cs_set = SdrrCsSet::new(false, None, None, None, None);
+        let addr = SdrrLogicalAddress::new(0x2000, cs_set);
+        assert_eq!(parsed.mangle_address(&addr), Ok(0x2000));
+
+        let cs_set = SdrrCsSet::new(true, None, None, None, None);
+        let addr = SdrrLogicalAddress::new(0x2000, cs_set);
+        assert_eq!(parsed.mangle_address(&addr), Ok(0xE000));
+
+        let addr = SdrrLogicalAddress::new(0x4000, cs_set);
+        assert!(parsed.mangle_address(&addr).is_err());
+    }
+
+    #[test]
+    fn test_logical_image() {
+        let info = test_info();
+        let pins = info.pins.as_ref().unwrap();
+        let rom: Vec<u8> = (0..8192).map(|ii: u32| (ii ^ (ii >> 8)) as u8).collect();
+
+        // Mangle the ROM into set 0's data, as sdrr-gen would
+        let mut data = vec![0xFF; info.rom_sets[0].size as usize];
+        let cs_set = SdrrCsSet::new(false, None, None, None, None);
+        for (addr, &byte) in rom.iter().enumerate() {
+            let addr = SdrrLogicalAddress::new(addr as u32, cs_set);
+            let physical = info.mangle_address_for_rom(0, 0, &addr).unwrap();
+            let mangled = (0..=255u8).find(|&m| pins.demangle_byte(m) == byte).unwrap();
+            data[physical as usize] = mangled;
+        }
+
+        let rom_set = &info.rom_sets[0];
+        assert_eq!(rom_set.logical_image_from_data(pins, 0, &data).unwrap(), rom);
+        assert!(rom_set.logical_image_from_data(pins, 0, &data[1..]).is_err());
+        assert!(rom_set.logical_image_from_data(pins, 1, &data).is_err());
+
+        let image = Writer::new(&info).rom_set_data(0, &data).write().unwrap();
+        let mut parser = Parser::new(MemoryReader::new(image, STM32F4_FLASH_BASE));
+        let parsed = smol::block_on(parser.parse_flash()).unwrap();
+        let pins = parsed.pins.as_ref().unwrap();
+        let logical = smol::block_on(parsed.rom_sets[0].logical_image(&mut parser, pins, 0));
+        assert_eq!(logical.unwrap(), rom);
+
+        // A corrupt size is rejected before the data is allocated
+        let mut rom_set = parsed.rom_sets[0].clone();
+        rom_set.size = u32::MAX - rom_set.data_ptr;
+        let err = smol::block_on(rom_set.logical_image(&mut parser, pins, 0)).unwrap_err();
+        assert!(err.contains("beyond the end of the firmware"), "{}", err);
+    }
+
+    #[test]
+    fn test_logical_image_multi_rom() {
+        // Set 1 holds a 2364 and a 2332, selected by CS1 and X1 respectively
+        let info = test_info();
+        let pins = info.pins.as_ref().unwrap();
+        let rom_set = &info.rom_sets[1];
+        let cs_set = rom_set.cs_set_selecting_rom(pins, 1).unwrap();
+        assert_eq!(cs_set, SdrrCsSet::new(true, None, None, Some(false), Some(true)));
+
+        // Mangle each ROM into the set's data
+        let roms: Vec<Vec<u8>> = [8192u32, 4096]
+            .iter()
+            .enumerate()
+            .map(|(rom, &size)| (0..size).map(|ii| (ii ^ (ii >> 8) ^ rom as u32) as u8).collect())
+            .collect();
+        let mut data = vec![0xFF; rom_set.size as usize];
+        for (rom, image) in roms.iter().enumerate() {
+            let cs_set = rom_set.cs_set_selecting_rom(pins, rom as u8).unwrap();
+            for (addr, &byte) in image.iter().enumerate() {
+                let addr = SdrrLogicalAddress::new(addr as u32, cs_set);
+                let physical = info.mangle_address_for_rom(1, rom as u8, &addr).unwrap();
+                let mangled = (0..=255u8).find(|&m| pins.demangle_byte(m) == byte).unwrap();
+                data[physical as usize] = mangled;
+            }
+        }
+
+        for (rom, image) in roms.iter().enumerate() {
+            assert_eq!(rom_set.logical_image_from_data(pins, rom as u8, &data).as_ref(), Ok(image));
+        }
+        assert!(rom_set.logical_image_from_data(pins, 2, &data).is_err());
+    }
+
+    #[test]
+    fn test_read_rom_byte_for_rom() {
+        // Set 1 holds a 2364 and a 2332
+        let info = test_info();
+        let data: Vec<u8> = (0..info.rom_sets[1].size).map(|ii| (ii ^ (ii >> 8)) as u8).collect();
+        let image = Writer::new(&info).rom_set_data(1, &data).write().unwrap();
+        let mut parser = Parser::new(MemoryReader::new(image, STM32F4_FLASH_BASE));
+        let mut parsed = smol::block_on(parser.parse_flash()).unwrap();
+
+        // CS2 is only a 2332 line, so moves the 2332's address but not the
+        // 2364's
+        let cs_set = SdrrCsSet::new(false, Some(true), None, Some(false), Some(true));
+        let addr = SdrrLogicalAddress::new(0x0123, cs_set);
+        let physical = parsed.mangle_address_for_rom(1, 1, &addr).unwrap();
+        assert_ne!(physical, parsed.mangle_address_for_rom(1, 0, &addr).unwrap());
+
+        let logical = SdrrAddress::Logical(addr);
+        let byte = smol::block_on(parsed.read_rom_byte_raw_for_rom(&mut parser, 1, 1, logical));
+        assert_eq!(byte, Ok(data[physical as usize]));
+        let byte = parsed.read_rom_byte_demangled_for_rom(&mut parser, 1, 1, logical);
+        let byte = smol::block_on(byte);
+        let pins = parsed.pins.as_ref().unwrap();
+        assert_eq!(byte, Ok(pins.demangle_byte(data[physical as usize])));
+
+        // 0x1000 is within the 2364, but beyond the end of the 2332
+        let logical = SdrrAddress::Logical(SdrrLogicalAddress::new(0x1000, cs_set));
+        let mut read = |rom| {
+            smol::block_on(parsed.read_rom_byte_raw_for_rom(&mut parser, 1, rom, logical))
+        };
+        assert!(read(0).is_ok());
+        assert!(read(1).is_err());
+        assert!(read(2).is_err());
+    }
+
+    #[test]
+    fn test_cs_set() {
+        assert_eq!(SdrrCsState::ActiveLow.active_level(), Some(false));
+        assert_eq!(SdrrCsState::ActiveHigh.inactive_level(), Some(false));
+        assert_eq!(SdrrCsState::NotUsed.active_level(), None);
+
+        let rom = test_rom(SdrrRomType::Rom2364, "kernal.bin");
+        assert_eq!(rom.cs_set(true), SdrrCsSet::new(false, None, None, None, None));
+        assert_eq!(rom.cs_set(false), SdrrCsSet::new(true, None, None, None, None));
+
+        let mut rom = test_rom(SdrrRomType::Rom2316, "char.bin");
+        rom.cs2_state = SdrrCsState::ActiveHigh;
+        rom.cs3_state = SdrrCsState::ActiveLow;
+        let expected = SdrrCsSet::new(false, Some(true), Some(false), None, None);
+        assert_eq!(rom.cs_set(true), expected);
+
+        // CS3 isn't present on a 2332, whatever its state
+        rom.rom_type = SdrrRomType::Rom2332;
+        let expected = SdrrCsSet::new(false, Some(true), None, None, None);
+        assert_eq!(rom.cs_set(true), expected);
+    }
+
+    #[test]
+    fn test_iter_roms() {
+        let info = test_info();
+        assert_eq!(info.rom_count(), 3);
+
+        let roms: Vec<_> = info
+            .iter_roms()
+            .map(|(set, rom, rom_info)| (set, rom, rom_info.rom_type))
+            .collect();
+        assert_eq!(
+            roms,
+            vec![
+                (0, 0, SdrrRomType::Rom2364),
+                (1, 0, SdrrRomType::Rom2364),
+                (1, 1, SdrrRomType::Rom2332),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        let info = test_info();
+        assert_eq!(info.validate(), vec![]);
+
+        let mut info = test_info();
+        info.rom_set_count = 3;
+        info.rom_sets[1].rom_count = 3;
+        info.rom_sets[0].size = 2048;
+        let errors = info.validate();
+        let fields: Vec<_> = errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(
+            fields,
+            vec!["rom_set_count", "rom_set[0]", "rom_set[0].roms[0]", "rom_set[1]"]
+        );
+        assert_eq!(errors[1].reason, "Size is 2048 bytes, expected 16384 bytes");
+        assert!(errors[1].is_warning());
+        assert_eq!(errors[2].kind, ErrorKind::OutOfBounds);
+        assert_eq!(errors[2].severity, Severity::Error);
+
+        // Status LED enabled on hardware without a status LED pin, as with
+        // some 24-d firmware
+        let mut info = test_info();
+        let pins = info.pins.as_mut().unwrap();
+        pins.status = 255;
+        let errors = info.validate();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field, "status_led_enabled");
+
+        info.status_led_enabled = false;
+        assert_eq!(info.validate(), vec![]);
+
+        info.boot_config = BootConfig([0x01, 0xFF, 0xFF, 0xFF]);
+        let errors = info.validate();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field, "boot_config");
+        assert!(errors[0].is_warning());
+    }
+
+    #[test]
+    fn test_boot_config() {
+        assert!(BootConfig::default().is_unset());
+        assert_eq!(format!("{}", BootConfig::UNSET), "0xFFFFFFFF (unset)");
+
+        let boot_config = BootConfig::from([0x01, 0x02, 0x03, 0x04]);
+        assert!(!boot_config.is_unset());
+        assert_eq!(boot_config.bytes(), [0x01, 0x02, 0x03, 0x04]);
+        assert_eq!(boot_config.as_u32(), 0x04030201);
+        assert_eq!(format!("{}", boot_config), "0x01020304 (reserved, should be unset)");
+
+        // Round trips through the firmware
+        let mut info = test_info();
+        info.boot_config = boot_config;
+        let image = Writer::new(&info).write().unwrap();
+        let mut parser = Parser::new(MemoryReader::new(image, STM32F4_FLASH_BASE));
+        let parsed = smol::block_on(parser.parse_flash()).unwrap();
+        assert_eq!(parsed.boot_config, boot_config);
+    }
+
+    #[test]
+    fn test_stm_variant_name() {
+        let mut info = test_info();
+        assert_eq!(info.stm_variant_name(), Some("F411RE"));
+
+        info.stm_line = McuLine::F401BC;
+        info.stm_storage = McuStorage::StorageB;
+        assert_eq!(info.stm_variant_name(), Some("F401RB"));
+
+        info.stm_storage = McuStorage::StorageG;
+        assert_eq!(info.stm_variant_name(), None);
+
+        info.stm_line = McuLine::Rp2350;
+        info.stm_storage = McuStorage::Storage2MB;
+        assert_eq!(info.stm_variant_name(), Some("RP2350"));
+    }
+
+    #[test]
+    fn test_pins_accessors() {
+        let mut pins = crate::writer::tests::test_pins();
+        pins.addr[13] = 0xFF;
+        pins.sel2 = 0xFF;
+
+        let data: Vec<_> = pins.data_pins().collect();
+        assert_eq!(data.len(), 8);
+        assert_eq!(data[3], (3, pins.data[3]));
+
+        let ports = pins.ports();
+        assert_eq!(ports.data, pins.data_port);
+        assert_eq!(ports.cs, pins.cs_port);
+        assert_eq!(ports.status, pins.status_port);
+
+        let addr: Vec<_> = pins.addr_pins().map(|(ii, _)| ii).collect();
+        assert!(!addr.contains(&13));
+        assert!(addr.iter().all(|&ii| pins.addr[ii] != 0xFF));
+
+        let sel: Vec<_> = pins.sel_pins().map(|(ii, _)| ii).collect();
+        assert!(!sel.contains(&2));
+
+        assert_eq!(pins.cs_pin(SdrrRomType::Rom2364, 1), Some(pins.cs1_2364));
+        assert_eq!(pins.cs_pin(SdrrRomType::Rom2364, 2), None);
+        assert_eq!(pins.cs_pin(SdrrRomType::Rom2316, 3), Some(pins.cs3_2316));
+        pins.cs3_2316 = 0xFF;
+        assert_eq!(pins.cs_pin(SdrrRomType::Rom2316, 3), None);
+
+        let display = format!("{}", pins);
+        assert!(display.starts_with("Data pin mapping:\n  D0:  P"));
+        assert!(!display.contains("A13:"));
+        assert!(!display.contains("SEL2:"));
+        assert!(!display.contains("2316 CS3"));
+        assert!(display.ends_with(&format!("  Pin: P{}:{}\n", pins.status_port, pins.status)));
+    }
+
+    #[test]
+    fn test_pins_builder() {
+        use deku::DekuContainerWrite;
+
+        // Unused pins and ports are all 0xFF and 0 respectively, and the
+        // reserved bytes 0, as sdrr-gen writes them
+        let bytes = SdrrPins::default().to_bytes().unwrap();
+        assert_eq!(bytes.len(), SdrrPins::size());
+        let mut expected = [0xFFu8; 64];
+        for range in [0..5, 6..8, 32..36, 47..52, 61..64] {
+            expected[range].fill(0);
+        }
+        assert_eq!(bytes, expected);
+
+        let mut pins = SdrrPins::default()
+            .with_data_port(SdrrMcuPort::PortA)
+            .with_addr_port(SdrrMcuPort::PortC)
+            .with_cs_port(SdrrMcuPort::PortC)
+            .with_sel_port(SdrrMcuPort::PortB)
+            .with_status_pin(SdrrMcuPort::PortB, 15)
+            .with_rom_pins(24)
+            .with_cs_pin(SdrrRomType::Rom2364, 1, 10)
+            .with_cs_pin(SdrrRomType::Rom2332, 1, 10)
+            .with_cs_pin(SdrrRomType::Rom2332, 2, 9)
+            .with_cs_pin(SdrrRomType::Rom2316, 1, 10)
+            .with_cs_pin(SdrrRomType::Rom2316, 2, 12)
+            .with_cs_pin(SdrrRomType::Rom2316, 3, 9)
+            .with_x_pins(14, 15, 1)
+            .with_sel_pin(0, 0)
+            .with_sel_pin(1, 1)
+            .with_sel_pin(2, 2)
+            .with_sel_pin(3, 7)
+            .with_sel_jumper_pull(1);
+        for line in 0..8 {
+            pins = pins.with_data_pin(line, 7 - line as u8);
+        }
+        for (line, pin) in [5, 4, 6, 7, 3, 2, 1, 0, 8, 13, 11, 12, 9].into_iter().enumerate() {
+            pins = pins.with_addr_pin(line, pin);
+        }
+        let test_pins = crate::writer::tests::test_pins();
+        assert_eq!(pins, test_pins);
+
+        // A pin block round trips byte for byte
+        let bytes = test_pins.to_bytes().unwrap();
+        let (_, parsed) = SdrrPins::from_bytes((&bytes, 0)).unwrap();
+        assert_eq!(parsed, test_pins);
+        assert_eq!(parsed.to_bytes().unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_absurd_rom_count() {
+        let info = test_info();
+        let image = Writer::new(&info).write().unwrap();
+        let header = SDRR_INFO_FW_OFFSET as usize;
+        let rom_sets_ptr = u32::from_le_bytes(image[header + 44..header + 48].try_into().unwrap());
+        let rom_count = (rom_sets_ptr - STM32F4_FLASH_BASE) as usize + 12;
+
+        for count in [5, 64, 255] {
+            let mut image = image.clone();
+            image[rom_count] = count;
+            let mut parser = Parser::new(counting_reader(image)).verify_checksum(false);
+            let parsed = smol::block_on(parser.parse_flash()).unwrap();
+
+            // The ROM sets are rejected, with an error, rather than reading
+            // through each of the bogus ROM pointers
+            assert!(parsed.rom_sets.is_empty());
+            let error = parsed
+                .parse_errors
+                .iter()
+                .find(|error| error.field == "ROM Sets")
+                .unwrap();
+            assert_eq!(error.kind, ErrorKind::UnsupportedValue);
+            assert!(error.reason.contains(&format!("has {} ROMs", count)), "{}", error);
+            assert!(parser.reader.reads < 20, "{} reads", parser.reader.reads);
+        }
+    }
+
+    // A simple xorshift PRNG, so the arbitrary inputs are repeatable
+    fn xorshift(state: &mut u32) -> u32 {
+        *state ^= *state << 13;
+        *state ^= *state >> 17;
+        *state ^= *state << 5;
+        *state
+    }
+
+    #[test]
+    fn test_parse_from_slice() {
+        let info = test_info();
+        let image = Writer::new(&info).write().unwrap();
+        let parsed = parse_from_slice(&image, STM32F4_FLASH_BASE).unwrap();
+        assert_eq!(parsed.rom_sets.len(), info.rom_sets.len());
+
+        // Truncated, corrupted and random firmware must not panic
+        let mut state = 0x1234_5678;
+        for ii in 0..20000 {
+            let mut data = image.clone();
+            match ii % 4 {
+                0 => {
+                    // Corrupt some bytes, mostly within the info structures
+                    for _ in 0..(xorshift(&mut state) % 8 + 1) {
+                        let limit = if ii % 2 == 0 { 0x400 } else { data.len() as u32 };
+                        let offset = (xorshift(&mut state) % limit) as usize;
+                        data[offset] = xorshift(&mut state) as u8;
+                    }
+                }
+                1 => data.truncate((xorshift(&mut state) as usize) % image.len()),
+                2 => {
+                    // Point a word at the top of the address space
+                    let offset = (xorshift(&mut state) as usize % (data.len() / 4)) * 4;
+                    let value = u32::MAX - (xorshift(&mut state) % 0x100);
+                    data[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+                }
+                _ => data.iter_mut().for_each(|byte| *byte = xorshift(&mut state) as u8),
+            }
+            let _ = parse_from_slice(&data, STM32F4_FLASH_BASE);
+        }
+        for field in (0x200..0x300).step_by(4) {
+            for value in [u32::MAX, u32::MAX - 3, u32::MAX - 0x20] {
+                let mut data = image.clone();
+                data[field..field + 4].copy_from_slice(&value.to_le_bytes());
+                let _ = parse_from_slice(&data, STM32F4_FLASH_BASE);
+            }
+        }
+        for base in [0, RP2350_FLASH_BASE, u32::MAX - 0x100, u32::MAX] {
+            let _ = parse_from_slice(&image, base);
+        }
+    }
+
+    #[test]
+    fn test_parse_equality() {
+        use std::hash::{BuildHasher, RandomState};
+
+        let image = Writer::new(&test_info()).write().unwrap();
+        let first = parse_from_slice(&image, STM32F4_FLASH_BASE).unwrap();
+        let second = parse_from_slice(&image, STM32F4_FLASH_BASE).unwrap();
+        assert_eq!(first, second);
+        let state = RandomState::new();
+        assert_eq!(state.hash_one(&first), state.hash_one(&second));
+
+        // A different configuration doesn't compare equal
+        let mut info = test_info();
+        info.rom_sets[0].roms[0].filename = Some("other.bin".to_string());
+        let image = Writer::new(&info).write().unwrap();
+        let other = parse_from_slice(&image, STM32F4_FLASH_BASE).unwrap();
+        assert_ne!(first, other);
+        assert_ne!(state.hash_one(&first), state.hash_one(&other));
+    }
+
+    #[test]
+    fn test_format_hex_dump() {
+        let bytes: Vec<u8> = (0x1Cu8..0x3F).collect();
+        let mut dump = String::new();
+        format_hex_dump(&bytes, 0xA000, &mut dump).unwrap();
+        assert_eq!(
+            dump,
+            "A000: 1C 1D 1E 1F  20 21 22 23  24 25 26 27  28 29 2A 2B  |.... !\"#$%&'()*+|\n\
+             A010: 2C 2D 2E 2F  30 31 32 33  34 35 36 37  38 39 3A 3B  |,-./0123456789:;|\n\
+             A020: 3C 3D 3E                                            |<=>|\n"
+        );
+
+        let mut row = String::new();
+        format_hex_dump_row(&bytes[..6], false, &mut row).unwrap();
+        assert_eq!(row, "1C 1D 1E 1F  20 21");
+
+        // Narrower rows keep the grouping, and pad the ASCII to the row width
+        let mut row = String::new();
+        format_hex_dump_row_wrapped(&bytes[..6], 8, true, &mut row).unwrap();
+        assert_eq!(row, "1C 1D 1E 1F  20 21        |.... !|");
+        let mut row = String::new();
+        format_hex_dump_row_wrapped(&bytes, 8, true, &mut row).unwrap();
+        assert_eq!(row, "1C 1D 1E 1F  20 21 22 23  |.... !\"#|");
+        let mut row = String::new();
+        format_hex_dump_row_wrapped(&bytes[..17], 32, false, &mut row).unwrap();
+        assert!(row.ends_with("28 29 2A 2B  2C"));
+
+        let mut dump = String::new();
+        format_hex_dump(&[], 0, &mut dump).unwrap();
+        assert!(dump.is_empty());
+    }
+
+    #[test]
+    fn test_parse_into() {
+        let image = Writer::new(&test_info()).write().unwrap();
+        let mut parser = Parser::new(MemoryReader::new(image.clone(), STM32F4_FLASH_BASE));
+        let info = smol::block_on(parser.parse_flash()).unwrap();
+
+        let mut parser = Parser::new(MemoryReader::new(image.clone(), STM32F4_FLASH_BASE));
+        let mut storage = SdrrInfoStorage::new();
+        smol::block_on(parser.parse_into(&mut storage)).unwrap();
+        assert!(!storage.version_newer);
+        assert_eq!(storage.major_version, info.major_version);
+        assert_eq!(storage.stm_line, info.stm_line);
+        assert_eq!(storage.freq, info.freq);
+        assert_eq!(storage.boot_logging_enabled, info.boot_logging_enabled);
+        assert_eq!(storage.build_date.as_str(), info.build_date.as_deref());
+        assert_eq!(storage.hw_rev.as_str(), info.hw_rev.as_deref());
+
+        assert_eq!(storage.rom_sets().len(), info.rom_sets.len());
+        for (stored, set) in storage.rom_sets().iter().zip(info.rom_sets.iter()) {
+            assert_eq!(stored.data_ptr, set.data_ptr);
+            assert_eq!(stored.size, set.size);
+            assert_eq!(stored.serve, set.serve);
+            assert_eq!(stored.roms().len(), set.roms.len());
+            for (stored_rom, rom) in stored.roms().iter().zip(set.roms.iter()) {
+                assert_eq!(stored_rom.rom_type, rom.rom_type);
+                assert_eq!(stored_rom.cs1_state, rom.cs1_state);
+                assert_eq!(stored_rom.filename_ptr.is_some(), rom.filename.is_some());
+            }
+        }
+
+        // Skipping strings
+        let reader = MemoryReader::new(image, STM32F4_FLASH_BASE);
+        let mut parser = Parser::new(reader).with_strings(false);
+        smol::block_on(parser.parse_into(&mut storage)).unwrap();
+        assert!(storage.build_date.is_empty());
+        assert!(storage.hw_rev.is_empty());
+        assert_eq!(storage.rom_sets().len(), info.rom_sets.len());
+
+        let info = smol::block_on(parser.parse_flash()).unwrap();
+        assert!(info.build_date.is_none());
+        assert!(info.rom_sets[0].roms.iter().all(|rom| rom.filename.is_none()));
+    }
+
+    #[test]
+    fn test_parse_error_kinds() {
+        // A missing build date is written as a null pointer
+        let mut info = test_info();
+        info.build_date = None;
+        let image = Writer::new(&info).write().unwrap();
+        let mut parser = Parser::new(MemoryReader::new(image, STM32F4_FLASH_BASE));
+        let parsed = smol::block_on(parser.parse_flash()).unwrap();
+        assert_eq!(parsed.parse_errors.len(), 1);
+        assert_eq!(parsed.parse_errors[0].field, "Build Date");
+        assert_eq!(parsed.parse_errors[0].kind, ErrorKind::InvalidPointer);
+        assert_eq!(parsed.parse_errors[0].severity, Severity::Error);
+
+        let error = ParseError::from_read("Hardware Revision", (ErrorKind::BadUtf8, "x".into()));
+        assert!(error.is_warning());
+        assert_eq!(format!("{}", error.severity), "Warning");
+    }
+
+    #[test]
+    fn test_find_header() {
+        // Move the header from the default offset to the end of the image
+        let mut image = Writer::new(&test_info()).write().unwrap();
+        let default = SDRR_INFO_FW_OFFSET as usize;
+        let header: Vec<u8> = image[default..default + SdrrInfoHeader::size()].to_vec();
+        image[default..default + SdrrInfoHeader::size()].fill(0xFF);
+        image.resize(image.len().next_multiple_of(4), 0xFF);
+        let offset = image.len() as u32;
+        image.extend_from_slice(&header);
+
+        let mut parser = Parser::new(MemoryReader::new(image.clone(), STM32F4_FLASH_BASE));
+        assert!(smol::block_on(parser.parse_flash()).is_err());
+        assert!(smol::block_on(parser.detect()).is_err());
+        let error = smol::block_on(parser.find_header(0..offset)).unwrap_err();
+        assert_eq!(error, ParseFirmwareError::HeaderNotFound { start: 0, end: offset });
+        assert_eq!(
+            error.to_string(),
+            format!("SDRR header not found in offsets 0x0-0x{:X}", offset)
+        );
+        assert_eq!(parser.info_offset(), SDRR_INFO_FW_OFFSET);
+        assert_eq!(smol::block_on(parser.find_header(0..offset + 4)), Ok(offset));
+        let info = smol::block_on(parser.parse_flash()).unwrap();
+        assert!(info.parse_errors.is_empty());
+
+        let reader = MemoryReader::new(image, STM32F4_FLASH_BASE);
+        let mut parser = Parser::new(reader).with_info_offset(offset);
+        assert!(smol::block_on(parser.detect()).is_ok());
+        assert!(smol::block_on(parser.parse_flash()).is_ok());
+    }
+
+    #[test]
+    fn test_parse_firmware_error() {
+        let error = parse_version(0, MAX_VERSION_MINOR + 1, 0, VersionPolicy::Strict).unwrap_err();
+        let expected = ParseFirmwareError::UnsupportedVersion {
+            major: 0,
+            minor: MAX_VERSION_MINOR + 1,
+            patch: 0,
+        };
+        assert_eq!(error, expected);
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "SDRR firmware version v0.{}.0 unsupported - max version v{}.{}.{}",
+                MAX_VERSION_MINOR + 1,
+                MAX_VERSION_MAJOR,
+                MAX_VERSION_MINOR,
+                MAX_VERSION_PATCH
+            )
+        );
+
+        // Usable with `?` in callers returning a boxed std error
+        fn parse_boxed(image: Vec<u8>) -> Result<SdrrInfo, Box<dyn std::error::Error>> {
+            let mut parser = Parser::new(MemoryReader::new(image, STM32F4_FLASH_BASE));
+            Ok(smol::block_on(parser.parse_flash())?)
+        }
+        let error = parse_boxed(vec![0xFF; 0x100]).unwrap_err();
+        assert_eq!(error.to_string(), "Failed to read SDRR header");
+
+        let mut parser = Parser::new(MemoryReader::new(vec![0; 4], STM32F4_RAM_BASE));
+        let error = smol::block_on(parser.parse_ram()).unwrap_err();
+        assert_eq!(error, ParseFirmwareError::RuntimeInfoReadFailed);
+    }
+
+    #[test]
+    fn test_rom_crc32() {
+        let mut info = test_info();
+        info.minor_version = 5;
+        info.patch_version = 0;
+        let data0 = vec![0x55; info.rom_sets[0].size as usize];
+        let data1: Vec<u8> = (0..info.rom_sets[1].size).map(|ii| ii as u8).collect();
+        let mut hasher = crc32fast::Hasher::new();
+        hasher.update(&data0);
+        hasher.update(&data1);
+        info.rom_crc32 = Some(hasher.finalize());
+
+        let write = |info: &SdrrInfo| {
+            Writer::new(info)
+                .rom_set_data(0, &data0)
+                .rom_set_data(1, &data1)
+                .write()
+                .unwrap()
+        };
+        let parse = |image: Vec<u8>, verify: bool| {
+            let reader = MemoryReader::new(image, STM32F4_FLASH_BASE);
+            let mut parser = Parser::new(reader).verify_checksum(verify);
+            smol::block_on(parser.parse_flash()).unwrap()
+        };
+
+        let parsed = parse(write(&info), true);
+        assert!(parsed.parse_errors.is_empty());
+        assert_eq!(parsed.rom_crc32, info.rom_crc32);
+
+        // Corrupt a byte of the second ROM set
+        let mut image = write(&info);
+        let offset = (info.rom_sets[1].data_ptr - STM32F4_FLASH_BASE) as usize + 0x1234;
+        image[offset] ^= 0x01;
+        let parsed = parse(image.clone(), true);
+        assert_eq!(parsed.parse_errors.len(), 1);
+        assert_eq!(parsed.parse_errors[0].kind, ErrorKind::ChecksumMismatch);
+        assert_eq!(parsed.parse_errors[0].severity, Severity::Error);
+        assert!(parse(image, false).parse_errors.is_empty());
+
+        // Firmware before v0.5.0 has no CRC32
+        info.minor_version = 4;
+        let parsed = parse(write(&info), true);
+        assert!(parsed.parse_errors.is_empty());
+        assert_eq!(parsed.rom_crc32, None);
+    }
+
+    #[test]
+    fn test_find_all_headers() {
+        // SDRR in ROM data, but not followed by a valid header
+        let info = test_info();
+        let mut data = vec![0u8; info.rom_sets[0].size as usize];
+        data[0x100..0x104].copy_from_slice(b"SDRR");
+        let mut image = Writer::new(&info).rom_set_data(0, &data).write().unwrap();
+
+        // Add a second header, for a different build, at the end of the image
+        let mut second = info;
+        second.build_number = 8;
+        let header = Writer::new(&second).header_bytes().unwrap();
+        image.resize(image.len().next_multiple_of(0x1000), 0xFF);
+        let offset = image.len() as u32;
+        image.extend_from_slice(&header);
+
+        let len = image.len() as u32;
+        let mut parser = Parser::new(MemoryReader::new(image, STM32F4_FLASH_BASE));
+        let offsets = smol::block_on(parser.find_all_headers(4, len));
+        assert_eq!(offsets, vec![SDRR_INFO_FW_OFFSET, offset]);
+
+        // Only 0x1000 aligned offsets
+        let offsets = smol::block_on(parser.find_all_headers(0x1000, len));
+        assert_eq!(offsets, vec![offset]);
+
+        let parsed = smol::block_on(parser.parse_at(offset)).unwrap();
+        assert_eq!(parsed.build_number, 8);
+        assert_eq!(parser.info_offset(), SDRR_INFO_FW_OFFSET);
+        let parsed = smol::block_on(parser.parse_flash()).unwrap();
+        assert_eq!(parsed.build_number, 7);
+    }
+
+    #[test]
+    fn test_rom_type_from_str() {
+        for rom_type in [
+            SdrrRomType::Rom2316,
+            SdrrRomType::Rom2332,
+            SdrrRomType::Rom2364,
+            SdrrRomType::Rom23128,
+        ] {
+            assert_eq!(rom_type.to_string().parse::<SdrrRomType>(), Ok(rom_type));
+            assert_eq!(1 << rom_type.num_addr_lines(), rom_type.size_bytes());
+        }
+        assert_eq!(" 2332 ".parse::<SdrrRomType>(), Ok(SdrrRomType::Rom2332));
+        assert!("2716".parse::<SdrrRomType>().is_err());
+    }
+
+    #[test]
+    fn test_effective_address_mask() {
+        for (rom_type, mask) in [
+            (SdrrRomType::Rom2316, 0x07FF),
+            (SdrrRomType::Rom2332, 0x0FFF),
+            (SdrrRomType::Rom2364, 0x1FFF),
+            (SdrrRomType::Rom23128, 0x3FFF),
+        ] {
+            let rom = test_rom(rom_type, "test.bin");
+            assert_eq!(rom.effective_address_mask(), mask);
+            assert_eq!(rom.max_address(), mask);
+            assert_eq!(rom.max_address() as usize + 1, rom_type.rom_size());
+        }
+    }
+
+    #[test]
+    fn test_access_count_address() {
+        let mut info = test_info();
+        info.count_rom_access = false;
+        assert_eq!(info.access_count_address(), None);
+        info.count_rom_access = true;
+        assert_eq!(info.access_count_address(), Some(0x20000008));
+    }
+
+    #[test]
+    fn test_hw_rev_supports_mangling() {
+        let mut info = test_info();
+        assert!(info.hw_rev_supports_mangling());
+
+        // A chip select line on a pin the address can't be mangled with
+        let pins = info.pins.as_mut().unwrap();
+        let cs1 = pins.cs1_2364;
+        pins.cs1_2364 = 0xFF;
+        assert!(!info.hw_rev_supports_mangling());
+        info.pins.as_mut().unwrap().cs1_2364 = cs1;
+
+        // X1/X2 are only needed for multi-ROM sets, such as set 1
+        info.pins.as_mut().unwrap().x1 = 0xFF;
+        assert!(!info.hw_rev_supports_mangling());
+        info.rom_sets.truncate(1);
+        assert!(info.hw_rev_supports_mangling());
+
+        // Data lines beyond the first byte of the data port, as on RP2350
+        info.pins.as_mut().unwrap().data[0] = 16;
+        assert!(!info.hw_rev_supports_mangling());
+
+        info.pins = None;
+        assert!(!info.hw_rev_supports_mangling());
+    }
+
+    #[test]
+    fn test_header_layout() {
+        // The header's fields must be at the offsets documented in
+        // sdrr_info_t - in particular count_rom_access at 42, followed by
+        // mco2_enabled, so rom_sets is 4 byte aligned at 44
+        let mut info = test_info();
+        info.count_rom_access = true;
+        info.mco_enabled = true;
+        info.mco2_enabled = true;
+        let bytes = Writer::new(&info).header_bytes().unwrap();
+        assert_eq!(bytes.len(), SdrrInfoHeader::size());
+        let (_, header) = SdrrInfoHeader::from_bytes((&bytes, 0)).unwrap();
+        let le_u32 =
+            |offset: usize| u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+
+        assert_eq!(&bytes[0..4], b"SDRR");
+        assert_eq!(le_u32(24), header.hw_rev_ptr);
+        assert_eq!(bytes[41], info.rom_sets.len() as u8);
+        assert_eq!(bytes[42], 1);
+        assert_eq!(bytes[40], 1);
+        assert_eq!(bytes[43], 1);
+        assert_eq!(le_u32(44), header.rom_sets_ptr);
+        assert_eq!(le_u32(48), header.pins_ptr);
+        assert_eq!(le_u32(56), header.extra_ptr);
+
+        // And firmware with access counting enabled parses with it set,
+        // and with its ROM sets intact
+        let image = Writer::new(&info).write().unwrap();
+        let reader = MemoryReader::new(image, STM32F4_FLASH_BASE);
+        let mut parser = Parser::new(reader).verify_checksum(false);
+        let parsed = smol::block_on(parser.parse_flash()).unwrap();
+        assert!(parsed.count_rom_access);
+        assert!(parsed.mco_enabled && parsed.mco2_enabled);
+        assert_eq!(parsed.rom_set_count, info.rom_set_count);
+        assert_eq!(parsed.rom_sets.len(), info.rom_sets.len());
+    }
+
+    #[test]
+    fn test_serve_round_trip() {
+        for value in 0..=u8::MAX {
+            if let Some(serve) = SdrrServe::from_u8(value) {
+                assert_eq!(serve.to_u8(), value);
+                let (_, parsed) = SdrrServe::from_bytes((&[value], 0)).unwrap();
+                assert_eq!(parsed, serve);
+            } else {
+                assert!(SdrrServe::from_bytes((&[value], 0)).is_err());
+            }
+        }
+        assert_eq!(SdrrServe::from_u8(3), None);
+        assert_eq!(SdrrServe::AddrOnAnyCs.as_str(), "addr_on_any_cs");
+    }
+
+    #[test]
+    fn test_port_and_line_round_trip() {
+        use deku::DekuReader;
+
+        for value in 0..=u8::MAX {
+            let port = SdrrMcuPort::from_u8(value);
+            assert_eq!(port.is_some(), SdrrMcuPort::all().iter().any(|p| p.to_u8() == value));
+            match SdrrMcuPort::from_bytes((&[value], 0)) {
+                Ok((_, parsed)) => assert_eq!(Some(parsed), port),
+                Err(_) => assert_eq!(port, None),
+            }
+            assert_eq!(SdrrMcuPort::try_from_u8(value).ok(), port);
+        }
+        for port in SdrrMcuPort::all() {
+            assert_eq!(SdrrMcuPort::from_u8(port.to_u8()), Some(*port));
+        }
+
+        // Check beyond the valid lines - no need to walk the whole u16 range
+        for value in 0..=0x100u16 {
+            let line = McuLine::from_u16(value);
+            assert_eq!(line.is_some(), McuLine::all().iter().any(|l| l.to_u16() == value));
+            let mut cursor = std::io::Cursor::new(value.to_le_bytes());
+            let mut reader = deku::reader::Reader::new(&mut cursor);
+            match McuLine::from_reader_with_ctx(&mut reader, deku::ctx::Endian::Little) {
+                Ok(parsed) => assert_eq!(Some(parsed), line),
+                Err(_) => assert_eq!(line, None),
+            }
+        }
+        for line in McuLine::all() {
+            assert_eq!(McuLine::try_from_u16(line.to_u16()), Ok(*line));
+        }
+
+        assert_eq!(
+            SdrrMcuPort::try_from_u8(7).unwrap_err(),
+            "Unknown port 0x07 - valid values are 0x00 (None), 0x01 (A), 0x02 (B), 0x03 (C), \
+             0x04 (D), 0x05 (0)"
+        );
+        assert!(McuLine::try_from_u16(6).unwrap_err().contains("0x0005 (RP2350)"));
+    }
+
+    #[test]
+    fn test_bad_pins_port() {
+        use deku::DekuContainerWrite;
+
+        let info = test_info();
+        let mut image = Writer::new(&info).write().unwrap();
+        let pins = info.pins.as_ref().unwrap().to_bytes().unwrap();
+        let offset = image.windows(pins.len()).position(|w| w == pins.as_slice()).unwrap();
+        image[offset + 2] = 0x42;
+
+        let mut parser = Parser::new(MemoryReader::new(image, STM32F4_FLASH_BASE));
+        let parsed = smol::block_on(parser.parse_flash()).unwrap();
+        assert!(parsed.pins.is_none());
+        let error = parsed.parse_errors.iter().find(|e| e.field == "Pins").unwrap();
+        assert_eq!(error.kind, ErrorKind::UnsupportedValue);
+        assert!(error.reason.contains("cs port: Unknown port 0x42"));
+    }
+
+    #[test]
+    fn test_recover_reader() {
+        let image = Writer::new(&test_info()).write().unwrap();
+        let mut parser = Parser::new(MemoryReader::new(image.clone(), STM32F4_FLASH_BASE))
+            .verify_checksum(false);
+        smol::block_on(parser.parse_flash()).unwrap();
+
+        let mut buf = [0u8; 4];
+        smol::block_on(parser.reader_mut().read(STM32F4_FLASH_BASE, &mut buf)).unwrap();
+        assert_eq!(buf, image[..4]);
+
+        let mut reader = parser.into_reader();
+        smol::block_on(reader.read(STM32F4_FLASH_BASE + 4, &mut buf)).unwrap();
+        assert_eq!(buf, image[4..8]);
+    }
+
+    #[test]
+    fn test_string_read_ahead() {
+        // A 100 byte string
+        let mut image = vec![b'a'; 100];
+        image.push(0);
+        image.resize(0x400, 0xFF);
+        let read_len = |image: &[u8], len, read_size| {
+            let mut reader = counting_reader(image.to_vec());
+            let base = STM32F4_FLASH_BASE;
+            let flash = parsing::Flash { base, len };
+            let result =
+                smol::block_on(parsing::read_bytes_at_ptr(&mut reader, base, flash, read_size));
+            (result, reader.reads)
+        };
+        let read = |image: &[u8], read_size| read_len(image, None, read_size);
+
+        // Reading in small chunks takes two reads, reading ahead takes one
+        let (string, reads) = read(&image, 64);
+        assert_eq!(string.unwrap().len(), 100);
+        assert_eq!(reads, 2);
+        let (string, reads) = read(&image, DEFAULT_STRING_READ_SIZE);
+        assert_eq!(string.unwrap().len(), 100);
+        assert_eq!(reads, 1);
+
+        // Reading ahead past the end of the image falls back to smaller reads
+        let (string, _) = read(&image[..101], DEFAULT_STRING_READ_SIZE);
+        assert_eq!(string.unwrap().len(), 100);
+        let (string, _) = read(&image[..100], DEFAULT_STRING_READ_SIZE);
+        assert_eq!(string.unwrap_err().0, ErrorKind::ReadFailed);
+
+        // Unless the length of the image is known, when reads stop at its end
+        let (string, reads) = read_len(&image[..101], Some(101), DEFAULT_STRING_READ_SIZE);
+        assert_eq!(string.unwrap().len(), 100);
+        assert_eq!(reads, 1);
+        let (string, reads) = read_len(&image[..100], Some(100), DEFAULT_STRING_READ_SIZE);
+        assert_eq!(string.unwrap_err().0, ErrorKind::OutOfBounds);
+        assert_eq!(reads, 1);
+
+        // Strings are still limited to 1KB
+        image.fill(b'a');
+        image.resize(0x800, b'a');
+        let (string, reads) = read(&image, DEFAULT_STRING_READ_SIZE);
+        assert_eq!(string.unwrap_err().0, ErrorKind::StringTooLong);
+        assert_eq!(reads, 4);
+    }
+
+    #[test]
+    fn test_latin1_strings() {
+        let mut info = test_info();
+        info.hw_rev = Some("cafe".into());
+        info.rom_sets[1].roms[1].filename = Some("charX.bin".into());
+        place_rom_sets(&mut info);
+        let mut image = Writer::new(&info).write().unwrap();
+
+        // Replace characters with Latin-1 bytes, which aren't valid UTF-8
+        for (from, to) in [(&b"cafe"[..], &b"caf\xE9"[..]), (b"charX", b"char\xE9")] {
+            let pos = image.windows(from.len()).position(|w| w == from).unwrap();
+            image[pos..pos + to.len()].copy_from_slice(to);
+        }
+
+        let reader = MemoryReader::new(image.clone(), STM32F4_FLASH_BASE);
+        let parsed = smol::block_on(Parser::new(reader).parse_flash()).unwrap();
+        assert_eq!(parsed.hw_rev.as_deref(), Some("caf\u{e9}"));
+        let filename = parsed.rom_sets[1].roms[1].filename.as_deref();
+        assert_eq!(filename, Some("char\u{e9}.bin"));
+        let fields: Vec<_> = parsed.parse_errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, ["Hardware Revision", "ROM Set 1 ROM 1 Filename"]);
+        assert!(parsed.parse_errors.iter().all(|e| e.is_warning() && e.kind == ErrorKind::BadUtf8));
+
+        let reader = MemoryReader::new(image, STM32F4_FLASH_BASE);
+        let mut parser = Parser::new(reader).with_strict_utf8(true);
+        let parsed = smol::block_on(parser.parse_flash()).unwrap();
+        assert_eq!(parsed.hw_rev, None);
+        assert_eq!(parsed.rom_sets[1].roms[1].filename, None);
+        assert_eq!(parsed.rom_sets[1].roms[0].filename.as_deref(), Some("basic.bin"));
+        assert_eq!(parsed.parse_errors.len(), 2);
+    }
+
+    #[test]
+    fn test_resolve_ptr() {
+        // Point the pins beyond the end of the firmware
+        let mut image = Writer::new(&test_info()).write().unwrap();
+        let pins_ptr = SDRR_INFO_FW_OFFSET as usize + 0x30;
+        let wild_ptr = STM32F4_FLASH_BASE + image.len() as u32 + 0x1000;
+        image[pins_ptr..pins_ptr + 4].copy_from_slice(&wild_ptr.to_le_bytes());
+
+        let len = image.len() as u32;
+        let mut parser = Parser::new(counting_reader(image.clone()));
+        assert_eq!(parser.firmware_len(), Some(len));
+        let parsed = smol::block_on(parser.parse_flash()).unwrap();
+        assert!(parsed.pins.is_none());
+        assert_eq!(parsed.parse_errors.len(), 1);
+        assert_eq!(parsed.parse_errors[0].field, "Pins");
+        assert_eq!(parsed.parse_errors[0].kind, ErrorKind::OutOfBounds);
+
+        // With the length overridden, the read itself fails instead
+        let mut parser = Parser::new(MemoryReader::new(image.clone(), STM32F4_FLASH_BASE));
+        parser = parser.with_firmware_len(u32::MAX);
+        let parsed = smol::block_on(parser.parse_flash()).unwrap();
+        assert_eq!(parsed.parse_errors[0].kind, ErrorKind::ReadFailed);
+
+        let parser = Parser::new(MemoryReader::new(image, STM32F4_FLASH_BASE));
+        let addr = parser.resolve_ptr(STM32F4_FLASH_BASE, len as usize).unwrap();
+        assert_eq!(addr, STM32F4_FLASH_BASE);
+        let error = parser.resolve_ptr(STM32F4_FLASH_BASE + 1, len as usize).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::OutOfBounds);
+        let error = parser.resolve_ptr(0, 1).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::InvalidPointer);
+    }
+}
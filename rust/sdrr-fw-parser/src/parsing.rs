@@ -9,17 +9,89 @@
 use deku::prelude::*;
 use static_assertions::const_assert_eq;
 
-use crate::Reader;
+use crate::{DEFAULT_STRING_READ_SIZE, ErrorKind, ParseError, ParseFirmwareError, Reader, VersionPolicy};
 use crate::{MAX_VERSION_MAJOR, MAX_VERSION_MINOR, MAX_VERSION_PATCH};
 use crate::{SdrrCsState, SdrrRomType, SdrrServe, McuLine, McuStorage};
-use crate::{SdrrPins, SdrrRomInfo, SdrrRomSet, SdrrExtraInfo};
+use crate::{SdrrMcuPort, SdrrPins, SdrrRomInfo, SdrrRomSet, SdrrExtraInfo};
+use crate::storage::MAX_ROMS_PER_SET;
 
 #[cfg(not(feature = "std"))]
 use alloc::{format, string::String, vec, vec::Vec};
 
 // Maximum length of strings and bits of strings read from firmware
 const MAX_STRING_LEN: usize = 1024;
-const STRING_READ_CHUNK_SIZE: usize = 64;
+
+// How strings are read from the firmware
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct StringOptions {
+    // Whether to read strings at all
+    pub enabled: bool,
+    // Bytes to read at a time, scanning each block for the NUL terminator
+    pub read_size: usize,
+    // Whether strings which aren't valid UTF-8 are rejected, rather than
+    // decoded as Latin-1
+    pub strict_utf8: bool,
+}
+
+impl Default for StringOptions {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            read_size: DEFAULT_STRING_READ_SIZE,
+            strict_utf8: false,
+        }
+    }
+}
+
+// Error from reading part of the firmware, recorded by the caller as a
+// [`ParseError`](crate::ParseError) against the relevant field
+pub(crate) type ReadError = (ErrorKind, String);
+
+// Where the firmware is in flash, used to check pointers before reading
+// through them
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Flash {
+    pub base: u32,
+    // Length of the firmware in bytes, if known
+    pub len: Option<u32>,
+}
+
+impl Flash {
+    /// Check that `needed` bytes from `ptr` are within the firmware, before
+    /// reading them, returning the address to read from
+    pub fn resolve_ptr(&self, ptr: u32, needed: usize) -> Result<u32, ReadError> {
+        if ptr < self.base {
+            return Err((ErrorKind::InvalidPointer, format!("Invalid pointer: 0x{:08X}", ptr)));
+        }
+
+        if u32::try_from(needed).ok().and_then(|n| ptr.checked_add(n)).is_none() {
+            let reason =
+                format!("Pointer 0x{:08X} (+{} bytes) overflows the address space", ptr, needed);
+            return Err((ErrorKind::OutOfBounds, reason));
+        }
+
+        if let Some(len) = self.len {
+            let end = (ptr - self.base) as u64 + needed as u64;
+            if end > len as u64 {
+                let reason = format!(
+                    "Pointer 0x{:08X} (+{} bytes) is beyond the end of the firmware (0x{:08X})",
+                    ptr,
+                    needed,
+                    self.base as u64 + len as u64
+                );
+                return Err((ErrorKind::OutOfBounds, reason));
+            }
+        }
+
+        Ok(ptr)
+    }
+
+    // Number of bytes of firmware from `ptr`, if known
+    fn remaining(&self, ptr: u32) -> Option<usize> {
+        self.len
+            .map(|len| (self.base as u64 + len as u64).saturating_sub(ptr as u64) as usize)
+    }
+}
 
 #[derive(Debug, DekuRead, DekuWrite)]
 #[deku(endian = "little", magic = b"sdrr")]
@@ -92,7 +164,7 @@ pub(crate) struct SdrrInfoHeader {
     pub mco_enabled: u8,
     pub rom_set_count: u8,
     pub count_rom_access: u8,
-    #[deku(pad_bytes_before = "1")]
+    pub mco2_enabled: u8,
     #[deku(endian = "little")]
     pub rom_sets_ptr: u32,
     #[deku(endian = "little")]
@@ -100,7 +172,8 @@ pub(crate) struct SdrrInfoHeader {
     #[deku(bytes = "4")]
     pub boot_config: [u8; 4],
     pub extra_ptr: u32,
-    pub _post: [u8; 4],
+    #[deku(endian = "little")]
+    pub rom_crc32: u32,
 }
 
 impl SdrrInfoHeader {
@@ -179,7 +252,7 @@ impl SdrrRomSetHeader {
 //
 // Only used internally
 #[derive(Debug, DekuRead, DekuWrite)]
-struct SdrrRomInfoBasic {
+pub(crate) struct SdrrRomInfoBasic {
     pub rom_type: SdrrRomType,
     pub cs1_state: SdrrCsState,
     pub cs2_state: SdrrCsState,
@@ -188,7 +261,7 @@ struct SdrrRomInfoBasic {
 
 impl SdrrRomInfoBasic {
     const ROM_INFO_BASIC_SIZE: usize = 4;
-    pub(crate) fn size() -> usize {
+    pub(crate) const fn size() -> usize {
         const_assert_eq!(
             core::mem::size_of::<SdrrRomInfoBasic>(),
             SdrrRomInfoBasic::ROM_INFO_BASIC_SIZE
@@ -205,7 +278,7 @@ impl SdrrRomInfoBasic {
 //
 // Only used internally
 #[derive(Debug, DekuRead, DekuWrite)]
-struct SdrrRomInfoWithLogging {
+pub(crate) struct SdrrRomInfoWithLogging {
     pub rom_type: SdrrRomType,
     pub cs1_state: SdrrCsState,
     pub cs2_state: SdrrCsState,
@@ -216,7 +289,7 @@ struct SdrrRomInfoWithLogging {
 
 impl SdrrRomInfoWithLogging {
     const ROM_INFO_WITH_LOGGING_SIZE: usize = 8;
-    pub(crate) fn size() -> usize {
+    pub(crate) const fn size() -> usize {
         const_assert_eq!(
             core::mem::size_of::<SdrrRomInfoWithLogging>(),
             SdrrRomInfoWithLogging::ROM_INFO_WITH_LOGGING_SIZE
@@ -226,81 +299,192 @@ impl SdrrRomInfoWithLogging {
 }
 
 /// Parse and validate runtime information from buffer
-pub(crate) fn parse_and_validate_runtime_info(data: &[u8]) -> Result<SdrrRuntimeInfoHeader, String> {
+pub(crate) fn parse_and_validate_runtime_info(
+    data: &[u8],
+) -> Result<SdrrRuntimeInfoHeader, ParseFirmwareError> {
     if data.len() < SdrrRuntimeInfoHeader::size() {
-        return Err("Runtime info data too small".into());
+        return Err(ParseFirmwareError::RuntimeInfoTooSmall);
     }
 
     let (_, header) = SdrrRuntimeInfoHeader::from_bytes((data, 0))
-        .map_err(|e| format!("Failed to parse runtime info header: {}", e))?;
+        .map_err(|e| ParseFirmwareError::InvalidRuntimeInfo(format!("{}", e)))?;
 
     if header.runtime_info_size < SdrrRuntimeInfoHeader::size() as u8 {
-        return Err(format!(
-            "Invalid runtime info size: {} < {}",
-            header.runtime_info_size,
-            SdrrRuntimeInfoHeader::size()
-        ));
+        return Err(ParseFirmwareError::InvalidRuntimeInfoSize {
+            size: header.runtime_info_size,
+            min: SdrrRuntimeInfoHeader::size(),
+        });
     }
 
     Ok(header)
 }
 
+/// Whether a firmware version is newer than this parser supports
+pub(crate) fn version_is_newer(major: u16, minor: u16, patch: u16) -> bool {
+    major > MAX_VERSION_MAJOR
+        || (major == MAX_VERSION_MAJOR && minor > MAX_VERSION_MINOR)
+        || (major == MAX_VERSION_MAJOR && minor == MAX_VERSION_MINOR && patch > MAX_VERSION_PATCH)
+}
+
+/// Whether a firmware version includes the ROM data CRC32 in its header -
+/// added in v0.5.0
+pub(crate) fn version_has_rom_crc32(major: u16, minor: u16) -> bool {
+    major > 0 || minor >= 5
+}
+
+/// Calculate the CRC32 of the ROM set images, in ROM set order, reading them
+/// in blocks of `read_size` bytes
+pub(crate) async fn rom_sets_crc32<R: Reader>(
+    reader: &mut R,
+    rom_sets: &[SdrrRomSet],
+    read_size: usize,
+) -> Result<u32, ReadError> {
+    let mut hasher = crc32fast::Hasher::new();
+    let mut buf = vec![0u8; read_size.max(1)];
+    for rom_set in rom_sets {
+        let mut addr = rom_set.data_ptr;
+        let end = rom_set.data_ptr.checked_add(rom_set.size).ok_or_else(|| {
+            let reason = format!("ROM data at 0x{:08X} overflows the address space", addr);
+            (ErrorKind::OutOfBounds, reason)
+        })?;
+        while addr < end {
+            let len = ((end - addr) as usize).min(buf.len());
+            reader.read(addr, &mut buf[..len]).await.map_err(|_| {
+                (ErrorKind::ReadFailed, format!("Failed to read ROM data at 0x{:08X}", addr))
+            })?;
+            hasher.update(&buf[..len]);
+            addr += len as u32;
+        }
+    }
+    Ok(hasher.finalize())
+}
+
 /// Parse and validate SDRR header from buffer
-pub(crate) fn parse_and_validate_header(data: &[u8]) -> Result<SdrrInfoHeader, String> {
+///
+/// Returns the header, plus a warning if the firmware version is newer than
+/// supported but was accepted due to a lenient [`VersionPolicy`].
+pub(crate) fn parse_and_validate_header(
+    data: &[u8],
+    version_policy: VersionPolicy,
+) -> Result<(SdrrInfoHeader, Option<String>), ParseFirmwareError> {
     if data.len() < SdrrInfoHeader::size() {
-        return Err("Header data too small".into());
+        return Err(ParseFirmwareError::HeaderTooSmall);
     }
 
     let (_, mut header) = SdrrInfoHeader::from_bytes((data, 0))
-        .map_err(|e| format!("Failed to parse header: {}", e))?;
+        .map_err(|e| ParseFirmwareError::InvalidHeader(format!("{}", e)))?;
 
     // Validate version
-    if header.major_version > MAX_VERSION_MAJOR
-        || (header.major_version == MAX_VERSION_MAJOR && header.minor_version > MAX_VERSION_MINOR)
-        || (header.major_version == MAX_VERSION_MAJOR
-            && header.minor_version == MAX_VERSION_MINOR
-            && header.patch_version > MAX_VERSION_PATCH)
-    {
-        return Err(format!(
-            "SDRR firmware version v{}.{}.{} unsupported - max version v{}.{}.{}",
-            header.major_version,
-            header.minor_version,
-            header.patch_version,
-            MAX_VERSION_MAJOR,
-            MAX_VERSION_MINOR,
-            MAX_VERSION_PATCH
-        ));
+    let mut warning = None;
+    if version_is_newer(header.major_version, header.minor_version, header.patch_version) {
+        let error = ParseFirmwareError::UnsupportedVersion {
+            major: header.major_version,
+            minor: header.minor_version,
+            patch: header.patch_version,
+        };
+
+        // A newer major version may have an incompatible layout, so is never
+        // accepted
+        if version_policy == VersionPolicy::Strict || header.major_version > MAX_VERSION_MAJOR {
+            return Err(error);
+        }
+        warning = Some(format!("{} - parsed known fields only", error));
     }
 
     if header.major_version == 0 && header.minor_version < 4 {
-        // Extra info and _post fields are invalid - re-initialize them.
+        // Extra info and ROM CRC fields are invalid - re-initialize them.
         header.extra_ptr = 0xFFFFFFFF;
-        header._post = [0xFF; 4];
+        header.rom_crc32 = 0xFFFFFFFF;
     }
 
-    Ok(header)
+    Ok((header, warning))
 }
 
+// A string read from the firmware, along with a warning if it wasn't valid
+// UTF-8, and so was decoded as Latin-1
+pub(crate) type StringRead = (String, Option<ReadError>);
+
 /// Read a null-terminated string from the given pointer
+///
+/// Strings which aren't valid UTF-8 are decoded as Latin-1, which maps each
+/// byte to a character and so never fails, with a warning returned alongside
+/// the string.  Use `strings.strict_utf8` to return an error instead.
 pub(crate) async fn read_string_at_ptr<R: Reader>(
     reader: &mut R,
     ptr: u32,
-    base_addr: u32,
-) -> Result<String, String> {
-    if ptr < base_addr {
-        return Err(format!("Invalid pointer: 0x{:08X}", ptr));
+    flash: Flash,
+    strings: StringOptions,
+) -> Result<StringRead, ReadError> {
+    let bytes = read_bytes_at_ptr(reader, ptr, flash, strings.read_size).await?;
+    match String::from_utf8(bytes) {
+        Ok(string) => Ok((string, None)),
+        Err(_) if strings.strict_utf8 => Err((ErrorKind::BadUtf8, "Invalid UTF-8 string".into())),
+        Err(e) => {
+            let string: String = e.as_bytes().iter().map(|&b| b as char).collect();
+            let reason = format!("Invalid UTF-8 string, decoded as Latin-1: {}", string);
+            Ok((string, Some((ErrorKind::BadUtf8, reason))))
+        }
     }
+}
 
+/// Return the string read, if any, recording any error or warning against
+/// `field`
+pub(crate) fn record_string_read(
+    field: &str,
+    result: Result<StringRead, ReadError>,
+    errors: &mut Vec<ParseError>,
+) -> Option<String> {
+    match result {
+        Ok((string, warning)) => {
+            if let Some(warning) = warning {
+                errors.push(ParseError::from_read(field, warning));
+            }
+            Some(string)
+        }
+        Err(e) => {
+            errors.push(ParseError::from_read(field, e));
+            None
+        }
+    }
+}
+
+/// Read the raw bytes of a null-terminated string from the given pointer,
+/// excluding the terminator
+///
+/// Reads `read_size` bytes at a time, so most strings are read in a single
+/// round-trip, only reading further if no NUL terminator is found.  Reads
+/// stop at the end of the firmware, if its length is known.  Otherwise, if a
+/// read fails, which a large read near the end of the firmware may, it is
+/// retried with progressively smaller reads.
+pub(crate) async fn read_bytes_at_ptr<R: Reader>(
+    reader: &mut R,
+    ptr: u32,
+    flash: Flash,
+    read_size: usize,
+) -> Result<Vec<u8>, ReadError> {
+    flash.resolve_ptr(ptr, 1)?;
+
+    let mut read_size = read_size.clamp(1, MAX_STRING_LEN);
     let mut result = Vec::new();
     let mut addr = ptr;
-    let mut buf = [0u8; STRING_READ_CHUNK_SIZE];
+    let mut buf = vec![0u8; read_size];
 
     loop {
-        let chunk_size = buf.len().min(MAX_STRING_LEN - result.len());
-        reader
-            .read(addr, &mut buf[..chunk_size])
-            .await
-            .map_err(|_| format!("Failed to read string at 0x{:08X}", ptr))?;
+        let mut chunk_size = read_size.min(MAX_STRING_LEN - result.len());
+        if let Some(remaining) = flash.remaining(addr) {
+            if remaining == 0 {
+                let reason = format!("String at 0x{:08X} runs past the end of the firmware", ptr);
+                return Err((ErrorKind::OutOfBounds, reason));
+            }
+            chunk_size = chunk_size.min(remaining);
+        }
+        if reader.read(addr, &mut buf[..chunk_size]).await.is_err() {
+            if read_size > 1 {
+                read_size /= 2;
+                continue;
+            }
+            return Err((ErrorKind::ReadFailed, format!("Failed to read string at 0x{:08X}", ptr)));
+        }
 
         if let Some(null_pos) = buf[..chunk_size].iter().position(|&b| b == 0) {
             result.extend_from_slice(&buf[..null_pos]);
@@ -310,31 +494,30 @@ pub(crate) async fn read_string_at_ptr<R: Reader>(
         result.extend_from_slice(&buf[..chunk_size]);
         addr += chunk_size as u32;
 
-        if result.len() >= 1024 {
-            return Err("String too long (>1KB)".into());
+        if result.len() >= MAX_STRING_LEN {
+            return Err((ErrorKind::StringTooLong, "String too long (>1KB)".into()));
         }
     }
 
-    String::from_utf8(result).map_err(|_| "Invalid UTF-8 string".into())
+    Ok(result)
 }
 
 pub(crate) async fn read_extra_info<R: Reader>(
     reader: &mut R,
     ptr: u32,
-    base_addr: u32,
-) -> Result<SdrrExtraInfo, String> {
-    if ptr < base_addr {
-        return Err(format!("Invalid pointer: 0x{:08X}", ptr));
-    }
-
+    flash: Flash,
+) -> Result<SdrrExtraInfo, ReadError> {
     let mut buf = [0u8; SdrrExtraInfoHeader::size()];
+    let ptr = flash.resolve_ptr(ptr, buf.len())?;
     reader
         .read(ptr, &mut buf)
         .await
-        .map_err(|_| format!("Failed to read extra info at 0x{:08X}", ptr))?;
+        .map_err(|_| {
+            (ErrorKind::ReadFailed, format!("Failed to read extra info at 0x{:08X}", ptr))
+        })?;
 
     let (_, header) = SdrrExtraInfoHeader::from_bytes((&buf, 0))
-        .map_err(|e| format!("Failed to parse extra info: {}", e))?;
+        .map_err(|e| (ErrorKind::UnsupportedValue, format!("Failed to parse extra info: {}", e)))?;
 
     Ok(SdrrExtraInfo {
         rtt_ptr: header.rtt_ptr,
@@ -346,35 +529,42 @@ pub(crate) async fn read_rom_sets<R: Reader>(
     reader: &mut R,
     ptr: u32,
     count: u8,
-    base_addr: u32,
+    flash: Flash,
     boot_logging_enabled: bool,
-) -> Result<Vec<SdrrRomSet>, String> {
-    if ptr < base_addr || count == 0 {
+    strings: StringOptions,
+    warnings: &mut Vec<ParseError>,
+) -> Result<Vec<SdrrRomSet>, ReadError> {
+    if ptr < flash.base || count == 0 {
         return Ok(Vec::new());
     }
 
     let mut rom_sets = Vec::with_capacity(count as usize);
 
     for i in 0..count {
-        let header_addr = ptr + (i as u32 * SdrrRomSetHeader::size() as u32);
+        let header_addr = ptr.saturating_add(i as u32 * SdrrRomSetHeader::size() as u32);
 
         // Read ROM set header
         let mut header_buf = [0u8; SdrrRomSetHeader::size()];
+        let header_addr = flash.resolve_ptr(header_addr, header_buf.len())?;
         reader
             .read(header_addr, &mut header_buf)
             .await
-            .map_err(|_| format!("Failed to read ROM set header {}", i))?;
+            .map_err(|_| (ErrorKind::ReadFailed, format!("Failed to read ROM set header {}", i)))?;
 
-        let (_, header) = SdrrRomSetHeader::from_bytes((&header_buf, 0))
-            .map_err(|e| format!("Failed to parse ROM set header {}: {}", i, e))?;
+        let (_, header) = SdrrRomSetHeader::from_bytes((&header_buf, 0)).map_err(|e| {
+            let reason = format!("Failed to parse ROM set header {}: {}", i, e);
+            (ErrorKind::UnsupportedValue, reason)
+        })?;
 
         // Read ROM infos
         let roms = read_rom_infos(
             reader,
-            header.roms_ptr,
-            header.rom_count,
-            base_addr,
+            i,
+            &header,
+            flash,
             boot_logging_enabled,
+            strings,
+            warnings,
         )
         .await?;
 
@@ -395,25 +585,44 @@ pub(crate) async fn read_rom_sets<R: Reader>(
 // Read ROM info structures
 async fn read_rom_infos<R: Reader>(
     reader: &mut R,
-    ptr: u32,
-    count: u8,
-    base_addr: u32,
+    set: u8,
+    set_header: &SdrrRomSetHeader,
+    flash: Flash,
     boot_logging_enabled: bool,
-) -> Result<Vec<SdrrRomInfo>, String> {
-    if ptr < base_addr || count == 0 {
+    strings: StringOptions,
+    warnings: &mut Vec<ParseError>,
+) -> Result<Vec<SdrrRomInfo>, ReadError> {
+    let ptr = set_header.roms_ptr;
+    let count = set_header.rom_count;
+    if ptr < flash.base || count == 0 {
         return Ok(Vec::new());
     }
 
+    // Check the ROM count, and that the whole pointer array is within the
+    // firmware, before reading any of it, so a corrupt count can't lead to
+    // reading through garbage pointers
+    if count as usize > MAX_ROMS_PER_SET {
+        let reason = format!(
+            "ROM set {} has {} ROMs, more than the maximum {}",
+            set, count, MAX_ROMS_PER_SET
+        );
+        return Err((ErrorKind::UnsupportedValue, reason));
+    }
+    flash.resolve_ptr(ptr, count as usize * core::mem::size_of::<u32>())?;
+
     let mut rom_infos = Vec::with_capacity(count as usize);
 
     for i in 0..count {
         // Read pointer to ROM info
-        let ptr_addr = ptr + (i as u32 * core::mem::size_of::<u32>() as u32);
+        let ptr_addr = ptr.saturating_add(i as u32 * core::mem::size_of::<u32>() as u32);
         let mut ptr_buf = [0u8; core::mem::size_of::<u32>()];
+        let ptr_addr = flash.resolve_ptr(ptr_addr, ptr_buf.len())?;
         reader
             .read(ptr_addr, &mut ptr_buf)
             .await
-            .map_err(|_| format!("Failed to read ROM info pointer {}", i))?;
+            .map_err(|_| {
+                (ErrorKind::ReadFailed, format!("Failed to read ROM info pointer {}", i))
+            })?;
 
         let rom_info_ptr = u32::from_le_bytes(ptr_buf);
 
@@ -424,19 +633,22 @@ async fn read_rom_infos<R: Reader>(
             SdrrRomInfoBasic::size()
         };
         let mut info_buf = vec![0u8; info_size];
+        let rom_info_ptr = flash.resolve_ptr(rom_info_ptr, info_size)?;
         reader
             .read(rom_info_ptr, &mut info_buf)
             .await
-            .map_err(|_| format!("Failed to read ROM info {}", i))?;
+            .map_err(|_| (ErrorKind::ReadFailed, format!("Failed to read ROM info {}", i)))?;
 
         let rom_info = if boot_logging_enabled {
-            let (_, info) = SdrrRomInfoWithLogging::from_bytes((&info_buf, 0))
-                .map_err(|e| format!("Failed to parse ROM info with logging {}: {}", i, e))?;
-
-            let filename = if info.filename_ptr >= base_addr {
-                read_string_at_ptr(reader, info.filename_ptr, base_addr)
-                    .await
-                    .ok()
+            let (_, info) = SdrrRomInfoWithLogging::from_bytes((&info_buf, 0)).map_err(|e| {
+                let reason = format!("Failed to parse ROM info with logging {}: {}", i, e);
+                (ErrorKind::UnsupportedValue, reason)
+            })?;
+
+            let filename = if strings.enabled && info.filename_ptr >= flash.base {
+                let field = format!("ROM Set {} ROM {} Filename", set, i);
+                let result = read_string_at_ptr(reader, info.filename_ptr, flash, strings);
+                record_string_read(&field, result.await, warnings)
             } else {
                 None
             };
@@ -449,8 +661,10 @@ async fn read_rom_infos<R: Reader>(
                 filename,
             }
         } else {
-            let (_, info) = SdrrRomInfoBasic::from_bytes((&info_buf, 0))
-                .map_err(|e| format!("Failed to parse ROM info basic {}: {}", i, e))?;
+            let (_, info) = SdrrRomInfoBasic::from_bytes((&info_buf, 0)).map_err(|e| {
+                let reason = format!("Failed to parse ROM info basic {}: {}", i, e);
+                (ErrorKind::UnsupportedValue, reason)
+            })?;
 
             SdrrRomInfo {
                 rom_type: info.rom_type,
@@ -471,19 +685,24 @@ async fn read_rom_infos<R: Reader>(
 pub(crate) async fn read_pins<R: Reader>(
     reader: &mut R,
     ptr: u32,
-    base_addr: u32,
-) -> Result<SdrrPins, String> {
-    if ptr < base_addr {
-        return Err(format!("Invalid pins pointer: 0x{:08X}", ptr));
-    }
-
+    flash: Flash,
+) -> Result<SdrrPins, ReadError> {
     let mut pins_buf = [0u8; SdrrPins::size()];
+    let ptr = flash.resolve_ptr(ptr, pins_buf.len())?;
     reader
         .read(ptr, &mut pins_buf)
         .await
-        .map_err(|_| "Failed to read pins data")?;
+        .map_err(|_| (ErrorKind::ReadFailed, "Failed to read pins data".into()))?;
+
+    // The five port fields lead the structure - check them up front so an
+    // unknown port gets a more useful error than deku's
+    for (name, &port) in ["data", "addr", "cs", "sel", "status"].iter().zip(&pins_buf[..5]) {
+        SdrrMcuPort::try_from_u8(port).map_err(|e| {
+            (ErrorKind::UnsupportedValue, format!("Failed to parse pins: {name} port: {e}"))
+        })?;
+    }
 
     SdrrPins::from_bytes((&pins_buf, 0))
-        .map_err(|e| format!("Failed to parse pins: {}", e))
+        .map_err(|e| (ErrorKind::UnsupportedValue, format!("Failed to parse pins: {}", e)))
         .map(|(_, pins)| pins)
 }
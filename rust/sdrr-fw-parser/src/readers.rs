@@ -5,8 +5,16 @@
 //! sdrr-fw-parser
 //!
 //! Contains standard re-usable reader implementations for parsing SDRR firmware
+//!
+//! # Reading from a live device
+//!
+//! With the `probe-rs` feature, `ProbeReader` reads a device's flash and RAM
+//! via a debug probe.  Wrap it in a [`CachingReader`] to reduce the number of
+//! round-trips, and in a [`RetryReader`] to retry reads which occasionally
+//! fail.
 
 use crate::Reader;
+use log::debug;
 
 #[cfg(not(feature = "std"))]
 use alloc::{format, string::String, vec::Vec};
@@ -85,4 +93,818 @@ impl Reader for MemoryReader {
     fn update_base_address(&mut self, new_base: u32) {
         self.base_address = new_base;
     }
+
+    fn firmware_len(&self) -> Option<u32> {
+        u32::try_from(self.data.len()).ok()
+    }
+}
+
+/// A reader that operates on a firmware file on disk.
+///
+/// Rather than loading the whole file into memory, as [`MemoryReader`] does,
+/// this seeks to the requested address within the file for each read.  Only
+/// available with the `std` feature.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use sdrr_fw_parser::{Parser, readers::FileReader};
+///
+/// let reader = FileReader::open("firmware.bin", 0x08000000)?;
+/// let mut parser = Parser::new(reader);
+/// let info = smol::block_on(parser.parse_flash())?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct FileReader {
+    file: std::fs::File,
+    base_address: u32,
+}
+
+#[cfg(feature = "std")]
+impl FileReader {
+    /// Open a firmware file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The firmware file to open
+    /// * `base_address` - The base address where this firmware would be loaded
+    ///   in the target device (typically `0x08000000` for STM32F4)
+    pub fn open(path: impl AsRef<std::path::Path>, base_address: u32) -> std::io::Result<Self> {
+        let file = std::fs::File::open(path)?;
+        Ok(Self::new(file, base_address))
+    }
+
+    /// Create a new file reader from an already opened file.
+    pub fn new(file: std::fs::File, base_address: u32) -> Self {
+        Self { file, base_address }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Reader for FileReader {
+    type Error = std::io::Error;
+
+    async fn read(&mut self, addr: u32, buf: &mut [u8]) -> Result<(), Self::Error> {
+        use std::io::{Read, Seek, SeekFrom};
+
+        if addr < self.base_address {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "Address 0x{:08X} is below base address 0x{:08X}",
+                    addr, self.base_address
+                ),
+            ));
+        }
+
+        let offset = (addr - self.base_address) as u64;
+        self.file.seek(SeekFrom::Start(offset))?;
+        self.file.read_exact(buf)
+    }
+
+    fn update_base_address(&mut self, new_base: u32) {
+        self.base_address = new_base;
+    }
+
+    fn firmware_len(&self) -> Option<u32> {
+        let len = self.file.metadata().ok()?.len();
+        u32::try_from(len).ok()
+    }
+}
+
+// A section of an ELF file which occupies memory in the target
+#[cfg(feature = "elf")]
+#[derive(Debug, Clone)]
+struct ElfSection {
+    name: String,
+    addr: u64,
+    size: u64,
+    offset: usize,
+    in_file: bool,
+}
+
+/// A reader that operates on an ELF firmware file, as produced by the
+/// firmware build.  Only available with the `elf` feature.
+///
+/// Absolute addresses are mapped to offsets within the file using each
+/// allocated section's address (`sh_addr`), so no synthetic binary image
+/// needs to be created.  Reading an address which isn't in a section, or is
+/// in a section with no data in the file (such as `.bss`), returns an error.
+///
+/// The firmware's flash base is detected from its lowest allocated section.
+/// Reads are made relative to that, so the parser's initial STM32F4 base
+/// address works for RP2350 firmware too.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use sdrr_fw_parser::{Parser, readers::ElfReader};
+///
+/// let reader = ElfReader::open("firmware.elf")?;
+/// let mut parser = Parser::new(reader);
+/// let info = smol::block_on(parser.parse_flash())?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[cfg(feature = "elf")]
+#[derive(Debug)]
+pub struct ElfReader {
+    data: Vec<u8>,
+    sections: Vec<ElfSection>,
+    elf_flash_base: u32,
+    base_address: u32,
+}
+
+#[cfg(feature = "elf")]
+impl ElfReader {
+    /// Open and parse an ELF firmware file.
+    pub fn open(path: impl AsRef<std::path::Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let data = std::fs::read(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        Self::from_bytes(data)
+    }
+
+    /// Parse an ELF firmware file which has already been loaded into memory.
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self, String> {
+        use goblin::elf::section_header::{SHF_ALLOC, SHT_NOBITS};
+
+        let elf = goblin::elf::Elf::parse(&data).map_err(|e| format!("Invalid ELF file: {}", e))?;
+
+        let mut sections = Vec::new();
+        for sh in elf.section_headers.iter() {
+            if sh.sh_flags & SHF_ALLOC as u64 == 0 || sh.sh_size == 0 {
+                continue;
+            }
+
+            let name = elf
+                .shdr_strtab
+                .get_at(sh.sh_name)
+                .unwrap_or("<unknown>")
+                .to_string();
+            let in_file = sh.sh_type != SHT_NOBITS;
+            if in_file && sh.sh_offset.saturating_add(sh.sh_size) > data.len() as u64 {
+                return Err(format!("ELF section {} extends past end of file", name));
+            }
+
+            sections.push(ElfSection {
+                name,
+                addr: sh.sh_addr,
+                size: sh.sh_size,
+                offset: sh.sh_offset as usize,
+                in_file,
+            });
+        }
+
+        let lowest = sections
+            .iter()
+            .filter(|section| section.in_file)
+            .map(|section| section.addr)
+            .min()
+            .ok_or("ELF file contains no allocated sections")?;
+        let elf_flash_base = (lowest as u32) & 0xFF00_0000;
+
+        Ok(Self {
+            data,
+            sections,
+            elf_flash_base,
+            base_address: crate::STM32F4_FLASH_BASE,
+        })
+    }
+
+    /// Returns the flash base address detected from the ELF file's sections.
+    pub fn elf_flash_base(&self) -> u32 {
+        self.elf_flash_base
+    }
+}
+
+#[cfg(feature = "elf")]
+impl Reader for ElfReader {
+    type Error = String;
+
+    async fn read(&mut self, addr: u32, buf: &mut [u8]) -> Result<(), Self::Error> {
+        if addr < self.base_address {
+            return Err(format!(
+                "Address 0x{:08X} is below base address 0x{:08X}",
+                addr, self.base_address
+            ));
+        }
+        let start = (addr - self.base_address) as u64 + self.elf_flash_base as u64;
+
+        // A read may span adjacent sections, so fill the buffer a section at
+        // a time
+        let mut done = 0;
+        while done < buf.len() {
+            let cur = start + done as u64;
+            let section = self
+                .sections
+                .iter()
+                .find(|section| cur >= section.addr && cur < section.addr + section.size)
+                .ok_or_else(|| format!("Address 0x{:08X} is not in any ELF section", cur))?;
+            if !section.in_file {
+                return Err(format!(
+                    "Address 0x{:08X} is in section {}, which has no data in the ELF file",
+                    cur, section.name
+                ));
+            }
+
+            let section_offset = (cur - section.addr) as usize;
+            let len = (section.size as usize - section_offset).min(buf.len() - done);
+            let offset = section.offset + section_offset;
+            buf[done..done + len].copy_from_slice(&self.data[offset..offset + len]);
+            done += len;
+        }
+
+        Ok(())
+    }
+
+    fn update_base_address(&mut self, new_base: u32) {
+        self.base_address = new_base;
+    }
+}
+
+/// A reader for a live device, attached via a debug probe using probe-rs.
+/// Only available with the `probe-rs` feature.
+///
+/// Reads are made at absolute addresses via the core's memory interface, so
+/// both the firmware in flash and the runtime info in RAM can be read.
+///
+/// By default the core is left running, and memory is read in the
+/// background, so a device can be inspected while serving its host.  Use
+/// [`ProbeReader::with_halt`] to halt the core for each read instead.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use sdrr_fw_parser::{Parser, readers::ProbeReader};
+///
+/// let reader = ProbeReader::attach("STM32F446RETx")?;
+/// let mut parser = Parser::new(reader);
+/// let info = smol::block_on(parser.parse_flash())?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[cfg(feature = "probe-rs")]
+pub struct ProbeReader {
+    session: probe_rs::Session,
+    halt: bool,
+}
+
+#[cfg(feature = "probe-rs")]
+impl ProbeReader {
+    // How long to wait for the core to halt before a read
+    const HALT_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(100);
+
+    /// Attach to a chip using the first debug probe found.
+    ///
+    /// # Arguments
+    ///
+    /// * `chip_id` - The probe-rs target name, such as `STM32F446RETx`, as
+    ///   returned by `McuVariant::chip_id()` in `sdrr-common`
+    pub fn attach(chip_id: &str) -> Result<Self, probe_rs::Error> {
+        let session = probe_rs::Session::auto_attach(chip_id, Default::default())?;
+        Ok(Self {
+            session,
+            halt: false,
+        })
+    }
+
+    /// Halt the core for each read, resuming it afterwards if it was
+    /// running.  Use this if the target doesn't support reading memory while
+    /// the core runs.
+    pub fn with_halt(mut self, halt: bool) -> Self {
+        self.halt = halt;
+        self
+    }
+
+    /// Returns the base address of the chip's flash, from probe-rs's memory
+    /// map for it, to pass to [`crate::Parser::with_base_flash_address`].
+    pub fn flash_base(&self) -> Option<u32> {
+        self.session
+            .target()
+            .memory_map
+            .iter()
+            .filter_map(|region| match region {
+                probe_rs::config::MemoryRegion::Nvm(nvm) if !nvm.is_alias => {
+                    u32::try_from(nvm.range.start).ok()
+                }
+                _ => None,
+            })
+            .min()
+    }
+}
+
+#[cfg(feature = "probe-rs")]
+impl Reader for ProbeReader {
+    type Error = probe_rs::Error;
+
+    async fn read(&mut self, addr: u32, buf: &mut [u8]) -> Result<(), Self::Error> {
+        use probe_rs::MemoryInterface;
+
+        let mut core = self.session.core(0)?;
+        let halted = self.halt && !core.core_halted()?;
+        if halted {
+            core.halt(Self::HALT_TIMEOUT)?;
+        }
+
+        // Always resume a core halted for the read, even if the read failed
+        let result = core.read(addr as u64, buf);
+        let resumed = if halted { core.run() } else { Ok(()) };
+        result.and(resumed)
+    }
+
+    fn update_base_address(&mut self, _new_base: u32) {
+        // Reads are made at absolute addresses
+    }
+}
+
+/// A reader adapter which caches recently read pages from another reader.
+///
+/// The parser reads headers and strings in small, often overlapping, chunks.
+/// When the underlying reader is slow - for example reading over SWD - it is
+/// worth reading whole aligned pages instead, and serving subsequent reads
+/// from those pages.
+///
+/// Up to `PAGES` pages of `PAGE_SIZE` bytes each are cached, with the oldest
+/// page replaced first.  The cache uses `PAGES * PAGE_SIZE` bytes of RAM, so
+/// embedded users can size it to suit.  `PAGE_SIZE` must be a power of 2.
+///
+/// If a full page cannot be read (for example the page runs past the end of
+/// the firmware), the read is passed straight through to the underlying
+/// reader instead.
+///
+/// # Example
+///
+/// ```rust
+/// use sdrr_fw_parser::readers::{CachingReader, MemoryReader};
+///
+/// let reader = MemoryReader::new(vec![0; 0x1000], 0x08000000);
+/// let reader: CachingReader<_, 256, 8> = CachingReader::new(reader);
+/// ```
+#[derive(Debug)]
+pub struct CachingReader<R: Reader, const PAGE_SIZE: usize = 256, const PAGES: usize = 8> {
+    reader: R,
+    pages: [[u8; PAGE_SIZE]; PAGES],
+    tags: [Option<u32>; PAGES],
+    next: usize,
+}
+
+impl<R: Reader, const PAGE_SIZE: usize, const PAGES: usize> CachingReader<R, PAGE_SIZE, PAGES> {
+    /// Create a new caching reader wrapping the given reader.
+    pub fn new(reader: R) -> Self {
+        assert!(
+            PAGE_SIZE.is_power_of_two(),
+            "Page size must be a power of 2"
+        );
+        assert!(PAGES > 0, "Must cache at least one page");
+        Self {
+            reader,
+            pages: [[0; PAGE_SIZE]; PAGES],
+            tags: [None; PAGES],
+            next: 0,
+        }
+    }
+
+    /// Discard all cached pages.
+    pub fn invalidate(&mut self) {
+        self.tags = [None; PAGES];
+        self.next = 0;
+    }
+
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    // Returns the index of the cached page starting at page_addr, reading it
+    // from the underlying reader if it isn't cached.
+    async fn page(&mut self, page_addr: u32) -> Result<usize, R::Error> {
+        if let Some(index) = self.tags.iter().position(|&tag| tag == Some(page_addr)) {
+            return Ok(index);
+        }
+
+        let index = self.next;
+        self.tags[index] = None;
+        self.reader.read(page_addr, &mut self.pages[index]).await?;
+        self.tags[index] = Some(page_addr);
+        self.next = (index + 1) % PAGES;
+
+        Ok(index)
+    }
+}
+
+impl<R, const PAGE_SIZE: usize, const PAGES: usize> Reader for CachingReader<R, PAGE_SIZE, PAGES>
+where
+    R: Reader + Send,
+    R::Error: Send,
+{
+    type Error = R::Error;
+
+    async fn read(&mut self, addr: u32, buf: &mut [u8]) -> Result<(), Self::Error> {
+        let mut done = 0;
+        while done < buf.len() {
+            let cur_addr = addr + done as u32;
+            let page_addr = cur_addr & !(PAGE_SIZE as u32 - 1);
+            let page_offset = (cur_addr - page_addr) as usize;
+            let len = (PAGE_SIZE - page_offset).min(buf.len() - done);
+
+            match self.page(page_addr).await {
+                Ok(index) => {
+                    buf[done..done + len]
+                        .copy_from_slice(&self.pages[index][page_offset..page_offset + len]);
+                    done += len;
+                }
+                Err(_) => {
+                    // Couldn't read the whole page, so read the rest directly
+                    return self.reader.read(cur_addr, &mut buf[done..]).await;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn update_base_address(&mut self, new_base: u32) {
+        self.invalidate();
+        self.reader.update_base_address(new_base);
+    }
+
+    fn firmware_len(&self) -> Option<u32> {
+        self.reader.firmware_len()
+    }
+}
+
+/// A reader adapter which retries failed reads of another reader.
+///
+/// Reads over a debug link such as SWD or JTAG occasionally fail, and a
+/// retry usually succeeds.  As the parser makes many small reads, a single
+/// failure part way through would otherwise lose everything parsed so far.
+///
+/// Each failed read is retried up to `retries` times, but only if
+/// `is_transient` returns `true` for the error - so errors such as reading
+/// outside of the firmware fail immediately.  There is no delay between
+/// attempts.
+///
+/// # Example
+///
+/// ```rust
+/// use sdrr_fw_parser::readers::{MemoryReader, RetryReader};
+///
+/// let reader = MemoryReader::new(vec![0; 0x1000], 0x08000000);
+/// let reader = RetryReader::new(reader, 3, |_e: &String| true);
+/// ```
+#[derive(Debug)]
+pub struct RetryReader<R: Reader, F> {
+    reader: R,
+    retries: usize,
+    is_transient: F,
+}
+
+impl<R, F> RetryReader<R, F>
+where
+    R: Reader,
+    F: FnMut(&R::Error) -> bool,
+{
+    /// Create a new retrying reader wrapping the given reader, retrying each
+    /// read up to `retries` times when `is_transient` returns `true` for the
+    /// error.
+    pub fn new(reader: R, retries: usize, is_transient: F) -> Self {
+        Self {
+            reader,
+            retries,
+            is_transient,
+        }
+    }
+
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R, F> Reader for RetryReader<R, F>
+where
+    R: Reader + Send,
+    R::Error: Send,
+    F: FnMut(&R::Error) -> bool + Send,
+{
+    type Error = R::Error;
+
+    async fn read(&mut self, addr: u32, buf: &mut [u8]) -> Result<(), Self::Error> {
+        let mut attempt = 0;
+        loop {
+            match self.reader.read(addr, buf).await {
+                Ok(()) => return Ok(()),
+                Err(e) if attempt < self.retries && (self.is_transient)(&e) => {
+                    attempt += 1;
+                    debug!("Retrying read of 0x{:08X} ({}/{})", addr, attempt, self.retries);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn update_base_address(&mut self, new_base: u32) {
+        self.reader.update_base_address(new_base);
+    }
+
+    fn firmware_len(&self) -> Option<u32> {
+        self.reader.firmware_len()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::writer::Writer;
+    use crate::writer::tests::test_info;
+    use crate::{Parser, STM32F4_FLASH_BASE};
+
+    // Counts the reads made of the wrapped reader
+    pub(crate) struct CountingReader {
+        pub(crate) reader: MemoryReader,
+        pub(crate) reads: usize,
+    }
+
+    impl Reader for CountingReader {
+        type Error = String;
+
+        async fn read(&mut self, addr: u32, buf: &mut [u8]) -> Result<(), Self::Error> {
+            self.reads += 1;
+            self.reader.read(addr, buf).await
+        }
+
+        fn update_base_address(&mut self, new_base: u32) {
+            self.reader.update_base_address(new_base);
+        }
+
+        fn firmware_len(&self) -> Option<u32> {
+            self.reader.firmware_len()
+        }
+    }
+
+    pub(crate) fn counting_reader(data: Vec<u8>) -> CountingReader {
+        CountingReader {
+            reader: MemoryReader::new(data, STM32F4_FLASH_BASE),
+            reads: 0,
+        }
+    }
+
+    #[test]
+    fn test_caching_reader_repeated_reads() {
+        let data: Vec<u8> = (0..0x1000).map(|ii| ii as u8).collect();
+        let mut reader: CachingReader<_, 256, 2> = CachingReader::new(counting_reader(data));
+
+        smol::block_on(async {
+            // 100 small reads within the same page, and straddling two pages
+            for _ in 0..50 {
+                let mut buf = [0; 4];
+                reader.read(STM32F4_FLASH_BASE + 0x210, &mut buf).await.unwrap();
+                assert_eq!(buf, [0x10, 0x11, 0x12, 0x13]);
+
+                let mut buf = [0; 4];
+                reader.read(STM32F4_FLASH_BASE + 0x2FE, &mut buf).await.unwrap();
+                assert_eq!(buf, [0xFE, 0xFF, 0x00, 0x01]);
+            }
+        });
+        assert_eq!(reader.reader.reads, 2);
+
+        // A third page evicts the oldest
+        smol::block_on(async {
+            let mut buf = [0; 1];
+            reader.read(STM32F4_FLASH_BASE + 0x400, &mut buf).await.unwrap();
+            reader.read(STM32F4_FLASH_BASE + 0x300, &mut buf).await.unwrap();
+            reader.read(STM32F4_FLASH_BASE + 0x200, &mut buf).await.unwrap();
+        });
+        assert_eq!(reader.reader.reads, 4);
+    }
+
+    #[test]
+    fn test_caching_reader_past_end() {
+        // The last page is only partially present, so must be read directly
+        let data: Vec<u8> = (0..0x180).map(|ii| ii as u8).collect();
+        let mut reader: CachingReader<_, 256, 2> = CachingReader::new(counting_reader(data));
+
+        smol::block_on(async {
+            let mut buf = [0; 2];
+            reader.read(STM32F4_FLASH_BASE + 0x17E, &mut buf).await.unwrap();
+            assert_eq!(buf, [0x7E, 0x7F]);
+
+            assert!(reader.read(STM32F4_FLASH_BASE + 0x17F, &mut buf).await.is_err());
+        });
+    }
+
+    #[test]
+    fn test_caching_reader_parse() {
+        let image = Writer::new(&test_info()).write().unwrap();
+
+        let mut parser = Parser::new(counting_reader(image.clone()));
+        let uncached = smol::block_on(parser.parse_flash()).unwrap();
+        let uncached_reads = parser.reader.reads;
+
+        let reader: CachingReader<_> = CachingReader::new(counting_reader(image));
+        let mut parser = Parser::new(reader);
+        let cached = smol::block_on(parser.parse_flash()).unwrap();
+        let cached_reads = parser.reader.reader.reads;
+
+        assert_eq!(format!("{:?}", cached), format!("{:?}", uncached));
+        assert!(
+            cached_reads < uncached_reads,
+            "{} cached reads, {} uncached",
+            cached_reads,
+            uncached_reads
+        );
+    }
+
+    // Fails the first `failures` reads, then succeeds
+    struct FlakyReader {
+        reader: CountingReader,
+        failures: usize,
+    }
+
+    impl Reader for FlakyReader {
+        type Error = String;
+
+        async fn read(&mut self, addr: u32, buf: &mut [u8]) -> Result<(), Self::Error> {
+            if self.failures > 0 {
+                self.failures -= 1;
+                self.reader.reads += 1;
+                return Err("Glitch".into());
+            }
+            self.reader.read(addr, buf).await
+        }
+
+        fn update_base_address(&mut self, new_base: u32) {
+            self.reader.update_base_address(new_base);
+        }
+    }
+
+    fn flaky_reader(failures: usize) -> FlakyReader {
+        let data: Vec<u8> = (0..0x100).map(|ii| ii as u8).collect();
+        FlakyReader {
+            reader: counting_reader(data),
+            failures,
+        }
+    }
+
+    #[test]
+    fn test_retry_reader() {
+        // Fails once, then succeeds on the retry
+        let mut reader = RetryReader::new(flaky_reader(1), 2, |_e: &String| true);
+        let mut buf = [0; 2];
+        smol::block_on(reader.read(STM32F4_FLASH_BASE + 0x10, &mut buf)).unwrap();
+        assert_eq!(buf, [0x10, 0x11]);
+        assert_eq!(reader.into_inner().reader.reads, 2);
+
+        // Gives up after the retries are used
+        let mut reader = RetryReader::new(flaky_reader(3), 2, |_e: &String| true);
+        let result = smol::block_on(reader.read(STM32F4_FLASH_BASE, &mut buf));
+        assert_eq!(result, Err("Glitch".to_string()));
+        assert_eq!(reader.into_inner().reader.reads, 3);
+
+        // Errors which aren't transient aren't retried
+        let mut reader = RetryReader::new(flaky_reader(1), 2, |e: &String| e != "Glitch");
+        assert!(smol::block_on(reader.read(STM32F4_FLASH_BASE, &mut buf)).is_err());
+        assert_eq!(reader.into_inner().reader.reads, 1);
+    }
+
+    #[test]
+    fn test_retry_reader_parse() {
+        // A glitch part way through parsing doesn't lose the parse
+        let image = Writer::new(&test_info()).write().unwrap();
+        let reader = FlakyReader {
+            reader: counting_reader(image),
+            failures: 0,
+        };
+        let mut parser = Parser::new(RetryReader::new(reader, 1, |_e: &String| true));
+        assert!(smol::block_on(parser.detect()).is_ok());
+        parser.reader.reader.failures = 1;
+        let info = smol::block_on(parser.parse_flash()).unwrap();
+        assert!(info.parse_errors.is_empty());
+    }
+
+    #[test]
+    fn test_file_reader() {
+        let image = Writer::new(&test_info()).write().unwrap();
+        let path = std::env::temp_dir().join(format!("sdrr-fw-parser-{}.bin", std::process::id()));
+        std::fs::write(&path, &image).unwrap();
+
+        let mut reader = FileReader::open(&path, STM32F4_FLASH_BASE).unwrap();
+        smol::block_on(async {
+            let mut buf = [0; 4];
+            reader.read(STM32F4_FLASH_BASE + 0x200, &mut buf).await.unwrap();
+            assert_eq!(&buf, b"SDRR");
+
+            // Outside of the file
+            assert!(reader.read(STM32F4_FLASH_BASE - 4, &mut buf).await.is_err());
+            let end = STM32F4_FLASH_BASE + image.len() as u32 - 2;
+            assert!(reader.read(end, &mut buf).await.is_err());
+        });
+
+        let mut parser = Parser::new(reader);
+        let info = smol::block_on(parser.parse_flash()).unwrap();
+        assert_eq!(format!("{:?}", info), format!("{:?}", test_info()));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    // Builds a minimal little endian ELF32 file containing the given
+    // sections, each (name, address, data or None for NOBITS, size)
+    #[cfg(feature = "elf")]
+    fn build_elf(sections: &[(&str, u32, Option<&[u8]>, u32)]) -> Vec<u8> {
+        const SHT_PROGBITS: u32 = 1;
+        const SHT_STRTAB: u32 = 3;
+        const SHT_NOBITS: u32 = 8;
+        const SHF_ALLOC: u32 = 2;
+
+        let mut shstrtab = vec![0u8];
+        let mut body = Vec::new();
+        let mut headers = vec![[0u32; 10]];
+        for (name, addr, data, size) in sections {
+            let name_offset = shstrtab.len() as u32;
+            shstrtab.extend_from_slice(name.as_bytes());
+            shstrtab.push(0);
+            let offset = 52 + body.len() as u32;
+            let sh_type = match data {
+                Some(data) => {
+                    body.extend_from_slice(data);
+                    SHT_PROGBITS
+                }
+                None => SHT_NOBITS,
+            };
+            headers.push([name_offset, sh_type, SHF_ALLOC, *addr, offset, *size, 0, 0, 4, 0]);
+        }
+        let shstrtab_name = shstrtab.len() as u32;
+        shstrtab.extend_from_slice(b".shstrtab\0");
+        let shstrtab_offset = 52 + body.len() as u32;
+        body.extend_from_slice(&shstrtab);
+        headers.push([
+            shstrtab_name, SHT_STRTAB, 0, 0, shstrtab_offset, shstrtab.len() as u32, 0, 0, 1, 0,
+        ]);
+        while body.len() % 4 != 0 {
+            body.push(0);
+        }
+        let shoff = 52 + body.len() as u32;
+
+        let mut elf = Vec::new();
+        elf.extend_from_slice(b"\x7fELF\x01\x01\x01\0\0\0\0\0\0\0\0\0");
+        elf.extend_from_slice(&2u16.to_le_bytes()); // e_type: EXEC
+        elf.extend_from_slice(&40u16.to_le_bytes()); // e_machine: ARM
+        elf.extend_from_slice(&1u32.to_le_bytes()); // e_version
+        elf.extend_from_slice(&0u32.to_le_bytes()); // e_entry
+        elf.extend_from_slice(&0u32.to_le_bytes()); // e_phoff
+        elf.extend_from_slice(&shoff.to_le_bytes()); // e_shoff
+        elf.extend_from_slice(&0u32.to_le_bytes()); // e_flags
+        elf.extend_from_slice(&52u16.to_le_bytes()); // e_ehsize
+        elf.extend_from_slice(&32u16.to_le_bytes()); // e_phentsize
+        elf.extend_from_slice(&0u16.to_le_bytes()); // e_phnum
+        elf.extend_from_slice(&40u16.to_le_bytes()); // e_shentsize
+        elf.extend_from_slice(&(headers.len() as u16).to_le_bytes()); // e_shnum
+        elf.extend_from_slice(&((headers.len() - 1) as u16).to_le_bytes()); // e_shstrndx
+        elf.extend_from_slice(&body);
+        for header in headers {
+            for field in header {
+                elf.extend_from_slice(&field.to_le_bytes());
+            }
+        }
+        elf
+    }
+
+    #[cfg(feature = "elf")]
+    #[test]
+    fn test_elf_reader() {
+        let info = test_info();
+        let image = Writer::new(&info).write().unwrap();
+
+        // Split the image into an "isr_vector" section and the rest, leaving
+        // a gap between them, and add a .bss section
+        let elf = build_elf(&[
+            (".isr_vector", STM32F4_FLASH_BASE, Some(&image[..0x100]), 0x100),
+            (".text", STM32F4_FLASH_BASE + 0x200, Some(&image[0x200..]), image.len() as u32 - 0x200),
+            (".bss", 0x20000000, None, 0x100),
+        ]);
+        let mut reader = ElfReader::from_bytes(elf).unwrap();
+        assert_eq!(reader.elf_flash_base(), STM32F4_FLASH_BASE);
+
+        smol::block_on(async {
+            let mut buf = [0; 4];
+            reader.read(STM32F4_FLASH_BASE + 0x200, &mut buf).await.unwrap();
+            assert_eq!(&buf, b"SDRR");
+
+            // In the gap between sections
+            let err = reader.read(STM32F4_FLASH_BASE + 0x180, &mut buf).await.unwrap_err();
+            assert!(err.contains("not in any ELF section"), "{}", err);
+
+            // Spanning the end of the first section into the gap
+            assert!(reader.read(STM32F4_FLASH_BASE + 0xFE, &mut buf).await.is_err());
+
+            // No data for .bss in the file
+            let err = reader.read(0x20000000, &mut buf).await.unwrap_err();
+            assert!(err.contains(".bss"), "{}", err);
+        });
+
+        let mut parser = Parser::new(reader);
+        let parsed = smol::block_on(parser.parse_flash()).unwrap();
+        assert_eq!(format!("{:?}", parsed), format!("{:?}", info));
+    }
 }
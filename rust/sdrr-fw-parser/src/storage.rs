@@ -0,0 +1,418 @@
+// Copyright (C) 2025 Piers Finlayson <piers@piers.rocks>
+//
+// MIT License
+
+//! sdrr-fw-parser
+//!
+//! Fixed capacity storage for parsing SDRR firmware without building any
+//! `Vec`s or `String`s, using [`Parser::parse_into`](crate::Parser::parse_into).
+//!
+//! This is intended for bootloaders and other deeply embedded users, which
+//! need the core firmware configuration - the ROM sets and where their data
+//! is - but want it in fixed size storage, rather than on the heap.
+//!
+//! Parsing this way does not allocate, but the crate still links `alloc`,
+//! which the rest of the parser uses, so an allocator is still required.
+//!
+//! The capacity is fixed at compile time:
+//! - [`MAX_ROM_SETS`] ROM sets
+//! - [`MAX_ROMS_PER_SET`] ROMs per set
+//! - [`MAX_STORED_STRING_LEN`] bytes per string (longer strings are truncated)
+//!
+//! Firmware exceeding the set or ROM capacity fails to parse with
+//! [`StorageError::TooManyRomSets`] or [`StorageError::TooManyRoms`].
+//!
+//! Only the build date and hardware revision strings are stored.  ROM
+//! filenames are not, but their pointers are, in
+//! [`SdrrRomInfoStorage::filename_ptr`].  Similarly, the pin configuration is
+//! not parsed, but its pointer is stored in [`SdrrInfoStorage::pins_ptr`].
+
+use core::fmt;
+use deku::prelude::*;
+
+use crate::Reader;
+use crate::parsing::{SdrrRomInfoBasic, SdrrRomInfoWithLogging, SdrrRomSetHeader};
+use crate::{BootConfig, McuLine, McuStorage, SdrrCsState, SdrrRomType, SdrrServe};
+
+/// Maximum number of ROM sets that can be stored.  This is as many 16KB
+/// single ROM sets as fit in the flash of a 512KB STM32F4.
+pub const MAX_ROM_SETS: usize = 32;
+
+/// Maximum number of ROMs per set that can be stored.  Multi-ROM sets contain
+/// up to 3 ROMs, and bank switched sets up to 4.
+pub const MAX_ROMS_PER_SET: usize = 4;
+
+/// Maximum length of each string stored, in bytes.
+pub const MAX_STORED_STRING_LEN: usize = 64;
+
+/// Errors from [`Parser::parse_into`](crate::Parser::parse_into).
+///
+/// Unlike the `String` errors returned by the rest of the parser, these do
+/// not require an allocator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageError {
+    /// Failed to read from the firmware at this address
+    Read(u32),
+
+    /// The SDRR info header was not found, or is invalid
+    InvalidHeader,
+
+    /// The firmware version is newer than this parser supports
+    UnsupportedVersion { major: u16, minor: u16, patch: u16 },
+
+    /// The firmware contains more ROM sets than [`MAX_ROM_SETS`]
+    TooManyRomSets(u8),
+
+    /// A ROM set contains more ROMs than [`MAX_ROMS_PER_SET`]
+    TooManyRoms { set: usize, count: u8 },
+
+    /// The header for this ROM set is invalid
+    InvalidRomSet(usize),
+
+    /// The information for this ROM is invalid
+    InvalidRomInfo { set: usize, rom: usize },
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::Read(addr) => write!(f, "Failed to read at 0x{addr:08X}"),
+            StorageError::InvalidHeader => write!(f, "SDRR header not found or invalid"),
+            StorageError::UnsupportedVersion { major, minor, patch } => write!(
+                f,
+                "SDRR firmware version v{major}.{minor}.{patch} unsupported - max version v{}.{}.{}",
+                crate::MAX_VERSION_MAJOR,
+                crate::MAX_VERSION_MINOR,
+                crate::MAX_VERSION_PATCH
+            ),
+            StorageError::TooManyRomSets(count) => {
+                write!(f, "Too many ROM sets: {count} > {MAX_ROM_SETS}")
+            }
+            StorageError::TooManyRoms { set, count } => write!(
+                f,
+                "Too many ROMs in set {set}: {count} > {MAX_ROMS_PER_SET}"
+            ),
+            StorageError::InvalidRomSet(set) => write!(f, "Invalid ROM set header {set}"),
+            StorageError::InvalidRomInfo { set, rom } => {
+                write!(f, "Invalid ROM info {rom} in set {set}")
+            }
+        }
+    }
+}
+
+/// A string read from the firmware into fixed storage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SdrrStringStorage {
+    buf: [u8; MAX_STORED_STRING_LEN],
+    len: usize,
+}
+
+impl SdrrStringStorage {
+    pub(crate) const fn new() -> Self {
+        Self {
+            buf: [0; MAX_STORED_STRING_LEN],
+            len: 0,
+        }
+    }
+
+    /// The raw bytes of the string, without the null terminator.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+
+    /// The string, or `None` if it is not valid UTF-8.
+    pub fn as_str(&self) -> Option<&str> {
+        core::str::from_utf8(self.as_bytes()).ok()
+    }
+
+    /// Whether the string is empty - which it is if strings were not read.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+/// Information about a ROM, stored by [`Parser::parse_into`](crate::Parser::parse_into).
+///
+/// The equivalent of [`SdrrRomInfo`](crate::SdrrRomInfo).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SdrrRomInfoStorage {
+    pub rom_type: SdrrRomType,
+    pub cs1_state: SdrrCsState,
+    pub cs2_state: SdrrCsState,
+    pub cs3_state: SdrrCsState,
+
+    /// Pointer to the ROM's filename, only present if boot logging is enabled
+    pub filename_ptr: Option<u32>,
+}
+
+impl SdrrRomInfoStorage {
+    const fn new() -> Self {
+        Self {
+            rom_type: SdrrRomType::Rom2364,
+            cs1_state: SdrrCsState::NotUsed,
+            cs2_state: SdrrCsState::NotUsed,
+            cs3_state: SdrrCsState::NotUsed,
+            filename_ptr: None,
+        }
+    }
+}
+
+/// A ROM set, stored by [`Parser::parse_into`](crate::Parser::parse_into).
+///
+/// The equivalent of [`SdrrRomSet`](crate::SdrrRomSet).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SdrrRomSetStorage {
+    pub data_ptr: u32,
+    pub size: u32,
+    pub rom_count: u8,
+    pub serve: SdrrServe,
+    pub multi_rom_cs1_state: SdrrCsState,
+    roms: [SdrrRomInfoStorage; MAX_ROMS_PER_SET],
+}
+
+impl SdrrRomSetStorage {
+    const fn new() -> Self {
+        Self {
+            data_ptr: 0,
+            size: 0,
+            rom_count: 0,
+            serve: SdrrServe::AddrOnCs,
+            multi_rom_cs1_state: SdrrCsState::NotUsed,
+            roms: [SdrrRomInfoStorage::new(); MAX_ROMS_PER_SET],
+        }
+    }
+
+    /// The ROMs in this set.
+    pub fn roms(&self) -> &[SdrrRomInfoStorage] {
+        &self.roms[..self.rom_count as usize]
+    }
+}
+
+/// Caller provided storage for [`Parser::parse_into`](crate::Parser::parse_into).
+///
+/// The equivalent of [`SdrrInfo`](crate::SdrrInfo), with fixed capacity - see
+/// the [module documentation](self) for the limits.  This is around 2.5KB in
+/// size, so may be better placed in a `static` than on the stack.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SdrrInfoStorage {
+    pub major_version: u16,
+    pub minor_version: u16,
+    pub patch_version: u16,
+    pub build_number: u16,
+    pub commit: [u8; 8],
+    pub stm_line: McuLine,
+    pub stm_storage: McuStorage,
+    pub freq: u16,
+    pub overclock: bool,
+    pub swd_enabled: bool,
+    pub preload_image_to_ram: bool,
+    pub bootloader_capable: bool,
+    pub status_led_enabled: bool,
+    pub boot_logging_enabled: bool,
+    pub mco_enabled: bool,
+    pub mco2_enabled: bool,
+    pub count_rom_access: bool,
+    pub boot_config: BootConfig,
+
+    /// Pointer to the pin configuration, which is not parsed
+    pub pins_ptr: u32,
+
+    /// Set if the firmware is newer than this parser supports, and was
+    /// parsed due to a lenient [`VersionPolicy`](crate::VersionPolicy)
+    pub version_newer: bool,
+
+    /// Build date - empty if strings were not read
+    pub build_date: SdrrStringStorage,
+
+    /// Hardware revision - empty if strings were not read
+    pub hw_rev: SdrrStringStorage,
+
+    rom_sets: [SdrrRomSetStorage; MAX_ROM_SETS],
+    rom_set_count: usize,
+}
+
+impl SdrrInfoStorage {
+    /// Creates empty storage.  `const` so it can be used to initialize a
+    /// `static`.
+    pub const fn new() -> Self {
+        Self {
+            major_version: 0,
+            minor_version: 0,
+            patch_version: 0,
+            build_number: 0,
+            commit: [0; 8],
+            stm_line: McuLine::F411,
+            stm_storage: McuStorage::StorageE,
+            freq: 0,
+            overclock: false,
+            swd_enabled: false,
+            preload_image_to_ram: false,
+            bootloader_capable: false,
+            status_led_enabled: false,
+            boot_logging_enabled: false,
+            mco_enabled: false,
+            mco2_enabled: false,
+            count_rom_access: false,
+            boot_config: BootConfig::UNSET,
+            pins_ptr: 0,
+            version_newer: false,
+            build_date: SdrrStringStorage::new(),
+            hw_rev: SdrrStringStorage::new(),
+            rom_sets: [SdrrRomSetStorage::new(); MAX_ROM_SETS],
+            rom_set_count: 0,
+        }
+    }
+
+    /// The ROM sets parsed.
+    pub fn rom_sets(&self) -> &[SdrrRomSetStorage] {
+        &self.rom_sets[..self.rom_set_count]
+    }
+}
+
+impl Default for SdrrInfoStorage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Read a null-terminated string into fixed storage, truncating it if
+// necessary.
+pub(crate) async fn read_string_into<R: Reader>(
+    reader: &mut R,
+    ptr: u32,
+    base_addr: u32,
+    string: &mut SdrrStringStorage,
+) -> Result<(), StorageError> {
+    *string = SdrrStringStorage::new();
+    if ptr < base_addr {
+        return Ok(());
+    }
+
+    while string.len < MAX_STORED_STRING_LEN {
+        let addr = ptr + string.len as u32;
+        let chunk = &mut string.buf[string.len..];
+        reader
+            .read(addr, chunk)
+            .await
+            .map_err(|_| StorageError::Read(addr))?;
+
+        if let Some(null_pos) = chunk.iter().position(|&b| b == 0) {
+            string.len += null_pos;
+            break;
+        }
+        string.len += chunk.len();
+    }
+
+    Ok(())
+}
+
+// Read the ROM sets, and their ROMs, into fixed storage.
+pub(crate) async fn read_rom_sets_into<R: Reader>(
+    reader: &mut R,
+    ptr: u32,
+    count: u8,
+    base_addr: u32,
+    boot_logging_enabled: bool,
+    storage: &mut SdrrInfoStorage,
+) -> Result<(), StorageError> {
+    storage.rom_set_count = 0;
+    if ptr < base_addr || count == 0 {
+        return Ok(());
+    }
+    if count as usize > MAX_ROM_SETS {
+        return Err(StorageError::TooManyRomSets(count));
+    }
+
+    for set in 0..count as usize {
+        let header_addr = ptr + (set * SdrrRomSetHeader::size()) as u32;
+        let mut header_buf = [0u8; SdrrRomSetHeader::size()];
+        reader
+            .read(header_addr, &mut header_buf)
+            .await
+            .map_err(|_| StorageError::Read(header_addr))?;
+        let (_, header) = SdrrRomSetHeader::from_bytes((&header_buf, 0))
+            .map_err(|_| StorageError::InvalidRomSet(set))?;
+
+        if header.rom_count as usize > MAX_ROMS_PER_SET {
+            return Err(StorageError::TooManyRoms {
+                set,
+                count: header.rom_count,
+            });
+        }
+
+        let rom_set = &mut storage.rom_sets[set];
+        rom_set.data_ptr = header.data_ptr;
+        rom_set.size = header.size;
+        rom_set.rom_count = 0;
+        rom_set.serve = header.serve;
+        rom_set.multi_rom_cs1_state = header.multi_rom_cs1_state;
+
+        if header.roms_ptr >= base_addr {
+            for rom in 0..header.rom_count as usize {
+                rom_set.roms[rom] = read_rom_info(
+                    reader,
+                    header.roms_ptr,
+                    set,
+                    rom,
+                    boot_logging_enabled,
+                )
+                .await?;
+            }
+            rom_set.rom_count = header.rom_count;
+        }
+
+        storage.rom_set_count = set + 1;
+    }
+
+    Ok(())
+}
+
+// Read a single ROM's information, via the ROM set's array of pointers to
+// them.
+async fn read_rom_info<R: Reader>(
+    reader: &mut R,
+    roms_ptr: u32,
+    set: usize,
+    rom: usize,
+    boot_logging_enabled: bool,
+) -> Result<SdrrRomInfoStorage, StorageError> {
+    let ptr_addr = roms_ptr + (rom * core::mem::size_of::<u32>()) as u32;
+    let mut ptr_buf = [0u8; core::mem::size_of::<u32>()];
+    reader
+        .read(ptr_addr, &mut ptr_buf)
+        .await
+        .map_err(|_| StorageError::Read(ptr_addr))?;
+    let rom_info_ptr = u32::from_le_bytes(ptr_buf);
+
+    let invalid = StorageError::InvalidRomInfo { set, rom };
+    let mut info_buf = [0u8; SdrrRomInfoWithLogging::size()];
+    if boot_logging_enabled {
+        reader
+            .read(rom_info_ptr, &mut info_buf)
+            .await
+            .map_err(|_| StorageError::Read(rom_info_ptr))?;
+        let (_, info) =
+            SdrrRomInfoWithLogging::from_bytes((&info_buf, 0)).map_err(|_| invalid)?;
+        Ok(SdrrRomInfoStorage {
+            rom_type: info.rom_type,
+            cs1_state: info.cs1_state,
+            cs2_state: info.cs2_state,
+            cs3_state: info.cs3_state,
+            filename_ptr: Some(info.filename_ptr),
+        })
+    } else {
+        let info_buf = &mut info_buf[..SdrrRomInfoBasic::size()];
+        reader
+            .read(rom_info_ptr, info_buf)
+            .await
+            .map_err(|_| StorageError::Read(rom_info_ptr))?;
+        let (_, info) = SdrrRomInfoBasic::from_bytes((info_buf, 0)).map_err(|_| invalid)?;
+        Ok(SdrrRomInfoStorage {
+            rom_type: info.rom_type,
+            cs1_state: info.cs1_state,
+            cs2_state: info.cs2_state,
+            cs3_state: info.cs3_state,
+            filename_ptr: None,
+        })
+    }
+}
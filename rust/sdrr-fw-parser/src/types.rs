@@ -10,7 +10,7 @@ use core::fmt;
 use deku::prelude::*;
 
 #[cfg(not(feature = "std"))]
-use alloc::{format, string::String};
+use alloc::{format, string::String, vec::Vec};
 
 use crate::SdrrInfo;
 
@@ -18,7 +18,16 @@ use crate::SdrrInfo;
 ///
 /// Relflects `stm_line_t` from `sdrr/include/config_base.h`
 #[derive(
-    Debug, Clone, Copy, PartialEq, Eq, DekuRead, DekuWrite, serde::Serialize, serde::Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    DekuRead,
+    DekuWrite,
+    serde::Serialize,
+    serde::Deserialize,
 )]
 #[deku(id_type = "u16", ctx = "endian: deku::ctx::Endian")]
 pub enum McuLine {
@@ -61,6 +70,49 @@ impl fmt::Display for McuLine {
 }
 
 impl McuLine {
+    /// Returns every valid MCU line, in `stm_line_t` order
+    pub fn all() -> &'static [Self] {
+        &[
+            McuLine::F401DE,
+            McuLine::F405,
+            McuLine::F411,
+            McuLine::F446,
+            McuLine::F401BC,
+            McuLine::Rp2350,
+        ]
+    }
+
+    /// Converts the raw `stm_line_t` value, as stored in the firmware, into
+    /// an MCU line
+    pub fn from_u16(value: u16) -> Option<Self> {
+        Self::all().iter().copied().find(|line| line.to_u16() == value)
+    }
+
+    /// Returns the raw `stm_line_t` value, as stored in the firmware
+    pub fn to_u16(&self) -> u16 {
+        match self {
+            McuLine::F401DE => 0x0000,
+            McuLine::F405 => 0x0001,
+            McuLine::F411 => 0x0002,
+            McuLine::F446 => 0x0003,
+            McuLine::F401BC => 0x0004,
+            McuLine::Rp2350 => 0x0005,
+        }
+    }
+
+    /// As [`Self::from_u16()`], but returns a descriptive error, listing the
+    /// valid values, if `value` is not a known MCU line
+    pub fn try_from_u16(value: u16) -> Result<Self, String> {
+        Self::from_u16(value).ok_or_else(|| {
+            let valid = Self::all()
+                .iter()
+                .map(|line| format!("0x{:04X} ({line})", line.to_u16()))
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!("Unknown MCU line 0x{value:04X} - valid values are {valid}")
+        })
+    }
+
     /// Returns the amount of SRAM of the device (not including any CCM RAM)
     pub fn ram_kb(&self) -> &str {
         match self {
@@ -78,7 +130,16 @@ impl McuLine {
 ///
 /// Reflects `stm_storage_t` from `sdrr/include/config_base.h`
 #[derive(
-    Debug, Clone, Copy, PartialEq, Eq, DekuRead, DekuWrite, serde::Serialize, serde::Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    DekuRead,
+    DekuWrite,
+    serde::Serialize,
+    serde::Deserialize,
 )]
 #[deku(id_type = "u16", ctx = "endian: deku::ctx::Endian")]
 pub enum McuStorage {
@@ -130,6 +191,12 @@ impl McuStorage {
         }
     }
 
+    /// Returns the storage size in bytes
+    pub fn bytes(&self) -> u32 {
+        // kb() is always a number
+        self.kb().parse::<u32>().unwrap_or(0) * 1024
+    }
+
     /// Returns the storage package code
     pub fn package_code(&self) -> &str {
         match self {
@@ -155,7 +222,16 @@ impl fmt::Display for McuStorage {
 ///
 /// Reflects `sdrr_rom_type_t` from `sdrr/include/config_base.h`
 #[derive(
-    Debug, Clone, Copy, PartialEq, Eq, DekuRead, DekuWrite, serde::Serialize, serde::Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    DekuRead,
+    DekuWrite,
+    serde::Serialize,
+    serde::Deserialize,
 )]
 #[deku(id_type = "u8")]
 pub enum SdrrRomType {
@@ -170,6 +246,10 @@ pub enum SdrrRomType {
     /// 2364 ROM, 13-bit address, 1 CS line, 8KB size
     #[deku(id = "2")]
     Rom2364,
+
+    /// 23128 ROM, 14-bit address, CE and OE lines, 16KB size
+    #[deku(id = "3")]
+    Rom23128,
 }
 
 impl fmt::Display for SdrrRomType {
@@ -178,28 +258,80 @@ impl fmt::Display for SdrrRomType {
             SdrrRomType::Rom2316 => write!(f, "2316"),
             SdrrRomType::Rom2332 => write!(f, "2332"),
             SdrrRomType::Rom2364 => write!(f, "2364"),
+            SdrrRomType::Rom23128 => write!(f, "23128"),
+        }
+    }
+}
+
+impl core::str::FromStr for SdrrRomType {
+    type Err = String;
+
+    /// Parses a ROM type from the same spelling as its `Display`, such as
+    /// "2364".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "2316" => Ok(SdrrRomType::Rom2316),
+            "2332" => Ok(SdrrRomType::Rom2332),
+            "2364" => Ok(SdrrRomType::Rom2364),
+            "23128" => Ok(SdrrRomType::Rom23128),
+            _ => Err(format!("Unknown ROM type: {}", s)),
         }
     }
 }
 
 impl SdrrRomType {
+    /// Converts the raw `sdrr_rom_type_t` value, as stored in the firmware,
+    /// into a ROM type
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(SdrrRomType::Rom2316),
+            1 => Some(SdrrRomType::Rom2332),
+            2 => Some(SdrrRomType::Rom2364),
+            3 => Some(SdrrRomType::Rom23128),
+            _ => None,
+        }
+    }
+
     /// Returns the size of the ROM in bytes
     pub fn rom_size(&self) -> usize {
         self.rom_size_kb() * 1024
     }
 
+    /// Returns the size of the ROM in bytes.  The same as
+    /// [`SdrrRomType::rom_size`], named to match `sdrr-common`'s `RomType`.
+    pub fn size_bytes(&self) -> usize {
+        self.rom_size()
+    }
+
+    /// Returns the number of address lines the ROM has
+    pub fn num_addr_lines(&self) -> usize {
+        match self {
+            SdrrRomType::Rom2316 => 11,
+            SdrrRomType::Rom2332 => 12,
+            SdrrRomType::Rom2364 => 13,
+            SdrrRomType::Rom23128 => 14,
+        }
+    }
+
     /// Returns the size of the ROM in KB
     pub fn rom_size_kb(&self) -> usize {
         match self {
             SdrrRomType::Rom2316 => 2,
             SdrrRomType::Rom2332 => 4,
             SdrrRomType::Rom2364 => 8,
+            SdrrRomType::Rom23128 => 16,
         }
     }
 
+    /// Returns the mask of the address bits the ROM decodes, e.g. 0x1FFF
+    /// for a 2364
+    pub fn address_mask(&self) -> u32 {
+        (1 << self.num_addr_lines()) - 1
+    }
+
     /// Returns the maximum addressable location in the ROM
     pub fn max_addr(&self) -> u32 {
-        (self.rom_size() - 1) as u32
+        self.address_mask()
     }
 
     /// Checks if the ROM type supports the CS2 line
@@ -208,6 +340,7 @@ impl SdrrRomType {
             SdrrRomType::Rom2316 => true,
             SdrrRomType::Rom2332 => true,
             SdrrRomType::Rom2364 => false,
+            SdrrRomType::Rom23128 => false,
         }
     }
 
@@ -217,6 +350,7 @@ impl SdrrRomType {
             SdrrRomType::Rom2316 => true,
             SdrrRomType::Rom2332 => false,
             SdrrRomType::Rom2364 => false,
+            SdrrRomType::Rom23128 => false,
         }
     }
 }
@@ -225,7 +359,16 @@ impl SdrrRomType {
 ///
 /// Reflects `sdrr_cs_state_t` from `sdrr/include/config_base.h`
 #[derive(
-    Debug, Clone, Copy, PartialEq, Eq, DekuRead, DekuWrite, serde::Serialize, serde::Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    DekuRead,
+    DekuWrite,
+    serde::Serialize,
+    serde::Deserialize,
 )]
 #[deku(id_type = "u8")]
 pub enum SdrrCsState {
@@ -242,6 +385,24 @@ pub enum SdrrCsState {
     NotUsed,
 }
 
+impl SdrrCsState {
+    /// Returns the level (true = high) of a line with this state when the
+    /// chip is selected, or `None` if the line isn't used
+    pub fn active_level(&self) -> Option<bool> {
+        match self {
+            SdrrCsState::ActiveLow => Some(false),
+            SdrrCsState::ActiveHigh => Some(true),
+            SdrrCsState::NotUsed => None,
+        }
+    }
+
+    /// Returns the level (true = high) of a line with this state when the
+    /// chip is not selected, or `None` if the line isn't used
+    pub fn inactive_level(&self) -> Option<bool> {
+        self.active_level().map(|level| !level)
+    }
+}
+
 impl fmt::Display for SdrrCsState {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -254,9 +415,20 @@ impl fmt::Display for SdrrCsState {
 
 /// SDRR serving algorithm options
 ///
-/// Reflects `sdrr_serve_t` from `sdrr/include/config_base.h`
+/// Reflects `sdrr_serve_t` from `sdrr/include/config_base.h`.  When adding an
+/// algorithm, add it there, here (with the same value) and to `ServeAlg` in
+/// `sdrr-common`, if it is user selectable.
 #[derive(
-    Debug, Clone, Copy, PartialEq, Eq, DekuRead, DekuWrite, serde::Serialize, serde::Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    DekuRead,
+    DekuWrite,
+    serde::Serialize,
+    serde::Deserialize,
 )]
 #[deku(id_type = "u8")]
 pub enum SdrrServe {
@@ -276,6 +448,38 @@ pub enum SdrrServe {
     AddrOnAnyCs,
 }
 
+impl SdrrServe {
+    /// Converts the raw `sdrr_serve_t` value, as stored in the firmware,
+    /// into a serving algorithm
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(SdrrServe::TwoCsOneAddr),
+            1 => Some(SdrrServe::AddrOnCs),
+            2 => Some(SdrrServe::AddrOnAnyCs),
+            _ => None,
+        }
+    }
+
+    /// Returns the raw `sdrr_serve_t` value, as stored in the firmware
+    pub fn to_u8(&self) -> u8 {
+        match self {
+            SdrrServe::TwoCsOneAddr => 0,
+            SdrrServe::AddrOnCs => 1,
+            SdrrServe::AddrOnAnyCs => 2,
+        }
+    }
+
+    /// Returns a stable, machine readable, name for the algorithm.  Unlike
+    /// the `Display` output, this will not change.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SdrrServe::TwoCsOneAddr => "two_cs_one_addr",
+            SdrrServe::AddrOnCs => "addr_on_cs",
+            SdrrServe::AddrOnAnyCs => "addr_on_any_cs",
+        }
+    }
+}
+
 impl fmt::Display for SdrrServe {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -290,7 +494,16 @@ impl fmt::Display for SdrrServe {
 ///
 /// Reflects `sdrr_stm_port_t` from `sdrr/include/config_base.h`
 #[derive(
-    Debug, Clone, Copy, PartialEq, Eq, DekuRead, DekuWrite, serde::Serialize, serde::Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    DekuRead,
+    DekuWrite,
+    serde::Serialize,
+    serde::Deserialize,
 )]
 #[deku(id_type = "u8")]
 pub enum SdrrMcuPort {
@@ -319,6 +532,51 @@ pub enum SdrrMcuPort {
     Port0,
 }
 
+impl SdrrMcuPort {
+    /// Returns every valid port, in `sdrr_stm_port_t` order
+    pub fn all() -> &'static [Self] {
+        &[
+            SdrrMcuPort::None,
+            SdrrMcuPort::PortA,
+            SdrrMcuPort::PortB,
+            SdrrMcuPort::PortC,
+            SdrrMcuPort::PortD,
+            SdrrMcuPort::Port0,
+        ]
+    }
+
+    /// Converts the raw `sdrr_stm_port_t` value, as stored in the firmware,
+    /// into a port
+    pub fn from_u8(value: u8) -> Option<Self> {
+        Self::all().iter().copied().find(|port| port.to_u8() == value)
+    }
+
+    /// Returns the raw `sdrr_stm_port_t` value, as stored in the firmware
+    pub fn to_u8(&self) -> u8 {
+        match self {
+            SdrrMcuPort::None => 0x00,
+            SdrrMcuPort::PortA => 0x01,
+            SdrrMcuPort::PortB => 0x02,
+            SdrrMcuPort::PortC => 0x03,
+            SdrrMcuPort::PortD => 0x04,
+            SdrrMcuPort::Port0 => 0x05,
+        }
+    }
+
+    /// As [`Self::from_u8()`], but returns a descriptive error, listing the
+    /// valid values, if `value` is not a known port
+    pub fn try_from_u8(value: u8) -> Result<Self, String> {
+        Self::from_u8(value).ok_or_else(|| {
+            let valid = Self::all()
+                .iter()
+                .map(|port| format!("0x{:02X} ({port})", port.to_u8()))
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!("Unknown port 0x{value:02X} - valid values are {valid}")
+        })
+    }
+}
+
 impl fmt::Display for SdrrMcuPort {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -477,3 +735,135 @@ impl fmt::Display for SdrrAddress {
         }
     }
 }
+
+/// The firmware's boot configuration - `boot_config` in `sdrr_info_t`.
+///
+/// This is reserved for future use.  For example, an external programmer may
+/// change it to pre-select an image on boot, rather than the image select
+/// jumpers being used.  It must currently be unset - all bytes 0xFF.
+///
+/// The raw bytes are available via [`BootConfig::bytes`], or the public
+/// field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
+pub struct BootConfig(pub [u8; 4]);
+
+impl BootConfig {
+    /// The unset, reserved, boot configuration
+    pub const UNSET: Self = Self([0xFF; 4]);
+
+    /// Returns the raw bytes, in the order they are stored in the firmware
+    pub const fn bytes(&self) -> [u8; 4] {
+        self.0
+    }
+
+    /// Returns the boot configuration as a little-endian word, as the
+    /// firmware would read it
+    pub const fn as_u32(&self) -> u32 {
+        u32::from_le_bytes(self.0)
+    }
+
+    /// Whether the boot configuration is unset - all bytes 0xFF
+    pub const fn is_unset(&self) -> bool {
+        self.as_u32() == u32::MAX
+    }
+}
+
+impl Default for BootConfig {
+    fn default() -> Self {
+        Self::UNSET
+    }
+}
+
+impl From<[u8; 4]> for BootConfig {
+    fn from(bytes: [u8; 4]) -> Self {
+        Self(bytes)
+    }
+}
+
+impl fmt::Display for BootConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "0x{:02X}{:02X}{:02X}{:02X}",
+            self.0[0], self.0[1], self.0[2], self.0[3]
+        )?;
+        if self.is_unset() {
+            write!(f, " (unset)")
+        } else {
+            write!(f, " (reserved, should be unset)")
+        }
+    }
+}
+
+// Width of the hex part of a full hex dump row of `row_len` bytes - a
+// single space between bytes and a double space between groups of 4
+const fn hex_dump_row_width(row_len: usize) -> usize {
+    let gaps = row_len.saturating_sub(1);
+    row_len * 2 + gaps + gaps / 4
+}
+
+/// Writes a hex dump of `bytes`, 16 bytes per row, with each row prefixed by
+/// the address of its first byte, starting from `base_addr`, and followed by
+/// its printable ASCII representation.
+///
+/// Doesn't allocate, so can be used in `no_std` environments, such as to log
+/// ROM contents over a serial port.
+///
+/// ```
+/// let mut dump = String::new();
+/// sdrr_fw_parser::format_hex_dump(b"SDRR\x00\x01", 0xE000, &mut dump).unwrap();
+/// assert!(dump.starts_with("E000: 53 44 52 52  00 01 "));
+/// assert!(dump.ends_with("  |SDRR..|\n"));
+/// ```
+pub fn format_hex_dump(bytes: &[u8], base_addr: u32, out: &mut impl fmt::Write) -> fmt::Result {
+    for (row, chunk) in bytes.chunks(16).enumerate() {
+        write!(out, "{:04X}: ", base_addr.wrapping_add((row as u32).wrapping_mul(16)))?;
+        format_hex_dump_row(chunk, true, out)?;
+        out.write_char('\n')?;
+    }
+    Ok(())
+}
+
+/// Writes a single row of a hex dump - up to 16 bytes of `bytes` as hex, in
+/// groups of 4 - without its address, for callers which format addresses
+/// themselves.  If `ascii` is set, the hex is padded to the width of a full
+/// row, and followed by the printable ASCII representation of the bytes,
+/// with '.' for non-printable ones.
+pub fn format_hex_dump_row(bytes: &[u8], ascii: bool, out: &mut impl fmt::Write) -> fmt::Result {
+    format_hex_dump_row_wrapped(bytes, 16, ascii, out)
+}
+
+/// As [`format_hex_dump_row()`], but for rows of up to `row_len` bytes
+/// rather than 16.  Bytes are still grouped in 4s, and the ASCII column,
+/// if any, is aligned to the width of a full `row_len` byte row.
+pub fn format_hex_dump_row_wrapped(
+    bytes: &[u8],
+    row_len: usize,
+    ascii: bool,
+    out: &mut impl fmt::Write,
+) -> fmt::Result {
+    let bytes = &bytes[..bytes.len().min(row_len)];
+
+    let mut width = 0;
+    for (ii, byte) in bytes.iter().enumerate() {
+        if ii > 0 {
+            // Bigger space every 4 bytes
+            let sep = if ii.is_multiple_of(4) { "  " } else { " " };
+            out.write_str(sep)?;
+            width += sep.len();
+        }
+        write!(out, "{:02X}", byte)?;
+        width += 2;
+    }
+
+    if ascii {
+        write!(out, "{:pad$}  |", "", pad = hex_dump_row_width(row_len) - width)?;
+        for &byte in bytes {
+            let c = if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '.' };
+            out.write_char(c)?;
+        }
+        out.write_char('|')?;
+    }
+    Ok(())
+}
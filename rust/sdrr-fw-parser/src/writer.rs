@@ -0,0 +1,693 @@
+// Copyright (C) 2025 Piers Finlayson <piers@piers.rocks>
+//
+// MIT License
+
+//! sdrr-fw-parser
+//!
+//! Serializes parsed SDRR firmware information back into a firmware image.
+//!
+//! This is the inverse of [`crate::Parser::parse_flash`], and is primarily
+//! intended for producing test firmware images without needing to build the
+//! C firmware.
+
+use deku::prelude::*;
+
+use crate::parsing::{
+    SdrrExtraInfoHeader, SdrrInfoHeader, SdrrRomInfoBasic, SdrrRomInfoWithLogging,
+    SdrrRomSetHeader,
+};
+use crate::{McuLine, SdrrInfo, SdrrPins};
+use crate::{RP2350_FLASH_BASE, SDRR_INFO_FW_OFFSET, STM32F4_FLASH_BASE};
+
+#[cfg(not(feature = "std"))]
+use alloc::{format, string::String, vec, vec::Vec};
+
+// All structures and strings are laid out on this alignment
+const ALIGNMENT: u32 = 4;
+
+// Value used for any bytes in the image not otherwise written - as erased
+// flash
+const FILL_BYTE: u8 = 0xFF;
+
+// Value written to the extra info pointer if there is no extra info
+const NO_EXTRA_INFO_PTR: u32 = 0xFFFFFFFF;
+
+// Absolute addresses of each of the structures laid out by the writer
+struct Layout {
+    header: u32,
+    extra_info: Option<u32>,
+    pins: Option<u32>,
+    rom_sets: u32,
+    rom_ptr_arrays: Vec<u32>,
+    rom_infos: Vec<Vec<u32>>,
+    build_date: Option<u32>,
+    hw_rev: Option<u32>,
+    filenames: Vec<Vec<Option<u32>>>,
+    end: u32,
+}
+
+/// Writes an [`SdrrInfo`] out as an SDRR firmware image.
+///
+/// The `sdrr_info_t` header is written at offset 0x200 from the base flash
+/// address, followed by the extra info, pin configuration, ROM set headers,
+/// ROM info pointer arrays, ROM infos, and finally a string table containing
+/// the build date, hardware revision and ROM filenames.
+///
+/// ROM set data is _not_ relocated - it is written at each set's `data_ptr`,
+/// which must lie beyond the metadata (see [`Writer::metadata_end`]).  This
+/// means the image produced can be parsed back into an identical `SdrrInfo`.
+///
+/// Some fields cannot be round-tripped:
+/// - `parse_errors` are not written.
+/// - ROM filenames are only written if `boot_logging_enabled` is set, as
+///   the firmware only includes them in that case.
+/// - A missing `build_date`, `hw_rev` or `pins` is written as a null
+///   pointer, and will cause a parse error when read back.
+/// - `rom_crc32` is written as is, not calculated from the ROM set data, and
+///   a missing `rom_crc32` is written as 0.
+///
+/// # Example
+///
+/// ```rust ignore
+/// use sdrr_fw_parser::{Parser, readers::MemoryReader, writer::Writer};
+///
+/// let image = Writer::new(&info)
+///     .rom_set_data(0, &rom_data)
+///     .write()?;
+///
+/// let mut parser = Parser::new(MemoryReader::new(image, 0x08000000));
+/// let parsed = parser.parse_flash().await?;
+/// ```
+pub struct Writer<'a> {
+    info: &'a SdrrInfo,
+    base_flash_address: u32,
+    rom_set_data: Vec<Option<&'a [u8]>>,
+}
+
+impl<'a> Writer<'a> {
+    /// Create a new writer, using the default flash base address for the
+    /// MCU line in `info`.
+    pub fn new(info: &'a SdrrInfo) -> Self {
+        let base_flash_address = match info.stm_line {
+            McuLine::Rp2350 => RP2350_FLASH_BASE,
+            _ => STM32F4_FLASH_BASE,
+        };
+        Self::with_base_flash_address(info, base_flash_address)
+    }
+
+    /// Create a new writer with a custom base flash address.
+    pub fn with_base_flash_address(info: &'a SdrrInfo, base_flash_address: u32) -> Self {
+        Self {
+            info,
+            base_flash_address,
+            rom_set_data: Vec::new(),
+        }
+    }
+
+    /// Supply the (mangled) data for a ROM set.  Must be exactly the set's
+    /// `size` bytes long.  Sets without data supplied are filled with 0xFF.
+    pub fn rom_set_data(mut self, set: usize, data: &'a [u8]) -> Self {
+        if self.rom_set_data.len() <= set {
+            self.rom_set_data.resize(set + 1, None);
+        }
+        self.rom_set_data[set] = Some(data);
+        self
+    }
+
+    /// Returns the first address after the metadata laid out by this writer.
+    /// ROM set `data_ptr`s must be at or beyond this address.
+    pub fn metadata_end(&self) -> Result<u32, String> {
+        Ok(self.layout()?.end)
+    }
+
+    /// Returns the serialized `sdrr_info_t` header, with pointers set to
+    /// where this writer lays out the rest of the metadata.
+    pub fn header_bytes(&self) -> Result<Vec<u8>, String> {
+        let layout = self.layout()?;
+        self.header(&layout)
+            .to_bytes()
+            .map_err(|e| format!("Failed to write header: {}", e))
+    }
+
+    /// Write the complete firmware image, starting at the base flash address.
+    pub fn write(&self) -> Result<Vec<u8>, String> {
+        let layout = self.layout()?;
+        let info = self.info;
+
+        // Validate the ROM sets, and find the end of the image
+        let mut end = layout.end;
+        let mut data_ranges = Vec::with_capacity(info.rom_sets.len());
+        for (ii, rom_set) in info.rom_sets.iter().enumerate() {
+            if rom_set.roms.len() != rom_set.rom_count as usize {
+                return Err(format!(
+                    "ROM set {} has rom_count {} but contains {} ROMs",
+                    ii,
+                    rom_set.rom_count,
+                    rom_set.roms.len()
+                ));
+            }
+            if rom_set.data_ptr < layout.end {
+                return Err(format!(
+                    "ROM set {} data pointer 0x{:08X} overlaps metadata ending at 0x{:08X}",
+                    ii, rom_set.data_ptr, layout.end
+                ));
+            }
+            let data_len = self.rom_set_data.get(ii).copied().flatten().map(|d| d.len());
+            if let Some(len) = data_len.filter(|&len| len != rom_set.size as usize) {
+                return Err(format!(
+                    "ROM set {} data is {} bytes, expected {}",
+                    ii, len, rom_set.size
+                ));
+            }
+            let data_end = rom_set.data_ptr.checked_add(rom_set.size).ok_or_else(|| {
+                format!(
+                    "ROM set {} data at 0x{:08X} ({} bytes) overflows the address space",
+                    ii, rom_set.data_ptr, rom_set.size
+                )
+            })?;
+            data_ranges.push((rom_set.data_ptr, data_end, ii));
+            end = end.max(data_end);
+        }
+        data_ranges.sort();
+        for pair in data_ranges.windows(2) {
+            if pair[0].1 > pair[1].0 {
+                return Err(format!(
+                    "ROM set {} data overlaps ROM set {} data",
+                    pair[0].2, pair[1].2
+                ));
+            }
+        }
+
+        // Bound the image by the MCU's flash, so a stray data pointer, such
+        // as a RAM address, is an error rather than a huge allocation
+        let flash_end = self
+            .base_flash_address
+            .checked_add(info.stm_storage.bytes())
+            .ok_or_else(|| {
+                format!(
+                    "{} KB of flash at 0x{:08X} overflows the address space",
+                    info.stm_storage.kb(),
+                    self.base_flash_address
+                )
+            })?;
+        if end > flash_end {
+            return Err(format!(
+                "Image ends at 0x{:08X}, beyond the end of the {} KB flash at 0x{:08X}",
+                end,
+                info.stm_storage.kb(),
+                flash_end
+            ));
+        }
+
+        let mut image = vec![FILL_BYTE; (end - self.base_flash_address) as usize];
+
+        // Header
+        let header = self
+            .header(&layout)
+            .to_bytes()
+            .map_err(|e| format!("Failed to write header: {}", e))?;
+        self.put(&mut image, layout.header, &header)?;
+
+        // Extra info
+        if let (Some(addr), Some(extra_info)) = (layout.extra_info, info.extra_info.as_ref()) {
+            let extra = SdrrExtraInfoHeader {
+                rtt_ptr: extra_info.rtt_ptr,
+                _post: [FILL_BYTE; 252],
+            };
+            let bytes = extra
+                .to_bytes()
+                .map_err(|e| format!("Failed to write extra info: {}", e))?;
+            self.put(&mut image, addr, &bytes)?;
+        }
+
+        // Pins
+        if let (Some(addr), Some(pins)) = (layout.pins, info.pins.as_ref()) {
+            if pins.data.len() != 8 || pins.addr.len() != 16 {
+                return Err(format!(
+                    "Pins must have 8 data and 16 address entries, found {} and {}",
+                    pins.data.len(),
+                    pins.addr.len()
+                ));
+            }
+            let bytes = pins
+                .to_bytes()
+                .map_err(|e| format!("Failed to write pins: {}", e))?;
+            self.put(&mut image, addr, &bytes)?;
+        }
+
+        // ROM sets, their ROM info pointer arrays and ROM infos
+        for (ii, rom_set) in info.rom_sets.iter().enumerate() {
+            let header = SdrrRomSetHeader {
+                data_ptr: rom_set.data_ptr,
+                size: rom_set.size,
+                roms_ptr: layout.rom_ptr_arrays[ii],
+                rom_count: rom_set.rom_count,
+                serve: rom_set.serve,
+                multi_rom_cs1_state: rom_set.multi_rom_cs1_state,
+            };
+            let bytes = header
+                .to_bytes()
+                .map_err(|e| format!("Failed to write ROM set header {}: {}", ii, e))?;
+            let header_addr = layout.rom_sets + (ii * SdrrRomSetHeader::size()) as u32;
+            self.put(&mut image, header_addr, &bytes)?;
+
+            for (jj, rom) in rom_set.roms.iter().enumerate() {
+                let info_addr = layout.rom_infos[ii][jj];
+                let ptr_addr = layout.rom_ptr_arrays[ii] + (jj * core::mem::size_of::<u32>()) as u32;
+                self.put(&mut image, ptr_addr, &info_addr.to_le_bytes())?;
+
+                let bytes = if info.boot_logging_enabled {
+                    SdrrRomInfoWithLogging {
+                        rom_type: rom.rom_type,
+                        cs1_state: rom.cs1_state,
+                        cs2_state: rom.cs2_state,
+                        cs3_state: rom.cs3_state,
+                        filename_ptr: layout.filenames[ii][jj].unwrap_or(0),
+                    }
+                    .to_bytes()
+                } else {
+                    SdrrRomInfoBasic {
+                        rom_type: rom.rom_type,
+                        cs1_state: rom.cs1_state,
+                        cs2_state: rom.cs2_state,
+                        cs3_state: rom.cs3_state,
+                    }
+                    .to_bytes()
+                }
+                .map_err(|e| format!("Failed to write ROM info {}/{}: {}", ii, jj, e))?;
+                self.put(&mut image, info_addr, &bytes)?;
+            }
+        }
+
+        // String table
+        if let (Some(addr), Some(s)) = (layout.build_date, info.build_date.as_ref()) {
+            self.put_string(&mut image, addr, s)?;
+        }
+        if let (Some(addr), Some(s)) = (layout.hw_rev, info.hw_rev.as_ref()) {
+            self.put_string(&mut image, addr, s)?;
+        }
+        for (ii, rom_set) in info.rom_sets.iter().enumerate() {
+            for (jj, rom) in rom_set.roms.iter().enumerate() {
+                if let (Some(addr), Some(s)) = (layout.filenames[ii][jj], rom.filename.as_ref()) {
+                    self.put_string(&mut image, addr, s)?;
+                }
+            }
+        }
+
+        // ROM set data
+        for (ii, rom_set) in info.rom_sets.iter().enumerate() {
+            if let Some(data) = self.rom_set_data.get(ii).copied().flatten() {
+                self.put(&mut image, rom_set.data_ptr, data)?;
+            }
+        }
+
+        Ok(image)
+    }
+
+    // Works out where each structure is placed in the image
+    fn layout(&self) -> Result<Layout, String> {
+        fn alloc(next: &mut u32, size: usize) -> Result<u32, String> {
+            let addr = *next;
+            *next = u32::try_from(size)
+                .ok()
+                .and_then(|size| addr.checked_add(size))
+                .and_then(|end| end.checked_next_multiple_of(ALIGNMENT))
+                .ok_or_else(|| {
+                    format!("{} bytes at 0x{:08X} overflow the address space", size, addr)
+                })?;
+            Ok(addr)
+        }
+
+        let info = self.info;
+        let mut next = self
+            .base_flash_address
+            .checked_add(SDRR_INFO_FW_OFFSET)
+            .ok_or_else(|| {
+                format!(
+                    "Base flash address 0x{:08X} leaves no room for the header",
+                    self.base_flash_address
+                )
+            })?;
+
+        let header = alloc(&mut next, SdrrInfoHeader::size())?;
+        let extra_info = info
+            .extra_info
+            .as_ref()
+            .map(|_| alloc(&mut next, SdrrExtraInfoHeader::size()))
+            .transpose()?;
+        let pins = info
+            .pins
+            .as_ref()
+            .map(|_| alloc(&mut next, SdrrPins::size()))
+            .transpose()?;
+        let rom_sets = alloc(&mut next, info.rom_sets.len() * SdrrRomSetHeader::size())?;
+
+        let rom_ptr_arrays = info
+            .rom_sets
+            .iter()
+            .map(|set| alloc(&mut next, set.roms.len() * core::mem::size_of::<u32>()))
+            .collect::<Result<_, _>>()?;
+
+        let rom_info_size = if info.boot_logging_enabled {
+            SdrrRomInfoWithLogging::size()
+        } else {
+            SdrrRomInfoBasic::size()
+        };
+        let rom_infos = info
+            .rom_sets
+            .iter()
+            .map(|set| {
+                set.roms
+                    .iter()
+                    .map(|_| alloc(&mut next, rom_info_size))
+                    .collect()
+            })
+            .collect::<Result<_, _>>()?;
+
+        let build_date = info
+            .build_date
+            .as_ref()
+            .map(|s| alloc(&mut next, s.len() + 1))
+            .transpose()?;
+        let hw_rev = info
+            .hw_rev
+            .as_ref()
+            .map(|s| alloc(&mut next, s.len() + 1))
+            .transpose()?;
+        let filenames = info
+            .rom_sets
+            .iter()
+            .map(|set| {
+                set.roms
+                    .iter()
+                    .map(|rom| match rom.filename.as_ref() {
+                        Some(s) if info.boot_logging_enabled => {
+                            alloc(&mut next, s.len() + 1).map(Some)
+                        }
+                        _ => Ok(None),
+                    })
+                    .collect()
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Layout {
+            header,
+            extra_info,
+            pins,
+            rom_sets,
+            rom_ptr_arrays,
+            rom_infos,
+            build_date,
+            hw_rev,
+            filenames,
+            end: next,
+        })
+    }
+
+    // Builds the internal header structure from the info and layout
+    fn header(&self, layout: &Layout) -> SdrrInfoHeader {
+        let info = self.info;
+        SdrrInfoHeader {
+            major_version: info.major_version,
+            minor_version: info.minor_version,
+            patch_version: info.patch_version,
+            build_number: info.build_number,
+            build_date_ptr: layout.build_date.unwrap_or(0),
+            commit: info.commit,
+            hw_rev_ptr: layout.hw_rev.unwrap_or(0),
+            stm_line: info.stm_line,
+            stm_storage: info.stm_storage,
+            freq: info.freq,
+            overclock: info.overclock as u8,
+            swd_enabled: info.swd_enabled as u8,
+            preload_image_to_ram: info.preload_image_to_ram as u8,
+            bootloader_capable: info.bootloader_capable as u8,
+            status_led_enabled: info.status_led_enabled as u8,
+            boot_logging_enabled: info.boot_logging_enabled as u8,
+            mco_enabled: info.mco_enabled as u8,
+            mco2_enabled: info.mco2_enabled as u8,
+            rom_set_count: info.rom_set_count,
+            count_rom_access: info.count_rom_access as u8,
+            rom_sets_ptr: if info.rom_sets.is_empty() {
+                0
+            } else {
+                layout.rom_sets
+            },
+            pins_ptr: layout.pins.unwrap_or(0),
+            boot_config: info.boot_config.bytes(),
+            extra_ptr: layout.extra_info.unwrap_or(NO_EXTRA_INFO_PTR),
+            rom_crc32: info.rom_crc32.unwrap_or(0),
+        }
+    }
+
+    fn put(&self, image: &mut [u8], addr: u32, bytes: &[u8]) -> Result<(), String> {
+        let offset = addr.checked_sub(self.base_flash_address).ok_or_else(|| {
+            format!(
+                "Address 0x{:08X} is below the base flash address 0x{:08X}",
+                addr, self.base_flash_address
+            )
+        })? as usize;
+        let dest = offset
+            .checked_add(bytes.len())
+            .and_then(|end| image.get_mut(offset..end))
+            .ok_or_else(|| {
+                format!("{} bytes at 0x{:08X} are beyond the end of the image", bytes.len(), addr)
+            })?;
+        dest.copy_from_slice(bytes);
+        Ok(())
+    }
+
+    fn put_string(&self, image: &mut [u8], addr: u32, s: &str) -> Result<(), String> {
+        self.put(image, addr, s.as_bytes())?;
+        let terminator = u32::try_from(s.len())
+            .ok()
+            .and_then(|len| addr.checked_add(len))
+            .ok_or_else(|| format!("String at 0x{:08X} overflows the address space", addr))?;
+        self.put(image, terminator, &[0])
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::readers::MemoryReader;
+    use crate::{
+        BootConfig, McuStorage, Parser, SdrrCsState, SdrrExtraInfo, SdrrMcuPort, SdrrRomInfo,
+        SdrrRomSet, SdrrRomType, SdrrServe,
+    };
+
+    pub(crate) fn test_pins() -> SdrrPins {
+        SdrrPins {
+            data_port: SdrrMcuPort::PortA,
+            addr_port: SdrrMcuPort::PortC,
+            cs_port: SdrrMcuPort::PortC,
+            sel_port: SdrrMcuPort::PortB,
+            status_port: SdrrMcuPort::PortB,
+            rom_pins: 24,
+            data: vec![7, 6, 5, 4, 3, 2, 1, 0],
+            addr: vec![
+                5, 4, 6, 7, 3, 2, 1, 0, 8, 13, 11, 12, 9, 255, 255, 255,
+            ],
+            cs1_2364: 10,
+            cs1_2332: 10,
+            cs1_2316: 10,
+            cs2_2332: 9,
+            cs2_2316: 12,
+            cs3_2316: 9,
+            x1: 14,
+            x2: 15,
+            ce_23128: 255,
+            oe_23128: 255,
+            x_jumper_pull: 1,
+            sel0: 0,
+            sel1: 1,
+            sel2: 2,
+            sel3: 7,
+            sel4: 255,
+            sel5: 255,
+            sel6: 255,
+            sel_jumper_pull: 1,
+            status: 15,
+        }
+    }
+
+    pub(crate) fn test_rom(rom_type: SdrrRomType, filename: &str) -> SdrrRomInfo {
+        SdrrRomInfo {
+            rom_type,
+            cs1_state: SdrrCsState::ActiveLow,
+            cs2_state: SdrrCsState::NotUsed,
+            cs3_state: SdrrCsState::NotUsed,
+            filename: Some(filename.into()),
+        }
+    }
+
+    pub(crate) fn test_info() -> SdrrInfo {
+        let mut info = SdrrInfo {
+            major_version: 0,
+            minor_version: 4,
+            patch_version: 1,
+            build_number: 7,
+            commit: *b"abcdef0\0",
+            stm_line: McuLine::F411,
+            stm_storage: McuStorage::StorageE,
+            freq: 100,
+            overclock: false,
+            swd_enabled: true,
+            preload_image_to_ram: true,
+            bootloader_capable: false,
+            status_led_enabled: true,
+            boot_logging_enabled: true,
+            mco_enabled: false,
+            mco2_enabled: false,
+            rom_set_count: 2,
+            count_rom_access: false,
+            boot_config: BootConfig::UNSET,
+            build_date: Some("Aug 30 2025 12:00:00".into()),
+            hw_rev: Some("24-f".into()),
+            rom_sets: vec![
+                SdrrRomSet {
+                    data_ptr: 0,
+                    size: 16384,
+                    roms: vec![test_rom(SdrrRomType::Rom2364, "kernal.bin")],
+                    rom_count: 1,
+                    serve: SdrrServe::AddrOnCs,
+                    multi_rom_cs1_state: SdrrCsState::NotUsed,
+                },
+                SdrrRomSet {
+                    data_ptr: 0,
+                    size: 65536,
+                    roms: vec![
+                        test_rom(SdrrRomType::Rom2364, "basic.bin"),
+                        test_rom(SdrrRomType::Rom2332, "char.bin"),
+                    ],
+                    rom_count: 2,
+                    serve: SdrrServe::AddrOnAnyCs,
+                    multi_rom_cs1_state: SdrrCsState::ActiveLow,
+                },
+            ],
+            pins: Some(test_pins()),
+            parse_errors: Vec::new(),
+            extra_info: Some(SdrrExtraInfo {
+                rtt_ptr: 0x20000100,
+            }),
+            rom_crc32: None,
+        };
+
+        place_rom_sets(&mut info);
+        info
+    }
+
+    /// Places the ROM set data after the metadata.  Call again after
+    /// changing the ROM sets.
+    pub(crate) fn place_rom_sets(info: &mut SdrrInfo) {
+        let mut data_ptr = Writer::new(info).metadata_end().unwrap().next_multiple_of(0x100);
+        for rom_set in info.rom_sets.iter_mut() {
+            rom_set.data_ptr = data_ptr;
+            data_ptr += rom_set.size;
+        }
+    }
+
+    fn parse(image: Vec<u8>, base: u32) -> SdrrInfo {
+        let mut parser = Parser::new(MemoryReader::new(image, base));
+        smol::block_on(parser.parse_flash()).expect("Failed to parse written image")
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let info = test_info();
+        let image = Writer::new(&info).write().unwrap();
+        let parsed = parse(image, STM32F4_FLASH_BASE);
+
+        assert!(parsed.parse_errors.is_empty(), "{:?}", parsed.parse_errors);
+        assert_eq!(format!("{:?}", parsed), format!("{:?}", info));
+    }
+
+    #[test]
+    fn test_round_trip_no_boot_logging() {
+        let mut info = test_info();
+        info.boot_logging_enabled = false;
+        for rom in info.rom_sets.iter_mut().flat_map(|set| set.roms.iter_mut()) {
+            rom.filename = None;
+        }
+        let image = Writer::new(&info).write().unwrap();
+        let parsed = parse(image, STM32F4_FLASH_BASE);
+
+        assert_eq!(format!("{:?}", parsed), format!("{:?}", info));
+    }
+
+    #[test]
+    fn test_rom_set_data() {
+        let info = test_info();
+        let data: Vec<u8> = (0..info.rom_sets[0].size).map(|ii| ii as u8).collect();
+        let image = Writer::new(&info).rom_set_data(0, &data).write().unwrap();
+
+        let offset = (info.rom_sets[0].data_ptr - STM32F4_FLASH_BASE) as usize;
+        assert_eq!(&image[offset..offset + data.len()], &data[..]);
+
+        let mut parsed = parse(image.clone(), STM32F4_FLASH_BASE);
+        let mut parser = Parser::new(MemoryReader::new(image, STM32F4_FLASH_BASE));
+        let mut buf = [0u8; 4];
+        smol::block_on(parsed.read_rom_set_data(&mut parser, 0, 0x100, &mut buf)).unwrap();
+        assert_eq!(buf, [0x00, 0x01, 0x02, 0x03]);
+    }
+
+    #[test]
+    fn test_header_bytes() {
+        let info = test_info();
+        let header = info.to_header_bytes().unwrap();
+        let image = Writer::new(&info).write().unwrap();
+
+        let offset = SDRR_INFO_FW_OFFSET as usize;
+        assert_eq!(header.len(), SdrrInfoHeader::size());
+        assert_eq!(&header[..4], b"SDRR");
+        assert_eq!(&image[offset..offset + header.len()], &header[..]);
+    }
+
+    #[test]
+    fn test_data_overlapping_metadata() {
+        let mut info = test_info();
+        info.rom_sets[0].data_ptr = STM32F4_FLASH_BASE + SDRR_INFO_FW_OFFSET;
+        assert!(Writer::new(&info).write().is_err());
+    }
+
+    #[test]
+    fn test_data_overflowing_address_space() {
+        let mut info = test_info();
+        info.rom_sets[1].data_ptr = u32::MAX - 0x100;
+        let err = Writer::new(&info).write().unwrap_err();
+        assert!(err.contains("overflows the address space"), "{}", err);
+
+        // Metadata laid out from a base near the top of the address space
+        let err = Writer::with_base_flash_address(&info, u32::MAX - 0x300)
+            .write()
+            .unwrap_err();
+        assert!(err.contains("overflow the address space"), "{}", err);
+    }
+
+    #[test]
+    fn test_data_beyond_flash() {
+        // A RAM address must not cause the whole address range to be
+        // allocated
+        let mut info = test_info();
+        info.rom_sets[1].data_ptr = 0x2000_0000;
+        let err = Writer::new(&info).write().unwrap_err();
+        assert!(err.contains("beyond the end of the 512 KB flash"), "{}", err);
+    }
+
+    #[test]
+    fn test_rp2350_base() {
+        let mut info = test_info();
+        info.stm_line = McuLine::Rp2350;
+        info.stm_storage = McuStorage::Storage2MB;
+        let offset = RP2350_FLASH_BASE - STM32F4_FLASH_BASE;
+        for rom_set in info.rom_sets.iter_mut() {
+            rom_set.data_ptr += offset;
+        }
+        let image = Writer::new(&info).write().unwrap();
+
+        // The parser always starts at the STM32F4 base, and switches once it
+        // sees the RP2350 line in the header
+        let parsed = parse(image, STM32F4_FLASH_BASE);
+        assert_eq!(format!("{:?}", parsed), format!("{:?}", info));
+    }
+}
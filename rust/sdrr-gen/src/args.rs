@@ -9,13 +9,20 @@
 //! logging, preloading image to RAM, and more.
 
 use clap::Parser;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use sdrr_common::args::{parse_hw_rev, parse_serve_alg, parse_mcu_variant};
+use sdrr_common::rom_db::{self, KnownRom};
 use sdrr_common::{CsLogic, HwConfig, RomType, ServeAlg, McuVariant};
 
-use crate::config::{Config, CsConfig, RomConfig, SizeHandling};
-use crate::file::{FileSource, check_output_dir, source_image_file};
+use crate::config::{
+    Config, CsConfig, MAX_BANKED_ROMS, MAX_MULTI_ROMS, OutputFormat, RomConfig, SizeHandling,
+};
+use crate::file::{
+    Downloader, FileSource, check_image_source, check_output_dir, expand_glob, file_crc32, is_glob,
+    load_rom_manifest, save_download, source_image_file, verify_sha256,
+};
 
 #[derive(Parser, Debug)]
 #[clap(
@@ -24,12 +31,19 @@ use crate::file::{FileSource, check_output_dir, source_image_file};
     version
 )]
 pub struct Args {
-    /// ROM configuration (file=path,type=2364,cs1=0)
-    #[clap(long, alias = "rom-config", required_unless_present = "list_hw_revs")]
+    /// ROM configuration (file=path,type=2364,cs1=0).  type may be omitted
+    /// for known ROMs (see --identify).  A local file may be a glob, such as
+    /// file=banks/*.bin, adding one ROM per matching file
+    #[clap(long, alias = "rom-config", required_unless_present_any = ["list_hw_revs", "list_freqs", "identify"])]
     rom: Vec<String>,
 
+    /// ROM set for ROMs without set= to join, so a single multi-ROM or
+    /// bank switched set doesn't need set= on every ROM
+    #[clap(long, value_name = "N")]
+    default_set: Option<usize>,
+
     /// MCU variant (f446rc, f446re, f411rc, f411re, f405rg, f401re, f401rb, f401rc, rp2350)
-    #[clap(long, alias = "stm", required_unless_present = "list_hw_revs", value_parser = parse_mcu_variant)]
+    #[clap(long, alias = "stm", required_unless_present_any = ["list_hw_revs", "identify"], value_parser = parse_mcu_variant)]
     mcu: Option<McuVariant>,
 
     /// Enable SWD
@@ -82,13 +96,18 @@ pub struct Args {
     hse: bool,
 
     /// Hardware revision (use --list-hw-revs for options)
-    #[clap(long, alias= "hw-rev", value_parser = parse_hw_rev, required_unless_present = "list_hw_revs")]
+    #[clap(long, alias= "hw-rev", value_parser = parse_hw_rev, required_unless_present_any = ["list_hw_revs", "list_freqs", "identify"])]
     hw: Option<HwConfig>,
 
     /// Target frequency in MHz (default: max for the variant)
     #[clap(long)]
     freq: Option<u32>,
 
+    /// Error, rather than warn, if the target frequency can't be achieved
+    /// exactly
+    #[clap(long)]
+    exact_freq: bool,
+
     /// Support the status LED
     #[clap(long)]
     status_led: bool,
@@ -97,6 +116,11 @@ pub struct Args {
     #[clap(long)]
     overclock: bool,
 
+    /// Allow overclocking beyond the processor's maximum known overclock,
+    /// which is likely to produce firmware which won't boot
+    #[clap(long, requires = "overclock")]
+    i_understand_overclocking: bool,
+
     /// Support entering bootloader mode when all select jumpers as closed
     #[clap(long)]
     bootloader: bool,
@@ -117,9 +141,91 @@ pub struct Args {
     #[clap(long, default_value = "false")]
     list_hw_revs: bool,
 
+    /// Output the hardware revision list as JSON, including each revision's
+    /// capabilities
+    #[clap(long, requires = "list_hw_revs")]
+    json: bool,
+
+    /// List achievable target frequencies for the MCU variant (use with
+    /// --overclock to include overclocked frequencies)
+    #[clap(long, default_value = "false", requires = "mcu")]
+    list_freqs: bool,
+
     /// Use to enable SDRR's ROM access count functionality.
     #[clap(long, default_value = "false")]
     count_rom_access: bool,
+
+    /// Identify a ROM image file from its CRC32 and exit
+    #[clap(long)]
+    identify: Option<PathBuf>,
+
+    /// CSV file of additional known ROMs (crc32,name,type), used when
+    /// identifying ROM images, and to infer the type of ROMs without type=
+    #[clap(long)]
+    rom_db: Option<PathBuf>,
+
+    /// CSV file of expected ROM CRC32s (source,expected_crc32).  Each ROM
+    /// file loaded must be listed, with a matching CRC32
+    #[clap(long, value_name = "MANIFEST")]
+    validate_roms: Option<PathBuf>,
+
+    /// Validate the configuration and local ROM file sizes, then exit without
+    /// downloading anything or writing to the output directory
+    #[clap(long)]
+    check_only: bool,
+
+    /// Load and transform the ROMs, then report the files which would be
+    /// written, without writing or downloading anything
+    #[clap(long, conflicts_with = "check_only")]
+    dry_run: bool,
+
+    /// Write a report of the ROM images' flash usage to this file
+    #[clap(long)]
+    layout: Option<PathBuf>,
+
+    /// Write all of the transformed ROM set images, with an index, to this
+    /// binary file, for flashing separately from the firmware
+    #[clap(long, value_name = "FILE")]
+    emit_images: Option<PathBuf>,
+
+    /// Check each transformed ROM image demangles back to its source image
+    /// before generating the output files
+    #[clap(long)]
+    verify_transforms: bool,
+
+    /// Error, rather than warn, if a ROM image looks like a bad dump - all
+    /// 0xFF, all 0x00, or only a handful of distinct byte values
+    #[clap(long)]
+    strict_roms: bool,
+
+    /// Format to generate the ROM images and tables in (c, rust)
+    #[clap(long, default_value = "c", value_parser = parse_output_format)]
+    output_format: OutputFormat,
+
+    /// Generate into a temporary directory and compare the output against
+    /// this previously generated output directory, failing if any differ
+    #[clap(long, conflicts_with_all = ["check_only", "dry_run"])]
+    compare: Option<PathBuf>,
+
+    /// Seconds to allow each ROM image download to take before failing
+    #[clap(long, value_name = "SECS", default_value = "60",
+        value_parser = clap::value_parser!(u64).range(1..))]
+    download_timeout: u64,
+
+    /// Number of times to retry each ROM image download after a connection
+    /// or server error, waiting twice as long before each retry
+    #[clap(long, value_name = "N", default_value = "0")]
+    download_retries: u32,
+
+    /// Copy each downloaded ROM image, after any extraction from a zip, into
+    /// this directory, for inspecting the exact images used in the build
+    #[clap(long, value_name = "DIR")]
+    save_downloads: Option<PathBuf>,
+
+    /// Write the command line which reproduces the build to this file,
+    /// rather than printing it after a successful run
+    #[clap(long, value_name = "FILE")]
+    emit_command: Option<PathBuf>,
 }
 
 impl Args {
@@ -128,6 +234,78 @@ impl Args {
         self.list_hw_revs
     }
 
+    /// Returns whether to output listings as JSON
+    pub fn json(&self) -> bool {
+        self.json
+    }
+
+    /// Returns the MCU variant to list frequencies for, if `list_freqs` is
+    /// set, and whether to include overclocked frequencies
+    pub fn list_freqs(&self) -> Option<(McuVariant, bool)> {
+        if self.list_freqs {
+            self.mcu.map(|mcu| (mcu, self.overclock))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the ROM image file to identify, if any
+    pub fn identify(&self) -> Option<&PathBuf> {
+        self.identify.as_ref()
+    }
+
+    /// Returns the database of known ROMs, including any user supplied ones
+    pub fn rom_db(&self) -> Result<Vec<KnownRom>, String> {
+        let user = match &self.rom_db {
+            Some(path) => rom_db::load_csv(path)?,
+            None => Vec::new(),
+        };
+        Ok(rom_db::with_user_roms(user))
+    }
+
+    /// Returns whether to only check the configuration
+    pub fn check_only(&self) -> bool {
+        self.check_only
+    }
+
+    /// Returns whether to report, rather than write, the output files
+    pub fn dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    /// Returns the file to write the reproduction command to, if any
+    pub fn emit_command(&self) -> Option<&PathBuf> {
+        self.emit_command.as_ref()
+    }
+
+    /// Returns a copy-pasteable shell command line which reproduces the
+    /// build, with the --rom arguments as given and the rest of the
+    /// configuration, including resolved defaults such as the frequency,
+    /// from `config`.  Options which only affect how sdrr-gen runs, rather
+    /// than what it generates - such as --overwrite, --yes, --dry-run and
+    /// the download options - are left out.
+    pub fn reproduction_command(&self, config: &Config) -> String {
+        let mut args = vec!["sdrr-gen".to_string()];
+        for rom in &self.rom {
+            args.extend(["--rom".to_string(), rom.clone()]);
+        }
+        if let Some(set) = self.default_set {
+            args.extend(["--default-set".to_string(), set.to_string()]);
+        }
+        args.extend(config.command_args());
+        if let Some(rom_db) = &self.rom_db {
+            args.extend(["--rom-db".to_string(), rom_db.display().to_string()]);
+        }
+        if let Some(manifest) = &self.validate_roms {
+            args.extend(["--validate-roms".to_string(), manifest.display().to_string()]);
+        }
+
+        args.iter()
+            .map(|arg| shell_quote(arg))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
     /// Returns the ROM configuration
     fn rom_config(&self) -> &Vec<String> {
         &self.rom
@@ -175,7 +353,23 @@ impl Args {
 
     /// Returns whether the output directory can be overwritten
     fn can_overwrite(&self) -> bool {
-        self.overwrite
+        self.overwrite || self.compare.is_some()
+    }
+
+    /// Returns the previously generated output directory to compare against,
+    /// if any
+    pub fn compare(&self) -> Option<&PathBuf> {
+        self.compare.as_ref()
+    }
+
+    /// Returns the directory to generate into.  When comparing, this is a
+    /// temporary directory, leaving the existing output untouched.
+    fn output_dir(&self) -> PathBuf {
+        if self.compare.is_some() {
+            std::env::temp_dir().join(format!("sdrr-gen-compare-{}", std::process::id()))
+        } else {
+            self.output_dir.clone()
+        }
     }
 
     /// Returns whether HSE should be used
@@ -236,12 +430,28 @@ impl Args {
     /// Parse the ROM configuration arguments into a vector of `RomConfig`.
     pub fn parse_rom_args(&self) -> Result<Vec<RomConfig>, String> {
         let mut roms = Vec::new();
-
-        for (rom_num, rom_config_str) in self.rom_config().iter().enumerate() {
-            let rom_config = self.parse_rom_arg(rom_num, rom_config_str).map_err(|e| {
+        let db = self.rom_db()?;
+        let timeout = Duration::from_secs(self.download_timeout);
+        let downloader = Downloader::new(timeout, self.download_retries)?;
+
+        for rom_config_str in self.rom_config() {
+            // A file= glob expands to one ROM per matching file
+            let expanded = expand_rom_arg(rom_config_str).map_err(|e| {
+                let rom_num = roms.len();
                 format!("ROM #{rom_num} configuration error: {e} (config: {rom_config_str})")
             })?;
-            roms.push(rom_config);
+
+            for rom_config_str in &expanded {
+                let rom_num = roms.len();
+                let rom_config = self
+                    .parse_rom_arg(rom_num, rom_config_str, &db, &downloader)
+                    .map_err(|e| {
+                        format!(
+                            "ROM #{rom_num} configuration error: {e} (config: {rom_config_str})"
+                        )
+                    })?;
+                roms.push(rom_config);
+            }
         }
 
         Ok(roms)
@@ -250,8 +460,12 @@ impl Args {
     /// Returns the entire sdrr-gen configuration object.
     pub fn gen_config(&self) -> Result<Config, String> {
         // Check whether output directory exists and if so, if user is happy to
-        // overwrite its content.
-        check_output_dir(&self.output_dir, self.can_overwrite()).map_err(|e| e.to_string())?;
+        // overwrite its content.  Not needed if only checking or doing a dry
+        // run, as nothing will be written.
+        if !self.check_only() && !self.dry_run() {
+            check_output_dir(&self.output_dir(), self.can_overwrite())
+                .map_err(|e| e.to_string())?;
+        }
 
         // Parse the ROM arguments
         let roms = self.parse_rom_args()?;
@@ -259,11 +473,17 @@ impl Args {
         // Get the MCU variant and frequency
         let (mcu_variant, freq) = self.mcu_and_freq()?;
 
+        let rom_manifest = self
+            .validate_roms
+            .as_ref()
+            .map(|path| load_rom_manifest(path))
+            .transpose()?;
+
         // Return the config object
         Ok(Config {
             roms,
             mcu_variant,
-            output_dir: self.output_dir.clone(),
+            output_dir: self.output_dir(),
             swd: self.swd(),
             count_rom_access: self.count_rom_access,
             mco: self.mco(),
@@ -276,16 +496,32 @@ impl Args {
             hse: self.hse(),
             hw: self.hw_config(),
             freq,
+            exact_freq: self.exact_freq,
             status_led: self.status_led(),
             overclock: self.overclock(),
+            overclock_beyond_max: self.i_understand_overclocking,
             bootloader: self.bootloader(),
             preload_to_ram: self.preload_to_ram(),
             auto_yes: self.auto_yes(),
             serve_alg: self.serve_alg(),
+            check_only: self.check_only(),
+            dry_run: self.dry_run(),
+            layout: self.layout.clone(),
+            emit_images: self.emit_images.clone(),
+            rom_manifest,
+            verify_transforms: self.verify_transforms,
+            strict_roms: self.strict_roms,
+            output_format: self.output_format,
         })
     }
 
-    fn parse_rom_arg(&self, rom_num: usize, s: &str) -> Result<RomConfig, String> {
+    fn parse_rom_arg(
+        &self,
+        rom_num: usize,
+        s: &str,
+        db: &[KnownRom],
+        downloader: &Downloader,
+    ) -> Result<RomConfig, String> {
         let mut original_file_source = None;
         let mut extract = None;
         let mut licence = None;
@@ -296,6 +532,7 @@ impl Args {
         let mut size_handling = SizeHandling::None;
         let mut set = None;
         let mut bank = None;
+        let mut sha256 = None;
 
         for pair in s.split(',') {
             let parts: Vec<&str> = pair.split('=').collect();
@@ -353,6 +590,17 @@ impl Args {
                     }
                     licence = Some(parts[1].to_string());
                 }
+                "sha256" => {
+                    if parts.len() != 2 {
+                        return Err(
+                            "Invalid 'sha256' parameter format - must include hash".to_string()
+                        );
+                    }
+                    if sha256.is_some() {
+                        return Err("sha256 specified multiple times".to_string());
+                    }
+                    sha256 = Some(parse_sha256(parts[1])?);
+                }
                 "type" => {
                     if parts.len() != 2 {
                         return Err(
@@ -444,9 +692,25 @@ impl Args {
             source
         };
 
-        let file = source_image_file(rom_num, &source, &self.output_dir)?;
+        let file = if self.check_only() || self.dry_run() {
+            check_image_source(rom_num, &source, &self.output_dir())?
+        } else {
+            let file = source_image_file(rom_num, &source, &self.output_dir(), downloader)?;
+            if let Some(dir) = &self.save_downloads {
+                save_download(&file, &source, dir)?;
+            }
+            // For zip files this is the extracted file, rather than the zip
+            if let Some(sha256) = &sha256 {
+                verify_sha256(&file, sha256)?;
+            }
+            file
+        };
 
-        let rom_type = rom_type.ok_or("Missing 'type' parameter")?;
+        // If the type wasn't specified, try to infer it from the image
+        let rom_type = match rom_type {
+            Some(rom_type) => rom_type,
+            None => infer_rom_type(rom_num, &file, db)?,
+        };
         let cs1 = cs1.ok_or("Missing 'cs1' parameter")?;
 
         Ok(RomConfig {
@@ -457,8 +721,258 @@ impl Args {
             rom_type,
             cs_config: CsConfig::new(cs1, cs2, cs3),
             size_handling,
-            set,
+            set: set.or(self.default_set),
             bank,
         })
     }
 }
+
+fn parse_output_format(s: &str) -> Result<OutputFormat, String> {
+    OutputFormat::try_from_str(s)
+        .ok_or_else(|| format!("Invalid output format: {}. Valid values are: c, rust", s))
+}
+
+// Quotes an argument for a POSIX shell, unless it only contains characters
+// which are safe unquoted
+fn shell_quote(arg: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "-_=,./:@%+".contains(c);
+    if !arg.is_empty() && arg.chars().all(safe) {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+// Parses a sha256= value, which must be 64 hex digits, returning it in
+// lowercase
+fn parse_sha256(s: &str) -> Result<String, String> {
+    if s.len() != 64 || !s.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("Invalid SHA-256: {s} (must be 64 hex digits)"));
+    }
+    Ok(s.to_ascii_lowercase())
+}
+
+// Expands a ROM configuration whose file= is a local glob pattern into one
+// configuration per matching file, in sorted order, sharing the other
+// sub-options.  If bank= is given, the files are assigned consecutive banks
+// starting from it.  Other configurations are returned unchanged.
+fn expand_rom_arg(s: &str) -> Result<Vec<String>, String> {
+    let pairs: Vec<&str> = s.split(',').collect();
+    let value = |key: &str| {
+        pairs
+            .iter()
+            .find_map(|pair| pair.strip_prefix(key).and_then(|pair| pair.strip_prefix('=')))
+    };
+
+    let pattern = match value("file") {
+        Some(file) if !file.starts_with("http://") && !file.starts_with("https://") => file,
+        _ => return Ok(vec![s.to_string()]),
+    };
+    if !is_glob(pattern) {
+        return Ok(vec![s.to_string()]);
+    }
+    let files = expand_glob(pattern)?;
+
+    // Check the files will fit in the set
+    let bank = value("bank")
+        .map(|bank| {
+            bank.parse::<usize>()
+                .map_err(|_| format!("Invalid bank number: {}", bank))
+        })
+        .transpose()?;
+    if let Some(bank) = bank {
+        if bank + files.len() > MAX_BANKED_ROMS {
+            return Err(format!(
+                "file={} matches {} files, but a banked set can only contain {} ROMs, starting from bank {}",
+                pattern,
+                files.len(),
+                MAX_BANKED_ROMS,
+                bank
+            ));
+        }
+    } else if value("set").is_some() && files.len() > MAX_MULTI_ROMS {
+        return Err(format!(
+            "file={} matches {} files, but a multi-ROM set can only contain {} ROMs",
+            pattern,
+            files.len(),
+            MAX_MULTI_ROMS
+        ));
+    }
+
+    Ok(files
+        .iter()
+        .enumerate()
+        .map(|(ii, file)| {
+            pairs
+                .iter()
+                .map(|pair| {
+                    if pair.starts_with("file=") {
+                        format!("file={}", file.display())
+                    } else if let Some(bank) = bank.filter(|_| pair.starts_with("bank=")) {
+                        format!("bank={}", bank + ii)
+                    } else {
+                        pair.to_string()
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(",")
+        })
+        .collect())
+}
+
+// Infers a ROM's type from its image, if it uniquely matches a known ROM
+fn infer_rom_type(rom_num: usize, file: &Path, db: &[KnownRom]) -> Result<RomType, String> {
+    if !file.exists() {
+        return Err("Missing 'type' parameter".to_string());
+    }
+
+    let crc = file_crc32(file)?;
+    match rom_db::identify(db, crc).as_slice() {
+        [known] => {
+            println!(
+                "- ROM #{rom_num}: identified as {} (CRC32 0x{:08X}), using type {}",
+                known.name,
+                crc,
+                known.rom_type.name()
+            );
+            Ok(known.rom_type)
+        }
+        [] => Err(format!(
+            "Missing 'type' parameter, and image (CRC32 0x{:08X}) is not a known ROM",
+            crc
+        )),
+        _ => Err(format!(
+            "Missing 'type' parameter, and image (CRC32 0x{:08X}) matches multiple known ROMs",
+            crc
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{File, create_dir_all, remove_dir_all};
+
+    #[test]
+    fn test_parse_sha256() {
+        let hash = "BA7816BF8F01CFEA414140DE5DAE2223B00361A396177A9CB410FF61F20015AD";
+        assert_eq!(parse_sha256(hash).unwrap(), hash.to_ascii_lowercase());
+        assert!(parse_sha256(&hash[1..]).is_err());
+        assert!(parse_sha256(&hash.replace('A', "g")).is_err());
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        let rom = "file=roms/kernal.bin,type=2364,cs1=0";
+        assert_eq!(shell_quote(rom), rom);
+        assert_eq!(shell_quote("file=banks/*.bin"), "'file=banks/*.bin'");
+        assert_eq!(shell_quote("file=http://x/a?b&c"), "'file=http://x/a?b&c'");
+        assert_eq!(shell_quote("file=it's here.bin"), "'file=it'\\''s here.bin'");
+        assert_eq!(shell_quote(""), "''");
+    }
+
+    #[test]
+    fn test_reproduction_command() {
+        let hw = HwConfig::new(include_str!("../../../sdrr-hw-config/24-f.json"), "24-f").unwrap();
+        let mut config = crate::config::tests::test_config(hw);
+        config.serve_alg = ServeAlg::AddrOnCs;
+        config.output_dir = PathBuf::from("my output");
+        // --list-hw-revs stands in for --hw, which would load the hardware
+        // configuration relative to the current directory.  The hardware
+        // comes from the configuration anyway.
+        let args = Args::try_parse_from([
+            "sdrr-gen",
+            "--list-hw-revs",
+            "--rom",
+            "file=kernal.bin,type=2364,cs1=0",
+            "--rom",
+            "file=banks/*.bin,set=1",
+            "--default-set",
+            "1",
+            "--mcu",
+            "f411re",
+            "--yes",
+        ])
+        .unwrap();
+
+        assert_eq!(
+            args.reproduction_command(&config),
+            "sdrr-gen --rom file=kernal.bin,type=2364,cs1=0 --rom 'file=banks/*.bin,set=1' \
+             --default-set 1 --mcu f411re --hw 24-f --freq 100 --swd --status-led \
+             --disable-preload-to-ram --serve-alg b --output-dir 'my output'"
+        );
+    }
+
+    #[test]
+    fn test_default_set() {
+        let dir = std::env::temp_dir().join(format!("sdrr-gen-default-set-{}", std::process::id()));
+        create_dir_all(&dir).unwrap();
+        for name in ["a.bin", "b.bin", "c.bin"] {
+            File::create(dir.join(name)).unwrap();
+        }
+        let rom = |name: &str, set: &str| {
+            format!("file={},type=2364,cs1=0{}", dir.join(name).display(), set)
+        };
+        // --list-hw-revs stands in for --hw, as in test_reproduction_command()
+        let parse = |roms: &[String]| {
+            let mut argv = vec!["sdrr-gen", "--list-hw-revs", "--check-only", "--default-set", "0"];
+            for rom in roms {
+                argv.extend(["--rom", rom.as_str()]);
+            }
+            let roms = Args::try_parse_from(argv).unwrap().parse_rom_args().unwrap();
+            let sets: Vec<Option<usize>> = roms.iter().map(|rom| rom.set).collect();
+            (roms, sets)
+        };
+
+        // ROMs without set= join the default set
+        let (_, sets) = parse(&[rom("a.bin", ""), rom("b.bin", "")]);
+        assert_eq!(sets, [Some(0), Some(0)]);
+
+        // Explicit and implicit sets can be mixed, and still validate
+        let (roms, sets) = parse(&[rom("a.bin", ""), rom("b.bin", ",set=1"), rom("c.bin", "")]);
+        assert_eq!(sets, [Some(0), Some(1), Some(0)]);
+        let hw = HwConfig::new(include_str!("../../../sdrr-hw-config/24-f.json"), "24-f").unwrap();
+        let mut config = crate::config::tests::test_config(hw);
+        config.roms = roms;
+        config.validate().unwrap();
+
+        // Without a default set, mixing is still all-or-nothing
+        config.roms[0].set = None;
+        assert!(config.validate().is_err());
+
+        remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_expand_rom_arg() {
+        let dir = std::env::temp_dir().join(format!("sdrr-gen-args-{}", std::process::id()));
+        create_dir_all(&dir).unwrap();
+        for name in ["bank0.bin", "bank1.bin", "bank2.bin"] {
+            File::create(dir.join(name)).unwrap();
+        }
+        let path = dir.display();
+
+        // Banks are assigned consecutively, other sub-options are shared
+        let expanded = expand_rom_arg(&format!("set=0,bank=1,file={path}/*.bin,cs1=0")).unwrap();
+        assert_eq!(
+            expanded,
+            [
+                format!("set=0,bank=1,file={path}/bank0.bin,cs1=0"),
+                format!("set=0,bank=2,file={path}/bank1.bin,cs1=0"),
+                format!("set=0,bank=3,file={path}/bank2.bin,cs1=0"),
+            ]
+        );
+
+        let err = expand_rom_arg(&format!("set=0,bank=2,file={path}/*.bin,cs1=0")).unwrap_err();
+        assert!(err.contains("matches 3 files"), "{}", err);
+        let err = expand_rom_arg(&format!("set=0,file={path}/*.rom,cs1=0")).unwrap_err();
+        assert!(err.starts_with("No files match"), "{}", err);
+
+        // Non-globs and URLs are unchanged
+        for arg in ["file=rom.bin,cs1=0", "file=https://example.com/*.bin,cs1=0"] {
+            assert_eq!(expand_rom_arg(arg).unwrap(), [arg]);
+        }
+
+        remove_dir_all(&dir).unwrap();
+    }
+}
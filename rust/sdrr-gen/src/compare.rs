@@ -0,0 +1,180 @@
+// Copyright (C) 2025 Piers Finlayson <piers@piers.rocks>
+//
+// MIT License
+
+//! sdrr-gen - Comparison of generated output against a previous run, for
+//! `--compare`.
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+use strum::IntoEnumIterator;
+
+use crate::config::OutputFormat;
+use crate::file::{OutType, out_filename};
+
+// Marks the line in each generated file's header before the (blank line and)
+// arguments line.  The arguments will differ between runs, so are ignored.
+const ARGS_MARKER: &str = "auto-generated by sdrr-gen using the following arguments:";
+
+/// Compares the files generated into `generated` against those in `existing`,
+/// printing a summary of any differences.
+///
+/// Returns whether all files match.
+pub fn compare_output(generated: &Path, existing: &Path, format: OutputFormat) -> Result<bool> {
+    if !existing.is_dir() {
+        anyhow::bail!("Output directory to compare against `{}` not found", existing.display());
+    }
+
+    println!(
+        "Comparing generated output against `{}/`",
+        existing.display()
+    );
+
+    let mut differ = 0;
+    for out_type in OutType::iter().filter(|out_type| out_type.is_for_format(format)) {
+        let filename = out_filename(out_type);
+        let new = fs::read(generated.join(&filename))
+            .with_context(|| format!("Failed to read generated file {}", filename.display()))?;
+
+        let existing_path = existing.join(&filename);
+        if !existing_path.exists() {
+            println!("  {}: missing from existing output", filename.display());
+            differ += 1;
+            continue;
+        }
+        let old = fs::read(&existing_path)
+            .with_context(|| format!("Failed to read file {}", existing_path.display()))?;
+
+        let new = String::from_utf8_lossy(&new);
+        let old = String::from_utf8_lossy(&old);
+        let new_lines = strip_args_line(&new);
+        let old_lines = strip_args_line(&old);
+        if new_lines == old_lines {
+            println!("  {}: identical", filename.display());
+            continue;
+        }
+        differ += 1;
+
+        let changed = new_lines
+            .iter()
+            .zip(old_lines.iter())
+            .filter(|(a, b)| a != b)
+            .count()
+            + new_lines.len().abs_diff(old_lines.len());
+        let first = new_lines
+            .iter()
+            .zip(old_lines.iter())
+            .position(|(a, b)| a != b)
+            .unwrap_or(new_lines.len().min(old_lines.len()));
+        println!(
+            "  {}: differs - {} line(s) changed, first at line {} ({} lines, previously {})",
+            filename.display(),
+            changed,
+            first + 1,
+            new_lines.len(),
+            old_lines.len()
+        );
+
+        let new_data = rom_data_bytes(&new_lines);
+        let old_data = rom_data_bytes(&old_lines);
+        if !new_data.is_empty() || !old_data.is_empty() {
+            println!("    ROM data: {}", byte_summary(&new_data, &old_data));
+        }
+    }
+
+    if differ == 0 {
+        println!("- Generated output matches");
+    } else {
+        println!("- {} file(s) differ", differ);
+    }
+
+    Ok(differ == 0)
+}
+
+// Returns the file's lines, without the arguments line from the header.
+fn strip_args_line(contents: &str) -> Vec<&str> {
+    let lines: Vec<&str> = contents.lines().collect();
+    let args_line = lines
+        .iter()
+        .position(|line| line.contains(ARGS_MARKER))
+        .map(|marker| marker + 2);
+    lines
+        .into_iter()
+        .enumerate()
+        .filter(|(num, _)| Some(*num) != args_line)
+        .map(|(_, line)| line)
+        .collect()
+}
+
+// Extracts the ROM image bytes from a generated file - the lines consisting of
+// comma separated hex bytes.
+fn rom_data_bytes(lines: &[&str]) -> Vec<u8> {
+    lines
+        .iter()
+        .map(|line| line.trim())
+        .filter(|line| line.starts_with("0x"))
+        .flat_map(|line| line.split(','))
+        .map(|byte| byte.trim())
+        .filter(|byte| !byte.is_empty())
+        .filter_map(|byte| u8::from_str_radix(byte.trim_start_matches("0x"), 16).ok())
+        .collect()
+}
+
+// Summarises the byte-level differences between two ROM data images.
+fn byte_summary(new: &[u8], old: &[u8]) -> String {
+    let changed = new.iter().zip(old.iter()).filter(|(a, b)| a != b).count();
+    let first = new.iter().zip(old.iter()).position(|(a, b)| a != b);
+
+    let mut summary = match first {
+        Some(first) => format!(
+            "{} of {} byte(s) differ, first at offset 0x{:05X}",
+            changed,
+            new.len().min(old.len()),
+            first
+        ),
+        None => format!("{} byte(s) identical", new.len().min(old.len())),
+    };
+    if new.len() != old.len() {
+        summary.push_str(&format!(
+            ", size {} byte(s), previously {}",
+            new.len(),
+            old.len()
+        ));
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_args_line() {
+        let contents = "// roms.c\n\n// This file was auto-generated by sdrr-gen using the following arguments:\n//\n//   --hw 24-f\n//\n#include \"roms.h\"\n";
+        let lines = strip_args_line(contents);
+        assert_eq!(lines.len(), 6);
+        assert!(!lines.iter().any(|line| line.contains("--hw")));
+        assert_eq!(lines[5], "#include \"roms.h\"");
+    }
+
+    #[test]
+    fn test_rom_data_bytes() {
+        let lines = [
+            "    // Address 0x0000",
+            "    0xc0, 0xc1, 0x00, ",
+            "    0xff,",
+            "};",
+        ];
+        assert_eq!(rom_data_bytes(&lines), vec![0xc0, 0xc1, 0x00, 0xff]);
+    }
+
+    #[test]
+    fn test_byte_summary() {
+        assert_eq!(byte_summary(&[1, 2, 3], &[1, 2, 3]), "3 byte(s) identical");
+        assert_eq!(
+            byte_summary(&[1, 0, 3, 0], &[1, 2, 3]),
+            "1 of 3 byte(s) differ, first at offset 0x00001, size 4 byte(s), previously 3"
+        );
+    }
+}
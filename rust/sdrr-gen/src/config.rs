@@ -4,10 +4,22 @@
 
 use crate::preprocessor::{RomImage, RomSet};
 use sdrr_common::HwConfig;
-use sdrr_common::{CsLogic, RomType, ServeAlg, McuVariant};
-use sdrr_common::hardware::Port;
+use sdrr_common::{CsLogic, McuFamily, RomType, ServeAlg, McuVariant};
 use std::collections::BTreeMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+// Flash reserved for the firmware itself - code, the SDRR info structures and
+// strings - ahead of the ROM images.  An estimate, based on the F401RB (128KB
+// flash) holding 6 16KB images.
+pub const FIRMWARE_CODE_BUDGET_KB: usize = 32;
+
+// Maximum number of ROMs in a banked set, selected by X1/X2
+pub const MAX_BANKED_ROMS: usize = 4;
+
+// Maximum number of ROMs in a multi-ROM set on any hardware, selected by CS1,
+// X1 and X2.  The limit for a particular hardware revision comes from
+// HwConfig::max_roms_per_set().
+pub const MAX_MULTI_ROMS: usize = 3;
 
 #[derive(Debug, Clone)]
 pub struct Config {
@@ -26,12 +38,41 @@ pub struct Config {
     pub hse: bool,
     pub hw: HwConfig,
     pub freq: u32,
+    pub exact_freq: bool,
     pub status_led: bool,
     pub overclock: bool,
+    pub overclock_beyond_max: bool,
     pub bootloader: bool,
     pub preload_to_ram: bool,
     pub auto_yes: bool,
     pub serve_alg: ServeAlg,
+    pub check_only: bool,
+    pub dry_run: bool,
+    pub layout: Option<PathBuf>,
+    pub emit_images: Option<PathBuf>,
+    pub rom_manifest: Option<Vec<(String, u32)>>,
+    pub verify_transforms: bool,
+    pub strict_roms: bool,
+    pub output_format: OutputFormat,
+}
+
+/// Format of the generated ROM images and tables
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// roms.c and roms.h, for the C firmware
+    C,
+    /// roms.rs, for Rust firmware
+    Rust,
+}
+
+impl OutputFormat {
+    pub fn try_from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "c" => Some(OutputFormat::C),
+            "rust" | "rs" => Some(OutputFormat::Rust),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -133,8 +174,13 @@ impl Config {
                 .inspect_err(|_| println!("Failed to process ROM {}", rom.file.display()))?;
         }
 
-        // Validate output directory
-        if !self.overwrite && self.output_dir.exists() {
+        // Validate option dependencies.  clap enforces these for the CLI,
+        // but a Config built another way may not have been through it.
+        self.validate_dependencies()?;
+
+        // Validate output directory.  A dry run reports existing files
+        // instead.
+        if !self.check_only && !self.dry_run && !self.overwrite && self.output_dir.exists() {
             for file_name in &["roms.h", "roms.c", "config.h", "sdrr_config.h"] {
                 let file_path = self.output_dir.join(file_name);
                 if file_path.exists() {
@@ -147,8 +193,19 @@ impl Config {
         }
 
         // Validate status LED settings
-        if self.status_led && ((self.hw.port_status() == Port::None) || (self.hw.pin_status() == 255)) {
-            return Err("Status LED enabled but no status LED pin configured for selected hardware".to_string());
+        if self.status_led && !self.hw.supports_status_led() {
+            return Err(format!(
+                "Status LED enabled, but hardware revision {} has no status LED pin - the status LED isn't wired on that revision.  Remove --status-led/STATUS_LED=1",
+                self.hw.name
+            ));
+        }
+
+        // Validate the hardware's family is supported
+        if !self.hw.mcu.family.is_supported() {
+            return Err(format!(
+                "Hardware revision {} uses the {} family, which is not yet supported",
+                self.hw.name, self.hw.mcu.family
+            ));
         }
 
         // Validate processor against family
@@ -168,12 +225,46 @@ impl Config {
                     .mcu_variant
                     .is_frequency_valid(self.freq, self.overclock)
                 {
-                    return Err(format!(
-                        "Frequency {}MHz is not valid for variant {}. Valid range: 16-{}MHz",
+                    return Err(self.invalid_freq_error());
+                }
+
+                self.validate_overclock()?;
+
+                // Check the frequency the PLL will actually produce.  The PLL
+                // configuration may not be able to hit the target exactly.
+                if let Some(actual) = self
+                    .mcu_variant
+                    .actual_sysclk_mhz(self.freq, self.overclock)
+                    && actual != self.freq
+                {
+                    if self.exact_freq {
+                        return Err(format!(
+                            "Frequency {}MHz is not exactly achievable for variant {} - closest is {}MHz",
+                            self.freq,
+                            self.mcu_variant.makefile_var(),
+                            actual
+                        ));
+                    }
+                    println!(
+                        "Warning: requested frequency {}MHz, but {} will run at {}MHz",
                         self.freq,
                         self.mcu_variant.makefile_var(),
-                        self.mcu_variant.processor().max_sysclk_mhz()
-                    ));
+                        actual
+                    );
+                }
+
+                // SWD builds may use USB, which needs a 48MHz clock
+                if self.swd
+                    && let Some(usb_khz) = self.mcu_variant.usb_clock_khz(self.freq, self.overclock)
+                    && !self.mcu_variant.usb_clock_valid(self.freq, self.overclock)
+                {
+                    println!(
+                        "Warning: {}MHz gives {} a USB clock of {}.{:03}MHz, outside USB's 48MHz +/-0.25%",
+                        self.freq,
+                        self.mcu_variant.makefile_var(),
+                        usb_khz / 1000,
+                        usb_khz % 1000
+                    );
                 }
             }
         }
@@ -245,10 +336,11 @@ impl Config {
                     }
 
                     // Max 4 ROMs for banked sets
-                    if roms_in_set.len() > 4 {
+                    if roms_in_set.len() > MAX_BANKED_ROMS {
                         return Err(format!(
-                            "Set {}: banked sets can contain maximum 4 ROMs, found {}",
+                            "Set {}: banked sets can contain maximum {} ROMs, found {}",
                             set_id,
+                            MAX_BANKED_ROMS,
                             roms_in_set.len()
                         ));
                     }
@@ -326,11 +418,13 @@ impl Config {
                         }
                     }
 
-                    // Max 3 ROMs for multi-ROM sets
-                    if roms_in_set.len() > 3 {
+                    // One ROM per chip select line the hardware exposes
+                    let max_roms = self.hw.max_roms_per_set();
+                    if roms_in_set.len() > max_roms {
                         return Err(format!(
-                            "Set {}: multi-ROM sets can contain maximum 3 ROMs, found {}",
+                            "Set {} exceeds this hardware's limit of {} ROMs, found {}",
                             set_id,
+                            max_roms,
                             roms_in_set.len()
                         ));
                     }
@@ -338,9 +432,207 @@ impl Config {
             }
         }
 
+        // Validate the hardware has enough select pins to choose between all
+        // of the ROM sets
+        let set_count = self.set_rom_counts().len();
+        let sel_pins_needed = set_count.next_power_of_two().trailing_zeros() as usize;
+        if sel_pins_needed > self.hw.sel_pin_count() {
+            return Err(format!(
+                "{} ROM sets need {} image select pins, but hardware revision {} only has {}",
+                set_count,
+                sel_pins_needed,
+                self.hw.name,
+                self.hw.sel_pin_count()
+            ));
+        }
+
+        // Validate the ROM images will fit in flash, rather than leaving it
+        // to the firmware build to fail to link
+        self.validate_flash_size()?;
+
+        // Validate the largest set can be preloaded to RAM
+        if self.preload_to_ram {
+            self.validate_ram_size()?;
+        }
+
+        Ok(())
+    }
+
+    /// Size in bytes of a ROM set's image in flash, given the number of ROMs
+    /// in the set
+    pub fn rom_set_image_size(&self, rom_count: usize) -> usize {
+        if rom_count == 1 {
+            match self.hw.mcu.family {
+                McuFamily::Stm32F4 | McuFamily::Stm32F7 => 16384,
+                McuFamily::Rp2350 => 65536,
+            }
+        } else {
+            // Multi-ROM/banked sets: combined 64KB image
+            65536
+        }
+    }
+
+    // Number of ROMs in each set - each ROM is its own set if sets aren't
+    // used
+    fn set_rom_counts(&self) -> Vec<usize> {
+        if self.roms.iter().any(|rom| rom.set.is_some()) {
+            let mut counts: BTreeMap<usize, usize> = BTreeMap::new();
+            for rom in &self.roms {
+                *counts.entry(rom.set.unwrap_or_default()).or_default() += 1;
+            }
+            counts.into_values().collect()
+        } else {
+            vec![1; self.roms.len()]
+        }
+    }
+
+    // Checks an overclocked frequency against the processor's tested and
+    // absolute overclocking limits.  Exceeding the tested limit warns, but
+    // exceeding the absolute limit is an error unless explicitly overridden.
+    // Mirrors the `requires` constraints on the CLI arguments
+    fn validate_dependencies(&self) -> Result<(), String> {
+        let dependencies = [
+            (self.mco2, "--mco2", self.mco, "--mco"),
+            (self.boot_logging, "--boot-logging", self.swd, "--swd"),
+            (
+                self.main_loop_logging,
+                "--main-loop-logging",
+                self.boot_logging,
+                "--boot-logging",
+            ),
+            (
+                self.main_loop_one_shot,
+                "--main-loop-one-shot",
+                self.main_loop_logging,
+                "--main-loop-logging",
+            ),
+            (self.debug_logging, "--debug-logging", self.boot_logging, "--boot-logging"),
+        ];
+        for (option, name, prerequisite, prerequisite_name) in dependencies {
+            if option && !prerequisite {
+                return Err(format!(
+                    "{} requires {}, which is not enabled",
+                    name, prerequisite_name
+                ));
+            }
+        }
         Ok(())
     }
 
+    fn validate_overclock(&self) -> Result<(), String> {
+        let processor = self.mcu_variant.processor();
+        if !self.overclock || self.freq <= processor.overclock_tested_mhz() {
+            return Ok(());
+        }
+
+        if self.freq > processor.overclock_max_mhz() && !self.overclock_beyond_max {
+            return Err(format!(
+                "Frequency {}MHz exceeds the maximum overclock for {} ({}MHz, tested {}MHz, datasheet maximum {}MHz) and the firmware is unlikely to boot.  Use --i-understand-overclocking to generate it anyway",
+                self.freq,
+                self.mcu_variant.makefile_var(),
+                processor.overclock_max_mhz(),
+                processor.overclock_tested_mhz(),
+                processor.max_sysclk_mhz()
+            ));
+        }
+
+        println!(
+            "Warning: overclocking {} to {}MHz, beyond the tested {}MHz (datasheet maximum {}MHz) - the firmware may not boot",
+            self.mcu_variant.makefile_var(),
+            self.freq,
+            processor.overclock_tested_mhz(),
+            processor.max_sysclk_mhz()
+        );
+        Ok(())
+    }
+
+    // Checks the ROM set images, plus the firmware itself, fit in the MCU's
+    // flash.  Only valid once the sets have been validated.
+    fn validate_flash_size(&self) -> Result<(), String> {
+        let rom_counts = self.set_rom_counts();
+        let sizes: Vec<usize> = rom_counts
+            .iter()
+            .map(|&count| self.rom_set_image_size(count))
+            .collect();
+        let images_kb = sizes.iter().sum::<usize>() / 1024;
+        let flash_kb = self.mcu_variant.flash_storage_kb();
+        if images_kb + FIRMWARE_CODE_BUDGET_KB <= flash_kb {
+            return Ok(());
+        }
+
+        let breakdown: Vec<String> = rom_counts
+            .iter()
+            .zip(sizes.iter())
+            .enumerate()
+            .map(|(set, (count, size))| {
+                let roms = if *count == 1 { "ROM" } else { "ROMs" };
+                format!("  Set {}: {}KB ({} {})", set, size / 1024, count, roms)
+            })
+            .collect();
+        Err(format!(
+            "ROM images ({}KB) + firmware (~{}KB) exceed {} flash ({}KB):\n{}",
+            images_kb,
+            FIRMWARE_CODE_BUDGET_KB,
+            self.mcu_variant.makefile_var(),
+            flash_kb,
+            breakdown.join("\n")
+        ))
+    }
+
+    // Checks the largest ROM set image, which the firmware copies to RAM
+    // when preloading, fits in the MCU's RAM alongside the firmware's own
+    // RAM usage.  Only one set is active, and so preloaded, at a time.
+    fn validate_ram_size(&self) -> Result<(), String> {
+        let ram_kb = self.mcu_variant.ram_kb();
+        let overhead_kb = self.mcu_variant.ram_overhead_kb();
+        let largest = self
+            .set_rom_counts()
+            .into_iter()
+            .enumerate()
+            .map(|(set, count)| (set, self.rom_set_image_size(count) / 1024))
+            .max_by_key(|(_, size_kb)| *size_kb);
+
+        match largest {
+            Some((set, image_kb)) if image_kb + overhead_kb > ram_kb => Err(format!(
+                "Set {}: preloading to RAM needs {}KB ({}KB image + {}KB logging buffer and \
+                 working RAM), but {} only has {}KB RAM.  Use --disable-preload-to-ram or a \
+                 larger variant",
+                set,
+                image_kb + overhead_kb,
+                image_kb,
+                overhead_kb,
+                self.mcu_variant.makefile_var(),
+                ram_kb
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    // Builds the error for an invalid target frequency, suggesting the
+    // nearest achievable frequency below it, and whether --overclock would
+    // make the target valid.  --overclock alone allows up to the processor's
+    // maximum overclock - see validate_overclock().
+    fn invalid_freq_error(&self) -> String {
+        let processor = self.mcu_variant.processor();
+        let mut err = format!(
+            "{}MHz invalid for {}; nearest is {}MHz",
+            self.freq,
+            self.mcu_variant.makefile_var(),
+            processor.nearest_achievable_frequency(self.freq, self.overclock)
+        );
+        let overclock_max = processor.overclock_max_mhz();
+        if !self.overclock
+            && self.freq <= overclock_max
+            && processor.is_frequency_valid(self.freq, true)
+        {
+            err.push_str(&format!(
+                ", or pass --overclock to allow up to {}MHz",
+                processor.nearest_achievable_frequency(overclock_max, true)
+            ));
+        }
+        err
+    }
+
     pub fn create_rom_sets(&self, rom_images: &[RomImage]) -> Result<Vec<RomSet>, String> {
         let sets: Vec<usize> = self.roms.iter().filter_map(|rom| rom.set).collect();
 
@@ -422,4 +714,460 @@ impl Config {
 
         Ok(rom_sets)
     }
+
+    /// Returns a one line description of the build, such as "F446RE @
+    /// 180MHz, 3 sets / 5 ROMs, hw 24-f, swd+boot_logging".  The enabled
+    /// options are always listed in the same order, and omitted if there are
+    /// none, so the line is stable enough to grep for.
+    pub fn summary(&self, rom_sets: &[RomSet]) -> String {
+        let plural = |count: usize, name: &str| {
+            format!("{} {}{}", count, name, if count == 1 { "" } else { "s" })
+        };
+        let rom_count = rom_sets.iter().map(|set| set.roms.len()).sum();
+        let mut summary = format!(
+            "{} @ {}MHz, {} / {}, hw {}",
+            self.mcu_variant
+                .makefile_var()
+                .trim_start_matches("stm32")
+                .to_uppercase(),
+            self.freq,
+            plural(rom_sets.len(), "set"),
+            plural(rom_count, "ROM"),
+            self.hw.name
+        );
+
+        let options: Vec<&str> = [
+            (self.swd, "swd"),
+            (self.boot_logging, "boot_logging"),
+            (self.main_loop_logging, "main_loop_logging"),
+            (self.main_loop_one_shot, "main_loop_one_shot"),
+            (self.debug_logging, "debug_logging"),
+            (self.mco, "mco"),
+            (self.mco2, "mco2"),
+            (self.status_led, "status_led"),
+            (self.count_rom_access, "count_rom_access"),
+            (self.bootloader, "bootloader"),
+            (self.preload_to_ram, "preload_to_ram"),
+            (self.hse, "hse"),
+            (self.overclock, "overclock"),
+        ]
+        .into_iter()
+        .filter_map(|(enabled, name)| enabled.then_some(name))
+        .collect();
+        if !options.is_empty() {
+            summary.push_str(", ");
+            summary.push_str(&options.join("+"));
+        }
+
+        summary
+    }
+
+    /// Returns the sdrr-gen arguments which reproduce this configuration's
+    /// MCU, hardware and options, unquoted.  The frequency is always
+    /// included, as its default depends on the MCU variant, but other
+    /// options only when not the default.  The ROMs aren't included, as the
+    /// configuration only holds them resolved - see
+    /// [`crate::args::Args::reproduction_command()`].
+    pub fn command_args(&self) -> Vec<String> {
+        let mut args = vec![
+            "--mcu".to_string(),
+            self.mcu_variant.makefile_var().trim_start_matches("stm32").to_string(),
+            "--hw".to_string(),
+            self.hw.name.clone(),
+            "--freq".to_string(),
+            self.freq.to_string(),
+        ];
+
+        let flags = [
+            (self.exact_freq, "--exact-freq"),
+            (self.swd, "--swd"),
+            (self.mco, "--mco"),
+            (self.mco2, "--mco2"),
+            (self.boot_logging, "--boot-logging"),
+            (self.main_loop_logging, "--main-loop-logging"),
+            (self.main_loop_one_shot, "--main-loop-one-shot"),
+            (self.debug_logging, "--debug-logging"),
+            (self.hse, "--hse"),
+            (self.status_led, "--status-led"),
+            (self.overclock, "--overclock"),
+            (self.overclock_beyond_max, "--i-understand-overclocking"),
+            (self.bootloader, "--bootloader"),
+            (!self.preload_to_ram, "--disable-preload-to-ram"),
+            (self.count_rom_access, "--count-rom-access"),
+            (self.verify_transforms, "--verify-transforms"),
+            (self.strict_roms, "--strict-roms"),
+        ];
+        args.extend(
+            flags
+                .into_iter()
+                .filter(|(enabled, _)| *enabled)
+                .map(|(_, flag)| flag.to_string()),
+        );
+
+        let serve_alg = match self.serve_alg {
+            ServeAlg::Default => None,
+            ServeAlg::TwoCsOneAddr => Some("a"),
+            ServeAlg::AddrOnCs => Some("b"),
+        };
+        if let Some(serve_alg) = serve_alg {
+            args.extend(["--serve-alg".to_string(), serve_alg.to_string()]);
+        }
+        if self.output_dir != Path::new("./output") {
+            args.extend(["--output-dir".to_string(), self.output_dir.display().to_string()]);
+        }
+        if self.output_format == OutputFormat::Rust {
+            args.extend(["--output-format".to_string(), "rust".to_string()]);
+        }
+
+        args
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::preprocessor::tests::rom_in_set;
+
+    pub(crate) fn test_config(hw: HwConfig) -> Config {
+        Config {
+            roms: vec![RomConfig {
+                file: PathBuf::from("rom.bin"),
+                original_source: "rom.bin".to_string(),
+                extract: None,
+                licence: None,
+                rom_type: RomType::Rom2364,
+                cs_config: CsConfig::new(CsLogic::ActiveLow, None, None),
+                size_handling: SizeHandling::None,
+                set: None,
+                bank: None,
+            }],
+            mcu_variant: McuVariant::F411RE,
+            output_dir: PathBuf::from("/nonexistent/sdrr-gen-test"),
+            swd: true,
+            count_rom_access: false,
+            mco: false,
+            mco2: false,
+            boot_logging: false,
+            main_loop_logging: false,
+            main_loop_one_shot: false,
+            debug_logging: false,
+            overwrite: true,
+            hse: false,
+            hw,
+            freq: 100,
+            exact_freq: false,
+            status_led: true,
+            overclock: false,
+            overclock_beyond_max: false,
+            bootloader: false,
+            preload_to_ram: false,
+            auto_yes: true,
+            serve_alg: ServeAlg::Default,
+            check_only: false,
+            dry_run: false,
+            layout: None,
+            emit_images: None,
+            rom_manifest: None,
+            verify_transforms: false,
+            strict_roms: false,
+            output_format: OutputFormat::C,
+        }
+    }
+
+    #[test]
+    fn test_status_led_without_status_pin() {
+        // 24-d has no status LED
+        let hw = HwConfig::new(include_str!("../../../sdrr-hw-config/24-d.json"), "24-d").unwrap();
+        assert_eq!(hw.pin_status(), 255);
+
+        let mut config = test_config(hw.clone());
+        let err = config.validate().unwrap_err();
+        assert!(err.contains("24-d"), "{}", err);
+        assert!(err.contains("status LED"), "{}", err);
+
+        let mut config = test_config(hw);
+        config.status_led = false;
+        assert!(config.validate().is_ok());
+
+        // 24-f does
+        let hw = HwConfig::new(include_str!("../../../sdrr-hw-config/24-f.json"), "24-f").unwrap();
+        let mut config = test_config(hw);
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_sel_pins() {
+        // 24-d has 3 select pins, so can choose between 8 sets
+        let hw = HwConfig::new(include_str!("../../../sdrr-hw-config/24-d.json"), "24-d").unwrap();
+        assert_eq!(hw.sel_pin_count(), 3);
+        let mut config = test_config(hw);
+        config.status_led = false;
+        let rom = config.roms[0].clone();
+        config.roms = vec![rom.clone(); 8];
+        assert!(config.validate().is_ok());
+
+        config.roms.push(rom);
+        let err = config.validate().unwrap_err();
+        assert_eq!(
+            err,
+            "9 ROM sets need 4 image select pins, but hardware revision 24-d only has 3"
+        );
+    }
+
+    #[test]
+    fn test_option_dependencies() {
+        let hw = HwConfig::new(include_str!("../../../sdrr-hw-config/24-f.json"), "24-f").unwrap();
+
+        let mut config = test_config(hw.clone());
+        config.mco2 = true;
+        assert_eq!(config.validate().unwrap_err(), "--mco2 requires --mco, which is not enabled");
+        config.mco = true;
+        assert!(config.validate().is_ok());
+
+        let mut config = test_config(hw.clone());
+        config.swd = false;
+        config.boot_logging = true;
+        assert_eq!(
+            config.validate().unwrap_err(),
+            "--boot-logging requires --swd, which is not enabled"
+        );
+
+        let mut config = test_config(hw.clone());
+        config.debug_logging = true;
+        assert_eq!(
+            config.validate().unwrap_err(),
+            "--debug-logging requires --boot-logging, which is not enabled"
+        );
+
+        let mut config = test_config(hw);
+        config.main_loop_one_shot = true;
+        config.boot_logging = true;
+        assert_eq!(
+            config.validate().unwrap_err(),
+            "--main-loop-one-shot requires --main-loop-logging, which is not enabled"
+        );
+        config.main_loop_logging = true;
+        config.debug_logging = true;
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_summary() {
+        let hw = HwConfig::new(include_str!("../../../sdrr-hw-config/24-f.json"), "24-f").unwrap();
+        let mut config = test_config(hw);
+        config.mcu_variant = McuVariant::F446RE;
+        config.freq = 180;
+        config.boot_logging = true;
+        config.status_led = false;
+
+        let rom = |index| rom_in_set(RomType::Rom2364, CsLogic::ActiveLow, 0, index);
+        let rom_sets = vec![
+            RomSet {
+                id: 0,
+                roms: vec![rom(0), rom(1), rom(2)],
+                is_banked: false,
+            },
+            RomSet {
+                id: 1,
+                roms: vec![rom(3)],
+                is_banked: false,
+            },
+            RomSet {
+                id: 2,
+                roms: vec![rom(4)],
+                is_banked: false,
+            },
+        ];
+        assert_eq!(
+            config.summary(&rom_sets),
+            "F446RE @ 180MHz, 3 sets / 5 ROMs, hw 24-f, swd+boot_logging"
+        );
+
+        config.mcu_variant = McuVariant::Rp2350;
+        config.freq = 150;
+        config.swd = false;
+        config.boot_logging = false;
+        assert_eq!(config.summary(&rom_sets[1..2]), "RP2350 @ 150MHz, 1 set / 1 ROM, hw 24-f");
+    }
+
+    #[test]
+    fn test_unsupported_family() {
+        let json = include_str!("../../../sdrr-hw-config/24-f.json")
+            .replace("\"family\": \"f4\"", "\"family\": \"f7\"");
+        let hw = HwConfig::new(&json, "24-f").unwrap();
+        let mut config = test_config(hw);
+        let err = config.validate().unwrap_err();
+        assert!(err.contains("F7 family, which is not yet supported"), "{}", err);
+    }
+
+    #[test]
+    fn test_invalid_freq_error() {
+        let hw = HwConfig::new(include_str!("../../../sdrr-hw-config/24-f.json"), "24-f").unwrap();
+        let mut config = test_config(hw);
+        config.mcu_variant = McuVariant::F446RE;
+        config.freq = 181;
+        assert_eq!(
+            config.validate().unwrap_err(),
+            "181MHz invalid for stm32f446re; nearest is 180MHz, or pass --overclock to allow up to 400MHz"
+        );
+
+        // Beyond the overclocking limit, only the nearest is suggested
+        config.freq = 1000;
+        let err = config.validate().unwrap_err();
+        assert_eq!(err, "1000MHz invalid for stm32f446re; nearest is 180MHz");
+        config.overclock = true;
+        let max = McuVariant::F446RE.processor().nearest_achievable_frequency(1000, true);
+        let err = config.validate().unwrap_err();
+        assert_eq!(err, format!("1000MHz invalid for stm32f446re; nearest is {}MHz", max));
+    }
+
+    #[test]
+    fn test_overclock() {
+        let hw = HwConfig::new(include_str!("../../../sdrr-hw-config/24-f.json"), "24-f").unwrap();
+        let mut config = test_config(hw);
+        config.overclock = true;
+        config.freq = 150;
+        assert!(config.validate().is_ok());
+
+        // Beyond the tested ceiling only warns
+        config.freq = 160;
+        assert!(config.validate().is_ok());
+
+        config.freq = 240;
+        let err = config.validate().unwrap_err();
+        assert!(
+            err.starts_with("Frequency 240MHz exceeds the maximum overclock for stm32f411re (200MHz"),
+            "{}",
+            err
+        );
+        assert!(err.contains("datasheet maximum 100MHz"), "{}", err);
+
+        config.overclock_beyond_max = true;
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_banked_set_requires_supported_variant() {
+        let hw = HwConfig::new(include_str!("../../../sdrr-hw-config/24-f.json"), "24-f").unwrap();
+        let mut config = test_config(hw);
+        let rom = config.roms[0].clone();
+        config.roms = (0..2)
+            .map(|bank| RomConfig {
+                set: Some(0),
+                bank: Some(bank),
+                ..rom.clone()
+            })
+            .collect();
+        assert!(config.validate().is_ok());
+
+        config.mcu_variant = McuVariant::F401RB;
+        config.freq = 84;
+        let err = config.validate().unwrap_err();
+        assert!(err.contains("banked ROMs are not supported"), "{}", err);
+    }
+
+    #[test]
+    fn test_multi_rom_set_limit() {
+        // 24-f exposes CS1, X1 and X2, so a multi-ROM set can hold 3 ROMs
+        let hw = HwConfig::new(include_str!("../../../sdrr-hw-config/24-f.json"), "24-f").unwrap();
+        assert_eq!(hw.max_roms_per_set(), 3);
+        let mut config = test_config(hw);
+        let rom = RomConfig {
+            set: Some(0),
+            ..config.roms[0].clone()
+        };
+        config.roms = vec![rom.clone(); 3];
+        assert!(config.validate().is_ok());
+
+        config.roms.push(rom);
+        let err = config.validate().unwrap_err();
+        assert_eq!(err, "Set 0 exceeds this hardware's limit of 3 ROMs, found 4");
+    }
+
+    #[test]
+    fn test_flash_size() {
+        // An F401RB has room for 6 single ROM sets
+        let hw = HwConfig::new(include_str!("../../../sdrr-hw-config/24-f.json"), "24-f").unwrap();
+        let mut config = test_config(hw);
+        config.mcu_variant = McuVariant::F401RB;
+        config.freq = 84;
+        let rom = config.roms[0].clone();
+        config.roms = vec![rom.clone(); 6];
+        assert!(config.validate().is_ok());
+
+        config.roms.push(rom.clone());
+        let err = config.validate().unwrap_err();
+        assert!(
+            err.starts_with("ROM images (112KB) + firmware (~32KB) exceed stm32f401rb flash (128KB)"),
+            "{}",
+            err
+        );
+        assert!(err.contains("\n  Set 6: 16KB (1 ROM)"), "{}", err);
+
+        config.mcu_variant = McuVariant::F411RE;
+        config.freq = 100;
+        assert!(config.validate().is_ok());
+
+        // Multi-ROM sets take 64KB, so an F411RC only has room for 3
+        config.mcu_variant = McuVariant::F411RC;
+        config.roms = (0..4)
+            .flat_map(|set| {
+                let mut second = RomConfig {
+                    set: Some(set),
+                    ..rom.clone()
+                };
+                second.cs_config.cs1 = CsLogic::ActiveHigh;
+                [
+                    RomConfig {
+                        set: Some(set),
+                        ..rom.clone()
+                    },
+                    second,
+                ]
+            })
+            .collect();
+        let err = config.validate().unwrap_err();
+        assert!(err.contains("exceed stm32f411rc flash (256KB):\n  Set 0: 64KB (2 ROMs)"), "{}", err);
+    }
+
+    #[test]
+    fn test_ram_size() {
+        let hw = HwConfig::new(include_str!("../../../sdrr-hw-config/24-f.json"), "24-f").unwrap();
+        let mut config = test_config(hw);
+        config.mcu_variant = McuVariant::F401RB;
+        config.freq = 84;
+        config.preload_to_ram = true;
+        let rom = config.roms[0].clone();
+        assert!(config.validate().is_ok());
+
+        // A 64KB multi-ROM set can't be preloaded in the F401RB's 64KB RAM.
+        // validate() rejects multi-ROM sets on the F401RB before getting this
+        // far, so check the RAM size directly.
+        let mut second = RomConfig {
+            set: Some(1),
+            ..rom.clone()
+        };
+        second.cs_config.cs1 = CsLogic::ActiveHigh;
+        config.roms = vec![
+            RomConfig {
+                set: Some(0),
+                ..rom.clone()
+            },
+            RomConfig {
+                set: Some(1),
+                ..rom
+            },
+            second,
+        ];
+        let err = config.validate_ram_size().unwrap_err();
+        assert!(
+            err.starts_with("Set 1: preloading to RAM needs 72KB (64KB image + 8KB"),
+            "{}",
+            err
+        );
+        assert!(err.contains("stm32f401rb only has 64KB RAM"), "{}", err);
+
+        config.mcu_variant = McuVariant::F411RE;
+        assert!(config.validate_ram_size().is_ok());
+    }
 }
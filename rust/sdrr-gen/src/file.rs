@@ -9,22 +9,25 @@
 
 use anyhow::{Context, Result};
 use bytes::Bytes;
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::fmt;
 use std::fs::{File, OpenOptions, copy, create_dir_all, remove_dir_all};
 use std::io::{Cursor, Read, Write};
 use std::path::{Path, PathBuf};
 use std::sync::LazyLock;
+use std::time::Duration;
 use strum::EnumIter;
 use urlencoding::decode;
 use zip::ZipArchive;
 
-use crate::config::Config;
+use crate::config::{Config, OutputFormat, RomConfig};
 use crate::preprocessor::RomImage;
 
 // Files generated by generator.rs
 const ROMS_C_FILE: &str = "roms.c";
 const ROMS_H_FILE: &str = "roms.h";
+const ROMS_RS_FILE: &str = "roms.rs";
 const SDRR_CONFIG_H_FILE: &str = "sdrr_config.h";
 const SDRR_CONFIG_C_FILE: &str = "sdrr_config.c";
 const GEN_MK_FILE: &str = "generated.mk";
@@ -41,6 +44,7 @@ const IMAGE_LIST_FILE: &str = "image_list.txt";
 pub enum OutType {
     RomsC,
     RomsH,
+    RomsRs,
     SdrrConfigH,
     SdrrConfigC,
     GenMk,
@@ -52,6 +56,7 @@ static OUT_FILES: LazyLock<HashMap<OutType, String>> = LazyLock::new(|| {
     let mut map = HashMap::new();
     map.insert(OutType::RomsC, ROMS_C_FILE.to_string());
     map.insert(OutType::RomsH, ROMS_H_FILE.to_string());
+    map.insert(OutType::RomsRs, ROMS_RS_FILE.to_string());
     map.insert(OutType::SdrrConfigH, SDRR_CONFIG_H_FILE.to_string());
     map.insert(OutType::SdrrConfigC, SDRR_CONFIG_C_FILE.to_string());
     map.insert(OutType::GenMk, GEN_MK_FILE.to_string());
@@ -60,6 +65,18 @@ static OUT_FILES: LazyLock<HashMap<OutType, String>> = LazyLock::new(|| {
     map
 });
 
+impl OutType {
+    /// Returns whether this file is generated for the given output format.
+    /// Only the ROM images and tables differ between formats.
+    pub fn is_for_format(&self, format: OutputFormat) -> bool {
+        match self {
+            OutType::RomsC | OutType::RomsH => format == OutputFormat::C,
+            OutType::RomsRs => format == OutputFormat::Rust,
+            _ => true,
+        }
+    }
+}
+
 // Return the output filename for a given output type
 pub fn out_filename(out_type: OutType) -> PathBuf {
     PathBuf::from(
@@ -76,7 +93,7 @@ fn out_filenames_iter() -> impl Iterator<Item = String> {
 }
 
 // Return the directory where generated files are stored
-fn image_dir(output_dir: &Path) -> PathBuf {
+pub fn image_dir(output_dir: &Path) -> PathBuf {
     output_dir.join(IMAGE_DIR)
 }
 
@@ -153,6 +170,7 @@ pub fn source_image_file(
     rom_num: usize,
     source: &FileSource,
     out_dir: &Path,
+    downloader: &Downloader,
 ) -> Result<PathBuf, String> {
     // Create filename for this ROM
     let image_dir = image_dir(out_dir);
@@ -162,9 +180,9 @@ pub fn source_image_file(
     // Get the file and put in the image directory
     match &source {
         FileSource::Local(path) => copy_local_file(path, &out_file)?,
-        FileSource::Url(url) => download_url(&out_file, url)?,
+        FileSource::Url(url) => download_url(&out_file, url, downloader)?,
         FileSource::UrlZip(url, extract_file) => {
-            download_and_extract_zip(&out_file, url, extract_file)?
+            download_and_extract_zip(&out_file, url, extract_file, downloader)?
         }
     };
 
@@ -174,14 +192,196 @@ pub fn source_image_file(
     Ok(out_file)
 }
 
-// Load the ROM files based on the configuration
+/// Checks a ROM image source without fetching it, for `--check-only`.  Local
+/// files must exist, and URLs must be well-formed.
+///
+/// Returns the local file to check the size of, which for URLs is where the
+/// file would be downloaded to.
+pub fn check_image_source(
+    rom_num: usize,
+    source: &FileSource,
+    out_dir: &Path,
+) -> Result<PathBuf, String> {
+    match source {
+        FileSource::Local(path) => {
+            if !path.is_file() {
+                return Err(format!("ROM file {} does not exist", path.display()));
+            }
+            Ok(path.clone())
+        }
+        FileSource::Url(url) | FileSource::UrlZip(url, _) => {
+            let parsed =
+                reqwest::Url::parse(url).map_err(|e| format!("Invalid URL {url}: {e}"))?;
+            if !matches!(parsed.scheme(), "http" | "https") || parsed.host_str().is_none() {
+                return Err(format!("Invalid URL {url}: must be an http(s) URL with a host"));
+            }
+            Ok(image_dir(out_dir).join(format!("{rom_num}.rom")))
+        }
+    }
+}
+
+/// Checks the sizes of local ROM files, without reading their contents, for
+/// `--check-only`.  ROMs sourced from URLs are not downloaded, so can't be
+/// checked.
+pub fn check_rom_files(config: &Config) -> Result<()> {
+    for (ii, rom_config) in config.roms.iter().enumerate() {
+        if is_downloaded(rom_config) {
+            println!(
+                "- ROM #{ii}: {} - not downloaded, size not checked",
+                rom_config.original_source
+            );
+            continue;
+        }
+
+        let len = std::fs::metadata(&rom_config.file)
+            .with_context(|| format!("Failed to read ROM file: {}", rom_config.file.display()))?
+            .len();
+        RomImage::check_size(len as usize, &rom_config.rom_type, &rom_config.size_handling)
+            .with_context(|| format!("Invalid ROM file: {}", rom_config.original_source))?;
+        println!(
+            "- ROM #{ii}: {} - {} bytes, OK for {}",
+            rom_config.original_source,
+            len,
+            rom_config.rom_type.name()
+        );
+    }
+    Ok(())
+}
+
+/// Returns the CRC32 of a file
+pub fn file_crc32(path: &Path) -> Result<u32, String> {
+    let data =
+        std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    Ok(crc32fast::hash(&data))
+}
+
+/// Returns the SHA-256 of a file, as lowercase hex
+pub fn file_sha256(path: &Path) -> Result<String, String> {
+    let data =
+        std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let digest = Sha256::digest(&data);
+    Ok(digest.iter().map(|byte| format!("{byte:02x}")).collect())
+}
+
+/// Checks a ROM image file has the SHA-256 given by its sha256= option
+pub fn verify_sha256(path: &Path, expected: &str) -> Result<(), String> {
+    let actual = file_sha256(path)?;
+    if actual != expected {
+        return Err(format!("SHA-256 mismatch: expected {expected}, got {actual}"));
+    }
+    Ok(())
+}
+
+/// Loads a ROM manifest for --validate-roms, listing the expected CRC32 of
+/// each ROM source, one per line, in the form:
+///
+/// ```text
+/// source,expected_crc32
+/// roms/kernal.901227-03.bin,DBE3E7C7
+/// ```
+///
+/// The source is as given with `file=`, or for a ROM extracted from a zip
+/// file, may be the `extract=` name.  The CRC32 is in hex, with an optional
+/// 0x prefix.  A header line, blank lines and lines starting with '#' are
+/// ignored.
+pub fn load_rom_manifest(path: &Path) -> Result<Vec<(String, u32)>, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read ROM manifest {}: {}", path.display(), e))?;
+    parse_rom_manifest(&contents).map_err(|e| format!("ROM manifest {}: {}", path.display(), e))
+}
+
+fn parse_rom_manifest(contents: &str) -> Result<Vec<(String, u32)>, String> {
+    let mut manifest = Vec::new();
+
+    for (ii, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.to_lowercase().starts_with("source,") {
+            continue;
+        }
+
+        // Split on the last comma, so sources can contain commas
+        let (source, crc) = line.rsplit_once(',').ok_or_else(|| {
+            format!("line {}: expected source,expected_crc32, got: {}", ii + 1, line)
+        })?;
+        let crc = crc.trim();
+        let hex = crc
+            .strip_prefix("0x")
+            .or_else(|| crc.strip_prefix("0X"))
+            .unwrap_or(crc);
+        let crc32 = u32::from_str_radix(hex, 16)
+            .map_err(|_| format!("line {}: invalid CRC32: {}", ii + 1, crc))?;
+
+        manifest.push((source.trim().to_string(), crc32));
+    }
+
+    Ok(manifest)
+}
+
+// Checks a loaded ROM file's CRC32 against the ROM manifest
+fn validate_rom_crc32(ii: usize, rom_config: &RomConfig, manifest: &[(String, u32)]) -> Result<()> {
+    let expected = manifest
+        .iter()
+        .find(|(source, _)| {
+            *source == rom_config.original_source || Some(source) == rom_config.extract.as_ref()
+        })
+        .map(|(_, crc32)| *crc32)
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "ROM #{ii}: {} is not in the ROM manifest",
+                rom_config.original_source
+            )
+        })?;
+
+    let actual = file_crc32(&rom_config.file).map_err(|e| anyhow::anyhow!(e))?;
+    if actual != expected {
+        anyhow::bail!(
+            "ROM #{ii}: {} has CRC32 0x{:08X}, but the ROM manifest expects 0x{:08X}",
+            rom_config.original_source,
+            actual,
+            expected
+        );
+    }
+    println!(
+        "- ROM #{ii}: {} - CRC32 0x{:08X} matches ROM manifest",
+        rom_config.original_source, actual
+    );
+
+    Ok(())
+}
+
+// Whether a ROM is downloaded, rather than being a local file
+fn is_downloaded(rom_config: &RomConfig) -> bool {
+    rom_config.extract.is_some()
+        || rom_config.original_source.starts_with("http://")
+        || rom_config.original_source.starts_with("https://")
+}
+
+// Load the ROM files based on the configuration.  For a dry run, ROMs which
+// would be downloaded are replaced with blank images of the right size.
 pub fn load_rom_files(config: &Config) -> Result<Vec<RomImage>> {
     let mut rom_images = Vec::new();
-    for rom_config in config.roms.iter() {
+    for (ii, rom_config) in config.roms.iter().enumerate() {
+        if config.dry_run && is_downloaded(rom_config) {
+            println!(
+                "- ROM #{ii}: {} - not downloaded, using blank image",
+                rom_config.original_source
+            );
+            rom_images.push(RomImage {
+                data: vec![0xFF; rom_config.rom_type.size_bytes()],
+            });
+            continue;
+        }
+
+        if let Some(manifest) = &config.rom_manifest {
+            validate_rom_crc32(ii, rom_config, manifest)?;
+        }
+
         let rom_image = RomImage::load_from_file(
             &rom_config.file,
+            &rom_config.original_source,
             &rom_config.rom_type,
             &rom_config.size_handling,
+            config.strict_roms,
         )
         .with_context(|| {
             format!(
@@ -212,19 +412,100 @@ fn copy_local_file(src: &Path, dest: &Path) -> Result<(), String> {
     }
 }
 
-fn download_file(url: &str) -> Result<Bytes, String> {
-    let response =
-        reqwest::blocking::get(url).map_err(|e| format!("Failed to download {url}: {e}"))?;
+/// Downloads ROM images, sharing one HTTP client, so connections are reused,
+/// and timing out each download so a hung server can't stall the build.
+/// Downloads which fail with connection errors or server (5xx) errors are
+/// retried, with exponential backoff.
+pub struct Downloader {
+    client: reqwest::blocking::Client,
+    timeout: Duration,
+    retries: u32,
+    backoff: Duration,
+}
+
+// Delay before the first retry, doubling for each subsequent one
+const DOWNLOAD_BACKOFF: Duration = Duration::from_secs(1);
 
-    response
-        .bytes()
-        .map_err(|e| format!("Failed to read download {url}: {e}"))
+// A failed download attempt, and whether it is worth retrying
+struct DownloadFailure {
+    reason: String,
+    retryable: bool,
 }
 
-fn download_url(dest_file: &Path, url: &str) -> Result<(), String> {
+impl Downloader {
+    pub fn new(timeout: Duration, retries: u32) -> Result<Self, String> {
+        let client = reqwest::blocking::Client::builder()
+            .timeout(timeout)
+            .build()
+            .map_err(|e| format!("Failed to create HTTP client: {e}"))?;
+        Ok(Self {
+            client,
+            timeout,
+            retries,
+            backoff: DOWNLOAD_BACKOFF,
+        })
+    }
+
+    fn download_file(&self, url: &str) -> Result<Bytes, String> {
+        let mut delay = self.backoff;
+        let mut attempt = 0;
+        loop {
+            let failure = match self.try_download_file(url) {
+                Ok(bytes) => return Ok(bytes),
+                Err(failure) => failure,
+            };
+            if !failure.retryable || attempt >= self.retries {
+                return Err(failure.reason);
+            }
+
+            attempt += 1;
+            eprintln!(
+                "{} - retrying in {}ms (retry {attempt} of {})",
+                failure.reason,
+                delay.as_millis(),
+                self.retries
+            );
+            std::thread::sleep(delay);
+            delay = delay.saturating_mul(2);
+        }
+    }
+
+    fn try_download_file(&self, url: &str) -> Result<Bytes, DownloadFailure> {
+        let response = self.client.get(url).send().map_err(|e| self.download_error(url, e))?;
+
+        // Other error statuses, such as 404, aren't transient, so are returned
+        // as before, for the caller to diagnose
+        let status = response.status();
+        if status.is_server_error() {
+            return Err(DownloadFailure {
+                reason: format!("Failed to download {url}: server returned {status}"),
+                retryable: true,
+            });
+        }
+
+        response.bytes().map_err(|e| self.download_error(url, e))
+    }
+
+    fn download_error(&self, url: &str, e: reqwest::Error) -> DownloadFailure {
+        let reason = if e.is_timeout() {
+            format!(
+                "Timed out downloading {url} after {}s (see --download-timeout)",
+                self.timeout.as_secs()
+            )
+        } else {
+            format!("Failed to download {url}: {e}")
+        };
+        DownloadFailure {
+            reason,
+            retryable: e.is_connect() || e.is_timeout() || e.is_body(),
+        }
+    }
+}
+
+fn download_url(dest_file: &Path, url: &str, downloader: &Downloader) -> Result<(), String> {
     println!("Downloading {url} to {}", dest_file.display());
 
-    let bytes = download_file(url)?;
+    let bytes = downloader.download_file(url)?;
 
     let mut file =
         File::create(dest_file).map_err(|e| format!("Failed to create download file: {e}"))?;
@@ -234,7 +515,12 @@ fn download_url(dest_file: &Path, url: &str) -> Result<(), String> {
     Ok(())
 }
 
-fn download_and_extract_zip(dest_file: &Path, url: &str, extract_file: &str) -> Result<(), String> {
+fn download_and_extract_zip(
+    dest_file: &Path,
+    url: &str,
+    extract_file: &str,
+    downloader: &Downloader,
+) -> Result<(), String> {
     // URL decode the extract filename to handle spaces and special characters
     let decoded_extract_file = decode(extract_file)
         .map_err(|e| format!("Failed to URL decode extract filename '{extract_file}': {e}"))?;
@@ -244,7 +530,7 @@ fn download_and_extract_zip(dest_file: &Path, url: &str, extract_file: &str) ->
         dest_file.display()
     );
 
-    let bytes = download_file(url)?;
+    let bytes = downloader.download_file(url)?;
 
     let cursor = Cursor::new(bytes);
     let mut archive =
@@ -292,6 +578,60 @@ fn download_and_extract_zip(dest_file: &Path, url: &str, extract_file: &str) ->
     Ok(())
 }
 
+/// Returns whether a local file path is a glob pattern
+pub fn is_glob(path: &str) -> bool {
+    path.contains(['*', '?'])
+}
+
+/// Expands a glob pattern into the matching files, in sorted order.  `*`
+/// matches any number of characters and `?` a single character, and are only
+/// supported in the file name, not the directory.
+pub fn expand_glob(pattern: &str) -> Result<Vec<PathBuf>, String> {
+    let path = Path::new(pattern);
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| format!("Invalid file pattern: {pattern}"))?;
+    if is_glob(&dir.to_string_lossy()) {
+        return Err(format!(
+            "Wildcards are only supported in the file name, not the directory: {pattern}"
+        ));
+    }
+
+    let read_dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+    let entries = std::fs::read_dir(read_dir)
+        .map_err(|e| format!("Failed to read directory {}: {e}", read_dir.display()))?;
+
+    let name: Vec<char> = name.chars().collect();
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_file())
+        .filter_map(|entry| entry.file_name().to_str().map(str::to_string))
+        .filter(|file_name| glob_match(&name, &file_name.chars().collect::<Vec<_>>()))
+        .map(|file_name| dir.join(file_name))
+        .collect();
+    files.sort();
+
+    if files.is_empty() {
+        return Err(format!("No files match {pattern}"));
+    }
+    Ok(files)
+}
+
+// Matches a file name against a pattern containing `*` and `?` wildcards
+fn glob_match(pattern: &[char], name: &[char]) -> bool {
+    match (pattern.first(), name.first()) {
+        (None, None) => true,
+        (Some('*'), _) => {
+            glob_match(&pattern[1..], name) || (!name.is_empty() && glob_match(pattern, &name[1..]))
+        }
+        (Some('?'), Some(_)) => glob_match(&pattern[1..], &name[1..]),
+        (Some(p), Some(n)) if p == n => glob_match(&pattern[1..], &name[1..]),
+        _ => false,
+    }
+}
+
 pub enum FileSource {
     Local(PathBuf),
     Url(String),
@@ -307,3 +647,195 @@ impl fmt::Display for FileSource {
         }
     }
 }
+
+impl FileSource {
+    /// Returns a filename for a downloaded image, for `--save-downloads`, or
+    /// None for local files.  The name is the file's own name, prefixed with
+    /// the CRC32 of the source, so images from different sources don't
+    /// collide, and the same source always gets the same name.
+    pub fn download_name(&self) -> Option<String> {
+        let path = match self {
+            FileSource::Local(_) => return None,
+            FileSource::Url(url) => url.split(['?', '#']).next().unwrap_or(url).to_string(),
+            FileSource::UrlZip(_, extract) => {
+                decode(extract).map_or_else(|_| extract.clone(), |path| path.into_owned())
+            }
+        };
+        let name: String = path
+            .rsplit('/')
+            .next()
+            .unwrap_or_default()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || "._-".contains(c) { c } else { '_' })
+            .collect();
+        let name = if name.is_empty() { "download" } else { &name };
+        Some(format!("{:08x}-{name}", crc32fast::hash(self.to_string().as_bytes())))
+    }
+}
+
+/// Copies a downloaded, and if necessary extracted, ROM image into `dir`, for
+/// `--save-downloads`.  Local files are not copied.
+pub fn save_download(file: &Path, source: &FileSource, dir: &Path) -> Result<(), String> {
+    let Some(name) = source.download_name() else {
+        return Ok(());
+    };
+    create_dir_all(dir).map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+    let dest = dir.join(name);
+    copy(file, &dest).map_err(|e| format!("Failed to save download to {}: {e}", dest.display()))?;
+    println!("Saved download to {}", dest.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rom_manifest() {
+        let contents = "source,expected_crc32\n\n# C64\nkernal.bin, 0xDBE3E7C7\nhttps://example.com/a,b.bin,f833d117\n";
+        let manifest = parse_rom_manifest(contents).unwrap();
+        assert_eq!(
+            manifest,
+            vec![
+                ("kernal.bin".to_string(), 0xDBE3E7C7),
+                ("https://example.com/a,b.bin".to_string(), 0xF833D117),
+            ]
+        );
+
+        assert!(parse_rom_manifest("kernal.bin").is_err());
+        assert!(parse_rom_manifest("kernal.bin,xyz").is_err());
+    }
+
+    fn matches(pattern: &str, name: &str) -> bool {
+        glob_match(
+            &pattern.chars().collect::<Vec<_>>(),
+            &name.chars().collect::<Vec<_>>(),
+        )
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(matches("*.bin", "bank0.bin"));
+        assert!(matches("bank?.bin", "bank0.bin"));
+        assert!(matches("*", "bank0.bin"));
+        assert!(matches("b*0*", "bank0.bin"));
+        assert!(!matches("*.bin", "bank0.rom"));
+        assert!(!matches("bank?.bin", "bank10.bin"));
+        assert!(!matches("bank", "bank0.bin"));
+    }
+
+    #[test]
+    fn test_expand_glob() {
+        let dir = std::env::temp_dir().join(format!("sdrr-gen-glob-{}", std::process::id()));
+        create_dir_all(&dir).unwrap();
+        for name in ["bank2.bin", "bank0.bin", "bank1.bin", "other.rom"] {
+            File::create(dir.join(name)).unwrap();
+        }
+
+        let pattern = format!("{}/bank*.bin", dir.display());
+        let files = expand_glob(&pattern).unwrap();
+        let names: Vec<_> = files.iter().map(|file| file.file_name().unwrap()).collect();
+        assert_eq!(names, ["bank0.bin", "bank1.bin", "bank2.bin"]);
+        assert_eq!(files[0], dir.join("bank0.bin"));
+
+        let err = expand_glob(&format!("{}/*.txt", dir.display())).unwrap_err();
+        assert!(err.starts_with("No files match"), "{}", err);
+        let err = expand_glob(&format!("{}/*/bank0.bin", dir.display())).unwrap_err();
+        assert!(err.contains("only supported in the file name"), "{}", err);
+
+        remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_download_timeout() {
+        // A server which accepts connections but never responds
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/rom.bin", listener.local_addr().unwrap());
+
+        let downloader = Downloader::new(Duration::from_millis(200), 0).unwrap();
+        let err = downloader.download_file(&url).unwrap_err();
+        assert!(err.starts_with("Timed out downloading"), "{}", err);
+        drop(listener);
+    }
+
+    // Serves the given HTTP statuses, one per connection, with a 4 byte body,
+    // returning the URL and a handle which yields the number of requests
+    fn serve_statuses(statuses: &[u16]) -> (String, std::thread::JoinHandle<usize>) {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/rom.bin", listener.local_addr().unwrap());
+        let statuses = statuses.to_vec();
+        let handle = std::thread::spawn(move || {
+            for status in &statuses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut buf = [0u8; 1024];
+                let _ = stream.read(&mut buf).unwrap();
+                write!(
+                    stream,
+                    "HTTP/1.1 {status} X\r\nContent-Length: 4\r\nConnection: close\r\n\r\nROM!"
+                )
+                .unwrap();
+            }
+            statuses.len()
+        });
+        (url, handle)
+    }
+
+    #[test]
+    fn test_download_retries() {
+        let downloader = |retries| Downloader {
+            backoff: Duration::from_millis(1),
+            ..Downloader::new(Duration::from_secs(5), retries).unwrap()
+        };
+
+        // Server errors are retried until the download succeeds
+        let (url, server) = serve_statuses(&[503, 500, 200]);
+        assert_eq!(downloader(2).download_file(&url).unwrap().as_ref(), b"ROM!");
+        assert_eq!(server.join().unwrap(), 3);
+
+        // But not beyond the retry limit
+        let (url, server) = serve_statuses(&[503, 503]);
+        let err = downloader(1).download_file(&url).unwrap_err();
+        assert!(err.contains("server returned 503"), "{}", err);
+        server.join().unwrap();
+
+        // A 404 isn't retried, and is left to the caller to diagnose
+        let (url, server) = serve_statuses(&[404]);
+        assert_eq!(downloader(3).download_file(&url).unwrap().as_ref(), b"ROM!");
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_verify_sha256() {
+        let dir = std::env::temp_dir().join(format!("sdrr-gen-sha256-{}", std::process::id()));
+        create_dir_all(&dir).unwrap();
+        let path = dir.join("rom.bin");
+        std::fs::write(&path, b"abc").unwrap();
+
+        let expected = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+        assert_eq!(file_sha256(&path).unwrap(), expected);
+        assert!(verify_sha256(&path, expected).is_ok());
+
+        std::fs::write(&path, b"abd").unwrap();
+        let err = verify_sha256(&path, expected).unwrap_err();
+        let prefix = format!("SHA-256 mismatch: expected {expected}, got ");
+        assert!(err.starts_with(&prefix), "{}", err);
+
+        remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_download_name() {
+        assert_eq!(FileSource::Local(PathBuf::from("kernal.bin")).download_name(), None);
+
+        let url = FileSource::Url("https://example.com/roms/kernal.bin?raw=1".to_string());
+        let name = url.download_name().unwrap();
+        assert!(name.ends_with("-kernal.bin"), "{}", name);
+        assert_eq!(url.download_name().unwrap(), name);
+
+        // The same file extracted from different zips gets different names
+        let zip = |url: &str| FileSource::UrlZip(url.to_string(), "c64/char%20rom.bin".to_string());
+        let name = zip("https://a.com/roms.zip").download_name().unwrap();
+        assert!(name.ends_with("-char_rom.bin"), "{}", name);
+        assert_ne!(zip("https://b.com/roms.zip").download_name().unwrap(), name);
+    }
+}
@@ -7,33 +7,37 @@
 use anyhow::{Context, Result};
 use std::fs;
 use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use strum::IntoEnumIterator;
 
-use sdrr_common::{CsLogic, McuFamily, RomType};
+use sdrr_common::images::{
+    IMAGES_HEADER_SIZE, IMAGES_INDEX_ENTRY_SIZE, ImagesIndexEntry, images_header,
+};
+use sdrr_common::{CsLogic, McuFamily, RomType, ServeAlg};
 
-use crate::config::Config;
-use crate::file::{OutType, out_filename};
+use crate::config::{Config, FIRMWARE_CODE_BUDGET_KB, RomConfig};
+use crate::file::{OutType, image_dir, out_filename};
 use crate::preprocessor::RomSet;
 
 // Generate all output files
 pub fn generate_files(config: &Config, rom_sets: &[RomSet]) -> Result<()> {
-    // Create output directory if it doesn't exist
-    if !config.output_dir.exists() {
-        fs::create_dir_all(&config.output_dir).with_context(|| {
-            format!(
-                "Failed to create output directory: {}",
-                config.output_dir.display()
-            )
-        })?;
+    let layout = FlashLayout::new(config, rom_sets);
+
+    // Check the ROM images can be recovered from the transformed images
+    // before generating anything from them
+    if config.verify_transforms {
+        verify_transforms(config, rom_sets)?;
     }
 
-    // Generate all of the output files
-    for out_type in OutType::iter() {
+    // Generate the contents of all of the output files, before writing any
+    let mut files = Vec::new();
+    for out_type in OutType::iter().filter(|out_type| out_type.is_for_format(config.output_format))
+    {
         let filename = out_filename(out_type);
-        match out_type {
+        let contents = match out_type {
             OutType::RomsC => generate_roms_implementation_file(&filename, config, rom_sets)?,
             OutType::RomsH => generate_roms_header_file(&filename, config, rom_sets)?,
+            OutType::RomsRs => generate_roms_rust_file(&filename, config, rom_sets)?,
             OutType::SdrrConfigH => generate_sdrr_config_header(&filename, config)?,
             OutType::SdrrConfigC => {
                 generate_sdrr_config_implementation(&filename, config, rom_sets)?
@@ -41,31 +45,258 @@ pub fn generate_files(config: &Config, rom_sets: &[RomSet]) -> Result<()> {
             OutType::GenMk => generate_makefile_fragment(&filename, config)?,
             OutType::LinkerLd => generate_linker_script(&filename, config)?,
             OutType::PlatformBootBlockLd => generate_platform_ld_script(&filename, config)?,
+        };
+        files.push((config.output_dir.join(filename), contents));
+    }
+
+    if let Some(layout_file) = &config.layout {
+        let contents = generate_layout_report(config, rom_sets, &layout)?;
+        files.push((layout_file.clone(), contents));
+    }
+
+    if let Some(images_file) = &config.emit_images {
+        let contents = generate_images_file(config, rom_sets)?;
+        files.push((images_file.clone(), contents));
+    }
+
+    if config.dry_run {
+        print_dry_run(config, &files);
+        return Ok(());
+    }
+
+    // Create output directory if it doesn't exist
+    if !config.output_dir.exists() {
+        fs::create_dir_all(&config.output_dir).with_context(|| {
+            format!(
+                "Failed to create output directory: {}",
+                config.output_dir.display()
+            )
+        })?;
+    }
+
+    for (path, contents) in files {
+        fs::write(&path, contents)
+            .with_context(|| format!("Failed to write file: {}", path.display()))?;
+    }
+
+    Ok(())
+}
+
+// Checks each ROM's transformed image demangles back to its source image,
+// failing on the first mismatching address.  This catches pin mapping errors
+// at generation time, rather than on real hardware.
+fn verify_transforms(config: &Config, rom_sets: &[RomSet]) -> Result<()> {
+    let mut count = 0;
+    for rom_set in rom_sets {
+        for (index, rom_in_set) in rom_set.roms.iter().enumerate() {
+            let source = &rom_in_set.image.data;
+            let demangled = rom_set.demangle_rom(index, &config.hw).with_context(|| {
+                format!("Failed to verify ROM set {} ROM {}", rom_set.id, index)
+            })?;
+
+            let source_crc = crc32fast::hash(source);
+            let demangled_crc = crc32fast::hash(&demangled);
+            if source_crc != demangled_crc {
+                let address = source
+                    .iter()
+                    .zip(demangled.iter())
+                    .position(|(a, b)| a != b)
+                    .unwrap_or(0);
+                anyhow::bail!(
+                    "ROM set {} ROM {} ({}) transformation is not lossless: CRC32 0x{:08X}, expected 0x{:08X}.  First mismatch at address 0x{:04X}: 0x{:02X}, expected 0x{:02X}",
+                    rom_set.id,
+                    index,
+                    rom_in_set.config.file.display(),
+                    demangled_crc,
+                    source_crc,
+                    address,
+                    demangled[address],
+                    source[address]
+                );
+            }
+            count += 1;
         }
     }
+
+    println!("- Verified {} ROM transformation(s)", count);
     Ok(())
 }
 
+// Report what would be written, for --dry-run
+fn print_dry_run(config: &Config, files: &[(PathBuf, Vec<u8>)]) {
+    println!("Dry run - no files written.  Would write:");
+    let mut existing = 0;
+    for (path, contents) in files {
+        let overwrite = if path.exists() {
+            if path.starts_with(&config.output_dir) {
+                existing += 1;
+            }
+            " - overwrites existing file"
+        } else {
+            ""
+        };
+        println!("  {} ({} bytes){}", path.display(), contents.len(), overwrite);
+    }
+    println!(
+        "  {}/ ({} ROM image(s) and the image list, replacing any existing)",
+        image_dir(&config.output_dir).display(),
+        config.roms.len()
+    );
+
+    if !config.overwrite && config.output_dir.exists() {
+        println!(
+            "Output directory {} already exists, so --overwrite is required{}",
+            config.output_dir.display(),
+            if existing > 0 {
+                format!(" - {} file(s) would be overwritten", existing)
+            } else {
+                String::new()
+            }
+        );
+    }
+}
+
+// Size in bytes of a ROM set's image in flash
+fn rom_set_image_size(config: &Config, rom_set: &RomSet) -> usize {
+    config.rom_set_image_size(rom_set.roms.len())
+}
+
+// Estimated flash layout of the ROM set images, which the linker places one
+// after the other following the firmware code
+struct FlashLayout {
+    // ROM set ID, offset from the start of flash and size, in bytes
+    sets: Vec<(usize, usize, usize)>,
+    images_bytes: usize,
+    available_bytes: usize,
+}
+
+impl FlashLayout {
+    fn new(config: &Config, rom_sets: &[RomSet]) -> Self {
+        let mut offset = FIRMWARE_CODE_BUDGET_KB * 1024;
+        let mut sets = Vec::new();
+        for rom_set in rom_sets {
+            let size = rom_set_image_size(config, rom_set);
+            sets.push((rom_set.id, offset, size));
+            offset += size;
+        }
+
+        let flash_bytes = config.mcu_variant.flash_storage_kb() * 1024;
+        Self {
+            sets,
+            images_bytes: offset - FIRMWARE_CODE_BUDGET_KB * 1024,
+            available_bytes: flash_bytes.saturating_sub(FIRMWARE_CODE_BUDGET_KB * 1024),
+        }
+    }
+}
+
+// Generate the human-readable flash layout report
+fn generate_layout_report(
+    config: &Config,
+    rom_sets: &[RomSet],
+    layout: &FlashLayout,
+) -> Result<Vec<u8>> {
+    let mut file = Vec::new();
+
+    writeln!(
+        file,
+        "SDRR flash layout - {}, {}KB flash",
+        config.mcu_variant.makefile_var(),
+        config.mcu_variant.flash_storage_kb()
+    )?;
+    writeln!(file)?;
+    writeln!(
+        file,
+        "Offsets are from the start of flash, and are estimates, as the firmware"
+    )?;
+    writeln!(file, "code size varies with the options selected.")?;
+    writeln!(file)?;
+    writeln!(file, "{:<10} {:<10} {:>6}  ROMs", "", "Offset", "Size")?;
+    writeln!(file, "{:<10} 0x{:08X} {:>4}KB  -", "Firmware", 0, FIRMWARE_CODE_BUDGET_KB)?;
+    for (rom_set, (id, offset, size)) in rom_sets.iter().zip(layout.sets.iter()) {
+        let roms: Vec<&str> = rom_set
+            .roms
+            .iter()
+            .map(|rom| {
+                let source = rom.config.extract.as_ref().unwrap_or(&rom.config.original_source);
+                source.split('/').next_back().unwrap_or(source)
+            })
+            .collect();
+        writeln!(
+            file,
+            "{:<10} 0x{:08X} {:>4}KB  {}",
+            format!("ROM set {}", id),
+            offset,
+            size / 1024,
+            roms.join(", ")
+        )?;
+    }
+    writeln!(file)?;
+
+    let flash_kb = config.mcu_variant.flash_storage_kb();
+    let used_kb = FIRMWARE_CODE_BUDGET_KB + layout.images_bytes / 1024;
+    writeln!(file, "Firmware code budget: {:>5}KB", FIRMWARE_CODE_BUDGET_KB)?;
+    writeln!(
+        file,
+        "ROM images:           {:>5}KB ({} set(s))",
+        layout.images_bytes / 1024,
+        layout.sets.len()
+    )?;
+    writeln!(file, "Total:                {:>5}KB of {}KB", used_kb, flash_kb)?;
+    writeln!(
+        file,
+        "Headroom:             {:>5}KB",
+        layout.available_bytes.saturating_sub(layout.images_bytes) / 1024
+    )?;
+
+    Ok(file)
+}
+
+// Generate the --emit-images file, containing every ROM set's transformed
+// image, exactly as stored in the firmware, preceded by an index - see
+// sdrr_common::images for the format.
+fn generate_images_file(config: &Config, rom_sets: &[RomSet]) -> Result<Vec<u8>> {
+    let count = u8::try_from(rom_sets.len()).context("Too many ROM sets for images file")?;
+
+    let mut file = images_header(count).to_vec();
+
+    let mut offset = IMAGES_HEADER_SIZE + rom_sets.len() * IMAGES_INDEX_ENTRY_SIZE;
+    for rom_set in rom_sets {
+        let entry = ImagesIndexEntry {
+            offset,
+            size: rom_set_image_size(config, rom_set),
+            set_id: rom_set.id as u8,
+            rom_count: rom_set.roms.len() as u8,
+            is_banked: rom_set.is_banked,
+        };
+        file.extend_from_slice(&entry.to_bytes());
+        offset += entry.size;
+    }
+
+    for rom_set in rom_sets {
+        file.extend_from_slice(&rom_set_data(config, rom_set)?);
+    }
+
+    Ok(file)
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum FileType {
     C,
+    Rust,
     Makefile,
     Linker,
 }
 
-fn create_file(output_dir: &Path, filename: &Path, filetype: FileType) -> Result<fs::File> {
-    let file_path = output_dir.join(filename);
-    let mut file = fs::File::create(&file_path)
-        .with_context(|| format!("Failed to create file: {}", file_path.display()))?;
-
+// Start a generated file's contents, with the standard header
+fn start_file(filename: &Path, filetype: FileType) -> Result<Vec<u8>> {
+    let mut file = Vec::new();
     write_header(filename, &mut file, filetype)?;
-
     Ok(file)
 }
 
-fn write_header(name: &Path, file: &mut fs::File, filetype: FileType) -> Result<()> {
+fn write_header(name: &Path, file: &mut impl Write, filetype: FileType) -> Result<()> {
     let comment = match filetype {
-        FileType::C => "//",
+        FileType::C | FileType::Rust => "//",
         FileType::Makefile => "#",
         FileType::Linker => "",
     };
@@ -107,8 +338,8 @@ fn write_header(name: &Path, file: &mut fs::File, filetype: FileType) -> Result<
 }
 
 // Generate roms.h header file
-fn generate_roms_header_file(filename: &Path, config: &Config, rom_sets: &[RomSet]) -> Result<()> {
-    let mut file = create_file(&config.output_dir, filename, FileType::C)?;
+fn generate_roms_header_file(filename: &Path, config: &Config, rom_sets: &[RomSet]) -> Result<Vec<u8>> {
+    let mut file = start_file(filename, FileType::C)?;
 
     writeln!(file, "#ifndef SDRR_ROMS_H")?;
     writeln!(file, "#define SDRR_ROMS_H")?;
@@ -128,7 +359,7 @@ fn generate_roms_header_file(filename: &Path, config: &Config, rom_sets: &[RomSe
 
     writeln!(file, "#endif // SDRR_ROMS_H")?;
 
-    Ok(())
+    Ok(file)
 }
 
 // Generate roms.c implementation file
@@ -136,8 +367,8 @@ fn generate_roms_implementation_file(
     filename: &Path,
     config: &Config,
     rom_sets: &[RomSet],
-) -> Result<()> {
-    let mut file = create_file(&config.output_dir, filename, FileType::C)?;
+) -> Result<Vec<u8>> {
+    let mut file = start_file(filename, FileType::C)?;
 
     writeln!(file, "#include \"sdrr_config.h\"")?;
     writeln!(file, "#include \"config_base.h\"")?;
@@ -148,18 +379,11 @@ fn generate_roms_implementation_file(
     writeln!(file, "// ROM filenames (BOOT_LOGGING only)")?;
     writeln!(file, "#if defined(BOOT_LOGGING)")?;
     for (i, rom_config) in config.roms.iter().enumerate() {
-        let source = rom_config
-            .extract
-            .as_ref()
-            .unwrap_or(&rom_config.original_source);
-        let filename = source
-            .split('/')
-            .next_back()
-            .unwrap_or_else(|| panic!("Failed to extract valid filename from source: {}", source));
         writeln!(
             file,
             "static const char sdrr_rom_{}_filename[] = \"{}\";",
-            i, filename
+            i,
+            rom_filename(rom_config)
         )?;
     }
     writeln!(file, "#endif // BOOT_LOGGING")?;
@@ -234,6 +458,7 @@ fn generate_roms_implementation_file(
             match config.mcu_variant.family() {
                 McuFamily::Rp2350 => writeln!(file, "#define ROM_SET_{}_DATA_SIZE  ROM_IMAGE_SIZE_RP235X", ii)?,
                 McuFamily::Stm32F4 => writeln!(file, "#define ROM_SET_{}_DATA_SIZE  ROM_IMAGE_SIZE_STM32F4", ii)?,
+                family @ McuFamily::Stm32F7 => return Err(unsupported_family(family)),
             }
             
         } else {
@@ -277,42 +502,9 @@ fn generate_roms_implementation_file(
         writeln!(file, "        .roms = rom_set_{}_roms,", ii)?;
         writeln!(file, "        .rom_count = ROM_SET_{}_ROM_COUNT,", ii)?;
         writeln!(file, "        .serve = {},", serve_alg)?;
-        let set_cs_state = if num_roms == 1 {
-            "CS_NOT_USED"
-        } else {
-            // Check that every ROM in this set has the same CS1 configuration
-            if rom_set
-                .roms
-                .iter()
-                .any(|rom| rom.config.cs_config.cs1 != rom_set.roms[0].config.cs_config.cs1)
-            {
-                return Err(anyhow::anyhow!(
-                    "All ROMs in a multi-ROM set must have the same CS1 configuration"
-                ));
-            }
-
-            if !rom_set.is_banked {
-                // Check that every ROM in this set has CS2 and CS3 ignored.
-                if rom_set.roms.iter().any(|rom| {
-                    rom.config
-                        .cs_config
-                        .cs2
-                        .is_some_and(|cs| cs != CsLogic::Ignore)
-                        || rom
-                            .config
-                            .cs_config
-                            .cs3
-                            .is_some_and(|cs| cs != CsLogic::Ignore)
-                }) {
-                    return Err(anyhow::anyhow!(
-                        "All ROMs in a multi-ROM set must have CS2 and CS3 ignored or not present"
-                    ));
-                }
-            }
-
-            // Use the CS1 state from any ROM image, as they must be the same
-            cs_logic_to_enum(rom_set.roms[0].config.cs_config.cs1)
-        };
+        let set_cs_state = multi_rom_cs1_state(rom_set)?
+            .map(cs_logic_to_enum)
+            .unwrap_or("CS_NOT_USED");
         writeln!(file, "        .multi_rom_cs1_state = {},", set_cs_state)?;
         writeln!(file, "    }},")?;
     }
@@ -321,18 +513,8 @@ fn generate_roms_implementation_file(
     writeln!(file)?;
 
     // Generate ROM set data arrays
-    let hw = &config.hw;
     for rom_set in rom_sets {
-        // Determine image size based on number of ROMs in the set
-        let image_size = if rom_set.roms.len() == 1 {
-            match config.hw.mcu.family {
-                McuFamily::Stm32F4 => 16384,
-                McuFamily::Rp2350 => 65536,
-            }
-        } else {
-            // Multi-ROM/banked sets: combined 64KB image
-            65536
-        };
+        let data = rom_set_data(config, rom_set)?;
         let ii = rom_set.id;
 
         writeln!(file, "// ROM set {} data", rom_set.id)?;
@@ -353,7 +535,7 @@ fn generate_roms_implementation_file(
                 .join(" ")
         }
 
-        for address in 0..image_size {
+        for (address, byte) in data.iter().enumerate() {
             if address % 256 == 0 {
                 // Comment address every 256 bytes
                 if address > 0 {
@@ -386,7 +568,6 @@ fn generate_roms_implementation_file(
                 write!(file, "    ")?;
             }
 
-            let byte = rom_set.get_byte(address, hw);
             write!(file, "0x{:02x}, ", byte)?;
         }
 
@@ -395,12 +576,224 @@ fn generate_roms_implementation_file(
         writeln!(file)?;
     }
 
-    Ok(())
+    Ok(file)
+}
+
+// Returns the file name of a ROM's source image, for logging by the firmware
+fn rom_filename(rom_config: &RomConfig) -> &str {
+    let source = rom_config
+        .extract
+        .as_ref()
+        .unwrap_or(&rom_config.original_source);
+    source
+        .split('/')
+        .next_back()
+        .unwrap_or_else(|| panic!("Failed to extract valid filename from source: {}", source))
+}
+
+// Returns the CS1 state shared by all of the ROMs in a multi-ROM or banked
+// set, checking they do share it, or None for a single ROM set
+fn multi_rom_cs1_state(rom_set: &RomSet) -> Result<Option<CsLogic>> {
+    if rom_set.roms.len() == 1 {
+        return Ok(None);
+    }
+
+    // Check that every ROM in this set has the same CS1 configuration
+    if rom_set
+        .roms
+        .iter()
+        .any(|rom| rom.config.cs_config.cs1 != rom_set.roms[0].config.cs_config.cs1)
+    {
+        return Err(anyhow::anyhow!(
+            "All ROMs in a multi-ROM set must have the same CS1 configuration"
+        ));
+    }
+
+    if !rom_set.is_banked {
+        // Check that every ROM in this set has CS2 and CS3 ignored.
+        if rom_set.roms.iter().any(|rom| {
+            rom.config
+                .cs_config
+                .cs2
+                .is_some_and(|cs| cs != CsLogic::Ignore)
+                || rom
+                    .config
+                    .cs_config
+                    .cs3
+                    .is_some_and(|cs| cs != CsLogic::Ignore)
+        }) {
+            return Err(anyhow::anyhow!(
+                "All ROMs in a multi-ROM set must have CS2 and CS3 ignored or not present"
+            ));
+        }
+    }
+
+    // Use the CS1 state from any ROM image, as they must be the same
+    Ok(Some(rom_set.roms[0].config.cs_config.cs1))
+}
+
+// Returns a ROM set's transformed image, as stored in the firmware
+fn rom_set_data(config: &Config, rom_set: &RomSet) -> Result<Vec<u8>> {
+    (0..rom_set_image_size(config, rom_set))
+        .map(|address| {
+            rom_set
+                .get_byte(address, &config.hw)
+                .with_context(|| format!("Failed to get byte for ROM set {}", rom_set.id))
+        })
+        .collect()
+}
+
+// Generate roms.rs, containing the ROM set images and tables as Rust
+// statics, for --output-format rust
+fn generate_roms_rust_file(
+    filename: &Path,
+    config: &Config,
+    rom_sets: &[RomSet],
+) -> Result<Vec<u8>> {
+    let mut file = start_file(filename, FileType::Rust)?;
+
+    let cs_logic_to_rust = |cs_logic: CsLogic| -> &'static str {
+        match cs_logic {
+            CsLogic::ActiveLow => "CsState::ActiveLow",
+            CsLogic::ActiveHigh => "CsState::ActiveHigh",
+            CsLogic::Ignore => "CsState::NotUsed",
+        }
+    };
+
+    // Types, mirroring those used by the C firmware
+    writeln!(file, "// Number of ROM images and sets")?;
+    writeln!(file, "pub const SDRR_NUM_IMAGES: usize = {};", config.roms.len())?;
+    writeln!(file, "pub const SDRR_NUM_SETS: usize = {};", rom_sets.len())?;
+    writeln!(file)?;
+    writeln!(file, "#[derive(Debug, Clone, Copy, PartialEq, Eq)]")?;
+    writeln!(file, "pub enum RomType {{")?;
+    writeln!(file, "    Rom2316,")?;
+    writeln!(file, "    Rom2332,")?;
+    writeln!(file, "    Rom2364,")?;
+    writeln!(file, "    Rom23128,")?;
+    writeln!(file, "}}")?;
+    writeln!(file)?;
+    writeln!(file, "#[derive(Debug, Clone, Copy, PartialEq, Eq)]")?;
+    writeln!(file, "pub enum CsState {{")?;
+    writeln!(file, "    ActiveLow,")?;
+    writeln!(file, "    ActiveHigh,")?;
+    writeln!(file, "    NotUsed,")?;
+    writeln!(file, "}}")?;
+    writeln!(file)?;
+    writeln!(file, "#[derive(Debug, Clone, Copy, PartialEq, Eq)]")?;
+    writeln!(file, "pub enum Serve {{")?;
+    writeln!(file, "    TwoCsOneAddr,")?;
+    writeln!(file, "    AddrOnCs,")?;
+    writeln!(file, "    AddrOnAnyCs,")?;
+    writeln!(file, "}}")?;
+    writeln!(file)?;
+    writeln!(file, "#[derive(Debug)]")?;
+    writeln!(file, "pub struct RomInfo {{")?;
+    writeln!(file, "    pub rom_type: RomType,")?;
+    writeln!(file, "    pub cs1_state: CsState,")?;
+    writeln!(file, "    pub cs2_state: CsState,")?;
+    writeln!(file, "    pub cs3_state: CsState,")?;
+    writeln!(file, "    pub filename: &'static str,")?;
+    writeln!(file, "}}")?;
+    writeln!(file)?;
+    writeln!(file, "#[derive(Debug)]")?;
+    writeln!(file, "pub struct RomSet {{")?;
+    writeln!(file, "    pub data: &'static [u8],")?;
+    writeln!(file, "    pub roms: &'static [&'static RomInfo],")?;
+    writeln!(file, "    pub serve: Serve,")?;
+    writeln!(file, "    pub multi_rom_cs1_state: CsState,")?;
+    writeln!(file, "}}")?;
+    writeln!(file)?;
+
+    // ROM information
+    for (ii, rom_config) in config.roms.iter().enumerate() {
+        let cs_config = &rom_config.cs_config;
+        writeln!(file, "// ROM {}", ii)?;
+        writeln!(file, "static ROM_{}_INFO: RomInfo = RomInfo {{", ii)?;
+        writeln!(file, "    rom_type: RomType::Rom{},", rom_config.rom_type.name())?;
+        writeln!(file, "    cs1_state: {},", cs_logic_to_rust(cs_config.cs1))?;
+        for (name, cs) in [("cs2", cs_config.cs2), ("cs3", cs_config.cs3)] {
+            let state = cs.map(cs_logic_to_rust).unwrap_or("CsState::NotUsed");
+            writeln!(file, "    {}_state: {},", name, state)?;
+        }
+        writeln!(file, "    filename: {:?},", rom_filename(rom_config))?;
+        writeln!(file, "}};")?;
+        writeln!(file)?;
+    }
+
+    // ROM sets
+    for rom_set in rom_sets {
+        let ii = rom_set.id;
+        writeln!(file, "// ROM set {}", ii)?;
+        writeln!(
+            file,
+            "static ROM_SET_{}_ROMS: [&RomInfo; {}] = [",
+            ii,
+            rom_set.roms.len()
+        )?;
+        for rom_in_set in &rom_set.roms {
+            writeln!(file, "    &ROM_{}_INFO,", rom_in_set.original_index)?;
+        }
+        writeln!(file, "];")?;
+        writeln!(file)?;
+    }
+
+    writeln!(file, "// ROM set array")?;
+    writeln!(file, "pub static ROM_SETS: [RomSet; SDRR_NUM_SETS] = [")?;
+    for rom_set in rom_sets {
+        let ii = rom_set.id;
+        let serve = if rom_set.roms.len() == 1 || rom_set.is_banked {
+            match config.serve_alg {
+                ServeAlg::TwoCsOneAddr => "Serve::TwoCsOneAddr",
+                ServeAlg::Default | ServeAlg::AddrOnCs => "Serve::AddrOnCs",
+            }
+        } else {
+            "Serve::AddrOnAnyCs"
+        };
+        let set_cs_state = multi_rom_cs1_state(rom_set)?
+            .map(cs_logic_to_rust)
+            .unwrap_or("CsState::NotUsed");
+        writeln!(file, "    RomSet {{")?;
+        writeln!(file, "        data: &ROM_SET_{},", ii)?;
+        writeln!(file, "        roms: &ROM_SET_{}_ROMS,", ii)?;
+        writeln!(file, "        serve: {},", serve)?;
+        writeln!(file, "        multi_rom_cs1_state: {},", set_cs_state)?;
+        writeln!(file, "    }},")?;
+    }
+    writeln!(file, "];")?;
+    writeln!(file)?;
+
+    // ROM set data
+    for rom_set in rom_sets {
+        let data = rom_set_data(config, rom_set)?;
+        writeln!(file, "// ROM set {} data", rom_set.id)?;
+        writeln!(
+            file,
+            "pub static ROM_SET_{}: [u8; {}] = [",
+            rom_set.id,
+            data.len()
+        )?;
+        for (address, chunk) in data.chunks(16).enumerate() {
+            let address = address * 16;
+            if address % 256 == 0 {
+                if address > 0 {
+                    writeln!(file)?;
+                }
+                writeln!(file, "    // Address 0x{:04x}", address)?;
+            }
+            let bytes: Vec<String> = chunk.iter().map(|byte| format!("0x{:02x},", byte)).collect();
+            writeln!(file, "    {}", bytes.join(" "))?;
+        }
+        writeln!(file, "];")?;
+        writeln!(file)?;
+    }
+
+    Ok(file)
 }
 
 // Generate sdrr_config.h header file
-fn generate_sdrr_config_header(filename: &Path, config: &Config) -> Result<()> {
-    let mut file = create_file(&config.output_dir, filename, FileType::C)?;
+fn generate_sdrr_config_header(filename: &Path, config: &Config) -> Result<Vec<u8>> {
+    let mut file = start_file(filename, FileType::C)?;
 
     writeln!(file, "#ifndef SDRR_CONFIG_H")?;
     writeln!(file, "#define SDRR_CONFIG_H")?;
@@ -454,6 +847,36 @@ fn generate_sdrr_config_header(filename: &Path, config: &Config) -> Result<()> {
     }
     writeln!(file, "// SDRR hardware revision")?;
 
+    // Bank switched ROM sets.  Banks are selected at runtime by the X1/X2
+    // jumpers, rather than the select jumpers which choose the set at boot.
+    let mut banked_sets: Vec<(usize, usize)> = Vec::new();
+    for rom in config.roms.iter().filter(|rom| rom.bank.is_some()) {
+        let set = rom.set.expect("Internal error - banked ROM without set");
+        match banked_sets.iter_mut().find(|(id, _)| *id == set) {
+            Some((_, count)) => *count += 1,
+            None => banked_sets.push((set, 1)),
+        }
+    }
+    if !banked_sets.is_empty() {
+        writeln!(file)?;
+        writeln!(file, "// Bank switched ROM sets")?;
+        writeln!(file, "#define BANK_SEL_X1_PIN  {}", config.hw.pin_x1())?;
+        writeln!(file, "#define BANK_SEL_X2_PIN  {}", config.hw.pin_x2())?;
+        for (set, count) in banked_sets {
+            writeln!(file, "#define ROM_SET_{}_BANKS  {}", set, count)?;
+            for bank in 0..4 {
+                let jumper = |bit: usize| if bank & bit != 0 { "closed" } else { "open" };
+                writeln!(
+                    file,
+                    "//   X1 {}, X2 {}: bank {}",
+                    jumper(1),
+                    jumper(2),
+                    bank % count
+                )?;
+            }
+        }
+    }
+
     writeln!(file)?;
     writeln!(file, "//")?;
     writeln!(file, "// Clocking configuration")?;
@@ -463,6 +886,18 @@ fn generate_sdrr_config_header(filename: &Path, config: &Config) -> Result<()> {
     writeln!(file)?;
     writeln!(file, "// Target frequency")?;
     writeln!(file, "#define TARGET_FREQ_MHZ    {}", config.freq)?;
+    if let Some(actual) = config
+        .mcu_variant
+        .actual_sysclk_mhz(config.freq, config.overclock)
+    {
+        writeln!(file, "// Actual SYSCLK: {}MHz", actual)?;
+    }
+    if let Some(usb_khz) = config
+        .mcu_variant
+        .usb_clock_khz(config.freq, config.overclock)
+    {
+        writeln!(file, "// USB clock: {}.{:03}MHz", usb_khz / 1000, usb_khz % 1000)?;
+    }
 
     // Oscillator config
     writeln!(file)?;
@@ -602,7 +1037,7 @@ fn generate_sdrr_config_header(filename: &Path, config: &Config) -> Result<()> {
     writeln!(file)?;
     writeln!(file, "#endif // SDRR_CONFIG_H")?;
 
-    Ok(())
+    Ok(file)
 }
 
 // Generate sdrr_config.c implementation file
@@ -610,8 +1045,8 @@ fn generate_sdrr_config_implementation(
     filename: &Path,
     config: &Config,
     rom_sets: &[RomSet],
-) -> Result<()> {
-    let mut file = create_file(&config.output_dir, filename, FileType::C)?;
+) -> Result<Vec<u8>> {
+    let mut file = start_file(filename, FileType::C)?;
 
     writeln!(file, "#include \"sdrr_config.h\"")?;
     writeln!(file, "#include \"config_base.h\"")?;
@@ -779,7 +1214,11 @@ fn generate_sdrr_config_implementation(
         "    .count_rom_access = {},",
         if config.count_rom_access { 1 } else { 0 }
     )?;
-    writeln!(file, "    .pad2 = {{0}},")?;
+    writeln!(
+        file,
+        "    .mco2_enabled = {},",
+        if config.mco && config.mco2 { 1 } else { 0 }
+    )?;
     writeln!(file, "    .rom_sets = rom_set,")?;
     writeln!(file, "    .pins = &sdrr_pins,")?;
 
@@ -787,13 +1226,20 @@ fn generate_sdrr_config_implementation(
     writeln!(file, "    .boot_config = {{0xff, 0xff, 0xff, 0xff}},")?;
     writeln!(file, "    .extra = &sdrr_extra_info,")?;
 
+    // CRC32 of all ROM set images, in order, as they appear in flash
+    let mut hasher = crc32fast::Hasher::new();
+    for rom_set in rom_sets {
+        hasher.update(&rom_set_data(config, rom_set)?);
+    }
+    writeln!(file, "    .rom_crc32 = 0x{:08X},", hasher.finalize())?;
+
     writeln!(file, "}};")?;
 
-    Ok(())
+    Ok(file)
 }
 
-fn generate_makefile_fragment(filename: &Path, config: &Config) -> Result<()> {
-    let mut file = create_file(&config.output_dir, filename, FileType::Makefile)?;
+fn generate_makefile_fragment(filename: &Path, config: &Config) -> Result<Vec<u8>> {
+    let mut file = start_file(filename, FileType::Makefile)?;
 
     // MCU variant
     writeln!(file, "# MCU variant")?;
@@ -806,11 +1252,17 @@ fn generate_makefile_fragment(filename: &Path, config: &Config) -> Result<()> {
 
     writeln!(file)?;
 
-    Ok(())
+    Ok(file)
+}
+
+// Error for families which are recognised, but not yet supported.  Config
+// validation rejects these, so this should only be hit while adding a port.
+fn unsupported_family(family: McuFamily) -> anyhow::Error {
+    anyhow::anyhow!("{} family is not yet supported", family)
 }
 
-fn generate_linker_script(filename: &Path, config: &Config) -> Result<()> {
-    let mut file = create_file(&config.output_dir, filename, FileType::Linker)?;
+fn generate_linker_script(filename: &Path, config: &Config) -> Result<Vec<u8>> {
+    let mut file = start_file(filename, FileType::Linker)?;
 
     writeln!(file, "INCLUDE \"common_vars.ld\"")?;
     writeln!(file)?;
@@ -831,6 +1283,7 @@ fn generate_linker_script(filename: &Path, config: &Config) -> Result<()> {
                 config.mcu_variant.flash_storage_kb()
             )?;
         }
+        family @ McuFamily::Stm32F7 => return Err(unsupported_family(family)),
     }
     writeln!(
         file,
@@ -854,8 +1307,9 @@ fn generate_linker_script(filename: &Path, config: &Config) -> Result<()> {
         McuFamily::Stm32F4 => {
             writeln!(file, "_Ram_Rom_Image_Start = ORIGIN(RAM) + _Sdrr_Runtime_Info_Size;")?;
         }
+        family @ McuFamily::Stm32F7 => return Err(unsupported_family(family)),
     }
-    if config.mcu_variant.ram_kb() > 72 {
+    if config.mcu_variant.ram_kb() > 64 + config.mcu_variant.ram_overhead_kb() {
         writeln!(file, "_Ram_Rom_Image_Size = 0x10000;  /* 64 KB */")?;
     } else {
         writeln!(file, "_Ram_Rom_Image_Size = 0x04000;  /* 16 KB */")?;
@@ -863,11 +1317,11 @@ fn generate_linker_script(filename: &Path, config: &Config) -> Result<()> {
     writeln!(file)?;
     writeln!(file, "INCLUDE \"common.ld\"")?;
 
-    Ok(())
+    Ok(file)
 }
 
-fn generate_platform_ld_script(filename: &Path, config: &Config) -> Result<()> {
-    let mut file = create_file(&config.output_dir, filename, FileType::Linker)?;
+fn generate_platform_ld_script(filename: &Path, config: &Config) -> Result<Vec<u8>> {
+    let mut file = start_file(filename, FileType::Linker)?;
     match config.mcu_variant.family() {
         McuFamily::Rp2350 => {
             writeln!(file, "/* RP2350 specific linker script */")?;
@@ -883,7 +1337,119 @@ fn generate_platform_ld_script(filename: &Path, config: &Config) -> Result<()> {
             writeln!(file)?;
             writeln!(file, "/* Intentionally empty */")?;
         }
+        family @ McuFamily::Stm32F7 => return Err(unsupported_family(family)),
     } 
 
-    Ok(())
-}
\ No newline at end of file
+    Ok(file)
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::OutputFormat;
+    use crate::config::tests::test_config;
+    use crate::preprocessor::tests::{hw_rev_f, rom_in_set};
+    use sdrr_common::McuVariant;
+    use sdrr_common::images::parse_images_index;
+
+    fn single_rom_sets(count: usize) -> Vec<RomSet> {
+        (0..count)
+            .map(|id| RomSet {
+                id,
+                roms: vec![rom_in_set(RomType::Rom2364, CsLogic::ActiveLow, 0, id)],
+                is_banked: false,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_flash_layout() {
+        // An F401RB has room for 6 single ROM sets
+        let mut config = test_config(hw_rev_f());
+        config.mcu_variant = McuVariant::F401RB;
+
+        let layout = FlashLayout::new(&config, &single_rom_sets(6));
+        assert_eq!(layout.sets[1], (1, 0xC000, 0x4000));
+        assert_eq!(layout.images_bytes, 6 * 16384);
+        assert_eq!(layout.available_bytes, 96 * 1024);
+
+        config.mcu_variant = McuVariant::F411RE;
+        let layout = FlashLayout::new(&config, &single_rom_sets(7));
+        assert_eq!(layout.images_bytes, 7 * 16384);
+        assert_eq!(layout.available_bytes, 480 * 1024);
+    }
+
+    #[test]
+    fn test_images_file() {
+        let config = test_config(hw_rev_f());
+        let rom_sets = single_rom_sets(2);
+        let file = generate_images_file(&config, &rom_sets).unwrap();
+
+        let data_start = IMAGES_HEADER_SIZE + 2 * IMAGES_INDEX_ENTRY_SIZE;
+        assert_eq!(&file[0..8], b"SDRI\x01\x02\x00\x00");
+        assert_eq!(file.len(), data_start + 2 * 16384);
+
+        // Second index entry, and its image
+        let index = parse_images_index(&file).unwrap();
+        let entry = index[1];
+        assert_eq!((entry.offset, entry.size), (data_start + 16384, 16384));
+        assert_eq!((entry.set_id, entry.rom_count, entry.is_banked), (1, 1, false));
+        let expected = rom_set_data(&config, &rom_sets[1]).unwrap();
+        assert_eq!(&file[entry.offset..entry.offset + entry.size], expected.as_slice());
+    }
+
+    #[test]
+    fn test_sdrr_config_header_banks() {
+        let mut config = test_config(hw_rev_f());
+        let rom = config.roms[0].clone();
+        config.roms = (0..3)
+            .map(|bank| RomConfig {
+                set: Some(0),
+                bank: Some(bank),
+                ..rom.clone()
+            })
+            .collect();
+        let contents = generate_sdrr_config_header(Path::new("sdrr_config.h"), &config).unwrap();
+        let contents = String::from_utf8(contents).unwrap();
+
+        let hw = &config.hw;
+        for expected in [
+            format!("#define BANK_SEL_X1_PIN  {}\n", hw.pin_x1()),
+            format!("#define BANK_SEL_X2_PIN  {}\n", hw.pin_x2()),
+            "#define ROM_SET_0_BANKS  3\n".to_string(),
+            "//   X1 closed, X2 closed: bank 0\n".to_string(),
+        ] {
+            assert!(contents.contains(&expected), "Missing: {}", expected);
+        }
+
+        // Without banked sets, none of the bank defines are emitted
+        config.roms.truncate(1);
+        config.roms[0].bank = None;
+        let contents = generate_sdrr_config_header(Path::new("sdrr_config.h"), &config).unwrap();
+        assert!(!String::from_utf8(contents).unwrap().contains("BANK"));
+    }
+
+    #[test]
+    fn test_roms_rust_file() {
+        let config = test_config(hw_rev_f());
+        let rom_sets = single_rom_sets(1);
+        let contents =
+            generate_roms_rust_file(Path::new("roms.rs"), &config, &rom_sets).unwrap();
+        let contents = String::from_utf8(contents).unwrap();
+
+        for expected in [
+            "    rom_type: RomType::Rom2364,\n    cs1_state: CsState::ActiveLow,",
+            "    filename: \"rom.bin\",\n",
+            "static ROM_SET_0_ROMS: [&RomInfo; 1] = [\n    &ROM_0_INFO,\n];",
+            "        serve: Serve::AddrOnCs,\n",
+            "pub static ROM_SET_0: [u8; 16384] = [\n    // Address 0x0000\n",
+        ] {
+            assert!(contents.contains(expected), "Missing: {}", expected);
+        }
+
+        // The C files aren't generated for Rust output, and vice versa
+        assert!(!OutType::RomsC.is_for_format(OutputFormat::Rust));
+        assert!(OutType::RomsRs.is_for_format(OutputFormat::Rust));
+        assert!(!OutType::RomsRs.is_for_format(OutputFormat::C));
+        assert!(OutType::LinkerLd.is_for_format(OutputFormat::Rust));
+    }
+}
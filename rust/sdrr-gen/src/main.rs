@@ -8,6 +8,7 @@
 //! of configuration and ROM images.
 
 mod args;
+mod compare;
 mod config;
 mod file;
 mod generator;
@@ -16,12 +17,16 @@ mod preprocessor;
 use anyhow::{Context, Result};
 use clap::Parser;
 use std::io::{self, Write};
+use std::path::Path;
 
-use sdrr_common::hardware::list_available_configs;
+use sdrr_common::rom_db::{self, KnownRom};
+use sdrr_common::{McuVariant, RomType};
+use sdrr_common::hardware::{get_hw_config, list_available_configs};
 
 use args::Args;
+use compare::compare_output;
 use config::Config;
-use file::load_rom_files;
+use file::{check_rom_files, file_crc32, load_rom_files};
 use generator::generate_files;
 
 fn main() -> Result<()> {
@@ -30,7 +35,21 @@ fn main() -> Result<()> {
     // If `list_hw_revs` is set, we don't need to process any other arguments,
     // just output the list and exit.
     if args.list_hw_revs() {
-        return list_hw_revs();
+        return list_hw_revs(args.json());
+    }
+
+    // And `identify`
+    if let Some(file) = args.identify() {
+        let db = args
+            .rom_db()
+            .map_err(|e| anyhow::anyhow!("ROM database error: {}", e))?;
+        return identify(file, &db);
+    }
+
+    // Similarly for `list_freqs`
+    if let Some((mcu_variant, overclock)) = args.list_freqs() {
+        list_freqs(mcu_variant, overclock);
+        return Ok(());
     }
 
     // Create configuration
@@ -43,8 +62,17 @@ fn main() -> Result<()> {
         .validate()
         .map_err(|e| anyhow::anyhow!("Configuration validation error: {}", e))?;
 
-    // Check and confirm licences before proceeding
-    confirm_licences(&config)?;
+    // If only checking, check ROM file sizes and stop before fetching any
+    // ROMs or generating anything
+    if args.check_only() {
+        return check_only(&config);
+    }
+
+    // Check and confirm licences before proceeding.  Not needed for a dry
+    // run, as nothing is downloaded.
+    if !args.dry_run() {
+        confirm_licences(&config)?;
+    }
 
     // Load ROM files into RAM
     let rom_images = load_rom_files(&config).with_context(|| "Failed to load ROM files")?;
@@ -63,18 +91,52 @@ fn main() -> Result<()> {
 
     // Generate output files
     generate_files(&config, &rom_sets).with_context(|| "Failed to generate output files")?;
+    if args.dry_run() {
+        return Ok(());
+    }
+
+    // If comparing, diff the temporary output against the existing output
+    if let Some(existing) = args.compare() {
+        let result = compare_output(&config.output_dir, existing, config.output_format);
+        let _ = std::fs::remove_dir_all(&config.output_dir);
+        if !result? {
+            anyhow::bail!("Generated output differs from `{}/`", existing.display());
+        }
+        return Ok(());
+    }
 
     println!(
         "Successfully transformed ROM images and generated output files in `{}/`",
         config.output_dir.display()
     );
+    println!("Build: {}", config.summary(&rom_sets));
+
+    let command = args.reproduction_command(&config);
+    match args.emit_command() {
+        Some(path) => {
+            std::fs::write(path, format!("{command}\n")).with_context(|| {
+                format!("Failed to write reproduction command to {}", path.display())
+            })?;
+            println!("Wrote reproduction command to `{}`", path.display());
+        }
+        None => println!("Command: {}", command),
+    }
 
     Ok(())
 }
 
-fn list_hw_revs() -> Result<()> {
+fn list_hw_revs(json: bool) -> Result<()> {
     // List available hardware revisions
     let hw_revs = list_available_configs()?;
+    if json {
+        let capabilities = hw_revs
+            .iter()
+            .map(|(name, _)| get_hw_config(name).map(|hw| hw.capabilities()))
+            .collect::<Result<Vec<_>>>()?;
+        println!("{}", serde_json::to_string_pretty(&capabilities)?);
+        return Ok(());
+    }
+
     if hw_revs.is_empty() {
         println!("No hardware revisions found.");
     } else {
@@ -86,6 +148,75 @@ fn list_hw_revs() -> Result<()> {
     Ok(())
 }
 
+fn identify(file: &Path, db: &[KnownRom]) -> Result<()> {
+    let crc = file_crc32(file).map_err(|e| anyhow::anyhow!(e))?;
+    let len = std::fs::metadata(file)?.len() as usize;
+    println!("{}: {} bytes, CRC32 0x{:08X}", file.display(), len, crc);
+
+    let matches = rom_db::identify(db, crc);
+    if matches.is_empty() {
+        println!("No known ROM matches");
+        let types: Vec<&str> = [
+            RomType::Rom2316,
+            RomType::Rom2332,
+            RomType::Rom2364,
+            RomType::Rom23128,
+        ]
+        .iter()
+        .filter(|rom_type| rom_type.size_bytes() == len)
+        .map(|rom_type| rom_type.name())
+        .collect();
+        if !types.is_empty() {
+            println!("Size matches ROM type {}", types.join(", "));
+        }
+    } else {
+        for known in matches {
+            println!("Known ROM: {}, {}", known.name, known.rom_type.name());
+        }
+    }
+
+    Ok(())
+}
+
+fn list_freqs(mcu_variant: McuVariant, overclock: bool) {
+    let processor = mcu_variant.processor();
+    let freqs = processor.achievable_frequencies(overclock);
+    println!(
+        "Achievable frequencies for {}{} (max without overclocking {}MHz):",
+        mcu_variant.makefile_var(),
+        if overclock { " with overclocking" } else { "" },
+        processor.max_sysclk_mhz()
+    );
+    for line in freqs.chunks(10) {
+        let line: Vec<String> = line.iter().map(|freq| format!("{:>4}", freq)).collect();
+        println!("  {}", line.join(" "));
+    }
+}
+
+fn check_only(config: &Config) -> Result<()> {
+    check_rom_files(config).with_context(|| "ROM file check failed")?;
+
+    let mut sets: Vec<usize> = config.roms.iter().filter_map(|rom| rom.set).collect();
+    sets.sort();
+    sets.dedup();
+    let set_count = if sets.is_empty() {
+        config.roms.len()
+    } else {
+        sets.len()
+    };
+
+    println!(
+        "Configuration OK: {} ROM(s) in {} set(s), hardware revision {}, {} at {}MHz",
+        config.roms.len(),
+        set_count,
+        config.hw.name,
+        config.mcu_variant.makefile_var(),
+        config.freq
+    );
+
+    Ok(())
+}
+
 fn confirm_licences(config: &Config) -> Result<()> {
     let licensed_roms: Vec<_> = config
         .roms
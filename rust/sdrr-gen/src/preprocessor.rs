@@ -9,11 +9,71 @@
 
 use crate::config::{RomInSet, SizeHandling};
 use anyhow::{Context, Result};
-use sdrr_common::hardware::HwConfig;
+use sdrr_common::hardware::{HwConfig, logical_to_physical, physical_to_logical};
 use sdrr_common::{CsLogic, RomType, McuFamily};
+use std::fmt;
 use std::fs;
 use std::path::Path;
 
+/// Errors produced while preprocessing ROM images into their physical
+/// (mangled) form
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PreprocessError {
+    /// A physical address translated to a logical address outside of the
+    /// ROM image.  `rom_type` is filled in once the ROM type is known.
+    AddressOutOfBounds {
+        address: usize,
+        size: usize,
+        rom_type: Option<RomType>,
+    },
+    /// The MCU family isn't yet supported
+    UnsupportedFamily(McuFamily),
+    /// A chip select or X1/X2 pin isn't one of the address port's pins
+    /// 0-15, so can't select a ROM
+    InvalidPin { name: &'static str, pin: u8 },
+}
+
+impl PreprocessError {
+    fn with_rom_type(self, rom_type: &RomType) -> Self {
+        match self {
+            PreprocessError::AddressOutOfBounds { address, size, .. } => {
+                PreprocessError::AddressOutOfBounds {
+                    address,
+                    size,
+                    rom_type: Some(*rom_type),
+                }
+            }
+            other => other,
+        }
+    }
+}
+
+impl fmt::Display for PreprocessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PreprocessError::AddressOutOfBounds {
+                address,
+                size,
+                rom_type,
+            } => {
+                write!(f, "address 0x{:04X} out of bounds for {}-byte", address, size)?;
+                match rom_type {
+                    Some(rom_type) => write!(f, " {}", rom_type.name()),
+                    None => write!(f, " ROM image"),
+                }
+            }
+            PreprocessError::UnsupportedFamily(family) => {
+                write!(f, "{} family is not yet supported", family)
+            }
+            PreprocessError::InvalidPin { name, pin } => {
+                write!(f, "{} pin {} is not one of the address pins 0-15", name, pin)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PreprocessError {}
+
 // A ROM image that has been validated and loaded
 #[derive(Debug, Clone)]
 pub struct RomImage {
@@ -23,19 +83,94 @@ pub struct RomImage {
 impl RomImage {
     pub fn load_from_file(
         file_path: &Path,
+        source: &str,
         rom_type: &RomType,
         size_handling: &SizeHandling,
+        strict: bool,
     ) -> Result<Self> {
         let data = fs::read(file_path)
             .with_context(|| format!("Failed to read ROM file: {}", file_path.display()))?;
 
+        Self::from_bytes(data, source, rom_type, size_handling, strict)
+            .with_context(|| format!("Invalid ROM file: {}", file_path.display()))
+    }
+
+    /// Creates a ROM image from in-memory data, checking its size against
+    /// the ROM type and duplicating or padding it as requested.
+    ///
+    /// Data which looks like a bad dump (see [`Self::check_contents()`]) is
+    /// warned about, naming `source`, or rejected if `strict` is set.
+    pub fn from_bytes(
+        data: Vec<u8>,
+        source: &str,
+        rom_type: &RomType,
+        size_handling: &SizeHandling,
+        strict: bool,
+    ) -> Result<Self> {
+        Self::check_size(data.len(), rom_type, size_handling)?;
+
+        if let Some(problem) = Self::check_contents(&data) {
+            if strict {
+                anyhow::bail!("ROM image {source} {problem}");
+            }
+            println!("Warning: ROM image {source} {problem}");
+        }
+
+        let expected_size = rom_type.size_bytes();
+        let final_data = match size_handling {
+            SizeHandling::None => data,
+            SizeHandling::Duplicate => {
+                let repeat_count = expected_size / data.len();
+                data.repeat(repeat_count)
+            }
+            SizeHandling::Pad => {
+                let mut padded = data;
+                padded.resize(expected_size, 0xAA);
+                padded
+            }
+        };
+
+        Ok(Self { data: final_data })
+    }
+
+    /// Checks ROM data for signs of a bad dump, returning a description of
+    /// the problem if found.  An image of all 0xFF bytes is almost always an
+    /// erased or empty chip, one of all 0x00 bytes a failed read, and real
+    /// ROM contents, even character ROMs, use far more than a handful of
+    /// distinct byte values.
+    pub fn check_contents(data: &[u8]) -> Option<String> {
+        const MIN_DISTINCT_BYTES: usize = 4;
+
+        let mut seen = [false; 256];
+        for &byte in data {
+            seen[byte as usize] = true;
+        }
+        let distinct = seen.iter().filter(|&&seen| seen).count();
+
+        match (distinct, data.first()) {
+            (0, _) => None,
+            (1, Some(0xFF)) => Some("is all 0xFF - is it an erased or empty dump?".to_string()),
+            (1, Some(0x00)) => Some("is all 0x00 - is it a failed read?".to_string()),
+            (1, Some(byte)) => Some(format!("is all 0x{byte:02X}")),
+            (distinct, _) if distinct < MIN_DISTINCT_BYTES => Some(format!(
+                "contains only {distinct} distinct byte values - is it a bad dump?"
+            )),
+            _ => None,
+        }
+    }
+
+    /// Checks a ROM file of `len` bytes can be used as the given ROM type,
+    /// with the given size handling.  Used both when loading ROM images and
+    /// when only checking the configuration, where only file lengths are
+    /// read.
+    pub fn check_size(len: usize, rom_type: &RomType, size_handling: &SizeHandling) -> Result<()> {
         let expected_size = rom_type.size_bytes();
 
-        let final_data = match data.len().cmp(&expected_size) {
+        match len.cmp(&expected_size) {
             std::cmp::Ordering::Equal => {
                 // Exact match - error if dup/pad specified unnecessarily
                 match size_handling {
-                    SizeHandling::None => data,
+                    SizeHandling::None => Ok(()),
                     _ => anyhow::bail!(
                         "ROM file is already correct size ({} bytes), 'dup' or 'pad' not needed",
                         expected_size
@@ -46,63 +181,64 @@ impl RomImage {
                 // File too small - handle with dup/pad
                 match size_handling {
                     SizeHandling::None => anyhow::bail!(
-                        "Invalid ROM size for {}: expected {} bytes, got {} bytes",
-                        file_path.display(),
+                        "Invalid ROM size: expected {} bytes, got {} bytes",
                         expected_size,
-                        data.len()
+                        len
                     ),
                     SizeHandling::Duplicate => {
-                        if expected_size % data.len() != 0 {
+                        if len == 0 || !expected_size.is_multiple_of(len) {
                             anyhow::bail!(
                                 "ROM size {} is not an exact divisor of {} bytes",
-                                data.len(),
+                                len,
                                 expected_size
                             );
                         }
-                        let repeat_count = expected_size / data.len();
-                        data.repeat(repeat_count)
-                    }
-                    SizeHandling::Pad => {
-                        let mut padded = data;
-                        padded.resize(expected_size, 0xAA);
-                        padded
+                        Ok(())
                     }
+                    SizeHandling::Pad => Ok(()),
                 }
             }
             std::cmp::Ordering::Greater => {
                 anyhow::bail!(
                     "ROM file too large: expected {} bytes, got {} bytes",
                     expected_size,
-                    data.len()
+                    len
                 );
             }
-        };
-
-        Ok(Self { data: final_data })
+        }
     }
 
     /// Transforms from a physical address (based on the hardware pins) to
     /// a logical ROM address, so we store the physical ROM mapping, rather
     /// than the logical one.
+    ///
+    /// Returns an error if the logical address doesn't fit within this image,
+    /// which indicates a pin map that doesn't match the ROM type.
     pub fn transform_address(
         &self,
         address: usize,
         phys_pin_to_addr_map: &[Option<usize>],
-    ) -> usize {
-        // Start with 0 result
-        let mut result = 0;
-
-        for (pin, item) in phys_pin_to_addr_map.iter().enumerate() {
-            if let Some(addr_bit) = item {
-                // Check if this pin is set in the original address
-                if (address & (1 << pin)) != 0 {
-                    // Set the corresponding address bit in the result
-                    result |= 1 << addr_bit;
-                }
-            }
+    ) -> Result<usize, PreprocessError> {
+        let result = physical_to_logical(address, phys_pin_to_addr_map);
+
+        // We must, by definition, have got a logical address that fits
+        // within the actual ROM size.
+        if result >= self.data.len() {
+            return Err(PreprocessError::AddressOutOfBounds {
+                address: result,
+                size: self.data.len(),
+                rom_type: None,
+            });
         }
 
-        result
+        Ok(result)
+    }
+
+    /// Transforms from a logical ROM address to the physical address (based
+    /// on the hardware pins) which selects it.  The inverse of
+    /// [`Self::transform_address`].
+    pub fn physical_address(logical: usize, phys_pin_to_addr_map: &[Option<usize>]) -> usize {
+        logical_to_physical(logical, phys_pin_to_addr_map)
     }
 
     /// Transforms a data byte by rearranging its bit positions to match the hardware's
@@ -141,6 +277,20 @@ impl RomImage {
         result
     }
 
+    /// Reverses [`Self::transform_byte`], returning the original byte from
+    /// one with its bits arranged according to the hardware's data pins.
+    pub fn untransform_byte(byte: u8, phys_pin_to_data_map: &[usize]) -> u8 {
+        let mut result = 0;
+
+        for (bit_pos, new_pos) in phys_pin_to_data_map.iter().take(8).enumerate() {
+            if (byte & (1 << new_pos)) != 0 {
+                result |= 1 << bit_pos;
+            }
+        }
+
+        result
+    }
+
     /// Get byte at the given address with both address and data
     /// transformations applied.
     ///
@@ -158,32 +308,18 @@ impl RomImage {
         address: usize,
         phys_pin_to_addr_map: &[Option<usize>],
         phys_pin_to_data_map: &[usize],
-    ) -> u8 {
+    ) -> Result<u8, PreprocessError> {
         // We have been passed a physical address based on the hardware pins,
         // so we need to transform it to a logical address based on the ROM
-        // image.
-        let transformed_address = self.transform_address(address, phys_pin_to_addr_map);
-
-        // Sanity check that we did get a logical address, which must by
-        // definition fit within the actual ROM size.
-        assert!(transformed_address < self.data.len());
+        // image.  This checks the logical address is within the image.
+        let transformed_address = self.transform_address(address, phys_pin_to_addr_map)?;
 
         // Get the byte from the logical ROM address.
-        let byte = self
-            .data
-            .get(transformed_address)
-            .copied()
-            .unwrap_or_else(|| {
-                panic!(
-                    "Address {} out of bounds for ROM image of size {}",
-                    transformed_address,
-                    self.data.len()
-                )
-            });
+        let byte = self.data[transformed_address];
 
         // Now transform the byte, as the physical data lines are not in the
         // expected order (0-7).
-        Self::transform_byte(byte, phys_pin_to_data_map)
+        Ok(Self::transform_byte(byte, phys_pin_to_data_map))
     }
 }
 
@@ -195,8 +331,26 @@ pub struct RomSet {
 }
 
 impl RomSet {
-    pub fn get_byte(&self, address: usize, hw: &HwConfig) -> u8 {
+    pub fn get_byte(&self, address: usize, hw: &HwConfig) -> Result<u8, PreprocessError> {
         let phys_pin_to_data_map = hw.get_phys_pin_to_data_map();
+        let check_address = |size: usize| {
+            if address < size {
+                Ok(())
+            } else {
+                Err(PreprocessError::AddressOutOfBounds {
+                    address,
+                    size,
+                    rom_type: None,
+                })
+            }
+        };
+        let check_pin = |name: &'static str, pin: u8| {
+            if pin < 16 {
+                Ok(pin)
+            } else {
+                Err(PreprocessError::InvalidPin { name, pin })
+            }
+        };
 
         // Hard-coded assumption that X1/X2 (STM32F4) are pins 14/15 for
         // single ROM sets and banked ROM sets.  However, for RP2350 they may
@@ -206,19 +360,22 @@ impl RomSet {
                 match hw.mcu.family {
                     McuFamily::Rp2350 => {
                         // Single ROM set: uses entire 64KB space
-                        assert!(address < 65536, "Address out of bounds for RP235X single ROM set");
+                        check_address(65536)?;
                     }
                     McuFamily::Stm32F4 => {
-                        // Single ROM set: uses entire 64KB space
-                        assert!(address < 16384, "Address out of bounds for STM32F4 single ROM set");
+                        // Single ROM set: uses 16KB space
+                        check_address(16384)?;
+                    }
+                    family @ McuFamily::Stm32F7 => {
+                        return Err(PreprocessError::UnsupportedFamily(family));
                     }
                 }
                 (0, address)
             } else {
                 // Banked mode: use X1/X2 to select ROM
-                assert!(address < 65536, "Address out of bounds for banked ROM set");
-                let x1_pin = hw.pin_x1();
-                let x2_pin = hw.pin_x2();
+                check_address(65536)?;
+                let x1_pin = check_pin("X1", hw.pin_x1())?;
+                let x2_pin = check_pin("X2", hw.pin_x2())?;
                 let bank = if hw.x_jumper_pull() == 1 {
                     ((address >> x1_pin) & 1) | (((address >> x2_pin) & 1) << 1)
                 } else {
@@ -232,19 +389,18 @@ impl RomSet {
                 (rom_index, masked_address)
             };
 
-            let num_addr_lines = self.roms[rom_index].config.rom_type.num_addr_lines();
-            let phys_pin_to_addr_map = hw.get_phys_pin_to_addr_map(num_addr_lines);
+            let rom_type = &self.roms[rom_index].config.rom_type;
+            let phys_pin_to_addr_map = hw.get_phys_pin_to_addr_map(rom_type.num_addr_lines());
 
-            return self.roms[rom_index].image.get_byte(
-                masked_address,
-                &phys_pin_to_addr_map,
-                &phys_pin_to_data_map,
-            );
+            return self.roms[rom_index]
+                .image
+                .get_byte(masked_address, &phys_pin_to_addr_map, &phys_pin_to_data_map)
+                .map_err(|e| e.with_rom_type(rom_type));
         }
 
         // Multiple ROMs: check CS line states to select responding ROM.  This
         // code can handle any X1/X2 positions - but the above can't.
-        assert!(address < 65536, "Address out of bounds for multi-ROM set");
+        check_address(65536)?;
         for (index, rom_in_set) in self.roms.iter().enumerate() {
             // Get the physical addr and data pin mappings.  We have to
             // retrieve this for each ROM in the set, as each ROM may be
@@ -258,8 +414,10 @@ impl RomSet {
             let pins_active_high = rom_in_set.config.cs_config.cs1 == CsLogic::ActiveHigh;
 
             // Get the CS pin that controls this ROM's selection
-            let cs_pin = hw.cs_pin_for_rom_in_set(&rom_in_set.config.rom_type, index);
-            assert!(cs_pin <= 15, "Internal error: CS pin is > 15");
+            let cs_pin = check_pin(
+                "ROM chip select",
+                hw.cs_pin_for_rom_in_set(&rom_in_set.config.rom_type, index),
+            )?;
 
             fn is_pin_active(active_high: bool, address: usize, pin: u8) -> bool {
                 if active_high {
@@ -273,9 +431,9 @@ impl RomSet {
 
             if cs_active {
                 // Verify exactly one CS pin is active
-                let cs1_pin = hw.pin_cs1(&rom_in_set.config.rom_type);
-                let x1_pin = hw.pin_x1();
-                let x2_pin = hw.pin_x2();
+                let cs1_pin = check_pin("CS1", hw.pin_cs1(&rom_in_set.config.rom_type))?;
+                let x1_pin = check_pin("X1", hw.pin_x1())?;
+                let x2_pin = check_pin("X2", hw.pin_x2())?;
 
                 let cs1_is_active = is_pin_active(pins_active_high, address, cs1_pin);
                 let x1_is_active = is_pin_active(pins_active_high, address, x1_pin);
@@ -289,16 +447,87 @@ impl RomSet {
                 // Only return the byte for a single CS active, otherwise
                 // it'll get 0xAA
                 if active_count == 1 && self.check_rom_cs_requirements(rom_in_set, address, hw) {
-                    return rom_in_set.image.get_byte(
+                    let masked_address = self.mask_cs_selection_bits(
                         address,
-                        &phys_pin_to_addr_map,
-                        &phys_pin_to_data_map,
+                        &rom_in_set.config.rom_type,
+                        hw,
                     );
+                    return rom_in_set
+                        .image
+                        .get_byte(masked_address, &phys_pin_to_addr_map, &phys_pin_to_data_map)
+                        .map_err(|e| e.with_rom_type(&rom_in_set.config.rom_type));
                 }
             }
         }
 
-        RomImage::transform_byte(0xAA, &phys_pin_to_data_map) // No ROM selected
+        Ok(RomImage::transform_byte(0xAA, &phys_pin_to_data_map)) // No ROM selected
+    }
+
+    /// Returns the physical address at which the ROM at `index` in this set
+    /// serves the given logical address - with X1/X2 and the CS lines driven
+    /// to select that ROM, for banked and multi-ROM sets.
+    fn select_address(&self, index: usize, logical: usize, hw: &HwConfig) -> usize {
+        let rom_in_set = &self.roms[index];
+        let rom_type = &rom_in_set.config.rom_type;
+        let phys_pin_to_addr_map = hw.get_phys_pin_to_addr_map(rom_type.num_addr_lines());
+        let mut address = RomImage::physical_address(logical, &phys_pin_to_addr_map);
+
+        // Pins which aren't present on this hardware are 255
+        let mut drive = |pin: u8, high: bool| {
+            if pin < 16 {
+                if high {
+                    address |= 1 << pin;
+                } else {
+                    address &= !(1 << pin);
+                }
+            }
+        };
+
+        // CS1/X1/X2 share the active high/low status of CS1
+        let active_high = rom_in_set.config.cs_config.cs1 == CsLogic::ActiveHigh;
+        if self.is_banked {
+            // The bank is selected by the X1/X2 jumpers
+            let (x1, x2) = (index & 1 != 0, index & 2 != 0);
+            let pull_high = hw.x_jumper_pull() == 1;
+            drive(hw.pin_cs1(rom_type), active_high);
+            drive(hw.pin_x1(), x1 == pull_high);
+            drive(hw.pin_x2(), x2 == pull_high);
+        } else if self.roms.len() > 1 {
+            // Exactly one of CS1/X1/X2 is active, selecting this ROM
+            let select_pins = [hw.pin_cs1(rom_type), hw.pin_x1(), hw.pin_x2()];
+            for (ii, pin) in select_pins.into_iter().enumerate() {
+                drive(pin, (ii == index) == active_high);
+            }
+
+            let cs_config = &rom_in_set.config.cs_config;
+            for (logic, pin) in [
+                (cs_config.cs2, hw.pin_cs2(rom_type)),
+                (cs_config.cs3, hw.pin_cs3(rom_type)),
+            ] {
+                match logic {
+                    Some(CsLogic::ActiveLow) => drive(pin, false),
+                    Some(CsLogic::ActiveHigh) => drive(pin, true),
+                    Some(CsLogic::Ignore) | None => (),
+                }
+            }
+        }
+
+        address
+    }
+
+    /// Reads back the logical image of the ROM at `index` in this set, by
+    /// reversing the address and data transformations applied to the bytes
+    /// returned by [`Self::get_byte`].  Used to check the transformations
+    /// are lossless - the result should match the ROM's source image.
+    pub fn demangle_rom(&self, index: usize, hw: &HwConfig) -> Result<Vec<u8>, PreprocessError> {
+        let phys_pin_to_data_map = hw.get_phys_pin_to_data_map();
+        (0..self.roms[index].image.data.len())
+            .map(|logical| {
+                let address = self.select_address(index, logical, hw);
+                let byte = self.get_byte(address, hw)?;
+                Ok(RomImage::untransform_byte(byte, &phys_pin_to_data_map))
+            })
+            .collect()
     }
 
     fn check_rom_cs_requirements(
@@ -359,40 +588,364 @@ impl RomSet {
         true
     }
 
-    #[allow(dead_code)]
-    fn mask_cs_selection_bits(&self, address: usize, rom_type: &RomType, hw: HwConfig) -> usize {
+    /// Strips the CS1, X1/X2 and any CS2/CS3 (or CE/OE) selection bits for
+    /// the given ROM type from a physical address, leaving only the bits on
+    /// pins used as address lines by that ROM type.
+    fn mask_cs_selection_bits(&self, address: usize, rom_type: &RomType, hw: &HwConfig) -> usize {
         let mut masked_address = address;
 
+        // Pins which aren't present on this hardware are 255, so ignore any
+        // pin outside of the 16-bit address space
+        let mut clear = |pin: u8| {
+            if pin < 16 {
+                masked_address &= !(1 << pin);
+            }
+        };
+
         // Remove the CS selection bits - only mask bits that exist on this hardware
-        masked_address &= !(1 << hw.pin_cs1(rom_type));
+        clear(hw.pin_cs1(rom_type));
 
         // Only mask X1/X2 on hardware that has them (revision F)
         if hw.supports_multi_rom_sets() {
-            let x1 = hw.pin_x1();
-            let x2 = hw.pin_x2();
-            assert!(x1 < 15 && x2 < 15, "X1/X2 pins must be less than 15");
-            masked_address &= !(1 << x1);
-            masked_address &= !(1 << x2);
+            clear(hw.pin_x1());
+            clear(hw.pin_x2());
         }
 
         // Remove CS2/CS3 bits based on ROM type
         match rom_type {
             RomType::Rom2332 => {
-                masked_address &= !(1 << hw.pin_cs2(rom_type));
+                clear(hw.pin_cs2(rom_type));
             }
             RomType::Rom2316 => {
-                masked_address &= !(1 << hw.pin_cs2(rom_type));
-                masked_address &= !(1 << hw.pin_cs3(rom_type));
+                clear(hw.pin_cs2(rom_type));
+                clear(hw.pin_cs3(rom_type));
             }
             RomType::Rom2364 => {
                 // 2364 only uses CS1, no additional bits to remove
             }
             RomType::Rom23128 => {
-                // No additional bits to remove
+                clear(hw.pin_ce(rom_type));
+                clear(hw.pin_oe(rom_type));
             }
         }
 
-        // Ensure address fits within ROM size
-        masked_address & ((1 << 13) - 1) // Mask to 13 bits max (8KB)
+        // Ensure address fits within ROM size, by only keeping the pins used
+        // for this ROM type's address lines.  The address pins aren't
+        // contiguous, so a simple (1 << num_addr_lines) - 1 mask won't do.
+        let addr_pins_mask = hw
+            .get_phys_pin_to_addr_map(rom_type.num_addr_lines())
+            .iter()
+            .enumerate()
+            .filter(|(_, addr_bit)| addr_bit.is_some())
+            .fold(0, |mask, (pin, _)| mask | (1 << pin));
+
+        masked_address & addr_pins_mask
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::config::{CsConfig, RomConfig};
+    use std::path::PathBuf;
+
+    pub(crate) fn hw_rev_f() -> HwConfig {
+        HwConfig::new(include_str!("../../../sdrr-hw-config/24-f.json"), "24-f").unwrap()
+    }
+
+    pub(crate) fn rom_in_set(rom_type: RomType, cs1: CsLogic, fill: u8, index: usize) -> RomInSet {
+        RomInSet {
+            config: RomConfig {
+                file: PathBuf::from(format!("rom{}.bin", index)),
+                original_source: format!("rom{}.bin", index),
+                extract: None,
+                licence: None,
+                rom_type,
+                cs_config: CsConfig {
+                    cs1,
+                    cs2: None,
+                    cs3: None,
+                },
+                size_handling: SizeHandling::None,
+                set: Some(0),
+                bank: None,
+            },
+            image: RomImage {
+                data: vec![fill; rom_type.size_bytes()],
+            },
+            original_index: index,
+        }
+    }
+
+    fn two_rom_set(cs1: CsLogic) -> RomSet {
+        RomSet {
+            id: 0,
+            roms: vec![
+                rom_in_set(RomType::Rom2364, cs1, 0x11, 0),
+                rom_in_set(RomType::Rom2364, cs1, 0x22, 1),
+            ],
+            is_banked: false,
+        }
+    }
+
+    #[test]
+    fn test_multi_rom_cs_selection() {
+        let hw = hw_rev_f();
+        let data_map = hw.get_phys_pin_to_data_map();
+        let cs1 = 1 << hw.pin_cs1(&RomType::Rom2364);
+        let x1 = 1 << hw.pin_x1();
+        let x2 = 1 << hw.pin_x2();
+        assert_ne!(hw.pin_x1(), 0, "Test requires X1 to be other than bit 0");
+
+        let rom0 = RomImage::transform_byte(0x11, &data_map);
+        let rom1 = RomImage::transform_byte(0x22, &data_map);
+        let none = RomImage::transform_byte(0xAA, &data_map);
+
+        // Active low - the selected ROM's CS line is low, the others high
+        let set = two_rom_set(CsLogic::ActiveLow);
+        assert_eq!(set.get_byte(x1 | x2, &hw).unwrap(), rom0);
+        assert_eq!(set.get_byte(cs1 | x2, &hw).unwrap(), rom1);
+        assert_eq!(set.get_byte(cs1 | x1 | x2, &hw).unwrap(), none);
+        assert_eq!(set.get_byte(x2, &hw).unwrap(), none);
+
+        // Active high - the selected ROM's CS line is high, the others low
+        let set = two_rom_set(CsLogic::ActiveHigh);
+        assert_eq!(set.get_byte(cs1, &hw).unwrap(), rom0);
+        assert_eq!(set.get_byte(x1, &hw).unwrap(), rom1);
+        assert_eq!(set.get_byte(0, &hw).unwrap(), none);
+        assert_eq!(set.get_byte(cs1 | x1, &hw).unwrap(), none);
+    }
+
+    fn patterned_rom_in_set(rom_type: RomType, cs1: CsLogic, index: usize) -> RomInSet {
+        let mut rom = rom_in_set(rom_type, cs1, 0, index);
+        for (addr, byte) in rom.image.data.iter_mut().enumerate() {
+            *byte = (addr ^ (addr >> 8) ^ index) as u8;
+        }
+        rom
+    }
+
+    // Returns the physical address for a logical address, using the
+    // hardware's address pin mapping
+    fn phys_address(hw: &HwConfig, rom_type: &RomType, logical: usize) -> usize {
+        RomImage::physical_address(logical, &hw.get_phys_pin_to_addr_map(rom_type.num_addr_lines()))
+    }
+
+    #[test]
+    fn test_multi_rom_matches_single_rom() {
+        let hw = hw_rev_f();
+        let cs1 = 1 << hw.pin_cs1(&RomType::Rom2332);
+        let x1 = 1 << hw.pin_x1();
+        let x2 = 1 << hw.pin_x2();
+
+        let multi = RomSet {
+            id: 0,
+            roms: vec![
+                patterned_rom_in_set(RomType::Rom2364, CsLogic::ActiveLow, 0),
+                patterned_rom_in_set(RomType::Rom2332, CsLogic::ActiveLow, 1),
+            ],
+            is_banked: false,
+        };
+        let single_2364 = RomSet {
+            id: 1,
+            roms: vec![multi.roms[0].clone()],
+            is_banked: false,
+        };
+        let single_2332 = RomSet {
+            id: 2,
+            roms: vec![multi.roms[1].clone()],
+            is_banked: false,
+        };
+
+        for logical in 0..RomType::Rom2332.size_bytes() {
+            let phys = phys_address(&hw, &RomType::Rom2332, logical);
+            assert_eq!(
+                multi.get_byte(phys | cs1 | x2, &hw).unwrap(),
+                single_2332.get_byte(phys, &hw).unwrap(),
+                "2332 mismatch at logical address 0x{:04X}",
+                logical
+            );
+        }
+
+        for logical in 0..RomType::Rom2364.size_bytes() {
+            let phys = phys_address(&hw, &RomType::Rom2364, logical);
+            assert_eq!(
+                multi.get_byte(phys | x1 | x2, &hw).unwrap(),
+                single_2364.get_byte(phys, &hw).unwrap(),
+                "2364 mismatch at logical address 0x{:04X}",
+                logical
+            );
+        }
+    }
+
+    #[test]
+    fn test_demangle_rom() {
+        let hw = hw_rev_f();
+        let data_map = hw.get_phys_pin_to_data_map();
+        for byte in 0..=255 {
+            let transformed = RomImage::transform_byte(byte, &data_map);
+            assert_eq!(RomImage::untransform_byte(transformed, &data_map), byte);
+        }
+
+        let single = RomSet {
+            id: 0,
+            roms: vec![patterned_rom_in_set(RomType::Rom2364, CsLogic::ActiveLow, 0)],
+            is_banked: false,
+        };
+        let multi = RomSet {
+            id: 1,
+            roms: vec![
+                patterned_rom_in_set(RomType::Rom2364, CsLogic::ActiveHigh, 0),
+                patterned_rom_in_set(RomType::Rom2332, CsLogic::ActiveHigh, 1),
+                patterned_rom_in_set(RomType::Rom2316, CsLogic::ActiveHigh, 2),
+            ],
+            is_banked: false,
+        };
+        let banked = RomSet {
+            id: 2,
+            roms: (0..4)
+                .map(|index| patterned_rom_in_set(RomType::Rom2364, CsLogic::ActiveLow, index))
+                .collect(),
+            is_banked: true,
+        };
+        for set in [single, multi, banked] {
+            for (index, rom) in set.roms.iter().enumerate() {
+                assert_eq!(
+                    set.demangle_rom(index, &hw).unwrap(),
+                    rom.image.data,
+                    "Set {} ROM {}",
+                    set.id,
+                    index
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_mask_cs_selection_bits() {
+        let hw = hw_rev_f();
+        let set = two_rom_set(CsLogic::ActiveLow);
+
+        // All 16 bits set - only the pins used for each ROM type's address
+        // lines should remain
+        for rom_type in [RomType::Rom2316, RomType::Rom2332, RomType::Rom2364] {
+            let expected = phys_address(&hw, &rom_type, rom_type.size_bytes() - 1);
+            assert_eq!(set.mask_cs_selection_bits(0xFFFF, &rom_type, &hw), expected);
+        }
+
+        // 23128 images use 14 address lines, with CE/OE on pins 15/14
+        let hw = HwConfig::new(include_str!("../../../sdrr-hw-config/28-a.json"), "28-a").unwrap();
+        let masked = set.mask_cs_selection_bits(0xFFFF, &RomType::Rom23128, &hw);
+        assert_eq!(masked, 0x3FFF);
+    }
+
+    #[test]
+    fn test_get_byte_out_of_bounds() {
+        let hw = hw_rev_f();
+        let data_map = hw.get_phys_pin_to_data_map();
+
+        // A 2364 pin map used with a 2332-sized image
+        let set = RomSet {
+            id: 0,
+            roms: vec![rom_in_set(RomType::Rom2332, CsLogic::ActiveLow, 0, 0)],
+            is_banked: false,
+        };
+        let addr_map = hw.get_phys_pin_to_addr_map(RomType::Rom2364.num_addr_lines());
+        let phys = phys_address(&hw, &RomType::Rom2364, 0x1001);
+
+        let err = set.roms[0].image.get_byte(phys, &addr_map, &data_map).unwrap_err();
+        assert_eq!(
+            err,
+            PreprocessError::AddressOutOfBounds {
+                address: 0x1001,
+                size: 4096,
+                rom_type: None,
+            }
+        );
+        assert_eq!(err.to_string(), "address 0x1001 out of bounds for 4096-byte ROM image");
+
+        let err = err.with_rom_type(&RomType::Rom2332);
+        assert_eq!(err.to_string(), "address 0x1001 out of bounds for 4096-byte 2332");
+
+        // An address beyond the set's image
+        let err = set.get_byte(0x4000, &hw).unwrap_err();
+        assert_eq!(err.to_string(), "address 0x4000 out of bounds for 16384-byte ROM image");
+
+        // More ROMs than there are CS1/X1/X2 lines to select them
+        let set = RomSet {
+            id: 0,
+            roms: (0..4)
+                .map(|index| rom_in_set(RomType::Rom2364, CsLogic::ActiveLow, 0, index))
+                .collect(),
+            is_banked: false,
+        };
+        let cs1 = 1 << hw.pin_cs1(&RomType::Rom2364);
+        let address = cs1 | (1 << hw.pin_x1()) | (1 << hw.pin_x2());
+        let err = set.get_byte(address, &hw).unwrap_err();
+        assert_eq!(
+            err,
+            PreprocessError::InvalidPin {
+                name: "ROM chip select",
+                pin: 255
+            }
+        );
+    }
+
+    #[test]
+    fn test_from_bytes() {
+        let rom_type = RomType::Rom2364;
+        let from_bytes = |data: Vec<u8>, size_handling: SizeHandling| {
+            RomImage::from_bytes(data, "test.bin", &rom_type, &size_handling, false)
+        };
+        let image = from_bytes(vec![0x55; 8192], SizeHandling::None).unwrap();
+        assert_eq!(image.data, vec![0x55; 8192]);
+
+        let data: Vec<u8> = (0..4096).map(|ii| ii as u8).collect();
+        let image = from_bytes(data.clone(), SizeHandling::Duplicate).unwrap();
+        assert_eq!(image.data, [data.as_slice(), data.as_slice()].concat());
+
+        let image = from_bytes(vec![0x55; 3000], SizeHandling::Pad).unwrap();
+        assert_eq!(image.data.len(), 8192);
+        assert_eq!(image.data[2999], 0x55);
+        assert_eq!(image.data[3000], 0xAA);
+
+        assert!(from_bytes(vec![0; 4096], SizeHandling::None).is_err());
+    }
+
+    #[test]
+    fn test_check_contents() {
+        let erased = RomImage::check_contents(&[0xFF; 8192]).unwrap();
+        assert!(erased.contains("all 0xFF"), "{}", erased);
+        let failed = RomImage::check_contents(&[0x00; 8192]).unwrap();
+        assert!(failed.contains("all 0x00"), "{}", failed);
+        assert_eq!(RomImage::check_contents(&[0x55; 16]).unwrap(), "is all 0x55");
+        let low = RomImage::check_contents(&[0x00, 0xFF, 0x00, 0x18]).unwrap();
+        assert!(low.contains("only 3 distinct"), "{}", low);
+
+        let data: Vec<u8> = (0..8192).map(|ii| (ii * 7) as u8).collect();
+        assert_eq!(RomImage::check_contents(&data), None);
+        assert_eq!(RomImage::check_contents(&[]), None);
+
+        // Only an error when strict
+        let rom_type = RomType::Rom2364;
+        let erased = vec![0xFF; 8192];
+        assert!(RomImage::from_bytes(erased.clone(), "a.bin", &rom_type, &SizeHandling::None, false)
+            .is_ok());
+        let err = RomImage::from_bytes(erased, "a.bin", &rom_type, &SizeHandling::None, true)
+            .unwrap_err()
+            .to_string();
+        assert!(err.starts_with("ROM image a.bin is all 0xFF"), "{}", err);
+    }
+
+    #[test]
+    fn test_check_size() {
+        let rom_type = RomType::Rom2364;
+        assert!(RomImage::check_size(8192, &rom_type, &SizeHandling::None).is_ok());
+        assert!(RomImage::check_size(8192, &rom_type, &SizeHandling::Pad).is_err());
+        assert!(RomImage::check_size(4096, &rom_type, &SizeHandling::None).is_err());
+        assert!(RomImage::check_size(4096, &rom_type, &SizeHandling::Duplicate).is_ok());
+        assert!(RomImage::check_size(3000, &rom_type, &SizeHandling::Duplicate).is_err());
+        assert!(RomImage::check_size(0, &rom_type, &SizeHandling::Duplicate).is_err());
+        assert!(RomImage::check_size(3000, &rom_type, &SizeHandling::Pad).is_ok());
+        assert!(RomImage::check_size(8193, &rom_type, &SizeHandling::Pad).is_err());
     }
 }
@@ -2,8 +2,10 @@
 //
 // MIT License
 
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
+use sdrr_fw_parser::STM32F4_FLASH_BASE;
 use std::path::PathBuf;
+use std::time::Duration;
 
 #[derive(Debug)]
 #[allow(dead_code)]
@@ -12,6 +14,7 @@ pub struct Args {
     pub firmware: PathBuf,
     pub detail: bool,
     pub set: Option<u8>,
+    pub sets: Option<SetSelection>,
     pub addr: Option<u32>,
     pub range: Option<(u32, u32)>,
     pub cs1: Option<bool>,
@@ -21,13 +24,125 @@ pub struct Args {
     pub x2: Option<bool>,
     pub output_mangled: Option<bool>,
     pub output_binary: Option<bool>,
+    pub ascii: bool,
+    pub wrap: usize,
+    pub addr_format: AddrFormat,
+    pub progress: bool,
+    pub csv: bool,
+    pub sort: RomSort,
+    pub only: Option<InfoField>,
+    pub info_format: InfoFormat,
+    pub original: Option<PathBuf>,
+    pub rom: Option<u8>,
+    pub selected: bool,
+    pub pattern: Option<Vec<u8>>,
+    pub mask: Option<Vec<u8>>,
+    pub output: Option<PathBuf>,
+    pub format: ExtractFormat,
+    pub hex_base: u32,
+    pub base_address: u32,
+    pub images: Option<PathBuf>,
+    pub probe: Option<String>,
+    pub interval: Option<Duration>,
+    pub json: bool,
+    pub explain: bool,
+}
+
+/// Default number of bytes per hex dump row
+pub const HEX_DUMP_WRAP: usize = 16;
+
+/// Firmware filename used to read the firmware from stdin
+pub const STDIN_FIRMWARE: &str = "-";
+
+/// File format for the extract command
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum ExtractFormat {
+    /// Raw binary image
+    Bin,
+    /// Intel HEX records
+    Hex,
+}
+
+/// Output format for the info command
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum InfoFormat {
+    /// Plain text, for reading in a terminal
+    Text,
+    /// Markdown tables, for documenting builds in wikis and PRs
+    Markdown,
+}
+
+/// Format to output addresses in, for the lookup commands
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum AddrFormat {
+    /// Hex, with a 0x prefix, e.g. 0x1000
+    Hex,
+    /// Decimal, e.g. 4096
+    Dec,
+    /// Hex, with a $ prefix, e.g. $1000
+    Dollar,
+}
+
+/// Order to list ROMs in, for the info command
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum RomSort {
+    /// ROM set and ROM index order, as stored in the firmware
+    Set,
+    /// ROM filename
+    Name,
+    /// ROM type
+    Type,
+}
+
+/// Single firmware property to output, for the info command
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+#[value(rename_all = "snake_case")]
+pub enum InfoField {
+    /// Firmware version, e.g. 0.5.0
+    Version,
+    /// Firmware build number
+    BuildNumber,
+    /// Firmware build date
+    BuildDate,
+    /// Git commit the firmware was built from
+    Commit,
+    /// Hardware revision, e.g. 24-f
+    HwRev,
+    /// STM32 variant, e.g. F411RE
+    Stm,
+    /// Target frequency in MHz
+    Freq,
+    /// Number of ROM sets
+    RomSets,
+    /// Number of ROMs
+    Roms,
+}
+
+/// ROM sets selected by a repeatable `--set` argument
+#[derive(Debug, Clone, PartialEq)]
+pub enum SetSelection {
+    All,
+    Sets(Vec<u8>),
+}
+
+// A single `--set` value - either a set number or "all"
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SetArg {
+    All,
+    Set(u8),
 }
 
 #[derive(Debug, PartialEq)]
 pub enum Command {
     Info,
+    List,
+    Pins,
     LookupRaw,
     Lookup,
+    Verify,
+    Search,
+    Extract,
+    Watch,
 }
 
 #[derive(Parser)]
@@ -39,6 +154,17 @@ struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
     firmware: Option<PathBuf>,
+    /// Address the firmware is linked to run from (in hex), for relocated
+    /// or custom-linked builds.  RP2350 firmware is always parsed at its
+    /// own flash base.
+    #[arg(long, global = true, value_parser = parse_hex, default_value = "0x08000000",
+        verbatim_doc_comment)]
+    base_address: u32,
+    /// Images file, from sdrr-gen --emit-images, containing ROM set data
+    /// which isn't in the firmware itself, for firmware flashed
+    /// separately from its ROM images
+    #[arg(long, global = true, value_name = "FILE", verbatim_doc_comment)]
+    images: Option<PathBuf>,
 }
 
 #[derive(Subcommand)]
@@ -50,11 +176,47 @@ enum Commands {
     /// - Embedded ROM image information
     #[command(verbatim_doc_comment)]
     Info {
-        /// Firmware filename (.bin or .elf files supported)
+        /// Firmware filename (.bin or .elf files, optionally gzipped), or - for stdin
         firmware: PathBuf,
         /// Provide detailed information about the ROMs
         #[arg(short, long, default_value = "false")]
         detail: bool,
+        /// Output the ROM table as CSV, one row per ROM, for importing
+        /// into a spreadsheet
+        #[arg(long, default_value = "false", conflicts_with = "detail", verbatim_doc_comment)]
+        csv: bool,
+        /// Order to list the ROMs in, with --detail or --csv
+        #[arg(long, value_enum, default_value_t = RomSort::Set)]
+        sort: RomSort,
+        /// Output only this property's value, without the header, for
+        /// use in scripts
+        #[arg(long, value_enum, conflicts_with_all = ["detail", "csv"], verbatim_doc_comment)]
+        only: Option<InfoField>,
+        /// Output format.  markdown renders the properties and each ROM
+        /// set as Markdown tables, for documenting builds
+        #[arg(long, value_enum, default_value_t = InfoFormat::Text,
+            conflicts_with_all = ["detail", "csv", "only"], verbatim_doc_comment)]
+        format: InfoFormat,
+    },
+    /// Lists the ROMs in the firmware, one per line, as
+    /// set/index: filename (type).  This is quicker than info, as the
+    /// ROM data is not read.
+    #[command(verbatim_doc_comment)]
+    List {
+        /// Firmware filename (.bin or .elf files, optionally gzipped), or - for stdin
+        firmware: PathBuf,
+    },
+    /// Outputs the firmware's pin configuration - which MCU port pins
+    /// the ROM's data, address, chip select and image select lines are
+    /// expected to be connected to.
+    #[command(verbatim_doc_comment)]
+    Pins {
+        /// Firmware filename (.bin or .elf files, optionally gzipped), or - for stdin
+        firmware: PathBuf,
+        /// Output the pin configuration as JSON, for checking a board's
+        /// wiring programmatically
+        #[arg(long, default_value = "false", verbatim_doc_comment)]
+        json: bool,
     },
     /// Lookup a byte associated with a raw STM32F4 address port line
     /// configuration.  Use this to detect what byte the STM32F4 will
@@ -77,7 +239,7 @@ enum Commands {
     /// SDRR 24-pin hardware revisions).
     #[command(verbatim_doc_comment)]
     LookupRaw {
-        /// Firmware filename (.bin or .elf files supported)
+        /// Firmware filename (.bin or .elf files, optionally gzipped), or - for stdin
         firmware: PathBuf,
         /// Provide detailed information about the address lookup
         #[arg(short, long, default_value = "false")]
@@ -100,6 +262,18 @@ enum Commands {
         /// (default: false = text output)
         #[arg(long, default_value = "false", verbatim_doc_comment)]
         output_binary: bool,
+        /// Add an ASCII column to the hex dump output.  Only valid
+        /// when --range is used
+        #[arg(long, default_value = "false", verbatim_doc_comment)]
+        ascii: bool,
+        /// Bytes per hex dump row, a multiple of 4 (default: 16).
+        /// Only valid when --range is used
+        #[arg(long, value_name = "N", value_parser = parse_wrap, verbatim_doc_comment)]
+        wrap: Option<usize>,
+        /// Format to output addresses in: hex (0x1000), dec (4096)
+        /// or dollar ($1000)
+        #[arg(long, value_enum, default_value_t = AddrFormat::Hex, verbatim_doc_comment)]
+        addr_format: AddrFormat,
     },
     /// Lookup a byte associated with an actual address lookup on the
     /// address bus, using a non-mangled address.  Use this to detect
@@ -122,14 +296,15 @@ enum Commands {
     /// nor does it check against the original image.
     #[command(verbatim_doc_comment)]
     Lookup {
-        /// Firmware filename (.bin or .elf files supported)
+        /// Firmware filename (.bin or .elf files, optionally gzipped), or - for stdin
         firmware: PathBuf,
         /// Provide detailed information about the address lookup
         #[arg(short, long, default_value = "false")]
         detail: bool,
-        /// ROM set number (starts from 0)
-        #[arg(short, long, default_value = "0")]
-        set: u8,
+        /// ROM set number (starts from 0), or "all".  May be repeated
+        /// to look up multiple sets in turn
+        #[arg(short, long, default_value = "0", value_parser = parse_set, verbatim_doc_comment)]
+        set: Vec<SetArg>,
         /// Address to look up (in hex, e.g., 0x1000 or $1000)
         #[arg(short, long, value_parser = parse_hex)]
         addr: Option<u32>,
@@ -151,6 +326,19 @@ enum Commands {
         /// X2 line state (0 or 1) - valid for multi-ROM/bank switched sets only
         #[arg(long, value_parser = parse_cs_line)]
         x2: Option<u8>,
+        /// Use the CS line states which select the ROM, based on the CS
+        /// configuration stored in the firmware, instead of --cs1 etc
+        #[arg(
+            long,
+            default_value = "false",
+            conflicts_with_all = ["cs1", "cs2", "cs3", "x1", "x2"],
+            verbatim_doc_comment
+        )]
+        selected: bool,
+        /// ROM number within the set to select (starts from 0) - only
+        /// valid with --selected, for multi-ROM and bank switched sets
+        #[arg(long, requires = "selected", verbatim_doc_comment)]
+        rom: Option<u8>,
         /// Output mangled data byte(s)
         /// (not specifying this outputs a demangled byte)
         #[arg(long, default_value = "false", verbatim_doc_comment)]
@@ -160,7 +348,153 @@ enum Commands {
         /// (default: false = text output)
         #[arg(long, default_value = "false", verbatim_doc_comment)]
         output_binary: bool,
+        /// Add an ASCII column to the hex dump output.  Only valid
+        /// when --range is used
+        #[arg(long, default_value = "false", verbatim_doc_comment)]
+        ascii: bool,
+        /// Bytes per hex dump row, a multiple of 4 (default: 16).
+        /// Only valid when --range is used
+        #[arg(long, value_name = "N", value_parser = parse_wrap, verbatim_doc_comment)]
+        wrap: Option<usize>,
+        /// Format to output addresses in: hex (0x1000), dec (4096)
+        /// or dollar ($1000)
+        #[arg(long, value_enum, default_value_t = AddrFormat::Hex, verbatim_doc_comment)]
+        addr_format: AddrFormat,
+        /// Show progress on stderr while reading a --range.  Only
+        /// shown when stderr is a terminal
+        #[arg(long, default_value = "false", verbatim_doc_comment)]
+        progress: bool,
+        /// Explain each step of the lookup: how the address lines
+        /// and CS lines map to pins, the mangled address, and how the
+        /// stored byte is demangled.  Only valid with --addr
+        #[arg(long, default_value = "false", requires = "addr", verbatim_doc_comment)]
+        explain: bool,
+    },
+    /// Verify that a ROM stored in the firmware serves exactly the same
+    /// bytes as the original ROM image file.
+    ///
+    /// Every logical address of the ROM is mangled using the CS
+    /// configuration stored in the firmware, the stored byte is read and
+    /// demangled, and the result compared to the original file.  This is
+    /// an end-to-end check of the sdrr-gen mangling process.
+    ///
+    /// Exits with a non-zero status if any byte differs.
+    #[command(verbatim_doc_comment)]
+    Verify {
+        /// Firmware filename (.bin or .elf files, optionally gzipped), or - for stdin
+        firmware: PathBuf,
+        /// ROM set number (starts from 0)
+        #[arg(short, long, default_value = "0")]
+        set: u8,
+        /// ROM number within the set (starts from 0) - only needed for
+        /// multi-ROM and bank switched sets
+        #[arg(long, default_value = "0", verbatim_doc_comment)]
+        rom: u8,
+        /// Original ROM image file to compare against
+        #[arg(short, long)]
+        original: PathBuf,
     },
+    /// Search a ROM's logical (demangled) image for a byte pattern or
+    /// text string, and output every matching logical address.
+    ///
+    /// An optional mask allows wildcard bytes (or bits) - a pattern
+    /// byte matches where (byte & mask) == (pattern & mask), so a mask
+    /// byte of 00 matches any byte.
+    #[command(verbatim_doc_comment)]
+    Search {
+        /// Firmware filename (.bin or .elf files, optionally gzipped), or - for stdin
+        firmware: PathBuf,
+        /// ROM set number (starts from 0)
+        #[arg(short, long, default_value = "0")]
+        set: u8,
+        /// ROM number within the set (starts from 0) - only needed for
+        /// multi-ROM and bank switched sets
+        #[arg(long, default_value = "0", verbatim_doc_comment)]
+        rom: u8,
+        /// Byte pattern to search for (in hex, e.g., DEADBEEF)
+        #[arg(short, long, value_parser = parse_hex_bytes, conflicts_with = "text")]
+        pattern: Option<HexBytes>,
+        /// Text string to search for (e.g., "COPYRIGHT")
+        #[arg(short, long)]
+        text: Option<String>,
+        /// Mask to apply to the pattern (in hex, same length as the
+        /// pattern, e.g., FF00FFFF)
+        #[arg(short, long, value_parser = parse_hex_bytes, verbatim_doc_comment)]
+        mask: Option<HexBytes>,
+    },
+    /// Extract a ROM's logical (demangled) image from the firmware to a
+    /// file, as it would be read from the ROM socket.  Use this to
+    /// program an EPROM with the same image as SDRR serves.
+    ///
+    /// The image can be written as raw binary (the default) or as
+    /// Intel HEX, as required by some EPROM programmers.
+    #[command(verbatim_doc_comment)]
+    Extract {
+        /// Firmware filename (.bin or .elf files, optionally gzipped), or - for stdin
+        firmware: PathBuf,
+        /// ROM set number (starts from 0)
+        #[arg(short, long, default_value = "0")]
+        set: u8,
+        /// ROM number within the set (starts from 0) - only needed for
+        /// multi-ROM and bank switched sets
+        #[arg(long, default_value = "0", verbatim_doc_comment)]
+        rom: u8,
+        /// Output filename
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Output format
+        #[arg(short, long, value_enum, default_value = "bin")]
+        format: ExtractFormat,
+        /// Address of the first byte of the image in the Intel HEX
+        /// records (in hex, e.g., 0x8000).  Only valid with --format hex
+        #[arg(long, value_parser = parse_hex, verbatim_doc_comment)]
+        hex_base: Option<u32>,
+    },
+    /// Repeatedly read a running device's runtime info via a debug probe,
+    /// printing the selected image, the ROM set being served, and how many
+    /// times the ROM was accessed in each interval.  Use this to check the
+    /// device is being read by its host.
+    ///
+    /// The firmware is read from the device, and must have been built with
+    /// ROM access counting.  Requires sdrr-info to be built with the probe
+    /// feature.
+    #[command(verbatim_doc_comment)]
+    Watch {
+        /// probe-rs chip name of the device's MCU (e.g., STM32F446RETx)
+        #[arg(short, long, value_name = "CHIP")]
+        probe: String,
+        /// Interval between reads (e.g., 500ms or 2s)
+        #[arg(short, long, default_value = "500ms", value_parser = parse_interval)]
+        interval: Duration,
+    },
+}
+
+// A string of hex bytes, e.g. DEADBEEF.  A newtype so clap treats it as a
+// single value, rather than multiple u8 values.
+#[derive(Debug, Clone)]
+struct HexBytes(Vec<u8>);
+
+fn parse_hex_bytes(s: &str) -> Result<HexBytes, String> {
+    let cleaned: String = s
+        .strip_prefix("0x")
+        .or_else(|| s.strip_prefix("0X"))
+        .unwrap_or(s)
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+
+    if cleaned.is_empty() || !cleaned.len().is_multiple_of(2) {
+        return Err(format!("Hex bytes must be an even number of hex digits: {}", s));
+    }
+
+    (0..cleaned.len())
+        .step_by(2)
+        .map(|ii| {
+            u8::from_str_radix(&cleaned[ii..ii + 2], 16)
+                .map_err(|_| format!("Invalid hex bytes: {}", s))
+        })
+        .collect::<Result<Vec<u8>, String>>()
+        .map(HexBytes)
 }
 
 fn parse_hex(s: &str) -> Result<u32, String> {
@@ -175,6 +509,22 @@ fn parse_hex(s: &str) -> Result<u32, String> {
     u32::from_str_radix(cleaned, 16).map_err(|_| format!("Invalid hex value: {}", s))
 }
 
+fn parse_set(s: &str) -> Result<SetArg, String> {
+    if s.eq_ignore_ascii_case("all") {
+        return Ok(SetArg::All);
+    }
+    s.parse::<u8>()
+        .map(SetArg::Set)
+        .map_err(|_| format!("Invalid ROM set: {} (must be a number or \"all\")", s))
+}
+
+fn parse_wrap(s: &str) -> Result<usize, String> {
+    match s.parse::<usize>() {
+        Ok(wrap) if wrap > 0 && wrap.is_multiple_of(4) => Ok(wrap),
+        _ => Err(format!("Invalid wrap: {} (must be a non-zero multiple of 4)", s)),
+    }
+}
+
 fn parse_cs_line(s: &str) -> Result<u8, String> {
     match s {
         "0" => Ok(0),
@@ -183,6 +533,22 @@ fn parse_cs_line(s: &str) -> Result<u8, String> {
     }
 }
 
+fn parse_interval(s: &str) -> Result<Duration, String> {
+    let (value, scale) = if let Some(ms) = s.strip_suffix("ms") {
+        (ms, 1)
+    } else if let Some(secs) = s.strip_suffix('s') {
+        (secs, 1000)
+    } else {
+        (s, 1)
+    };
+
+    match value.trim().parse::<u64>().map(|value| value.checked_mul(scale)) {
+        Ok(Some(ms)) if ms > 0 => Ok(Duration::from_millis(ms)),
+        Ok(None) => Err(format!("Interval is too long: {}", s)),
+        _ => Err("Interval must be a non-zero number of ms or s (e.g., 500ms or 2s)".to_string()),
+    }
+}
+
 fn parse_range(s: &str) -> Result<(u32, u32), String> {
     let parts: Vec<&str> = s.split('-').collect();
     if parts.len() != 2 {
@@ -199,39 +565,102 @@ fn parse_range(s: &str) -> Result<(u32, u32), String> {
     Ok((start, end))
 }
 
+impl Args {
+    /// Whether the firmware is to be read from stdin, rather than a file
+    pub fn firmware_from_stdin(&self) -> bool {
+        self.firmware.as_os_str() == STDIN_FIRMWARE
+    }
+
+    fn new(command: Command, firmware: PathBuf) -> Self {
+        Self {
+            command,
+            firmware,
+            detail: false,
+            set: None,
+            sets: None,
+            addr: None,
+            range: None,
+            cs1: None,
+            cs2: None,
+            cs3: None,
+            x1: None,
+            x2: None,
+            output_mangled: None,
+            output_binary: None,
+            ascii: false,
+            wrap: HEX_DUMP_WRAP,
+            addr_format: AddrFormat::Hex,
+            progress: false,
+            csv: false,
+            sort: RomSort::Set,
+            only: None,
+            info_format: InfoFormat::Text,
+            original: None,
+            rom: None,
+            selected: false,
+            pattern: None,
+            mask: None,
+            output: None,
+            format: ExtractFormat::Bin,
+            hex_base: 0,
+            base_address: STM32F4_FLASH_BASE,
+            images: None,
+            probe: None,
+            interval: None,
+            json: false,
+            explain: false,
+        }
+    }
+}
+
+fn check_hex_dump_args(
+    ascii: bool,
+    wrap: bool,
+    range: bool,
+    output_binary: bool,
+) -> Result<(), String> {
+    for (name, set) in [("ascii", ascii), ("wrap", wrap)] {
+        if set && !range {
+            return Err(format!("--{} only valid when using --range", name));
+        }
+        if set && output_binary {
+            return Err(format!("--{} cannot be used with --output-binary", name));
+        }
+    }
+    Ok(())
+}
+
 pub fn parse_args() -> Result<Args, String> {
     let cli = Cli::parse();
 
-    let (
-        command,
-        firmware,
-        detail,
-        set,
-        addr,
-        range,
-        cs1,
-        cs2,
-        cs3,
-        x1,
-        x2,
-        output_mangled,
-        output_binary,
-    ) = match cli.command {
-        Some(Commands::Info { firmware, detail }) => (
-            Command::Info,
+    let mut args = match cli.command {
+        Some(Commands::Info {
             firmware,
             detail,
-            None,
-            None,
-            None,
-            None,
-            None,
-            None,
-            None,
-            None,
-            None,
-            None,
-        ),
+            csv,
+            sort,
+            only,
+            format,
+        }) => {
+            if sort != RomSort::Set && !detail && !csv {
+                return Err("--sort requires --detail or --csv".to_string());
+            }
+
+            Args {
+                detail,
+                csv,
+                sort,
+                only,
+                info_format: format,
+                ..Args::new(Command::Info, firmware)
+            }
+        }
+
+        Some(Commands::List { firmware }) => Args::new(Command::List, firmware),
+        Some(Commands::Pins { firmware, json }) => Args {
+            json,
+            ..Args::new(Command::Pins, firmware)
+        },
 
         Some(Commands::LookupRaw {
             firmware,
@@ -241,21 +670,25 @@ pub fn parse_args() -> Result<Args, String> {
             range,
             output_mangled,
             output_binary,
-        }) => (
-            Command::LookupRaw,
-            firmware,
-            detail,
-            Some(set),
-            addr,
-            range,
-            None,
-            None,
-            None,
-            None,
-            None,
-            Some(output_mangled),
-            Some(output_binary),
-        ),
+            ascii,
+            wrap,
+            addr_format,
+        }) => {
+            check_hex_dump_args(ascii, wrap.is_some(), range.is_some(), output_binary)?;
+
+            Args {
+                detail,
+                set: Some(set),
+                addr,
+                range,
+                output_mangled: Some(output_mangled),
+                output_binary: Some(output_binary),
+                ascii,
+                wrap: wrap.unwrap_or(HEX_DUMP_WRAP),
+                addr_format,
+                ..Args::new(Command::LookupRaw, firmware)
+            }
+        }
 
         Some(Commands::Lookup {
             firmware,
@@ -268,9 +701,17 @@ pub fn parse_args() -> Result<Args, String> {
             cs3,
             x1,
             x2,
+            selected,
+            rom,
             output_mangled,
             output_binary,
+            ascii,
+            wrap,
+            addr_format,
+            progress,
+            explain,
         }) => {
+            check_hex_dump_args(ascii, wrap.is_some(), range.is_some(), output_binary)?;
             if addr.is_some() && range.is_some() {
                 return Err("Cannot specify both --addr and --range".to_string());
             }
@@ -280,6 +721,9 @@ pub fn parse_args() -> Result<Args, String> {
             if range.is_none() && output_binary {
                 return Err("--output-binary only valid when using --range".to_string());
             }
+            if range.is_none() && progress {
+                return Err("--progress only valid when using --range".to_string());
+            }
             if output_binary {
                 if range.is_none() {
                     return Err("--output-binary requires --range to be specified".to_string());
@@ -289,40 +733,128 @@ pub fn parse_args() -> Result<Args, String> {
                 }
             }
 
-            (
-                Command::Lookup,
-                firmware,
+            let sets = if set.contains(&SetArg::All) {
+                if set.len() > 1 {
+                    return Err("Cannot combine --set all with other --set values".to_string());
+                }
+                SetSelection::All
+            } else {
+                SetSelection::Sets(
+                    set.iter()
+                        .filter_map(|s| match s {
+                            SetArg::Set(set) => Some(*set),
+                            SetArg::All => None,
+                        })
+                        .collect(),
+                )
+            };
+
+            Args {
                 detail,
-                Some(set),
+                sets: Some(sets),
                 addr,
                 range,
-                Some(cs1),
-                cs2,
-                cs3,
-                x1,
-                x2,
-                Some(output_mangled),
-                Some(output_binary),
-            )
+                cs1: Some(cs1 != 0),
+                cs2: cs2.map(|c| c != 0),
+                cs3: cs3.map(|c| c != 0),
+                x1: x1.map(|c| c != 0),
+                x2: x2.map(|c| c != 0),
+                selected,
+                rom: Some(rom.unwrap_or(0)),
+                output_mangled: Some(output_mangled),
+                output_binary: Some(output_binary),
+                ascii,
+                wrap: wrap.unwrap_or(HEX_DUMP_WRAP),
+                addr_format,
+                progress,
+                explain,
+                ..Args::new(Command::Lookup, firmware)
+            }
+        }
+
+        Some(Commands::Verify {
+            firmware,
+            set,
+            rom,
+            original,
+        }) => {
+            if !original.exists() {
+                return Err(format!(
+                    "Original ROM file does not exist: {}",
+                    original.display()
+                ));
+            }
+
+            Args {
+                set: Some(set),
+                rom: Some(rom),
+                original: Some(original),
+                ..Args::new(Command::Verify, firmware)
+            }
+        }
+
+        Some(Commands::Search {
+            firmware,
+            set,
+            rom,
+            pattern,
+            text,
+            mask,
+        }) => {
+            let mask = mask.map(|mask| mask.0);
+            let pattern = match (pattern, text) {
+                (Some(pattern), None) => pattern.0,
+                (None, Some(text)) if !text.is_empty() => text.into_bytes(),
+                _ => return Err("Must specify either --pattern or --text".to_string()),
+            };
+            if mask.as_ref().is_some_and(|mask| mask.len() != pattern.len()) {
+                return Err(format!(
+                    "--mask must be the same length as the pattern ({} bytes)",
+                    pattern.len()
+                ));
+            }
+
+            Args {
+                set: Some(set),
+                rom: Some(rom),
+                pattern: Some(pattern),
+                mask,
+                ..Args::new(Command::Search, firmware)
+            }
+        }
+
+        Some(Commands::Extract {
+            firmware,
+            set,
+            rom,
+            output,
+            format,
+            hex_base,
+        }) => {
+            if hex_base.is_some() && format != ExtractFormat::Hex {
+                return Err("--hex-base only valid with --format hex".to_string());
+            }
+
+            Args {
+                set: Some(set),
+                rom: Some(rom),
+                output: Some(output),
+                format,
+                hex_base: hex_base.unwrap_or(0),
+                ..Args::new(Command::Extract, firmware)
+            }
         }
 
+        // The firmware is read from the device, rather than a file
+        Some(Commands::Watch { probe, interval }) => Args {
+            probe: Some(probe),
+            interval: Some(interval),
+            ..Args::new(Command::Watch, PathBuf::new())
+        },
+
         _none => {
             if let Some(firmware) = cli.firmware {
-                (
-                    Command::Info,
-                    firmware,
-                    false,
-                    None,
-                    None,
-                    None,
-                    None,
-                    None,
-                    None,
-                    None,
-                    None,
-                    None,
-                    None,
-                )
+                Args::new(Command::Info, firmware)
             } else {
                 return Err(
                     "No firmware file specified. Use --help for usage information.".to_string(),
@@ -330,32 +862,46 @@ pub fn parse_args() -> Result<Args, String> {
             }
         }
     };
+    args.base_address = cli.base_address;
+    args.images = cli.images;
 
-    // Validate firmware file exists
-    if !firmware.exists() {
-        return Err(format!(
-            "Firmware file does not exist: {}",
-            firmware.display()
-        ));
-    }
+    // Validate firmware file exists and its extension.  There's no filename
+    // when reading from stdin, so the firmware type is detected from its
+    // contents alone.
+    if args.command != Command::Watch && !args.firmware_from_stdin() {
+        if !args.firmware.exists() {
+            return Err(format!(
+                "Firmware file does not exist: {}",
+                args.firmware.display()
+            ));
+        }
 
-    // Validate firmware file extension
-    if let Some(ext) = firmware.extension() {
-        let ext_str = ext.to_string_lossy().to_lowercase();
-        if ext_str != "bin" && ext_str != "elf" {
+        // Validate firmware file extension.  Gzipped firmware is decompressed
+        // when loaded, so check the extension underneath any .gz.
+        let mut firmware = args.firmware.clone();
+        if firmware
+            .extension()
+            .is_some_and(|ext| ext.to_string_lossy().to_lowercase() == "gz")
+        {
+            firmware.set_extension("");
+        }
+        if let Some(ext) = firmware.extension() {
+            let ext_str = ext.to_string_lossy().to_lowercase();
+            if ext_str != "bin" && ext_str != "elf" {
+                return Err("Firmware file must have .bin or .elf extension".to_string());
+            }
+        } else {
             return Err("Firmware file must have .bin or .elf extension".to_string());
         }
-    } else {
-        return Err("Firmware file must have .bin or .elf extension".to_string());
     }
 
     // Validate the address/range
-    if let Some(addr) = addr {
+    if let Some(addr) = args.addr {
         if addr > 0xFFFF {
             return Err("Address must be in the range 0x0000 to 0xFFFF".to_string());
         }
     }
-    if let Some(range) = range {
+    if let Some(range) = args.range {
         if range.0 > 0xFFFF || range.1 > 0xFFFF {
             return Err(format!(
                 "Range must be in the range 0x0000 to 0xFFFF ({:#X}-{:#X})",
@@ -364,19 +910,23 @@ pub fn parse_args() -> Result<Args, String> {
         }
     }
 
-    Ok(Args {
-        command,
-        firmware,
-        detail,
-        set,
-        addr,
-        range,
-        cs1: cs1.map(|c| c != 0),
-        cs2: cs2.map(|c| c != 0),
-        cs3: cs3.map(|c| c != 0),
-        x1: x1.map(|c| c != 0),
-        x2: x2.map(|c| c != 0),
-        output_mangled,
-        output_binary,
-    })
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_interval() {
+        assert_eq!(parse_interval("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_interval("2s"), Ok(Duration::from_secs(2)));
+        assert_eq!(parse_interval("250"), Ok(Duration::from_millis(250)));
+        assert!(parse_interval("0s").is_err());
+        assert!(parse_interval("fast").is_err());
+        assert_eq!(
+            parse_interval("99999999999999999s"),
+            Err("Interval is too long: 99999999999999999s".to_string())
+        );
+    }
 }
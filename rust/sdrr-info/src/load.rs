@@ -2,128 +2,473 @@
 //
 // MIT License
 
-use anyhow::Result;
-use goblin::elf::Elf;
+use anyhow::{Context, Result};
+use flate2::read::GzDecoder;
 use std::fs;
+use std::io::Read;
 use std::path::Path;
 
+use crate::args::STDIN_FIRMWARE;
 use crate::{FileType, FirmwareData};
-use crate::{SDRR_INFO_OFFSET, STM32F4_FLASH_BASE};
-use sdrr_fw_parser::{Parser, readers::MemoryReader};
+use sdrr_common::images::parse_images_index;
+use sdrr_fw_parser::{
+    McuLine, Parser, RP2350_FLASH_BASE, Reader, SDRR_INFO_FW_OFFSET, STM32F4_FLASH_BASE,
+    STM32F4_RAM_BASE, SdrrInfo,
+    readers::{ElfReader, MemoryReader},
+};
 
-pub async fn load_sdrr_firmware<P: AsRef<Path>>(path: P) -> Result<FirmwareData> {
-    let firmware_data = fs::read(path)?;
+// SDRR info structure offset in firmware binary
+const SDRR_INFO_OFFSET: usize = SDRR_INFO_FW_OFFSET as usize;
 
-    if firmware_data.len() >= 4 && &firmware_data[0..4] == b"\x7fELF" {
-        load_from_elf(firmware_data).await
+// Loads firmware from the given file, or from stdin if the path is
+// STDIN_FIRMWARE.  Gzipped firmware is decompressed first.  ELF files are
+// detected by their magic number, anything else is treated as a raw binary.
+//
+// verify_checksum controls whether the ROM data is read to check its CRC32,
+// which callers that only need the ROM table can skip.  base_address is the
+// flash address the firmware is linked at.  images is an optional images
+// file, from sdrr-gen --emit-images, containing ROM set data which isn't in
+// the firmware itself.
+pub async fn load_sdrr_firmware<P: AsRef<Path>>(
+    path: P,
+    verify_checksum: bool,
+    base_address: u32,
+    images: Option<&Path>,
+) -> Result<FirmwareData> {
+    let images = images
+        .map(|path| {
+            fs::read(path).with_context(|| format!("Failed to read images file {}", path.display()))
+        })
+        .transpose()?;
+    let images = images.as_deref();
+
+    let firmware_data = if path.as_ref() == Path::new(STDIN_FIRMWARE) {
+        let mut data = Vec::new();
+        std::io::stdin().read_to_end(&mut data)?;
+        data
     } else {
-        load_from_binary(firmware_data).await
-    }
-}
+        fs::read(path)?
+    };
+    let firmware_data = gunzip_if_compressed(firmware_data)?;
+
+    let file_type = if firmware_data.len() >= 4 && &firmware_data[0..4] == b"\x7fELF" {
+        FileType::Elf
+    } else {
+        FileType::Orc
+    };
 
-async fn load_from_binary(firmware_data: Vec<u8>) -> Result<FirmwareData> {
     let file_size = firmware_data.len();
-    if file_size < SDRR_INFO_OFFSET + 48 {
+    if file_type == FileType::Orc && file_size < SDRR_INFO_OFFSET + 48 {
         return Err(anyhow::anyhow!("Firmware file too small"));
     }
 
-    // Create reader with the firmware data
-    let reader = MemoryReader::new(firmware_data, STM32F4_FLASH_BASE);
-    let mut parser = Parser::new(reader);
-
-    // Parse the firmware
-    let info = parser.parse_flash().await.map_err(|e| anyhow::anyhow!(e))?;
+    let (parser, info) =
+        parse_firmware(firmware_data, file_type, verify_checksum, base_address, images).await?;
 
     Ok(FirmwareData {
-        file_type: FileType::Orc,
+        file_type,
         file_size,
         parser,
         info,
     })
 }
 
-async fn load_from_elf(firmware_data: Vec<u8>) -> Result<FirmwareData> {
-    let elf = Elf::parse(&firmware_data)?;
+// Decompresses the firmware if it starts with the gzip magic number, otherwise
+// returns it unchanged.
+fn gunzip_if_compressed(data: Vec<u8>) -> Result<Vec<u8>> {
+    if !data.starts_with(&[0x1f, 0x8b]) {
+        return Ok(data);
+    }
 
-    // Find the sdrr_info symbol
-    let sdrr_symbol = elf
-        .syms
-        .iter()
-        .find(|sym| {
-            if let Some(name) = elf.strtab.get_at(sym.st_name) {
-                name == "sdrr_info"
-            } else {
-                false
+    let mut decompressed = Vec::new();
+    GzDecoder::new(data.as_slice())
+        .read_to_end(&mut decompressed)
+        .context("Failed to decompress gzipped firmware")?;
+    Ok(decompressed)
+}
+
+// The firmware, as read from a binary or ELF file
+#[derive(Debug)]
+enum Firmware {
+    Binary(MemoryReader),
+    Elf(ElfReader),
+}
+
+// Reads the firmware, from a binary or ELF file, with the data of any ROM
+// sets which aren't in the firmware itself added from an images file.
+#[derive(Debug)]
+pub struct FirmwareReader {
+    firmware: Firmware,
+    // (address, data) of each ROM set image added from an images file
+    images: Vec<(u32, Vec<u8>)>,
+    base_address: u32,
+}
+
+impl FirmwareReader {
+    // Creates a reader for the firmware, which is loaded at base_address if
+    // it is a binary.  ELF files contain their own addresses.
+    fn new(firmware_data: Vec<u8>, file_type: FileType, base_address: u32) -> Result<Self> {
+        let firmware = match file_type {
+            FileType::Elf => Firmware::Elf(ElfReader::from_bytes(firmware_data).map_err(|e| {
+                anyhow::anyhow!("Failed to load ELF firmware: {}", e)
+            })?),
+            FileType::Orc => {
+                if base_address as u64 + firmware_data.len() as u64 > 1 << 32 {
+                    return Err(anyhow::anyhow!(
+                        "Firmware of {} bytes at base address 0x{:08X} exceeds the address space",
+                        firmware_data.len(),
+                        base_address
+                    ));
+                }
+                Firmware::Binary(MemoryReader::new(firmware_data, base_address))
             }
+        };
+
+        Ok(Self {
+            firmware,
+            images: Vec::new(),
+            base_address,
         })
-        .ok_or_else(|| anyhow::anyhow!("sdrr_info symbol not found"))?;
-
-    // Get the section containing the symbol
-    let sdrr_section = &elf.section_headers[sdrr_symbol.st_shndx];
-
-    // Calculate file offset of the symbol within its section
-    let symbol_offset_in_section = sdrr_symbol.st_value - sdrr_section.sh_addr;
-    let symbol_file_offset = sdrr_section.sh_offset + symbol_offset_in_section;
-
-    // Extract symbol data
-    let sdrr_data = &firmware_data
-        [symbol_file_offset as usize..(symbol_file_offset + sdrr_symbol.st_size) as usize];
-
-    // Find .rodata section
-    let rodata_section = elf
-        .section_headers
-        .iter()
-        .find(|sh| {
-            if let Some(name) = elf.shdr_strtab.get_at(sh.sh_name) {
-                name == ".rodata"
+    }
+
+    async fn read_firmware(&mut self, addr: u32, buf: &mut [u8]) -> Result<(), String> {
+        match &mut self.firmware {
+            Firmware::Binary(reader) => reader.read(addr, buf).await,
+            Firmware::Elf(reader) => reader.read(addr, buf).await,
+        }
+    }
+}
+
+impl Reader for FirmwareReader {
+    type Error = String;
+
+    // Reads which span the start or end of an image are split between the
+    // image and the firmware
+    async fn read(&mut self, addr: u32, buf: &mut [u8]) -> Result<(), Self::Error> {
+        let mut done = 0;
+        while done < buf.len() {
+            let cur = addr
+                .checked_add(done as u32)
+                .ok_or_else(|| format!("Read at 0x{:08X} overflows the address space", addr))?;
+            let remaining = buf.len() - done;
+
+            let image = self
+                .images
+                .iter()
+                .find(|(start, data)| cur >= *start && ((cur - start) as usize) < data.len());
+            if let Some((start, data)) = image {
+                let offset = (cur - start) as usize;
+                let len = (data.len() - offset).min(remaining);
+                buf[done..done + len].copy_from_slice(&data[offset..offset + len]);
+                done += len;
             } else {
-                false
+                // Read from the firmware up to the start of the next image
+                let next = self
+                    .images
+                    .iter()
+                    .map(|(start, _)| *start)
+                    .filter(|&start| start > cur)
+                    .min();
+                let len = next.map_or(remaining, |next| ((next - cur) as usize).min(remaining));
+                self.read_firmware(cur, &mut buf[done..done + len]).await?;
+                done += len;
             }
-        })
-        .ok_or_else(|| anyhow::anyhow!("No .rodata section found"))?;
+        }
 
-    // Create synthetic binary
-    let synthetic_binary =
-        create_synthetic_binary_from_symbol(&firmware_data, sdrr_data, rodata_section)?;
+        Ok(())
+    }
 
-    // Create reader with synthetic binary
-    let reader = MemoryReader::new(synthetic_binary, STM32F4_FLASH_BASE);
-    let mut parser = Parser::new(reader);
+    fn update_base_address(&mut self, new_base: u32) {
+        self.base_address = new_base;
+        match &mut self.firmware {
+            Firmware::Binary(reader) => reader.update_base_address(new_base),
+            Firmware::Elf(reader) => reader.update_base_address(new_base),
+        }
+    }
 
-    // Parse the firmware
-    let info = parser.parse_flash().await.map_err(|e| anyhow::anyhow!(e))?;
+    // Includes any images added beyond the end of the firmware
+    fn firmware_len(&self) -> Option<u32> {
+        let len = match &self.firmware {
+            Firmware::Binary(reader) => reader.firmware_len(),
+            Firmware::Elf(reader) => reader.firmware_len(),
+        }?;
+        let images_end = self
+            .images
+            .iter()
+            .filter_map(|(start, data)| {
+                u32::try_from(data.len())
+                    .ok()
+                    .and_then(|len| start.checked_add(len))?
+                    .checked_sub(self.base_address)
+            })
+            .max()
+            .unwrap_or(0);
+        Some(len.max(images_end))
+    }
+}
 
-    // TODO: Consider if we should track that this was an ELF file somehow
+// Creates a parser for the firmware, with the flash base at base_address
+fn parser_at(
+    reader: FirmwareReader,
+    verify_checksum: bool,
+    base_address: u32,
+) -> Parser<FirmwareReader> {
+    Parser::with_base_flash_address(reader, base_address, STM32F4_RAM_BASE)
+        .verify_checksum(verify_checksum)
+}
 
-    Ok(FirmwareData {
-        file_type: FileType::Elf,
-        file_size: firmware_data.len(),
-        parser,
-        info,
-    })
+// Parses the firmware, with the flash base at base_address.  If an images
+// file is provided, the firmware is first parsed without verifying its
+// checksum, to find where the ROM set data should be, and the data missing
+// from the firmware is then added from the images file before parsing it for
+// real.
+async fn parse_firmware(
+    firmware_data: Vec<u8>,
+    file_type: FileType,
+    verify_checksum: bool,
+    base_address: u32,
+    images: Option<&[u8]>,
+) -> Result<(Parser<FirmwareReader>, SdrrInfo)> {
+    let images = match images {
+        Some(images) => {
+            let reader = FirmwareReader::new(firmware_data.clone(), file_type, base_address)?;
+            let mut parser = parser_at(reader, false, base_address);
+            let info = parser.parse_flash().await?;
+            missing_images(parser.reader_mut(), images, &info).await?
+        }
+        None => Vec::new(),
+    };
+
+    let mut reader = FirmwareReader::new(firmware_data, file_type, base_address)?;
+    reader.images = images;
+    let mut parser = parser_at(reader, verify_checksum, base_address);
+    let info = parser.parse_flash().await?;
+    Ok((parser, info))
 }
 
-fn create_synthetic_binary_from_symbol(
-    elf_data: &[u8],
-    sdrr_data: &[u8],
-    rodata_section: &goblin::elf::SectionHeader,
-) -> Result<Vec<u8>> {
-    let rodata_offset = (rodata_section.sh_addr - STM32F4_FLASH_BASE as u64) as usize;
+// Returns the (address, data) of the image of each ROM set whose data isn't
+// within the firmware, from the images file.  The images file is indexed in
+// the same order as the firmware's ROM sets.
+async fn missing_images(
+    reader: &mut FirmwareReader,
+    images: &[u8],
+    info: &SdrrInfo,
+) -> Result<Vec<(u32, Vec<u8>)>> {
+    let index = parse_images_index(images)?;
+    if index.len() != info.rom_sets.len() {
+        return Err(anyhow::anyhow!(
+            "Images file has {} ROM set(s), but the firmware has {}",
+            index.len(),
+            info.rom_sets.len()
+        ));
+    }
+
+    // Images can't be placed beyond the end of the MCU's flash
+    let flash_base = match info.stm_line {
+        McuLine::Rp2350 => RP2350_FLASH_BASE,
+        _ => STM32F4_FLASH_BASE,
+    };
+    let flash_end = flash_base as u64 + info.stm_storage.bytes() as u64;
+
+    let mut missing = Vec::new();
+    for (set, (rom_set, entry)) in info.rom_sets.iter().zip(index.iter()).enumerate() {
+        let (offset, size) = (entry.offset, entry.size);
+
+        // Check the size, which comes from the firmware, before allocating
+        // to read the data
+        if rom_set.data_ptr as u64 + rom_set.size as u64 > flash_end {
+            return Err(anyhow::anyhow!(
+                "ROM set {} data at 0x{:08X} ({} bytes) is beyond the end of the {}KB of flash at 0x{:08X}",
+                set,
+                rom_set.data_ptr,
+                rom_set.size,
+                info.stm_storage.kb(),
+                flash_base
+            ));
+        }
+        let mut data = vec![0u8; rom_set.size as usize];
+        if reader.read(rom_set.data_ptr, &mut data).await.is_ok() {
+            continue;
+        }
+        if size != rom_set.size as usize {
+            return Err(anyhow::anyhow!(
+                "ROM set {} is {} bytes in the images file, but {} bytes in the firmware",
+                set,
+                size,
+                rom_set.size
+            ));
+        }
+
+        missing.push((rom_set.data_ptr, images[offset..offset + size].to_vec()));
+    }
+
+    Ok(missing)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::tests::{info, rom, rom_set};
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use sdrr_fw_parser::writer::Writer;
+    use sdrr_fw_parser::{STM32F4_FLASH_BASE, SdrrRomType};
+    use std::io::Write;
+
+    #[test]
+    fn test_gunzip_if_compressed() {
+        let data: Vec<u8> = (0..=255).collect();
+        assert_eq!(gunzip_if_compressed(data.clone()).unwrap(), data);
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&data).unwrap();
+        let compressed = encoder.finish().unwrap();
+        assert_eq!(gunzip_if_compressed(compressed).unwrap(), data);
+
+        assert!(gunzip_if_compressed(vec![0x1f, 0x8b, 0x00]).is_err());
+    }
+
+    #[test]
+    fn test_firmware_reader_new() {
+        let new = |len, base| FirmwareReader::new(vec![0; len], FileType::Orc, base);
+        assert!(new(0x1000, 0x0800_0000).is_ok());
+        assert!(new(0x1000, 0xFFFF_F000).is_ok());
+        assert!(new(0x1001, 0xFFFF_F000).is_err());
+        assert!(FirmwareReader::new(vec![0; 0x100], FileType::Elf, 0x0800_0000).is_err());
+    }
+
+    // Returns firmware with a single 2364 ROM set, the ROM set's data
+    // pointer, the ROM data and an images file containing the ROM data
+    fn firmware_and_images() -> (Vec<u8>, u32, Vec<u8>, Vec<u8>) {
+        let mut info = info(vec![rom_set(vec![rom(SdrrRomType::Rom2364, None)])]);
+        let data_ptr = Writer::new(&info).metadata_end().unwrap().next_multiple_of(0x100);
+        info.rom_sets[0].data_ptr = data_ptr;
+        info.rom_sets[0].size = 16384;
+        let rom_data: Vec<u8> = (0..16384).map(|ii| (ii * 7) as u8).collect();
+        let firmware = Writer::new(&info).rom_set_data(0, &rom_data).write().unwrap();
 
-    let total_size = std::cmp::max(
-        SDRR_INFO_OFFSET + sdrr_data.len(),
-        rodata_offset + rodata_section.sh_size as usize,
-    );
+        let mut images = b"SDRI\x01\x01\x00\x00".to_vec();
+        images.extend_from_slice(&20u32.to_le_bytes());
+        images.extend_from_slice(&16384u32.to_le_bytes());
+        images.extend_from_slice(&[0, 1, 0, 0]);
+        images.extend_from_slice(&rom_data);
 
-    let mut synthetic = vec![0u8; total_size];
+        (firmware, data_ptr, rom_data, images)
+    }
+
+    // Builds a minimal little endian ELF32 file, with a single allocated
+    // section containing data at addr
+    fn elf_file(addr: u32, data: &[u8]) -> Vec<u8> {
+        const SHT_PROGBITS: u32 = 1;
+        const SHT_STRTAB: u32 = 3;
+        const SHF_ALLOC: u32 = 2;
+
+        let shstrtab = b"\0.text\0.shstrtab\0";
+        let strtab_offset = 52 + data.len() as u32;
+        let shoff = (strtab_offset + shstrtab.len() as u32).next_multiple_of(4);
+        let headers = [
+            [0; 10],
+            [1, SHT_PROGBITS, SHF_ALLOC, addr, 52, data.len() as u32, 0, 0, 4, 0],
+            [7, SHT_STRTAB, 0, 0, strtab_offset, shstrtab.len() as u32, 0, 0, 1, 0],
+        ];
+
+        let mut elf = b"\x7fELF\x01\x01\x01".to_vec();
+        elf.resize(16, 0);
+        // e_type (EXEC) and e_machine (ARM)
+        for half in [2u16, 40] {
+            elf.extend_from_slice(&half.to_le_bytes());
+        }
+        // e_version, e_entry, e_phoff, e_shoff and e_flags
+        for word in [1u32, 0, 0, shoff, 0] {
+            elf.extend_from_slice(&word.to_le_bytes());
+        }
+        // e_ehsize, e_phentsize, e_phnum, e_shentsize, e_shnum and e_shstrndx
+        for half in [52u16, 32, 0, 40, 3, 2] {
+            elf.extend_from_slice(&half.to_le_bytes());
+        }
+        elf.extend_from_slice(data);
+        elf.extend_from_slice(shstrtab);
+        elf.resize(shoff as usize, 0);
+        for word in headers.iter().flatten() {
+            elf.extend_from_slice(&word.to_le_bytes());
+        }
+        elf
+    }
+
+    #[test]
+    fn test_elf_firmware() {
+        let (firmware, data_ptr, rom_data, images) = firmware_and_images();
+        let parse = |elf: Vec<u8>, images: Option<&[u8]>| {
+            let base = STM32F4_FLASH_BASE;
+            smol::block_on(parse_firmware(elf, FileType::Elf, true, base, images)).unwrap()
+        };
+
+        let mut buf = [0u8; 16];
+        let (mut parser, mut parsed) = parse(elf_file(STM32F4_FLASH_BASE, &firmware), None);
+        smol::block_on(parsed.read_rom_set_data(&mut parser, 0, 0x3FF0, &mut buf)).unwrap();
+        assert_eq!(buf, rom_data[0x3FF0..]);
+
+        // An ELF file without its ROM data, which is in the images file
+        let firmware = &firmware[..(data_ptr - STM32F4_FLASH_BASE) as usize];
+        let (mut parser, mut parsed) = parse(elf_file(STM32F4_FLASH_BASE, firmware), Some(&images));
+        smol::block_on(parsed.read_rom_set_data(&mut parser, 0, 0x3FF0, &mut buf)).unwrap();
+        assert_eq!(buf, rom_data[0x3FF0..]);
+    }
 
-    // Place sdrr_info at expected offset
-    synthetic[SDRR_INFO_OFFSET..SDRR_INFO_OFFSET + sdrr_data.len()].copy_from_slice(sdrr_data);
+    #[test]
+    fn test_images_file() {
+        let (firmware, data_ptr, rom_data, mut images) = firmware_and_images();
 
-    // Place .rodata at its virtual address offset
-    let rodata_raw = &elf_data[rodata_section.sh_offset as usize
-        ..(rodata_section.sh_offset + rodata_section.sh_size) as usize];
-    synthetic[rodata_offset..rodata_offset + rodata_raw.len()].copy_from_slice(rodata_raw);
+        // Firmware flashed without its ROM data, which is in the images file
+        let firmware = firmware[..(data_ptr - STM32F4_FLASH_BASE) as usize].to_vec();
 
-    Ok(synthetic)
+        let parse = |images: Option<&[u8]>| {
+            let firmware = firmware.clone();
+            let base = STM32F4_FLASH_BASE;
+            smol::block_on(parse_firmware(firmware, FileType::Orc, false, base, images))
+        };
+
+        let (mut parser, mut parsed) = parse(Some(&images)).unwrap();
+        let mut buf = [0u8; 16];
+        smol::block_on(parsed.read_rom_set_data(&mut parser, 0, 0x3FF0, &mut buf)).unwrap();
+        assert_eq!(buf, rom_data[0x3FF0..]);
+
+        // Reads spanning the end of the firmware and the start of the image
+        let addr = data_ptr - 8;
+        smol::block_on(parser.reader_mut().read(addr, &mut buf)).unwrap();
+        assert_eq!(buf[..8], firmware[firmware.len() - 8..]);
+        assert_eq!(buf[8..], rom_data[..8]);
+
+        // Without the images file, the ROM data can't be read
+        let (mut parser, mut parsed) = parse(None).unwrap();
+        assert!(smol::block_on(parsed.read_rom_set_data(&mut parser, 0, 0, &mut buf)).is_err());
+
+        // ROM set data which would be beyond the end of the MCU's flash
+        let (mut parser, mut parsed) = parse(None).unwrap();
+        parsed.rom_sets[0].data_ptr = STM32F4_FLASH_BASE + parsed.stm_storage.bytes() - 0x100;
+        let err = smol::block_on(missing_images(parser.reader_mut(), &images, &parsed));
+        let err = err.unwrap_err().to_string();
+        assert!(err.contains("beyond the end of the 512KB of flash"), "{}", err);
+
+        // A corrupt ROM set size is rejected before the data is allocated
+        parsed.rom_sets[0].data_ptr = data_ptr;
+        parsed.rom_sets[0].size = u32::MAX;
+        let err = smol::block_on(missing_images(parser.reader_mut(), &images, &parsed));
+        assert!(err.unwrap_err().to_string().contains("beyond the end"));
+
+        // An image at the top of the address space doesn't overflow the
+        // firmware length
+        let base = STM32F4_FLASH_BASE;
+        let mut reader = FirmwareReader::new(firmware.clone(), FileType::Orc, base).unwrap();
+        reader.images = vec![(u32::MAX - 4, vec![0; 16])];
+        assert_eq!(reader.firmware_len(), Some(firmware.len() as u32));
+
+        // Images files which don't match the firmware
+        let err = |images: &[u8]| parse(Some(images)).err().unwrap().to_string();
+        assert!(err(&images[..100]).contains("truncated"));
+        images[5] = 2;
+        assert!(err(&images).contains("truncated"));
+        images[0] = b'X';
+        assert_eq!(err(&images), "Not an SDRR images file");
+    }
 }
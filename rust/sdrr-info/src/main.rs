@@ -41,17 +41,20 @@ use std::fs::metadata;
 use std::io::Write;
 use std::path::Path;
 
-use args::{Args, Command, parse_args};
-use load::load_sdrr_firmware;
-use sdrr_fw_parser::{Parser, readers::MemoryReader};
-use sdrr_fw_parser::{SdrrAddress, SdrrCsSet, SdrrInfo, SdrrServe, SdrrMcuPort, McuLine};
-use utils::add_commas;
-
-// SDRR info structure offset in firmware binary
-pub const SDRR_INFO_OFFSET: usize = 0x200;
-
-// STM32F4 flash base address
-pub const STM32F4_FLASH_BASE: u32 = 0x08000000;
+use args::{
+    AddrFormat, Args, Command, ExtractFormat, InfoFormat, RomSort, SetSelection, parse_args,
+};
+use load::{FirmwareReader, load_sdrr_firmware};
+use sdrr_fw_parser::Parser;
+use sdrr_fw_parser::{
+    SdrrAddress, SdrrCsSet, SdrrCsState, SdrrInfo, SdrrLogicalAddress, SdrrRomType, SdrrServe,
+};
+#[cfg(feature = "probe")]
+use sdrr_fw_parser::{STM32F4_FLASH_BASE, STM32F4_RAM_BASE, readers::ProbeReader};
+use utils::{
+    Progress, add_commas, explain_lookup, format_addr, info_field, intel_hex, markdown_report,
+    pins_json, print_hex_dump, set_type, sorted_roms,
+};
 
 // Supported file types
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -75,7 +78,7 @@ impl fmt::Display for FileType {
 struct FirmwareData {
     file_type: FileType,
     file_size: usize,
-    parser: Parser<MemoryReader>,
+    parser: Parser<FirmwareReader>,
     info: SdrrInfo,
 }
 
@@ -98,8 +101,24 @@ async fn async_main() -> Result<(), Box<dyn std::error::Error>> {
         }
     };
 
+    // Watching reads the firmware from the device, rather than a file
+    if args.command == Command::Watch {
+        watch(&args).await;
+        return Ok(());
+    }
+
     let firmware_path = &args.firmware;
-    let mut fw_data = match load_sdrr_firmware(firmware_path).await {
+    // Listing the ROMs doesn't need the ROM data, so skip reading it all to
+    // verify its checksum
+    let verify_checksum = !matches!(args.command, Command::List | Command::Pins);
+    let fw_data = load_sdrr_firmware(
+        firmware_path,
+        verify_checksum,
+        args.base_address,
+        args.images.as_deref(),
+    )
+    .await;
+    let mut fw_data = match fw_data {
         Ok(info) => info,
         Err(e) => {
             print_header();
@@ -121,12 +140,47 @@ async fn async_main() -> Result<(), Box<dyn std::error::Error>> {
     }
 
     match args.command {
-        Command::Info => print_sdrr_info(&fw_data, &args),
+        Command::Info => {
+            for error in fw_data.info.validate() {
+                eprintln!("{}: {}", error.severity, error);
+            }
+            if let Some(field) = args.only {
+                println!("{}", info_field(&fw_data.info, field))
+            } else if args.csv {
+                print_sdrr_info_csv(&fw_data, &args)
+            } else if args.info_format == InfoFormat::Markdown {
+                let file_name = (!args.firmware_from_stdin())
+                    .then(|| args.firmware.file_name().map(|n| n.to_string_lossy()))
+                    .flatten();
+                print!("{}", markdown_report(&fw_data.info, file_name.as_deref()))
+            } else {
+                print_sdrr_info(&fw_data, &args)
+            }
+        }
+        Command::List => list_roms(&fw_data.info),
+        Command::Pins => print_pins(&fw_data.info, args.json)?,
+        Command::LookupRaw
+        | Command::Lookup
+        | Command::Verify
+        | Command::Search
+        | Command::Extract
+            if !fw_data.info.hw_rev_supports_mangling() =>
+        {
+            eprintln!(
+                "Address lookup isn't supported for {} firmware",
+                fw_data.info.hw_rev.as_deref().unwrap_or("unknown")
+            );
+            std::process::exit(1);
+        }
         Command::LookupRaw => match args.range {
             None => lookup_raw(&mut fw_data, &args).await,
             Some(_) => lookup_raw_range(&mut fw_data, &args).await,
         },
         Command::Lookup => lookup(&mut fw_data, &args).await,
+        Command::Verify => verify(&mut fw_data, &args).await,
+        Command::Search => search(&mut fw_data, &args).await,
+        Command::Extract => extract(&mut fw_data, &args).await,
+        Command::Watch => unreachable!("Internal error: watch doesn't load a firmware file"),
     }
 
     Ok(())
@@ -140,21 +194,25 @@ fn print_sdrr_info(fw_data: &FirmwareData, args: &Args) {
 
     println!("Core Firmware Properties");
     println!("------------------------");
-    println!(
-        "File name:     {}",
-        Path::new(&args.firmware)
-            .file_name()
-            .map(|n| n.to_string_lossy())
-            .unwrap_or_else(|| "".into())
-    );
-    let modified_str = metadata(&args.firmware)
-        .and_then(|data| data.modified())
-        .map(|modified| {
-            let datetime: DateTime<Local> = modified.into();
-            datetime.format("%b %e %Y %H:%M:%S").to_string()
-        })
-        .unwrap_or_else(|_| "error".to_string());
-    println!("File modified: {}", modified_str);
+    if args.firmware_from_stdin() {
+        println!("File name:     (stdin)");
+    } else {
+        println!(
+            "File name:     {}",
+            Path::new(&args.firmware)
+                .file_name()
+                .map(|n| n.to_string_lossy())
+                .unwrap_or_else(|| "".into())
+        );
+        let modified_str = metadata(&args.firmware)
+            .and_then(|data| data.modified())
+            .map(|modified| {
+                let datetime: DateTime<Local> = modified.into();
+                datetime.format("%b %e %Y %H:%M:%S").to_string()
+            })
+            .unwrap_or_else(|_| "error".to_string());
+        println!("File modified: {}", modified_str);
+    }
     println!("File type:     {}", fw_data.file_type);
     println!(
         "File size:     {} bytes ({}KB)",
@@ -181,30 +239,19 @@ fn print_sdrr_info(fw_data: &FirmwareData, args: &Args) {
         info.hw_rev.as_deref().unwrap_or("unknown")
     );
 
-    match info.stm_line {
-        McuLine::F401BC |
-        McuLine::F401DE => println!(
-            "MCU:           F401R{} ({}KB flash, {}KB RAM)",
-            info.stm_storage.package_code(),
+    match info.stm_variant_name() {
+        Some(name) => println!(
+            "MCU:           {} ({}KB flash, {}KB RAM)",
+            name,
             info.stm_storage.kb(),
             info.stm_line.ram_kb()
         ),
-        McuLine::F405 |
-        McuLine::F411 |
-        McuLine::F446 => println!(
-            "MCU:           {:?}R{} ({}KB flash, {}KB RAM)",
+        None => println!(
+            "MCU:           {} (unknown variant, {}KB flash, {}KB RAM)",
             info.stm_line,
-            info.stm_storage.package_code(),
             info.stm_storage.kb(),
             info.stm_line.ram_kb()
         ),
-        McuLine::Rp2350 => {
-            println!(
-                "MCU:           RP2350 ({}KB flash, {}KB RAM)",
-                info.stm_storage.kb(),
-                info.stm_line.ram_kb()
-            );
-        }
     }
     println!(
         "Frequency:     {} MHz (Overclocking: {})",
@@ -234,7 +281,13 @@ fn print_sdrr_info(fw_data: &FirmwareData, args: &Args) {
     println!("SWD enabled:      {}", info.swd_enabled);
     println!("Boot logging:     {}", info.boot_logging_enabled);
     println!("Status LED:       {}", info.status_led_enabled);
-    println!("Count ROM access: {}", info.count_rom_access);
+    match info.access_count_address() {
+        Some(addr) => println!(
+            "Count ROM access: true (live count in runtime info at 0x{:08X})",
+            addr
+        ),
+        None => println!("Count ROM access: false"),
+    }
     println!("STM bootloader:   {}", bootloader);
     let mco = if info.mco_enabled {
         "true (exposed via test pad)"
@@ -242,10 +295,17 @@ fn print_sdrr_info(fw_data: &FirmwareData, args: &Args) {
         "false"
     };
     println!("MCO enabled:      {}", mco);
-    println!(
-        "Boot config:      0x{:2X}{:2X}{:2X}{:2X} - Reserved, should be 0xFFFFFFFF",
-        info.boot_config[0], info.boot_config[1], info.boot_config[2], info.boot_config[3]
-    );
+    let mco2 = if info.mco2_enabled {
+        "true (exposed on PC9)"
+    } else {
+        "false"
+    };
+    println!("MCO2 enabled:     {}", mco2);
+    println!("Boot config:      {}", info.boot_config);
+    if !info.boot_config.is_unset() {
+        // Reserved, so show how the firmware would read it
+        println!("                  (as word 0x{:08X})", info.boot_config.as_u32());
+    }
     println!();
 
     if args.detail {
@@ -255,97 +315,7 @@ fn print_sdrr_info(fw_data: &FirmwareData, args: &Args) {
         let pins = &info.pins;
         if let Some(pins) = pins {
             println!();
-            println!("Data pin mapping:");
-            for (ii, &pin) in pins.data.iter().enumerate() {
-                if pin != 0xFF {
-                    println!(
-                        "  D{}: {}P{}:{}",
-                        ii,
-                        if ii < 10 { " " } else { "" },
-                        pins.data_port,
-                        pin
-                    );
-                }
-            }
-            println!();
-            println!("Address pin mapping:");
-            for (ii, &pin) in pins.addr.iter().enumerate() {
-                if pin != 0xFF {
-                    println!(
-                        "  A{}: {}P{}:{}",
-                        ii,
-                        if ii < 10 { " " } else { "" },
-                        pins.addr_port,
-                        pin
-                    );
-                }
-            }
-            println!();
-            println!("Chip select pins:");
-            if pins.cs1_2364 != 0xFF {
-                println!("  2364 CS1: P{}:{}", pins.cs_port, pins.cs1_2364);
-            }
-            if pins.cs1_2332 != 0xFF {
-                println!("  2332 CS1: P{}:{}", pins.cs_port, pins.cs1_2332);
-            }
-            if pins.cs2_2332 != 0xFF {
-                println!("  2332 CS2: P{}:{}", pins.cs_port, pins.cs2_2332);
-            }
-            if pins.cs1_2316 != 0xFF {
-                println!("  2316 CS1: P{}:{}", pins.cs_port, pins.cs1_2316);
-            }
-            if pins.cs2_2316 != 0xFF {
-                println!("  2316 CS2: P{}:{}", pins.cs_port, pins.cs2_2316);
-            }
-            if pins.cs3_2316 != 0xFF {
-                println!("  2316 CS3: P{}:{}", pins.cs_port, pins.cs3_2316);
-            }
-            if pins.ce_23128 != 0xFF {
-                println!("  23128 CE: P{}:{}", pins.cs_port, pins.ce_23128);
-            }
-            if pins.oe_23128 != 0xFF {
-                println!("  23128 OE: P{}:{}", pins.cs_port, pins.oe_23128);
-            }
-            if pins.x1 != 0xFF {
-                println!("  Multi X1: P{}:{}", pins.cs_port, pins.x1);
-            }
-            if pins.x2 != 0xFF {
-                println!("  Multi X2: P{}:{}", pins.cs_port, pins.x2);
-            }
-            if pins.x_jumper_pull != 0xFF {
-                println!("  X1/2 Jumper Pull: P{}:{}", pins.cs_port, pins.x_jumper_pull);
-            }
-            println!();
-            println!("Image select pins:");
-            if pins.sel0 != 0xFF {
-                println!("  SEL0: P{}:{}", pins.sel_port, pins.sel0);
-            }
-            if pins.sel1 != 0xFF {
-                println!("  SEL1: P{}:{}", pins.sel_port, pins.sel1);
-            }
-            if pins.sel2 != 0xFF {
-                println!("  SEL2: P{}:{}", pins.sel_port, pins.sel2);
-            }
-            if pins.sel3 != 0xFF {
-                println!("  SEL3: P{}:{}", pins.sel_port, pins.sel3);
-            }
-            if pins.sel4 != 0xFF {
-                println!("  SEL4: P{}:{}", pins.sel_port, pins.sel4);
-            }
-            if pins.sel5 != 0xFF {
-                println!("  SEL5: P{}:{}", pins.sel_port, pins.sel5);
-            }
-            if pins.sel6 != 0xFF {
-                println!("  SEL6: P{}:{}", pins.sel_port, pins.sel6);
-            }
-            println!("  Pin jumper pull: {}", pins.sel_jumper_pull);
-            println!();
-            println!("Status LED pin:");
-            if pins.status_port == SdrrMcuPort::None {
-                println!("  Pin: None");
-            } else {
-                println!("  Pin: P{}:{}", pins.status_port, pins.status);
-            }
+            print!("{}", pins);
             println!();
         } else {
             println!("No pin configuration available");
@@ -356,11 +326,11 @@ fn print_sdrr_info(fw_data: &FirmwareData, args: &Args) {
     println!("ROMs Summary:");
     println!("-------------");
     println!("Total sets: {}", info.rom_set_count);
-    // Count up total number of ROM images across all sets
-    let total_roms: usize = info.rom_sets.iter().map(|set| set.roms.len()).sum();
-    println!("Total ROMs: {}", total_roms);
+    println!("Total ROMs: {}", info.rom_count());
 
-    if args.detail {
+    if args.detail && args.sort != RomSort::Set {
+        print_sorted_rom_details(info, args.sort);
+    } else if args.detail {
         println!();
         println!("ROM Details:");
         println!("--------------");
@@ -370,14 +340,7 @@ fn print_sdrr_info(fw_data: &FirmwareData, args: &Args) {
                 println!("-----------");
             }
             println!("ROM Set: {}", ii);
-            let set_type = if rom_set.serve == SdrrServe::AddrOnAnyCs {
-                "Multi-ROM socket"
-            } else if rom_set.rom_count > 1 {
-                "Dynamic bank switching"
-            } else {
-                "Single ROM image"
-            };
-            println!("  Set type:      {}", set_type);
+            println!("  Set type:      {}", set_type(rom_set));
             println!("  Size:          {} bytes", rom_set.size);
             println!("  ROM Count:     {}", rom_set.rom_count);
             println!("  Algorithm:     {}", rom_set.serve);
@@ -399,21 +362,118 @@ fn print_sdrr_info(fw_data: &FirmwareData, args: &Args) {
     }
 }
 
+// Prints one line per ROM, without the header, so the output is easy to use
+// in scripts
+fn list_roms(info: &SdrrInfo) {
+    for (set, index, rom) in info.iter_roms() {
+        println!(
+            "{}/{}: {} ({})",
+            set,
+            index,
+            rom.filename.as_deref().unwrap_or("<no filename>"),
+            rom.rom_type
+        );
+    }
+}
+
+fn print_pins(info: &SdrrInfo, json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(pins) = info.pins.as_ref() else {
+        eprintln!("Firmware doesn't contain a pin configuration");
+        std::process::exit(1);
+    };
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&pins_json(pins))?);
+    } else {
+        print!("{}", pins);
+    }
+    Ok(())
+}
+
+// Prints each ROM's details in sorted order, rather than grouped by set
+fn print_sorted_rom_details(info: &SdrrInfo, sort: RomSort) {
+    println!();
+    let by = match sort {
+        RomSort::Set => "set",
+        RomSort::Name => "name",
+        RomSort::Type => "type",
+    };
+    let title = format!("ROM Details (by {}):", by);
+    println!("{}", title);
+    println!("{}", "-".repeat(title.len()));
+
+    for (set, rom_index, rom) in sorted_roms(info, sort) {
+        println!("ROM Set: {}, ROM: {}", set, rom_index);
+        println!("  Type:          {}", rom.rom_type);
+        println!(
+            "  Name:          {}",
+            rom.filename.as_deref().unwrap_or("<not present>")
+        );
+        println!(
+            "  CS States:     {}/{}/{}",
+            rom.cs1_state, rom.cs2_state, rom.cs3_state
+        );
+    }
+}
+
+// Quotes a CSV field if it contains a separator, quote or newline
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn print_sdrr_info_csv(fw_data: &FirmwareData, args: &Args) {
+    let info = &fw_data.info;
+
+    let file_name = Path::new(&args.firmware)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let version = format!(
+        "{}.{}.{}",
+        info.major_version, info.minor_version, info.patch_version
+    );
+    let hw_rev = info.hw_rev.as_deref().unwrap_or("unknown");
+
+    println!("File,Version,Hardware,Set,ROM,Type,Filename,CS1,CS2,CS3");
+    for (set_index, rom_index, rom) in sorted_roms(info, args.sort) {
+        let fields = [
+            file_name.clone(),
+            version.clone(),
+            hw_rev.to_string(),
+            set_index.to_string(),
+            rom_index.to_string(),
+            rom.rom_type.to_string(),
+            rom.filename.clone().unwrap_or_default(),
+            rom.cs1_state.to_string(),
+            rom.cs2_state.to_string(),
+            rom.cs3_state.to_string(),
+        ];
+        let row: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+        println!("{}", row.join(","));
+    }
+}
+
 async fn lookup_byte_at_address(
     fw_data: &mut FirmwareData,
     detail: bool,
     set: u8,
+    rom: u8,
     addr: SdrrAddress,
     output_mangled_byte: bool,
+    addr_format: AddrFormat,
 ) -> Result<(), String> {
     let info = &mut fw_data.info;
     let parser = &mut fw_data.parser;
 
     // Get the size of this rom set
     let byte = if output_mangled_byte {
-        info.read_rom_byte_raw(parser, set, addr).await
+        info.read_rom_byte_raw_for_rom(parser, set, rom, addr).await
     } else {
-        info.read_rom_byte_demangled(parser, set, addr).await
+        info.read_rom_byte_demangled_for_rom(parser, set, rom, addr).await
     }?;
 
     // Get ROM names
@@ -433,19 +493,43 @@ async fn lookup_byte_at_address(
         if let SdrrAddress::Logical(addr) = addr {
             // We can unwrap the mangled address, because read_rom_byte() above
             // has successfully mangled it
-            println!("Mangled address 0x{:04X}", addr.mangle(info).unwrap());
+            println!(
+                "Mangled address {}",
+                format_addr(info.mangle_address_for_rom(set, rom, &addr).unwrap(), addr_format)
+            );
         }
     }
 
+    let addr = describe_addr(&addr, addr_format);
     if output_mangled_byte {
-        println!("{addr:#}: 0x{byte:02X} (mangled byte)")
+        println!("{addr}: 0x{byte:02X} (mangled byte)")
     } else {
-        println!("{addr:#}: 0x{byte:02X} (demangled byte)")
+        println!("{addr}: 0x{byte:02X} (demangled byte)")
     }
 
     Ok(())
 }
 
+// Describes an address, including the CS line states for logical addresses,
+// in the given address format
+fn describe_addr(addr: &SdrrAddress, addr_format: AddrFormat) -> String {
+    match addr {
+        SdrrAddress::Raw(raw) => format!("Raw address: {}", format_addr(*raw, addr_format)),
+        SdrrAddress::Logical(logical) => {
+            let cs_set = logical.cs_set();
+            format!(
+                "Logical address: {} (CS1: {}, CS2: {:?}, CS3: {:?}, X1: {:?}, X2: {:?})",
+                format_addr(logical.addr(), addr_format),
+                cs_set.cs1(),
+                cs_set.cs2(),
+                cs_set.cs3(),
+                cs_set.x1(),
+                cs_set.x2()
+            )
+        }
+    }
+}
+
 async fn lookup_raw(fw_data: &mut FirmwareData, args: &Args) {
     println!("Lookup Byte Using Raw (mangled) Address");
     println!("---------------------------------------");
@@ -461,8 +545,10 @@ async fn lookup_raw(fw_data: &mut FirmwareData, args: &Args) {
         fw_data,
         args.detail,
         set,
+        0,
         SdrrAddress::from_raw(addr),
         output_mangled_byte,
+        args.addr_format,
     )
     .await
     {
@@ -533,11 +619,13 @@ async fn lookup_raw_range(fw_data: &mut FirmwareData, args: &Args) {
         if detail {
             println!("Byte lookup ROM set {} ({})", set, rom_name);
             println!(
-                "Raw address range 0x{:04X} to 0x{:04X}:",
-                start_addr, end_addr
+                "Raw address range {} to {}:",
+                format_addr(start_addr, args.addr_format),
+                format_addr(end_addr, args.addr_format)
             );
         }
 
+        let mut bytes = Vec::new();
         for addr in start_addr..=end_addr {
             let log_addr = SdrrAddress::from_raw(addr);
             let byte = info
@@ -552,34 +640,10 @@ async fn lookup_raw_range(fw_data: &mut FirmwareData, args: &Args) {
                     .expect("Pin configuration not available")
             };
 
-            let byte_pos = (addr - start_addr) as usize;
-
-            // Print address at start of each line
-            if byte_pos % 16 == 0 {
-                print!("{:04X}: ", addr);
-            }
-
-            // Print the byte
-            print!("{:02X}", output_byte);
-
-            // Add spacing
-            if (byte_pos + 1) % 16 == 0 {
-                // Newline every 16 bytes
-                println!();
-            } else if (byte_pos + 1) % 4 == 0 {
-                // Bigger space every 4 bytes
-                print!("  ");
-            } else {
-                // Regular space between bytes
-                print!(" ");
-            }
+            bytes.push(output_byte);
         }
 
-        // Ensure we end with a newline if we didn't just print one
-        let total_bytes = (end_addr - start_addr + 1) as usize;
-        if total_bytes % 16 != 0 {
-            println!();
-        }
+        print_hex_dump(start_addr, &bytes, args.ascii, args.wrap, args.addr_format);
     }
 }
 
@@ -588,15 +652,24 @@ async fn lookup_range(
     fw_data: &mut FirmwareData,
     detail: bool,
     set: u8,
+    rom: u8,
     start_addr: u32,
     end_addr: u32,
     cs_set: &SdrrCsSet,
     output_mangled: bool,
     output_binary: bool,
+    ascii: bool,
+    wrap: usize,
+    addr_format: AddrFormat,
+    progress: bool,
 ) -> Result<(), String> {
     let info = &mut fw_data.info;
     let parser = &mut fw_data.parser;
 
+    if set as usize >= info.rom_sets.len() {
+        return Err(format!("ROM set {} not found", set));
+    }
+
     let roms: Vec<String> = info.rom_sets[set as usize]
         .roms
         .iter()
@@ -608,15 +681,36 @@ async fn lookup_range(
         .collect();
     let rom_name = roms.join(", ");
 
-    // Validate address range
-    let rom_type = info.rom_sets[set as usize].roms[0].rom_type;
-    let max_addr = rom_type.max_addr();
-    if start_addr > end_addr || start_addr > max_addr || end_addr > max_addr {
+    // Validate address range.  A range which starts within the ROM but runs
+    // past the end of it (for example a 2364 range used with a 2316) is
+    // clamped to the end of the ROM, rather than wrapping around.
+    let rom_info = info.rom_sets[set as usize]
+        .roms
+        .get(rom as usize)
+        .ok_or_else(|| format!("ROM {} not found in set {}", rom, set))?;
+    let rom_type = rom_info.rom_type;
+    let max_addr = rom_info.max_address();
+    if start_addr > end_addr {
+        return Err(format!(
+            "Invalid address range: 0x{:04X} to 0x{:04X}",
+            start_addr, end_addr
+        ));
+    }
+    if start_addr > max_addr {
         return Err(format!(
-            "Invalid address range: 0x{:04X} to 0x{:04X} for ROM type {}",
-            start_addr, end_addr, rom_type
+            "Range start 0x{:04X} is beyond the end of the {} ROM - max valid address is 0x{:04X}",
+            start_addr, rom_type, max_addr
         ));
     }
+    let end_addr = if end_addr > max_addr {
+        eprintln!(
+            "Warning: Range end 0x{:04X} is beyond the end of the {} ROM - clamping to max valid address 0x{:04X}",
+            end_addr, rom_type, max_addr
+        );
+        max_addr
+    } else {
+        end_addr
+    };
     if cs_set.cs2().is_some() && !rom_type.supports_cs2() {
         return Err(format!("ROM type {} does not support CS2 line", rom_type));
     }
@@ -629,13 +723,15 @@ async fn lookup_range(
         return Err("Multi-ROM X1/X2 lines can only be used with multi-ROM sets".to_string());
     }
 
+    let mut progress = Progress::new((end_addr - start_addr + 1) as usize, progress);
+
     if output_binary {
         // Collect bytes for binary output
         let mut binary_data = Vec::new();
 
         for addr in start_addr..=end_addr {
             let log_addr = SdrrAddress::from_logical(addr, cs_set);
-            let byte = info.read_rom_byte_raw(parser, set, log_addr).await?;
+            let byte = info.read_rom_byte_raw_for_rom(parser, set, rom, log_addr).await?;
 
             let output_byte = if output_mangled {
                 byte
@@ -644,7 +740,9 @@ async fn lookup_range(
             };
 
             binary_data.push(output_byte);
+            progress.inc();
         }
+        progress.finish();
 
         // Write binary data to stdout
         std::io::stdout()
@@ -654,12 +752,17 @@ async fn lookup_range(
         // Hex dump output
         if detail {
             println!("Byte lookup ROM set {} ({})", set, rom_name);
-            println!("Address range 0x{:04X} to 0x{:04X}:", start_addr, end_addr);
+            println!(
+                "Address range {} to {}:",
+                format_addr(start_addr, addr_format),
+                format_addr(end_addr, addr_format)
+            );
         }
 
+        let mut bytes = Vec::new();
         for addr in start_addr..=end_addr {
             let log_addr = SdrrAddress::from_logical(addr, cs_set);
-            let byte = info.read_rom_byte_raw(parser, set, log_addr).await?;
+            let byte = info.read_rom_byte_raw_for_rom(parser, set, rom, log_addr).await?;
 
             let output_byte = if output_mangled {
                 byte
@@ -667,34 +770,12 @@ async fn lookup_range(
                 info.demangle_byte(byte)?
             };
 
-            let byte_pos = (addr - start_addr) as usize;
-
-            // Print address at start of each line
-            if byte_pos % 16 == 0 {
-                print!("{:04X}: ", addr);
-            }
-
-            // Print the byte
-            print!("{:02X}", output_byte);
-
-            // Add spacing
-            if (byte_pos + 1) % 16 == 0 {
-                // Newline every 16 bytes
-                println!();
-            } else if (byte_pos + 1) % 4 == 0 {
-                // Bigger space every 4 bytes
-                print!("  ");
-            } else {
-                // Regular space between bytes
-                print!(" ");
-            }
+            bytes.push(output_byte);
+            progress.inc();
         }
+        progress.finish();
 
-        // Ensure we end with a newline if we didn't just print one
-        let total_bytes = (end_addr - start_addr + 1) as usize;
-        if total_bytes % 16 != 0 {
-            println!();
-        }
+        print_hex_dump(start_addr, &bytes, ascii, wrap, addr_format);
     }
 
     Ok(())
@@ -708,48 +789,464 @@ async fn lookup(fw_data: &mut FirmwareData, args: &Args) {
     }
 
     // Ensure we have the arguments
-    let set = args.set.expect("Internal error: set number is required");
-    let output_mangled = args
-        .output_mangled
-        .expect("Internal error: output_mangled is required");
-    let _output_binary = args
-        .output_binary
-        .expect("Internal error: output_binary is required");
+    let sets = match args
+        .sets
+        .as_ref()
+        .expect("Internal error: set selection is required")
+    {
+        SetSelection::All => (0..fw_data.info.rom_sets.len() as u8).collect(),
+        SetSelection::Sets(sets) => sets.clone(),
+    };
     let cs1 = args.cs1.expect("Internal error: cs1 is required");
     let cs2 = args.cs2;
     let cs3 = args.cs3;
     let x1 = args.x1;
     let x2 = args.x2;
-    let cs_set = SdrrCsSet::new(cs1, cs2, cs3, x1, x2);
+    let mut cs_set = SdrrCsSet::new(cs1, cs2, cs3, x1, x2);
+    let rom = args.rom.unwrap_or(0);
+
+    for (ii, &set) in sets.iter().enumerate() {
+        // Name each set's block when dumping more than one set.  Binary
+        // output is concatenated, so it can be piped.
+        if sets.len() > 1 && !binary {
+            if ii > 0 {
+                println!();
+            }
+            match fw_data.info.rom_sets.get(set as usize) {
+                Some(rom_set) => {
+                    let roms: Vec<&str> = rom_set
+                        .roms
+                        .iter()
+                        .map(|rom| rom.filename.as_deref().unwrap_or("<unknown>"))
+                        .collect();
+                    println!("ROM set {} ({})", set, roms.join(", "));
+                }
+                None => println!("ROM set {}", set),
+            }
+        }
+
+        // With --selected, use the CS line states which select the ROM
+        if args.selected {
+            cs_set = match selected_cs_set(&fw_data.info, set, rom) {
+                Ok(cs_set) => cs_set,
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                }
+            };
+            if args.detail {
+                println!("Using the CS line states which select ROM {} in set {}", rom, set);
+            }
+        }
+
+        if let Err(e) = lookup_set(fw_data, args, set, rom, &cs_set).await {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+async fn lookup_set(
+    fw_data: &mut FirmwareData,
+    args: &Args,
+    set: u8,
+    rom: u8,
+    cs_set: &SdrrCsSet,
+) -> Result<(), String> {
+    let output_mangled = args
+        .output_mangled
+        .expect("Internal error: output_mangled is required");
+    let output_binary = args
+        .output_binary
+        .expect("Internal error: output_binary is required");
 
     if let Some((start_addr, end_addr)) = args.range {
         // Range lookup
-        let output_binary = args.output_binary.unwrap_or(false);
-        if let Err(e) = lookup_range(
+        lookup_range(
             fw_data,
             args.detail,
             set,
+            rom,
             start_addr,
             end_addr,
-            &cs_set,
+            cs_set,
             output_mangled,
             output_binary,
+            args.ascii,
+            args.wrap,
+            args.addr_format,
+            args.progress,
         )
         .await
-        {
-            eprintln!("Error: {}", e);
-            std::process::exit(1);
-        }
     } else {
         // Single address lookup
         let addr = args.addr.expect("Internal error: address is required");
-        let addr = SdrrAddress::from_logical(addr, &cs_set);
+        if let Some(rom_set) = fw_data.info.rom_sets.get(set as usize) {
+            let rom_info = rom_set
+                .roms
+                .get(rom as usize)
+                .ok_or_else(|| format!("ROM {} not found in set {}", rom, set))?;
+            if addr & !rom_info.effective_address_mask() != 0 {
+                return Err(format!(
+                    "Address 0x{:04X} is beyond the end of the {} ROM - max valid address is 0x{:04X}",
+                    addr,
+                    rom_info.rom_type,
+                    rom_info.max_address()
+                ));
+            }
+        }
+        let logical_addr = SdrrLogicalAddress::new(addr, *cs_set);
+        let addr = SdrrAddress::from_logical(addr, cs_set);
 
-        if let Err(e) =
-            lookup_byte_at_address(fw_data, args.detail, set, addr, output_mangled).await
-        {
-            eprintln!("Error: {}", e);
+        lookup_byte_at_address(
+            fw_data,
+            args.detail,
+            set,
+            rom,
+            addr,
+            output_mangled,
+            args.addr_format,
+        )
+        .await?;
+
+        if args.explain {
+            let stored = fw_data
+                .info
+                .read_rom_byte_raw_for_rom(&mut fw_data.parser, set, rom, addr)
+                .await?;
+            println!();
+            for line in explain_lookup(&fw_data.info, set, rom, &logical_addr, stored)? {
+                println!("{}", line);
+            }
+        }
+        Ok(())
+    }
+}
+
+// Builds the CS line states which select the given ROM within the given set,
+// based on the CS configuration stored in the firmware.
+fn cs_set_selecting_rom(info: &SdrrInfo, set: u8, rom: u8) -> Result<SdrrCsSet, String> {
+    let rom_set = info
+        .rom_sets
+        .get(set as usize)
+        .ok_or_else(|| format!("ROM set {} not found", set))?;
+    if rom as usize >= rom_set.roms.len() {
+        return Err(format!("ROM {} not found in set {}", rom, set));
+    }
+    let pins = info
+        .pins
+        .as_ref()
+        .ok_or("Pin configuration not available")?;
+    rom_set.cs_set_selecting_rom(pins, rom)
+}
+
+// As cs_set_selecting_rom(), for `lookup --selected`, but errors if the ROM's
+// CS1 line isn't used, as then there is no level which selects it.
+fn selected_cs_set(info: &SdrrInfo, set: u8, rom: u8) -> Result<SdrrCsSet, String> {
+    let rom_set = info
+        .rom_sets
+        .get(set as usize)
+        .ok_or_else(|| format!("ROM set {} not found", set))?;
+    let rom_info = rom_set
+        .roms
+        .get(rom as usize)
+        .ok_or_else(|| format!("ROM {} not found in set {}", rom, set))?;
+    let cs1_state = if rom_set.rom_count > 1 && rom_set.serve == SdrrServe::AddrOnAnyCs {
+        rom_set.multi_rom_cs1_state
+    } else {
+        rom_info.cs1_state
+    };
+    if cs1_state == SdrrCsState::NotUsed {
+        return Err(format!(
+            "ROM set {} ROM {} does not use CS1, so the CS line states which select it are ambiguous - specify them with --cs1 etc instead",
+            set, rom
+        ));
+    }
+
+    cs_set_selecting_rom(info, set, rom)
+}
+
+// Returns the type and filename of the given ROM within the given set
+fn rom_type_and_name(info: &SdrrInfo, set: u8, rom: u8) -> Result<(SdrrRomType, String), String> {
+    let rom_info = info
+        .rom_sets
+        .get(set as usize)
+        .ok_or_else(|| format!("ROM set {} not found", set))?
+        .roms
+        .get(rom as usize)
+        .ok_or_else(|| format!("ROM {} not found in set {}", rom, set))?;
+    let rom_name = rom_info
+        .filename
+        .clone()
+        .unwrap_or_else(|| "<unknown>".to_string());
+
+    Ok((rom_info.rom_type, rom_name))
+}
+
+async fn extract(fw_data: &mut FirmwareData, args: &Args) {
+    // Ensure we have the arguments
+    let set = args.set.expect("Internal error: set number is required");
+    let rom = args.rom.expect("Internal error: ROM number is required");
+    let output = args
+        .output
+        .as_ref()
+        .expect("Internal error: output file is required");
+
+    if let Err(e) = extract_rom(fw_data, set, rom, output, args.format, args.hex_base).await {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+async fn extract_rom(
+    fw_data: &mut FirmwareData,
+    set: u8,
+    rom: u8,
+    output: &Path,
+    format: ExtractFormat,
+    hex_base: u32,
+) -> Result<(), String> {
+    let (rom_type, rom_name) = rom_type_and_name(&fw_data.info, set, rom)?;
+    let pins = fw_data.info.pins.as_ref().ok_or("Pin configuration not available")?;
+    let rom_set = &fw_data.info.rom_sets[set as usize];
+    let image = rom_set.logical_image(&mut fw_data.parser, pins, rom).await?;
+
+    let contents = match format {
+        ExtractFormat::Bin => image,
+        ExtractFormat::Hex => intel_hex(hex_base, &image)?.into_bytes(),
+    };
+    std::fs::write(output, contents)
+        .map_err(|e| format!("Failed to write {}: {}", output.display(), e))?;
+
+    println!(
+        "Extracted ROM set {}, ROM {} ({}, type {}) to {}",
+        set,
+        rom,
+        rom_name,
+        rom_type,
+        output.display()
+    );
+
+    Ok(())
+}
+
+#[cfg(feature = "probe")]
+async fn watch(args: &Args) {
+    // Ensure we have the arguments
+    let chip = args.probe.as_deref().expect("Internal error: probe chip is required");
+    let interval = args.interval.expect("Internal error: interval is required");
+
+    let reader = match ProbeReader::attach(chip) {
+        Ok(reader) => reader,
+        Err(e) => {
+            eprintln!("Error: Failed to attach to {} via a debug probe: {}", chip, e);
+            std::process::exit(1);
+        }
+    };
+
+    // The reader isn't wrapped in a CachingReader, as the runtime info must
+    // be re-read from the device each interval
+    let flash_base = reader.flash_base().unwrap_or(STM32F4_FLASH_BASE);
+    let mut parser = Parser::with_base_flash_address(reader, flash_base, STM32F4_RAM_BASE)
+        .verify_checksum(false);
+    let info = match parser.parse_flash().await {
+        Ok(info) => info,
+        Err(e) => {
+            eprintln!("Error: Failed to read SDRR firmware from {}: {}", chip, e);
             std::process::exit(1);
         }
+    };
+    if !info.count_rom_access {
+        eprintln!("Error: Firmware was not built with ROM access counting, so cannot be watched");
+        std::process::exit(1);
+    }
+
+    println!();
+    println!("Watching Runtime Info");
+    println!("---------------------");
+    println!(
+        "Reading {} every {}ms - press Ctrl-C to stop",
+        chip,
+        interval.as_millis()
+    );
+
+    let mut last_count = None;
+    loop {
+        let line = match parser.parse_ram().await {
+            Ok(runtime) if runtime.count_rom_access == 0 => {
+                last_count = None;
+                "Running firmware is not counting ROM accesses".to_string()
+            }
+            Ok(runtime) => {
+                let count = runtime.last_parsed_access_count;
+                let delta = match last_count {
+                    Some(last) => format!("{:+}", count.wrapping_sub(last)),
+                    None => "-".to_string(),
+                };
+                last_count = Some(count);
+                format!(
+                    "Image select: 0x{:02X}  ROM set: {}  Access count: {}  Delta: {}",
+                    runtime.image_sel, runtime.rom_set_index, count, delta
+                )
+            }
+            Err(e) => {
+                last_count = None;
+                format!("Error: {}", e)
+            }
+        };
+
+        // Refresh the line in place, clearing any remains of a longer one
+        print!("\r{}\x1b[K", line);
+        let _ = std::io::stdout().flush();
+
+        smol::Timer::after(interval).await;
+    }
+}
+
+#[cfg(not(feature = "probe"))]
+async fn watch(_args: &Args) {
+    eprintln!("Error: watch needs debug probe support - rebuild sdrr-info with --features probe");
+    std::process::exit(1);
+}
+
+async fn verify(fw_data: &mut FirmwareData, args: &Args) {
+    println!("Verify ROM Against Original Image");
+    println!("---------------------------------");
+
+    // Ensure we have the arguments
+    let set = args.set.expect("Internal error: set number is required");
+    let rom = args.rom.expect("Internal error: ROM number is required");
+    let original_path = args
+        .original
+        .as_ref()
+        .expect("Internal error: original file is required");
+
+    if let Err(e) = verify_rom(fw_data, set, rom, original_path).await {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+async fn verify_rom(
+    fw_data: &mut FirmwareData,
+    set: u8,
+    rom: u8,
+    original_path: &Path,
+) -> Result<(), String> {
+    let info = &fw_data.info;
+
+    let original = std::fs::read(original_path)
+        .map_err(|e| format!("Failed to read {}: {}", original_path.display(), e))?;
+
+    let (rom_type, rom_name) = rom_type_and_name(info, set, rom)?;
+    if original.len() != rom_type.rom_size() {
+        return Err(format!(
+            "Original file is {} bytes, but ROM type {} is {} bytes",
+            original.len(),
+            rom_type,
+            rom_type.rom_size()
+        ));
+    }
+
+    println!("ROM set {}, ROM {} ({}, type {})", set, rom, rom_name, rom_type);
+    println!("Original file: {}", original_path.display());
+
+    let pins = fw_data.info.pins.as_ref().ok_or("Pin configuration not available")?;
+    let rom_set = &fw_data.info.rom_sets[set as usize];
+    let image = rom_set.logical_image(&mut fw_data.parser, pins, rom).await?;
+
+    let mut mismatches = 0;
+    let mut first_mismatch = None;
+    for (addr, (&expected, &byte)) in original.iter().zip(image.iter()).enumerate() {
+        if byte != expected {
+            mismatches += 1;
+            if first_mismatch.is_none() {
+                first_mismatch = Some((addr, expected, byte));
+            }
+        }
+    }
+
+    match first_mismatch {
+        None => {
+            println!("Verified {} bytes: OK", add_commas(original.len() as u64));
+            Ok(())
+        }
+        Some((addr, expected, byte)) => Err(format!(
+            "{} of {} bytes differ, first at 0x{:04X} (expected 0x{:02X}, found 0x{:02X})",
+            add_commas(mismatches),
+            add_commas(original.len() as u64),
+            addr,
+            expected,
+            byte
+        )),
+    }
+}
+
+async fn search(fw_data: &mut FirmwareData, args: &Args) {
+    println!("Search ROM Image");
+    println!("----------------");
+
+    // Ensure we have the arguments
+    let set = args.set.expect("Internal error: set number is required");
+    let rom = args.rom.expect("Internal error: ROM number is required");
+    let pattern = args
+        .pattern
+        .as_ref()
+        .expect("Internal error: pattern is required");
+
+    if let Err(e) = search_rom(fw_data, set, rom, pattern, args.mask.as_deref()).await {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+async fn search_rom(
+    fw_data: &mut FirmwareData,
+    set: u8,
+    rom: u8,
+    pattern: &[u8],
+    mask: Option<&[u8]>,
+) -> Result<(), String> {
+    let (rom_type, rom_name) = rom_type_and_name(&fw_data.info, set, rom)?;
+    println!("ROM set {}, ROM {} ({}, type {})", set, rom, rom_name, rom_type);
+    print!("Pattern:");
+    for byte in pattern {
+        print!(" {:02X}", byte);
+    }
+    println!();
+    if let Some(mask) = mask {
+        print!("Mask:   ");
+        for byte in mask {
+            print!(" {:02X}", byte);
+        }
+        println!();
     }
+
+    let pins = fw_data.info.pins.as_ref().ok_or("Pin configuration not available")?;
+    let rom_set = &fw_data.info.rom_sets[set as usize];
+    let image = rom_set.logical_image(&mut fw_data.parser, pins, rom).await?;
+
+    let matches: Vec<usize> = image
+        .windows(pattern.len())
+        .enumerate()
+        .filter(|(_, window)| {
+            window.iter().zip(pattern.iter()).enumerate().all(|(ii, (&byte, &pat))| {
+                let mask = mask.map(|m| m[ii]).unwrap_or(0xFF);
+                (byte & mask) == (pat & mask)
+            })
+        })
+        .map(|(addr, _)| addr)
+        .collect();
+
+    println!();
+    if matches.is_empty() {
+        println!("No matches found");
+    } else {
+        println!("{} match(es) found:", matches.len());
+        for addr in matches {
+            println!("  0x{:04X}", addr);
+        }
+    }
+
+    Ok(())
 }
@@ -2,6 +2,15 @@
 //
 // MIT License
 
+use sdrr_fw_parser::{
+    SdrrInfo, SdrrLogicalAddress, SdrrPins, SdrrRomInfo, SdrrRomSet, SdrrRomType, SdrrServe,
+    format_hex_dump_row_wrapped,
+};
+use serde_json::{Value, json};
+use std::io::{IsTerminal, Write};
+
+use crate::args::{AddrFormat, InfoField, RomSort};
+
 pub fn add_commas(n: u64) -> String {
     let s = n.to_string();
     let chars: Vec<char> = s.chars().collect();
@@ -15,3 +24,696 @@ pub fn add_commas(n: u64) -> String {
     }
     result
 }
+
+/// Returns the value of a single firmware property, as output by
+/// `info --only`.
+pub fn info_field(info: &SdrrInfo, field: InfoField) -> String {
+    match field {
+        InfoField::Version => format!(
+            "{}.{}.{}",
+            info.major_version, info.minor_version, info.patch_version
+        ),
+        InfoField::BuildNumber => info.build_number.to_string(),
+        InfoField::BuildDate => info.build_date.as_deref().unwrap_or("unknown").to_string(),
+        InfoField::Commit => std::str::from_utf8(&info.commit)
+            .map(|commit| commit.trim_end_matches('\0').to_string())
+            .unwrap_or_else(|_| "<error>".to_string()),
+        InfoField::HwRev => info.hw_rev.as_deref().unwrap_or("unknown").to_string(),
+        InfoField::Stm => match info.stm_variant_name() {
+            Some(name) => name.to_string(),
+            None => info.stm_line.to_string(),
+        },
+        InfoField::Freq => info.freq.to_string(),
+        InfoField::RomSets => info.rom_set_count.to_string(),
+        InfoField::Roms => info.rom_count().to_string(),
+    }
+}
+
+/// Returns every ROM, with its set and ROM index, in the order given by
+/// `sort`.  ROMs which sort equally stay in set order, and ROMs without a
+/// filename sort after those with one.
+pub fn sorted_roms(info: &SdrrInfo, sort: RomSort) -> Vec<(u8, u8, &SdrrRomInfo)> {
+    let mut roms: Vec<_> = info.iter_roms().collect();
+    match sort {
+        RomSort::Set => (),
+        RomSort::Name => roms.sort_by_key(|(_, _, rom)| name_sort_key(rom)),
+        RomSort::Type => {
+            roms.sort_by_key(|(_, _, rom)| (rom.rom_type.rom_size(), name_sort_key(rom)))
+        }
+    }
+    roms
+}
+
+fn name_sort_key(rom: &SdrrRomInfo) -> (bool, Option<String>) {
+    let name = rom.filename.as_ref().map(|name| name.to_lowercase());
+    (name.is_none(), name)
+}
+
+// Number of items between progress updates
+const PROGRESS_INTERVAL: usize = 1024;
+
+/// Reports progress through a long running operation on stderr, as a
+/// percentage updated every 1024 items.  It does nothing unless enabled and
+/// stderr is a terminal, so it never ends up in redirected output, and it
+/// never writes to stdout, so it can't corrupt binary output.
+pub struct Progress {
+    total: usize,
+    done: usize,
+    enabled: bool,
+}
+
+impl Progress {
+    pub fn new(total: usize, enabled: bool) -> Self {
+        Self {
+            total,
+            done: 0,
+            enabled: enabled && total > 0 && std::io::stderr().is_terminal(),
+        }
+    }
+
+    /// Records that one more item has been processed
+    pub fn inc(&mut self) {
+        self.done += 1;
+        if self.enabled && (self.done.is_multiple_of(PROGRESS_INTERVAL) || self.done == self.total) {
+            eprint!("\r{:3}%", self.done * 100 / self.total);
+            let _ = std::io::stderr().flush();
+        }
+    }
+
+    /// Clears the progress indicator, so it doesn't interleave with any
+    /// output which follows
+    pub fn finish(&self) {
+        if self.enabled {
+            eprint!("\r    \r");
+            let _ = std::io::stderr().flush();
+        }
+    }
+}
+
+/// Formats an address for output, as 0x1000, 4096 or $1000 depending on
+/// `format`.
+pub fn format_addr(addr: u32, format: AddrFormat) -> String {
+    match format {
+        AddrFormat::Hex => format!("0x{:04X}", addr),
+        AddrFormat::Dec => format!("{}", addr),
+        AddrFormat::Dollar => format!("${:04X}", addr),
+    }
+}
+
+// Formats a hex dump row's address.  Hex addresses are output without a
+// prefix, as the rest of the row is hex, and decimal addresses are padded so
+// the rows line up.
+fn format_row_addr(addr: u32, format: AddrFormat) -> String {
+    match format {
+        AddrFormat::Hex => format!("{:04X}", addr),
+        AddrFormat::Dec => format!("{:5}", addr),
+        AddrFormat::Dollar => format!("${:04X}", addr),
+    }
+}
+
+/// Prints a hex dump of `bytes`, `wrap` bytes per row, with each row prefixed
+/// by the address of its first byte.  If `ascii` is set, each row is followed
+/// by the printable ASCII representation of its bytes ('.' for
+/// non-printable).
+pub fn print_hex_dump(
+    start_addr: u32,
+    bytes: &[u8],
+    ascii: bool,
+    wrap: usize,
+    addr_format: AddrFormat,
+) {
+    for (row, chunk) in bytes.chunks(wrap).enumerate() {
+        let row_addr = start_addr + (row * wrap) as u32;
+        let mut line = format!("{}: ", format_row_addr(row_addr, addr_format));
+        format_hex_dump_row_wrapped(chunk, wrap, ascii, &mut line)
+            .expect("Writing to a String can't fail");
+        println!("{}", line);
+    }
+}
+
+// Formats a single Intel HEX record, including its checksum
+fn intel_hex_record(record_type: u8, addr: u16, data: &[u8]) -> String {
+    let mut bytes = vec![data.len() as u8, (addr >> 8) as u8, addr as u8, record_type];
+    bytes.extend_from_slice(data);
+    let checksum = bytes
+        .iter()
+        .fold(0u8, |sum, byte| sum.wrapping_add(*byte))
+        .wrapping_neg();
+    bytes.push(checksum);
+
+    let mut record = String::from(":");
+    for byte in bytes {
+        record.push_str(&format!("{:02X}", byte));
+    }
+    record.push('\n');
+    record
+}
+
+/// Formats `bytes` as Intel HEX, with the first byte at `base`.  Emits 16
+/// byte data records, extended linear address records whenever the upper 16
+/// bits of the address change, and a final end of file record.
+pub fn intel_hex(base: u32, bytes: &[u8]) -> Result<String, String> {
+    if base as u64 + bytes.len() as u64 > 1 << 32 {
+        return Err(format!(
+            "Image of {} bytes at base 0x{:08X} exceeds the Intel HEX address space",
+            bytes.len(),
+            base
+        ));
+    }
+
+    let mut hex = String::new();
+    let mut upper = 0u16;
+    let mut offset = 0;
+    while offset < bytes.len() {
+        let addr = base + offset as u32;
+        if (addr >> 16) as u16 != upper {
+            upper = (addr >> 16) as u16;
+            hex.push_str(&intel_hex_record(0x04, 0, &upper.to_be_bytes()));
+        }
+
+        // Don't let a record cross a 64KB boundary
+        let len = (bytes.len() - offset)
+            .min(16)
+            .min(0x10000 - (addr & 0xFFFF) as usize);
+        hex.push_str(&intel_hex_record(0x00, addr as u16, &bytes[offset..offset + len]));
+        offset += len;
+    }
+    hex.push_str(&intel_hex_record(0x01, 0, &[]));
+
+    Ok(hex)
+}
+
+// Converts (line, pin) pairs to a JSON array of objects
+fn pin_list(pins: impl Iterator<Item = (usize, u8)>) -> Value {
+    pins.map(|(line, pin)| json!({ "line": line, "pin": pin })).collect()
+}
+
+// Converts a pin to JSON, with null for an unused (0xFF) pin
+fn pin_value(pin: u8) -> Value {
+    if pin == 0xFF { Value::Null } else { json!(pin) }
+}
+
+/// Returns the firmware's pin configuration as JSON, for `pins --json`.
+/// Unused pins are omitted from the data, address and select lists, and are
+/// null elsewhere.
+pub fn pins_json(pins: &SdrrPins) -> Value {
+    let cs = |rom_type: SdrrRomType, names: &[&str]| -> Value {
+        names
+            .iter()
+            .enumerate()
+            .map(|(ii, name)| {
+                let pin = pins.cs_pin(rom_type, ii as u8 + 1);
+                (name.to_string(), pin.map_or(Value::Null, |pin| json!(pin)))
+            })
+            .collect::<serde_json::Map<_, _>>()
+            .into()
+    };
+
+    json!({
+        "rom_pins": pins.rom_pins,
+        "ports": {
+            "data": pins.data_port.to_string(),
+            "addr": pins.addr_port.to_string(),
+            "cs": pins.cs_port.to_string(),
+            "sel": pins.sel_port.to_string(),
+            "status": pins.status_port.to_string(),
+        },
+        "data": pin_list(pins.data_pins()),
+        "addr": pin_list(pins.addr_pins()),
+        "cs": {
+            "2364": cs(SdrrRomType::Rom2364, &["cs1"]),
+            "2332": cs(SdrrRomType::Rom2332, &["cs1", "cs2"]),
+            "2316": cs(SdrrRomType::Rom2316, &["cs1", "cs2", "cs3"]),
+            "23128": cs(SdrrRomType::Rom23128, &["ce", "oe"]),
+        },
+        "x1": pin_value(pins.x1),
+        "x2": pin_value(pins.x2),
+        "x_jumper_pull": pins.x_jumper_pull,
+        "sel": pin_list(pins.sel_pins()),
+        "sel_jumper_pull": pins.sel_jumper_pull,
+        "status": pin_value(pins.status),
+    })
+}
+
+/// Explains, one line per step, how the byte at a logical address is looked
+/// up: how each address line maps to a port pin, which pins the CS and X
+/// line states set, the resulting mangled address, and how the `stored` byte
+/// read from that address is demangled onto D0-D7.
+///
+/// Each address line's pin is found by mangling the address with only that
+/// line set, so this describes exactly what the lookup itself does.  The
+/// address lines are those of ROM `rom` within the set.
+pub fn explain_lookup(
+    info: &SdrrInfo,
+    set: u8,
+    rom: u8,
+    addr: &SdrrLogicalAddress,
+    stored: u8,
+) -> Result<Vec<String>, String> {
+    let pins = info.pins.as_ref().ok_or("Pin configuration not available")?;
+    let rom_set = info
+        .rom_sets
+        .get(set as usize)
+        .ok_or_else(|| format!("ROM set {} not found", set))?;
+    let rom_info = rom_set
+        .roms
+        .get(rom as usize)
+        .ok_or_else(|| format!("ROM {} not found in set {}", rom, set))?;
+    let num_addr_lines = rom_info.rom_type.num_addr_lines();
+    let cs_set = *addr.cs_set();
+    let mangle = |logical: u32| {
+        info.mangle_address_for_rom(set, rom, &SdrrLogicalAddress::new(logical, cs_set))
+    };
+    let pin_name = |pin: u32| format!("P{}{}", pins.addr_port, pin);
+
+    let mut lines = vec![format!(
+        "Logical address:  0x{:04X} = 0b{:0width$b} (A{}-A0)",
+        addr.addr(),
+        addr.addr(),
+        num_addr_lines - 1,
+        width = num_addr_lines
+    )];
+
+    // The pins set by the CS and X line states alone
+    let base = mangle(0)?;
+    for line in (0..num_addr_lines).rev() {
+        let pin = (mangle(1 << line)? ^ base).trailing_zeros();
+        lines.push(format!(
+            "  A{:<2} = {} -> {}",
+            line,
+            (addr.addr() >> line) & 1,
+            pin_name(pin)
+        ));
+    }
+    let cs_pins: Vec<String> = (0..16).filter(|pin| base & (1 << pin) != 0).map(pin_name).collect();
+    lines.push(format!(
+        "  CS/X lines set: {}",
+        if cs_pins.is_empty() {
+            "none".to_string()
+        } else {
+            cs_pins.join(", ")
+        }
+    ));
+
+    let mangled = mangle(addr.addr())?;
+    lines.push(format!("Mangled address:  0x{:04X} = 0b{:016b}", mangled, mangled));
+    lines.push(format!("Stored byte:      0x{:02X} = 0b{:08b}", stored, stored));
+    let mut data_pins: Vec<(usize, u8)> = pins.data_pins().collect();
+    data_pins.sort();
+    for (line, pin) in data_pins.into_iter().rev() {
+        lines.push(format!(
+            "  P{}{} = {} -> D{}",
+            pins.data_port,
+            pin,
+            (stored >> pin) & 1,
+            line
+        ));
+    }
+    let byte = info.demangle_byte(stored)?;
+    lines.push(format!("Demangled byte:   0x{:02X} = 0b{:08b}", byte, byte));
+
+    Ok(lines)
+}
+
+/// Returns a description of how a ROM set is served
+pub fn set_type(rom_set: &SdrrRomSet) -> &'static str {
+    if rom_set.serve == SdrrServe::AddrOnAnyCs {
+        "Multi-ROM socket"
+    } else if rom_set.rom_count > 1 {
+        "Dynamic bank switching"
+    } else {
+        "Single ROM image"
+    }
+}
+
+// Escapes a Markdown table cell, so pipes and newlines in filenames don't
+// break the table
+fn md_cell(cell: &str) -> String {
+    cell.replace('|', "\\|").replace(['\r', '\n'], " ")
+}
+
+// Formats a Markdown table, with a header row
+fn md_table(header: &[&str], rows: &[Vec<String>]) -> String {
+    let row = |cells: Vec<String>| format!("| {} |\n", cells.join(" | "));
+    let mut table = row(header.iter().map(|cell| md_cell(cell)).collect());
+    table.push_str(&row(header.iter().map(|_| "---".to_string()).collect()));
+    for cells in rows {
+        table.push_str(&row(cells.iter().map(|cell| md_cell(cell)).collect()));
+    }
+    table
+}
+
+/// Returns the firmware's properties and ROM sets as a Markdown report, for
+/// `info --format markdown`.  The core properties are a single table, and
+/// each ROM set has a table of its properties followed by a table of its
+/// ROMs.  Nothing depending on when or where the report is generated, such
+/// as the file's modification time, is included, so the output diffs
+/// cleanly when checked in.
+pub fn markdown_report(info: &SdrrInfo, file_name: Option<&str>) -> String {
+    let property = |name: &str, value: String| vec![name.to_string(), value];
+    let mut properties = Vec::new();
+    if let Some(file_name) = file_name {
+        properties.push(property("File name", file_name.to_string()));
+    }
+    properties.extend([
+        property(
+            "Version",
+            format!(
+                "{} (build {})",
+                info_field(info, InfoField::Version),
+                info.build_number
+            ),
+        ),
+        property("Build date", info_field(info, InfoField::BuildDate)),
+        property("Git commit", info_field(info, InfoField::Commit)),
+        property("Hardware", info_field(info, InfoField::HwRev)),
+        property(
+            "MCU",
+            format!(
+                "{} ({}KB flash, {}KB RAM)",
+                info_field(info, InfoField::Stm),
+                info.stm_storage.kb(),
+                info.stm_line.ram_kb()
+            ),
+        ),
+        property("Frequency", format!("{} MHz", info.freq)),
+        property("Overclocking", info.overclock.to_string()),
+        property(
+            "ROM emulation",
+            info.pins
+                .as_ref()
+                .map_or("unknown".to_string(), |pins| format!("{} pin ROM", pins.rom_pins)),
+        ),
+        property("Serve image from RAM", info.preload_image_to_ram.to_string()),
+        property("SWD enabled", info.swd_enabled.to_string()),
+        property("Boot logging", info.boot_logging_enabled.to_string()),
+        property("Status LED", info.status_led_enabled.to_string()),
+        property("Count ROM access", info.count_rom_access.to_string()),
+        property("STM bootloader", info.bootloader_capable.to_string()),
+        property("MCO enabled", info.mco_enabled.to_string()),
+        property("MCO2 enabled", info.mco2_enabled.to_string()),
+        property("Boot config", info.boot_config.to_string()),
+    ]);
+
+    let mut report = String::from("# SDRR Firmware\n\n");
+    report.push_str(&md_table(&["Property", "Value"], &properties));
+    report.push_str(&format!(
+        "\n## ROM Sets\n\n{} sets, {} ROMs\n",
+        info.rom_set_count,
+        info.rom_count()
+    ));
+
+    for (ii, rom_set) in info.rom_sets.iter().enumerate() {
+        report.push_str(&format!("\n### ROM Set {}\n\n", ii));
+        let set_properties = [
+            property("Set type", set_type(rom_set).to_string()),
+            property("Size", format!("{} bytes", rom_set.size)),
+            property("ROM count", rom_set.rom_count.to_string()),
+            property("Algorithm", rom_set.serve.to_string()),
+            property("Multi-ROM CS1", rom_set.multi_rom_cs1_state.to_string()),
+        ];
+        report.push_str(&md_table(&["Property", "Value"], &set_properties));
+        report.push('\n');
+
+        let roms: Vec<Vec<String>> = rom_set
+            .roms
+            .iter()
+            .enumerate()
+            .map(|(jj, rom)| {
+                vec![
+                    jj.to_string(),
+                    rom.rom_type.to_string(),
+                    rom.filename.clone().unwrap_or_else(|| "<not present>".to_string()),
+                    rom.cs1_state.to_string(),
+                    rom.cs2_state.to_string(),
+                    rom.cs3_state.to_string(),
+                ]
+            })
+            .collect();
+        report.push_str(&md_table(&["ROM", "Type", "Name", "CS1", "CS2", "CS3"], &roms));
+    }
+
+    report
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use sdrr_fw_parser::{
+        BootConfig, McuLine, McuStorage, SdrrCsSet, SdrrCsState, SdrrMcuPort,
+    };
+
+    // Parses Intel HEX output, checking each record's checksum, and returns
+    // the (address, byte) pairs it contains
+    fn parse_intel_hex(hex: &str) -> Vec<(u32, u8)> {
+        let mut bytes = Vec::new();
+        let mut upper = 0u32;
+        let mut eof = false;
+        for line in hex.lines() {
+            assert!(!eof, "Record after EOF");
+            let record: Vec<u8> = (1..line.len())
+                .step_by(2)
+                .map(|ii| u8::from_str_radix(&line[ii..ii + 2], 16).unwrap())
+                .collect();
+            assert!(line.starts_with(':'));
+            assert_eq!(record.len(), record[0] as usize + 5);
+            assert_eq!(
+                record.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)),
+                0,
+                "Bad checksum: {}",
+                line
+            );
+
+            let addr = u16::from_be_bytes([record[1], record[2]]) as u32;
+            let data = &record[4..record.len() - 1];
+            match record[3] {
+                0x00 => {
+                    for (ii, byte) in data.iter().enumerate() {
+                        bytes.push(((upper << 16) + addr + ii as u32, *byte));
+                    }
+                }
+                0x01 => eof = true,
+                0x04 => upper = u16::from_be_bytes([data[0], data[1]]) as u32,
+                _ => panic!("Unexpected record type: {}", line),
+            }
+        }
+        assert!(eof, "No EOF record");
+        bytes
+    }
+
+    pub(crate) fn rom(rom_type: SdrrRomType, filename: Option<&str>) -> SdrrRomInfo {
+        SdrrRomInfo {
+            rom_type,
+            cs1_state: SdrrCsState::ActiveLow,
+            cs2_state: SdrrCsState::NotUsed,
+            cs3_state: SdrrCsState::NotUsed,
+            filename: filename.map(|name| name.to_string()),
+        }
+    }
+
+    pub(crate) fn rom_set(roms: Vec<SdrrRomInfo>) -> SdrrRomSet {
+        SdrrRomSet {
+            data_ptr: 0,
+            size: 65536,
+            rom_count: roms.len() as u8,
+            roms,
+            serve: SdrrServe::AddrOnAnyCs,
+            multi_rom_cs1_state: SdrrCsState::ActiveLow,
+        }
+    }
+
+    pub(crate) fn info(rom_sets: Vec<SdrrRomSet>) -> SdrrInfo {
+        SdrrInfo {
+            major_version: 0,
+            minor_version: 5,
+            patch_version: 0,
+            build_number: 1,
+            commit: *b"abcdef0\0",
+            stm_line: McuLine::F411,
+            stm_storage: McuStorage::StorageE,
+            freq: 100,
+            overclock: false,
+            swd_enabled: true,
+            preload_image_to_ram: true,
+            bootloader_capable: false,
+            status_led_enabled: true,
+            boot_logging_enabled: true,
+            mco_enabled: false,
+            mco2_enabled: false,
+            rom_set_count: rom_sets.len() as u8,
+            count_rom_access: false,
+            boot_config: BootConfig::UNSET,
+            build_date: None,
+            hw_rev: None,
+            rom_sets,
+            pins: None,
+            parse_errors: Vec::new(),
+            extra_info: None,
+            rom_crc32: None,
+        }
+    }
+
+    #[test]
+    fn test_sorted_roms() {
+        let info = info(vec![
+            rom_set(vec![
+                rom(SdrrRomType::Rom2364, Some("kernal.bin")),
+                rom(SdrrRomType::Rom2316, None),
+            ]),
+            rom_set(vec![
+                rom(SdrrRomType::Rom2332, Some("Char.bin")),
+                rom(SdrrRomType::Rom2364, Some("basic.bin")),
+            ]),
+        ]);
+        let order = |sort| -> Vec<(u8, u8)> {
+            sorted_roms(&info, sort)
+                .iter()
+                .map(|(set, rom, _)| (*set, *rom))
+                .collect()
+        };
+
+        assert_eq!(order(RomSort::Set), vec![(0, 0), (0, 1), (1, 0), (1, 1)]);
+        assert_eq!(order(RomSort::Name), vec![(1, 1), (1, 0), (0, 0), (0, 1)]);
+        assert_eq!(order(RomSort::Type), vec![(0, 1), (1, 0), (1, 1), (0, 0)]);
+    }
+
+    #[test]
+    fn test_info_field() {
+        let mut info = info(vec![rom_set(vec![
+            rom(SdrrRomType::Rom2364, Some("kernal.bin")),
+            rom(SdrrRomType::Rom2364, Some("basic.bin")),
+        ])]);
+        assert_eq!(info_field(&info, InfoField::Version), "0.5.0");
+        assert_eq!(info_field(&info, InfoField::BuildNumber), "1");
+        assert_eq!(info_field(&info, InfoField::Commit), "abcdef0");
+        assert_eq!(info_field(&info, InfoField::HwRev), "unknown");
+        assert_eq!(info_field(&info, InfoField::Stm), "F411RE");
+        assert_eq!(info_field(&info, InfoField::Freq), "100");
+        assert_eq!(info_field(&info, InfoField::RomSets), "1");
+        assert_eq!(info_field(&info, InfoField::Roms), "2");
+
+        info.hw_rev = Some("24-f".to_string());
+        assert_eq!(info_field(&info, InfoField::HwRev), "24-f");
+    }
+
+    #[test]
+    fn test_format_addr() {
+        assert_eq!(format_addr(0x1000, AddrFormat::Hex), "0x1000");
+        assert_eq!(format_addr(0x1000, AddrFormat::Dec), "4096");
+        assert_eq!(format_addr(0x1000, AddrFormat::Dollar), "$1000");
+        assert_eq!(format_addr(0xA, AddrFormat::Dollar), "$000A");
+
+        assert_eq!(format_row_addr(0x10, AddrFormat::Hex), "0010");
+        assert_eq!(format_row_addr(0x10, AddrFormat::Dec), "   16");
+        assert_eq!(format_row_addr(0x10, AddrFormat::Dollar), "$0010");
+    }
+
+    #[test]
+    fn test_intel_hex() {
+        assert_eq!(
+            intel_hex(0, &[0x01, 0x02, 0x03]).unwrap(),
+            ":03000000010203F7\n:00000001FF\n"
+        );
+
+        let image: Vec<u8> = (0..8192).map(|ii| (ii * 7) as u8).collect();
+        for base in [0, 0x8000, 0xFFF8, 0x1_0000] {
+            let hex = intel_hex(base, &image).unwrap();
+            let parsed = parse_intel_hex(&hex);
+            let expected: Vec<(u32, u8)> = image
+                .iter()
+                .enumerate()
+                .map(|(ii, byte)| (base + ii as u32, *byte))
+                .collect();
+            assert_eq!(parsed, expected);
+        }
+
+        assert!(intel_hex(0xFFFF_F000, &image).is_err());
+    }
+
+    #[test]
+    fn test_pins_json() {
+        let pins = SdrrPins::default()
+            .with_data_port(SdrrMcuPort::PortA)
+            .with_cs_port(SdrrMcuPort::PortC)
+            .with_rom_pins(24)
+            .with_data_pin(0, 7)
+            .with_addr_pin(12, 3)
+            .with_cs_pin(SdrrRomType::Rom2364, 1, 10)
+            .with_cs_pin(SdrrRomType::Rom2332, 2, 11)
+            .with_sel_pin(2, 14)
+            .with_sel_jumper_pull(1);
+        let json = pins_json(&pins);
+
+        assert_eq!(json["rom_pins"], 24);
+        assert_eq!(json["ports"]["data"], "A");
+        assert_eq!(json["ports"]["addr"], "None");
+        assert_eq!(json["data"], json!([{ "line": 0, "pin": 7 }]));
+        assert_eq!(json["addr"], json!([{ "line": 12, "pin": 3 }]));
+        assert_eq!(json["cs"]["2364"], json!({ "cs1": 10 }));
+        assert_eq!(json["cs"]["2332"], json!({ "cs1": null, "cs2": 11 }));
+        assert_eq!(json["cs"]["23128"], json!({ "ce": null, "oe": null }));
+        assert_eq!(json["x1"], Value::Null);
+        assert_eq!(json["sel"], json!([{ "line": 2, "pin": 14 }]));
+        assert_eq!(json["sel_jumper_pull"], 1);
+        assert_eq!(json["status"], Value::Null);
+    }
+
+    #[test]
+    fn test_markdown_report() {
+        let mut info = info(vec![rom_set(vec![
+            rom(SdrrRomType::Rom2364, Some("a|b.bin")),
+            rom(SdrrRomType::Rom2332, None),
+        ])]);
+        info.hw_rev = Some("24-f".to_string());
+
+        let report = markdown_report(&info, Some("fw.bin"));
+        assert_eq!(report, markdown_report(&info, Some("fw.bin")));
+        assert!(report.starts_with("# SDRR Firmware\n\n| Property | Value |\n| --- | --- |\n"));
+        assert!(report.contains("| File name | fw.bin |\n| Version | 0.5.0 (build 1) |\n"));
+        assert!(report.contains("| Hardware | 24-f |\n"));
+        assert!(report.contains("## ROM Sets\n\n1 sets, 2 ROMs\n\n### ROM Set 0\n"));
+        assert!(report.contains("| Set type | Multi-ROM socket |\n"));
+        assert!(report.contains("| 0 | 2364 | a\\|b.bin | Active Low | Not Used | Not Used |\n"));
+        let last = "| 1 | 2332 | <not present> | Active Low | Not Used | Not Used |\n";
+        assert!(report.ends_with(last));
+
+        assert!(!markdown_report(&info, None).contains("File name"));
+    }
+
+    #[test]
+    fn test_explain_lookup() {
+        // Address lines reversed onto PC12-PC0, CS1 on PC13, and data lines
+        // reversed onto PA7-PA0
+        let mut pins = SdrrPins::default()
+            .with_data_port(SdrrMcuPort::PortA)
+            .with_addr_port(SdrrMcuPort::PortC)
+            .with_cs_port(SdrrMcuPort::PortC)
+            .with_rom_pins(24)
+            .with_cs_pin(SdrrRomType::Rom2364, 1, 13);
+        for line in 0..13 {
+            pins = pins.with_addr_pin(line, 12 - line as u8);
+        }
+        for line in 0..8 {
+            pins = pins.with_data_pin(line, 7 - line as u8);
+        }
+        let mut info = info(vec![rom_set(vec![rom(SdrrRomType::Rom2364, None)])]);
+        info.pins = Some(pins);
+
+        let cs_set = SdrrCsSet::new(true, None, None, None, None);
+        let addr = SdrrLogicalAddress::new(0x0003, cs_set);
+        let lines = explain_lookup(&info, 0, 0, &addr, 0x01).unwrap();
+        assert_eq!(lines[0], "Logical address:  0x0003 = 0b0000000000011 (A12-A0)");
+        assert_eq!(lines[1], "  A12 = 0 -> PC0");
+        assert_eq!(lines[13], "  A0  = 1 -> PC12");
+        assert_eq!(lines[14], "  CS/X lines set: PC13");
+        assert_eq!(lines[15], "Mangled address:  0x3800 = 0b0011100000000000");
+        assert_eq!(lines[16], "Stored byte:      0x01 = 0b00000001");
+        assert_eq!(lines[17], "  PA0 = 1 -> D7");
+        assert_eq!(lines[25], "Demangled byte:   0x80 = 0b10000000");
+        assert_eq!(lines.len(), 26);
+        assert!(explain_lookup(&info, 0, 1, &addr, 0x01).is_err());
+
+        info.pins = None;
+        assert!(explain_lookup(&info, 0, 0, &addr, 0x01).is_err());
+    }
+}
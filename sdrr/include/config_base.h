@@ -200,8 +200,11 @@ typedef struct {
     // 1 byte
     const uint8_t count_rom_access;
 
-    // Reserved for future use
-    const uint8_t pad2[1];
+    // Whether MCO2 is enabled, as well as MCO (STM32F4 only).  Reserved, and
+    // 0, before v0.5.0
+    // Offset: 43
+    // 1 byte
+    const uint8_t mco2_enabled;
     
     // Pointer to array of ROM sets
     // Offset: 44
@@ -231,8 +234,13 @@ typedef struct {
     // Pointer to RTT control block
     const sdrr_extra_info_t *extra;
 
-    // 4 further bytes
-    uint8_t _post[4];
+    // v0.5.0 beyond here
+
+    // CRC32 of the ROM set images, in ROM set order, calculated by sdrr-gen.
+    // Allows tools to check the ROM data in flash is intact.
+    // Offset: 60
+    // 4 bytes
+    const uint32_t rom_crc32;
 
     // Length: 64
 } sdrr_info_t;